@@ -0,0 +1,95 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    path::Path,
+};
+
+use rustdoc_rag::query_log::{self, LoggedQuery};
+
+/// Summarize the queries of a --query-log file: the most asked ones, the parts of
+/// the project their top results are in, and the ones without results, which
+/// point at missing documentation.
+pub fn run(path: &Path, top: usize) -> Result<(), Box<dyn Error>> {
+    let Ok(queries) = query_log::read(path) else {
+        eprintln!("Couldn't read the query log {}", path.display());
+        eprintln!("Add --query-log {} to the queries to log", path.display());
+        std::process::exit(1);
+    };
+    let clients = queries
+        .iter()
+        .filter_map(|query| query.client.as_deref())
+        .collect::<BTreeSet<_>>();
+    let unanswered = queries
+        .iter()
+        .filter(|query| query.count == 0)
+        .collect::<Vec<_>>();
+    print!("{} queries", queries.len());
+    if !clients.is_empty() {
+        print!(" from {} clients", clients.len());
+    }
+    println!(", {} without results", unanswered.len());
+
+    print_counts(
+        "Most asked queries",
+        most_common(queries.iter().map(normalize), top),
+    );
+    print_counts(
+        "Most asked topics",
+        most_common(queries.iter().filter_map(topic), top),
+    );
+    print_counts(
+        "Queries without results",
+        most_common(unanswered.into_iter().map(normalize), top),
+    );
+    Ok(())
+}
+
+/// The query with its case and spacing ignored, to count rephrasings once.
+fn normalize(query: &LoggedQuery) -> String {
+    query
+        .query
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// What a query was about: the module or type of its top result, like
+/// `bevy_ecs::system` for `struct:bevy_ecs::system::Commands`.
+fn topic(query: &LoggedQuery) -> Option<String> {
+    let id = query.results.first()?;
+    let path = id.split_once(':').map_or(id.as_str(), |(_, path)| path);
+    Some(
+        path.rsplit_once("::")
+            .map_or(path, |(parent, _)| parent)
+            .to_string(),
+    )
+}
+
+/// The `top` most frequent values with their counts, most frequent first.
+fn most_common(values: impl Iterator<Item = String>, top: usize) -> Vec<(String, usize)> {
+    let mut counts = HashMap::<String, usize>::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    counts.truncate(top);
+    counts
+}
+
+fn print_counts(title: &str, counts: Vec<(String, usize)>) {
+    if counts.is_empty() {
+        return;
+    }
+    println!();
+    println!("{}:", title);
+    let width = counts
+        .iter()
+        .map(|(_, count)| count.to_string().len())
+        .max()
+        .unwrap_or_default();
+    for (value, count) in counts {
+        println!("  {:>width$}  {}", count, value);
+    }
+}
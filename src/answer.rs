@@ -0,0 +1,299 @@
+use std::{error::Error, io::Write};
+
+use serde_json::{json, Value};
+
+use crate::{chunk::floor_char_boundary, llm::Llm};
+
+/// Rough number of characters per token of chat models, for English and code
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Smallest part of a document worth keeping when truncating it to fit the budget
+const MIN_TRUNCATED_TOKENS: usize = 64;
+
+/// What answers are made of.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum AnswerMode {
+    /// An explanation citing the documents of the items
+    #[default]
+    Docs,
+    /// A short working snippet adapted from the indexed examples, with links to the
+    /// items of the API it uses, for "how do I" questions
+    Snippet,
+}
+
+/// Answers questions from retrieved documents with a chat model.
+pub struct Answerer {
+    llm: Llm,
+    project: String,
+    mode: AnswerMode,
+}
+
+impl Answerer {
+    pub fn new(llm: Llm, project: &str) -> Self {
+        Answerer {
+            llm,
+            project: project.to_string(),
+            mode: AnswerMode::Docs,
+        }
+    }
+
+    /// Answer in this mode, from documents found for it by
+    /// [`Retriever::answer_context`](crate::Retriever::answer_context).
+    pub fn with_mode(mut self, mode: AnswerMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Answer the question using only the given documents, as `(id, text)` pairs.
+    /// The answer is printed as it's generated, and returned once complete.
+    pub async fn answer(
+        &self,
+        question: &str,
+        documents: &[(String, String)],
+    ) -> Result<String, Box<dyn Error>> {
+        let mut stdout = std::io::stdout();
+        let answer = self
+            .answer_streaming(question, documents, |token| {
+                print!("{}", token);
+                stdout.flush()?;
+                Ok(())
+            })
+            .await?;
+        println!();
+        Ok(answer)
+    }
+
+    /// Like [`Answerer::answer`], for a question following the previous turns of the
+    /// conversation, which the model sees.
+    pub async fn answer_in(
+        &self,
+        conversation: &Conversation,
+        question: &str,
+        documents: &[(String, String)],
+    ) -> Result<String, Box<dyn Error>> {
+        let Value::Array(mut messages) = self.messages(question, documents) else {
+            unreachable!()
+        };
+        let question = messages.pop().unwrap();
+        for (previous, answer) in &conversation.turns {
+            messages.push(json!({ "role": "user", "content": previous }));
+            messages.push(json!({ "role": "assistant", "content": answer }));
+        }
+        messages.push(question);
+        let mut stdout = std::io::stdout();
+        let answer = self
+            .llm
+            .chat(Value::Array(messages), |token| {
+                print!("{}", token);
+                stdout.flush()?;
+                Ok(())
+            })
+            .await?;
+        println!();
+        Ok(answer)
+    }
+
+    /// The question rewritten by the model to be understood without the previous
+    /// turns of the conversation, like `what about 2D?` after a question about
+    /// cameras into `How do I set up a 2D camera?`. The first question is kept.
+    pub async fn standalone_question(
+        &self,
+        conversation: &Conversation,
+        question: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        if conversation.turns.is_empty() {
+            return Ok(question.to_string());
+        }
+        let system = format!(
+            "You rewrite follow-up questions about the Rust project {} into standalone \
+            search queries. Given a conversation and a follow-up question, reply with the \
+            question rewritten to be understood without the conversation, naming the items \
+            it refers to. Reply with the question only.",
+            self.project
+        );
+        let mut history = String::new();
+        for (previous, answer) in &conversation.turns {
+            history.push_str(&format!("User: {}\nAssistant: {}\n\n", previous, answer));
+        }
+        let messages = json!([
+            { "role": "system", "content": system },
+            {
+                "role": "user",
+                "content": format!("Conversation:\n\n{}Follow-up question: {}", history, question),
+            },
+        ]);
+        let rewritten = self.llm.chat(messages, |_| Ok(())).await?;
+        let rewritten = rewritten.trim().trim_matches('"').trim();
+        Ok(if rewritten.is_empty() {
+            question.to_string()
+        } else {
+            rewritten.to_string()
+        })
+    }
+
+    /// A single question asking which of the things the documents are about the user
+    /// means, like `Do you mean UI text or 3D text?`, or `None` when the model finds
+    /// the question clear enough to answer.
+    pub async fn clarifying_question(
+        &self,
+        question: &str,
+        documents: &[(String, String)],
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        let system = format!(
+            "You help users search the API documentation of the Rust project {}. The \
+            documents found for their question are about different things. If the question \
+            could mean several of them, reply with a single short question asking which one \
+            they mean, naming the alternatives, like \"Do you mean UI text or 3D text?\". If \
+            the question is clear enough to answer, reply with NONE.",
+            self.project
+        );
+        let mut found = String::new();
+        for (id, text) in documents {
+            let summary = text.lines().find(|line| !line.trim().is_empty());
+            found.push_str(&format!(
+                "- {}: {}\n",
+                id,
+                summary.unwrap_or_default().trim()
+            ));
+        }
+        let messages = json!([
+            { "role": "system", "content": system },
+            {
+                "role": "user",
+                "content": format!("Documents found:\n{}\nQuestion: {}", found, question),
+            },
+        ]);
+        let reply = self.llm.chat(messages, |_| Ok(())).await?;
+        let reply = reply.trim().trim_matches('"').trim();
+        Ok((!reply.is_empty() && !reply.starts_with("NONE")).then(|| reply.to_string()))
+    }
+
+    /// Like [`Answerer::answer`], giving the tokens to a callback instead of printing them.
+    pub async fn answer_streaming(
+        &self,
+        question: &str,
+        documents: &[(String, String)],
+        on_token: impl FnMut(&str) -> Result<(), Box<dyn Error>>,
+    ) -> Result<String, Box<dyn Error>> {
+        self.llm
+            .chat(self.messages(question, documents), on_token)
+            .await
+    }
+
+    fn messages(&self, question: &str, documents: &[(String, String)]) -> Value {
+        let system = match self.mode {
+            AnswerMode::Docs => format!(
+                "You answer questions about the Rust project {} using only the documentation \
+                provided. If it doesn't contain the answer, say so instead of guessing. \
+                Every document starts with a number in brackets and the path of the item it \
+                documents. Cite the documents you use with their number in brackets, like [1], \
+                right after the sentence relying on them.",
+                self.project
+            ),
+            AnswerMode::Snippet => format!(
+                "You answer how-to questions about the Rust project {} with a short working \
+                code snippet in a rust code block, then a list of the items of the API it \
+                uses, each as a markdown link to its documentation URL when it has one. \
+                Adapt the snippet from the examples provided, the documents whose path \
+                starts with `example:`, and check the names and signatures it uses against \
+                the documentation of the items provided. If they don't show how to do it, \
+                say so instead of guessing. Every document starts with a number in brackets \
+                and its path. Cite the documents you use with their number in brackets, \
+                like [1].",
+                self.project
+            ),
+        };
+        let mut context = String::new();
+        for (i, (id, text)) in documents.iter().enumerate() {
+            context.push_str(&format!("## [{}] {}\n\n{}\n\n", i + 1, id, text.trim()));
+        }
+        json!([
+            { "role": "system", "content": system },
+            {
+                "role": "user",
+                "content": format!("Documentation:\n\n{}Question: {}", context, question),
+            },
+        ])
+    }
+}
+
+/// The previous questions and answers of a chat, with the documents retrieved for
+/// them to answer follow-up questions from.
+#[derive(Default)]
+pub struct Conversation {
+    turns: Vec<(String, String)>,
+    /// Documents retrieved for the previous questions, most recent first
+    context: Vec<(String, String)>,
+}
+
+impl Conversation {
+    /// Documents to answer a question from: the ones retrieved for it, then the ones
+    /// carried over from the previous questions, within the token budget. The oldest
+    /// turns are forgotten when they don't fit in the rest of the budget.
+    pub fn context(
+        &mut self,
+        documents: Vec<(String, String)>,
+        budget: usize,
+    ) -> Vec<(String, String)> {
+        let mut history = self
+            .turns
+            .iter()
+            .map(|(question, answer)| estimate_tokens(question) + estimate_tokens(answer))
+            .sum::<usize>();
+        // The turns take at most half of the budget
+        while history > budget / 2 && !self.turns.is_empty() {
+            let (question, answer) = self.turns.remove(0);
+            history -= estimate_tokens(&question) + estimate_tokens(&answer);
+        }
+        let mut merged = documents;
+        for document in std::mem::take(&mut self.context) {
+            if !merged.iter().any(|(id, _)| *id == document.0) {
+                merged.push(document);
+            }
+        }
+        self.context = pack_context(merged, budget - history);
+        self.context.clone()
+    }
+
+    pub fn record(&mut self, question: &str, answer: &str) {
+        self.turns.push((question.to_string(), answer.to_string()));
+    }
+}
+
+/// Documents cited in an answer as `[n]`, with their number, in the order they
+/// were given to the model.
+pub fn citations<'a>(answer: &str, documents: &'a [(String, String)]) -> Vec<(usize, &'a str)> {
+    documents
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| answer.contains(&format!("[{}]", i + 1)))
+        .map(|(i, (id, _))| (i + 1, id.as_str()))
+        .collect()
+}
+
+/// Estimated number of tokens of a text for a chat model.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Keep documents in order while they fit in the token budget, truncating the
+/// first one that doesn't if enough of it fits, and dropping the rest.
+pub fn pack_context(documents: Vec<(String, String)>, budget: usize) -> Vec<(String, String)> {
+    let mut remaining = budget;
+    let mut packed = vec![];
+    for (id, text) in documents {
+        let tokens = estimate_tokens(&id) + estimate_tokens(&text);
+        if tokens <= remaining {
+            remaining -= tokens;
+            packed.push((id, text));
+            continue;
+        }
+        let available = remaining.saturating_sub(estimate_tokens(&id));
+        if available >= MIN_TRUNCATED_TOKENS {
+            let end = floor_char_boundary(&text, available * CHARS_PER_TOKEN);
+            packed.push((id, format!("{}...", &text[..end])));
+        }
+        break;
+    }
+    packed
+}
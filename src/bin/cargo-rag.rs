@@ -0,0 +1,100 @@
+use std::error::Error;
+
+use clap::{parser::ValueSource, CommandFactory, FromArgMatches, Parser, Subcommand};
+use rustdoc_rag::{answer::Answerer, cargo, watch, Indexer, Options, Retriever};
+
+/// Index and query the documentation of the current Cargo package
+#[derive(Parser, Debug)]
+#[command(bin_name = "cargo rag", version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Action,
+
+    /// Package to document, defaults to the one in the current directory
+    #[arg(long = "package", global = true)]
+    package: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Action {
+    /// Index the rustdoc JSON of the package, only embedding what changed
+    Index {
+        #[command(flatten)]
+        options: Options,
+    },
+    /// List the items matching a prompt, or answer it with --answer
+    Ask {
+        prompt: String,
+        #[command(flatten)]
+        options: Options,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    // Cargo passes the name of the subcommand as the first argument
+    let mut args_os = std::env::args_os().collect::<Vec<_>>();
+    if args_os.get(1).is_some_and(|arg| arg == "rag") {
+        args_os.remove(1);
+    }
+    let matches = Args::command().get_matches_from(args_os);
+    let args = Args::from_arg_matches(&matches)?;
+    let package = cargo::package(None, args.package.as_deref())?;
+    let (mut options, prompt) = match args.command {
+        Action::Index { options } => (options, None),
+        Action::Ask { prompt, options } => (options, Some(prompt)),
+    };
+
+    // Options given explicitly win over the ones found from the package
+    let explicit = |id| {
+        matches
+            .subcommand()
+            .is_some_and(|(_, matches)| matches.value_source(id) == Some(ValueSource::CommandLine))
+    };
+    if !explicit("project") && !explicit("manifest_path") {
+        options.project = package.rustdoc_json().to_string_lossy().to_string();
+        // Indexing always refreshes the rustdoc JSON, asking only generates it if missing
+        if prompt.is_none() || !package.rustdoc_json().exists() {
+            options.manifest_path = Some(package.manifest_path.clone());
+        }
+    }
+    if !explicit("out_dir") {
+        options.out_dir = package.target_dir.join("rustdoc-rag");
+    }
+
+    cargo::use_manifest(&mut options, true)?;
+
+    match prompt {
+        None => {
+            options.update = true;
+            let indexer = Indexer::new(options)?;
+            if indexer.options().watch {
+                watch::watch(&indexer).await?;
+            } else {
+                indexer.open().await?;
+            }
+        }
+        Some(prompt) => {
+            let indexer = Indexer::new(options)?;
+            let options = indexer.options();
+            let retriever = Retriever::new(options.clone(), indexer.open().await?)?;
+            let results = retriever.search(&prompt).await?;
+            if results.ranking.is_empty() {
+                println!("No sufficiently relevant results");
+                std::process::exit(1);
+            }
+            if options.answer {
+                let documents = retriever.answer_context(&prompt, &results).await?;
+                Answerer::new(options.chat_model()?, &options.project)
+                    .with_mode(options.answer_mode)
+                    .answer(&prompt, &documents)
+                    .await?;
+            } else {
+                for (i, (id, score)) in results.ranking.iter().enumerate() {
+                    println!("{:02}. {} {:.3}", i + 1, id, score);
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use sha2::{Digest, Sha256};
+
+use crate::embedding::Provider;
+
+/// On-disk cache of raw embeddings, keyed by the model and a hash of the
+/// exact text sent to it.
+pub struct EmbeddingCache {
+    dir: PathBuf,
+}
+
+impl EmbeddingCache {
+    pub fn new(root: impl Into<PathBuf>, provider: Provider, model: &str) -> Self {
+        let model = format!("{:?}-{}", provider, model).replace(['/', ':', '\\'], "_");
+        EmbeddingCache {
+            dir: root.into().join(model),
+        }
+    }
+
+    pub fn get(&self, text: &str) -> Option<Vec<f32>> {
+        let content = std::fs::read(self.path(text)).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    /// Writes through a temporary file renamed in place, so that a reader never
+    /// sees a partly written entry.
+    pub fn put(&self, text: &str, embedding: &[f32]) {
+        let Ok(content) = serde_json::to_vec(embedding) else {
+            return;
+        };
+        if std::fs::create_dir_all(&self.dir).is_err() {
+            return;
+        }
+        let path = self.path(text);
+        let temporary = path.with_extension(format!("{}.tmp", std::process::id()));
+        if std::fs::write(&temporary, content).is_err()
+            || std::fs::rename(&temporary, &path).is_err()
+        {
+            let _ = std::fs::remove_file(&temporary);
+        }
+    }
+
+    fn path(&self, text: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", content_hash(text)))
+    }
+}
+
+pub fn content_hash(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
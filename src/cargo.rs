@@ -0,0 +1,185 @@
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde_json::Value;
+
+use crate::{error, Options};
+
+/// What Cargo knows about the package being documented.
+pub struct Package {
+    pub name: String,
+    /// Name of its library, as used for the rustdoc JSON file
+    pub crate_name: String,
+    pub manifest_path: PathBuf,
+    pub target_dir: PathBuf,
+}
+
+/// Crates of the standard library distributed as rustdoc JSON by the
+/// `rust-docs-json` component
+pub const STD_CRATES: &[&str] = &["std", "core", "alloc", "proc_macro", "test"];
+
+fn cargo() -> Command {
+    Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+}
+
+/// Find a package with `cargo metadata`, the one of the manifest or of the current
+/// directory unless another is named.
+pub fn package(
+    manifest_path: Option<&Path>,
+    name: Option<&str>,
+) -> Result<Package, Box<dyn Error>> {
+    let mut command = cargo();
+    command.args(["metadata", "--format-version", "1", "--no-deps"]);
+    if let Some(manifest_path) = manifest_path {
+        command.arg("--manifest-path").arg(manifest_path);
+    }
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(error::Error::Rustdoc(format!(
+            "couldn't read the Cargo metadata of the package: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .into());
+    }
+    let metadata: Value = serde_json::from_slice(&output.stdout)?;
+    let dir = match manifest_path.and_then(Path::parent) {
+        Some(dir) if dir != Path::new("") => dir.canonicalize()?,
+        _ => std::env::current_dir()?,
+    };
+    let packages = metadata["packages"].as_array().cloned().unwrap_or_default();
+    let package = match name {
+        Some(name) => packages.iter().find(|package| package["name"] == name),
+        // The package with the closest manifest above the directory
+        None => packages
+            .iter()
+            .filter(|package| {
+                package["manifest_path"]
+                    .as_str()
+                    .and_then(|path| Path::new(path).parent())
+                    .is_some_and(|package_dir| dir.starts_with(package_dir))
+            })
+            .max_by_key(|package| package["manifest_path"].as_str().map_or(0, str::len))
+            .or(packages.first()),
+    };
+    let Some(package) = package else {
+        return Err(error::Error::Options(format!(
+            "couldn't find package {}, choose one of the workspace with --package",
+            name.unwrap_or_default()
+        ))
+        .into());
+    };
+    let library = package["targets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|target| {
+            target["kind"]
+                .as_array()
+                .is_some_and(|kinds| kinds.iter().any(|kind| kind == "lib"))
+        });
+    let Some(library) = library else {
+        return Err(error::Error::Options(format!(
+            "package {} has no library to document",
+            package["name"]
+        ))
+        .into());
+    };
+    Ok(Package {
+        name: package["name"].as_str().unwrap_or_default().to_string(),
+        crate_name: library["name"]
+            .as_str()
+            .unwrap_or_default()
+            .replace('-', "_"),
+        manifest_path: PathBuf::from(package["manifest_path"].as_str().unwrap_or_default()),
+        target_dir: PathBuf::from(metadata["target_directory"].as_str().unwrap_or("target")),
+    })
+}
+
+impl Package {
+    pub fn doc_dir(&self) -> PathBuf {
+        self.target_dir.join("doc")
+    }
+
+    pub fn rustdoc_json(&self) -> PathBuf {
+        self.doc_dir().join(format!("{}.json", self.crate_name))
+    }
+
+    /// Generate the rustdoc JSON of the package and of its dependencies with the
+    /// given toolchain, waiting for it to finish.
+    pub fn generate_rustdoc(&self, toolchain: &str) -> Result<(), Box<dyn Error>> {
+        if !self.run_rustdoc(toolchain)? {
+            return Err(error::Error::Rustdoc(format!(
+                "couldn't generate the rustdoc JSON of {}, is the {} toolchain installed? > rustup toolchain install {}",
+                self.name, toolchain, toolchain
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Like [`Package::generate_rustdoc`], returning whether it succeeded.
+    pub fn run_rustdoc(&self, toolchain: &str) -> Result<bool, Box<dyn Error>> {
+        eprintln!("Generating the rustdoc JSON of {}", self.name);
+        let status = cargo()
+            .arg(format!("+{}", toolchain))
+            .args(["doc", "--package", &self.name, "--manifest-path"])
+            .arg(&self.manifest_path)
+            .env("RUSTDOCFLAGS", "-Z unstable-options --output-format json")
+            // `cargo +toolchain` only works through rustup, not the cargo running us
+            .env_remove("CARGO")
+            .env_remove("RUSTC")
+            .status()?;
+        Ok(status.success())
+    }
+}
+
+/// Directory of the rustdoc JSON of the standard library installed with the
+/// `rust-docs-json` component of the toolchain, if it is.
+pub fn std_json_dir(toolchain: &str) -> Option<PathBuf> {
+    let output = Command::new("rustc")
+        .arg(format!("+{}", toolchain))
+        .args(["--print", "sysroot"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let dir = PathBuf::from(String::from_utf8(output.stdout).ok()?.trim())
+        .join("share")
+        .join("doc")
+        .join("rust")
+        .join("json");
+    dir.join("std.json").exists().then_some(dir)
+}
+
+/// Like [`std_json_dir`], explaining how to install the component when missing.
+pub fn require_std_json_dir(toolchain: &str) -> error::Result<PathBuf> {
+    std_json_dir(toolchain).ok_or_else(|| {
+        error::Error::Rustdoc(format!(
+            "couldn't find the rustdoc JSON of the standard library, install it with the rust-docs-json component of the {} toolchain: > rustup component add rust-docs-json --toolchain {}",
+            toolchain, toolchain
+        ))
+    })
+}
+
+/// Point the options to the rustdoc JSON of the package given with `--manifest-path`,
+/// generating it first if asked. Does nothing without a manifest.
+pub fn use_manifest(options: &mut Options, generate: bool) -> Result<(), Box<dyn Error>> {
+    let Some(manifest_path) = &options.manifest_path else {
+        return Ok(());
+    };
+    let package = package(Some(manifest_path), None)?;
+    if generate {
+        package.generate_rustdoc(&options.toolchain)?;
+    }
+    options.project = package.rustdoc_json().to_string_lossy().to_string();
+    options.jsons_dir = Some(package.doc_dir());
+    let examples = package.manifest_path.with_file_name("examples");
+    if examples.is_dir() && !options.examples.contains(&examples) {
+        options.examples.push(examples);
+    }
+    Ok(())
+}
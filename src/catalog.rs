@@ -0,0 +1,120 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde_json::{Map, Value};
+
+use crate::{cleanup, docs_rs, document::Document};
+
+/// An indexed item as results display it.
+pub struct Entry {
+    pub path: String,
+    pub kind: String,
+    pub krate: Option<String>,
+    pub version: Option<String>,
+    pub url: Option<String>,
+    pub summary: Option<String>,
+    pub metadata: Map<String, Value>,
+}
+
+/// SQLite database next to the indexes with what results show of every item, so
+/// they can be displayed when the vector store only keeps ids and embeddings.
+pub struct ItemCatalog {
+    connection: Mutex<Connection>,
+}
+
+impl ItemCatalog {
+    pub fn path(out_dir: &Path, project: &str) -> PathBuf {
+        out_dir.join("catalogs").join(format!("{}.sqlite", project))
+    }
+
+    /// Replace the catalog with the documents.
+    pub fn write(
+        path: &Path,
+        documents: &BTreeMap<String, Document>,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut connection = Connection::open(path)?;
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(
+            "DROP TABLE IF EXISTS items;
+            CREATE TABLE items (
+                id TEXT PRIMARY KEY,
+                path TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                crate TEXT,
+                version TEXT,
+                url TEXT,
+                summary TEXT,
+                metadata TEXT NOT NULL
+            );",
+        )?;
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO items (id, path, kind, crate, version, url, summary, metadata)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for (id, document) in documents {
+                let field = |key| document.metadata.get(key).and_then(Value::as_str);
+                insert.execute(params![
+                    id,
+                    document.path,
+                    document.kind,
+                    field("crate"),
+                    field("version"),
+                    docs_rs::item_url(id, &document.metadata),
+                    cleanup::summary(&document.text),
+                    serde_json::to_string(&document.metadata)?,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub fn open(path: &Path) -> Option<Self> {
+        if !path.exists() {
+            return None;
+        }
+        let connection =
+            Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).ok()?;
+        Some(ItemCatalog {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Entries of the items with these ids that are in the catalog.
+    pub fn get(&self, ids: &[&str]) -> Result<HashMap<String, Entry>, Box<dyn Error>> {
+        let connection = self.connection.lock().unwrap();
+        let mut select = connection.prepare_cached(
+            "SELECT path, kind, crate, version, url, summary, metadata FROM items WHERE id = ?1",
+        )?;
+        let mut entries = HashMap::new();
+        for id in ids {
+            let entry = select
+                .query_row([id], |row| {
+                    Ok(Entry {
+                        path: row.get(0)?,
+                        kind: row.get(1)?,
+                        krate: row.get(2)?,
+                        version: row.get(3)?,
+                        url: row.get(4)?,
+                        summary: row.get(5)?,
+                        metadata: serde_json::from_str(&row.get::<_, String>(6)?)
+                            .unwrap_or_default(),
+                    })
+                })
+                .optional()?;
+            if let Some(entry) = entry {
+                entries.insert(id.to_string(), entry);
+            }
+        }
+        Ok(entries)
+    }
+}
@@ -0,0 +1,124 @@
+use std::{
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use chromadb::v2::ChromaClient;
+
+use crate::{error, Options};
+
+/// Name of the docker container started for Chroma, reused once created.
+const CONTAINER: &str = "rustdoc-rag-chroma";
+
+/// How long to wait for a started Chroma to answer.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Port of Chroma when its URL is on this machine, where it can be started.
+pub fn local_port(url: &str) -> Option<u16> {
+    reqwest::Url::parse(url)
+        .ok()
+        .filter(|url| {
+            matches!(
+                url.host_str(),
+                Some("localhost" | "127.0.0.1" | "[::1]" | "0.0.0.0")
+            )
+        })
+        .and_then(|url| url.port_or_known_default())
+}
+
+/// Make sure Chroma is reachable, starting it with --start-chroma when it's
+/// configured on this machine, with the `chroma` binary if installed or docker
+/// otherwise. Its data is kept in the output directory.
+pub async fn ensure_running(options: &Options, chroma: &ChromaClient) -> error::Result<()> {
+    if chroma.heartbeat().await.is_ok() {
+        return Ok(());
+    }
+    let unreachable = |advice: String| {
+        error::Error::Store(
+            format!(
+                "Chroma isn't reachable at {}, {}",
+                options.chroma_url, advice
+            )
+            .into(),
+        )
+    };
+    let Some(port) = local_port(&options.chroma_url) else {
+        return Err(unreachable("is the database running?".to_string()));
+    };
+    if !options.start_chroma {
+        return Err(unreachable(format!(
+            "start it with > docker run -p {}:8000 chromadb/chroma, or add --start-chroma to start it automatically",
+            port
+        )));
+    }
+
+    let data = std::path::absolute(options.out_dir.join("chroma"))
+        .map_err(|err| error::Error::Other(err.into()))?;
+    std::fs::create_dir_all(&data).map_err(|err| error::Error::Other(err.into()))?;
+    let started = if installed("chroma") {
+        eprintln!("Starting chroma with its data in {}", data.display());
+        Command::new("chroma")
+            .arg("run")
+            .arg("--path")
+            .arg(&data)
+            .args(["--port", &port.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .is_ok()
+    } else if installed("docker") {
+        eprintln!(
+            "Starting the {} container with its data in {}",
+            CONTAINER,
+            data.display()
+        );
+        // A container from a previous run keeps its data, start it again
+        Command::new("docker")
+            .args(["start", CONTAINER])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+            || Command::new("docker")
+                .args(["run", "-d", "--name", CONTAINER])
+                .args(["-p", &format!("{}:8000", port)])
+                .arg("-v")
+                .arg(format!("{}:/data", data.display()))
+                .arg("chromadb/chroma")
+                .stdout(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success())
+    } else {
+        return Err(unreachable(
+            "neither chroma nor docker are installed to start it, install one of them, or add --store local to keep the vectors on disk".to_string(),
+        ));
+    };
+    if !started {
+        return Err(unreachable("and it couldn't be started".to_string()));
+    }
+
+    let start = std::time::Instant::now();
+    while chroma.heartbeat().await.is_err() {
+        if start.elapsed() > STARTUP_TIMEOUT {
+            return Err(error::Error::Store(
+                format!(
+                    "Chroma didn't answer at {} after starting it",
+                    options.chroma_url
+                )
+                .into(),
+            ));
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    Ok(())
+}
+
+/// Whether a program can be run from the `PATH`.
+fn installed(program: &str) -> bool {
+    Command::new(program)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
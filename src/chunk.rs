@@ -0,0 +1,114 @@
+/// Split a document into chunks of at most `size` bytes, preferring paragraph
+/// boundaries, with each chunk starting with the last `overlap` bytes of the
+/// previous one.
+pub fn chunk(text: &str, size: usize, overlap: usize) -> Vec<String> {
+    if size == 0 || text.len() <= size {
+        return vec![text.to_string()];
+    }
+    let overlap = overlap.min(size / 2);
+
+    let mut pieces = vec![];
+    for paragraph in text.split_inclusive("\n\n") {
+        let mut paragraph = paragraph;
+        while paragraph.len() > size - overlap {
+            let split = floor_char_boundary(paragraph, size - overlap);
+            let split = paragraph[..split]
+                .rfind(' ')
+                .filter(|i| *i > 0)
+                .unwrap_or(split);
+            pieces.push(&paragraph[..split]);
+            paragraph = &paragraph[split..];
+        }
+        pieces.push(paragraph);
+    }
+
+    let mut chunks = vec![];
+    let mut current = String::new();
+    for piece in pieces {
+        if !current.is_empty() && current.len() + piece.len() > size {
+            let tail = &current[floor_char_boundary(&current, current.len() - overlap)..];
+            let tail = tail.to_string();
+            chunks.push(std::mem::replace(&mut current, tail));
+        }
+        current.push_str(piece);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Id of a chunk of the given document.
+pub fn chunk_id(parent: &str, index: usize) -> String {
+    format!("{}#{}", parent, index)
+}
+
+/// Parts of a document embedded on their own with --multi-vector, besides its text.
+pub const FACETS: &[&str] = &["name", "signature"];
+
+/// Id of the vector of a facet of the given document.
+pub fn facet_id(parent: &str, facet: &str) -> String {
+    format!("{}#{}", parent, facet)
+}
+
+/// Id of the document a chunk or facet belongs to.
+pub fn parent_id(id: &str) -> &str {
+    match id.rsplit_once('#') {
+        Some((parent, index)) if index.chars().all(|c| c.is_ascii_digit()) => parent,
+        Some((parent, facet)) if FACETS.contains(&facet) => parent,
+        _ => id,
+    }
+}
+
+pub fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_documents_are_one_chunk() {
+        assert_eq!(chunk("short text", 100, 10), ["short text"]);
+        assert_eq!(chunk("no size means no chunking", 0, 10).len(), 1);
+    }
+
+    #[test]
+    fn chunks_split_at_paragraphs_and_overlap() {
+        let text = "first paragraph here\n\nsecond paragraph here\n\nthird one";
+        let chunks = chunk(text, 30, 5);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 30));
+        assert_eq!(chunks[0], "first paragraph here\n\n");
+        for pair in chunks.windows(2) {
+            let tail = &pair[0][pair[0].len() - 5..];
+            assert!(pair[1].starts_with(tail));
+        }
+    }
+
+    #[test]
+    fn long_paragraphs_split_on_character_boundaries() {
+        let text = "é".repeat(40);
+        let chunks = chunk(&text, 15, 0);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 15));
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn parents_of_chunks_and_facets() {
+        assert_eq!(
+            parent_id(&chunk_id("struct:bevy::Foo", 2)),
+            "struct:bevy::Foo"
+        );
+        assert_eq!(
+            parent_id(&facet_id("fn:bevy::foo", "signature")),
+            "fn:bevy::foo"
+        );
+        assert_eq!(parent_id("guide:intro#setup"), "guide:intro#setup");
+        assert_eq!(parent_id("struct:bevy::Foo"), "struct:bevy::Foo");
+    }
+}
@@ -0,0 +1,160 @@
+/// Code blocks longer than this are cut, they say little more for their length.
+const MAX_CODE_LINES: usize = 30;
+
+/// HTML elements removed from the documentation, keeping the text inside them.
+const HTML_TAGS: &[&str] = &[
+    "a", "b", "br", "center", "code", "details", "div", "em", "h1", "h2", "h3", "h4", "h5", "h6",
+    "hr", "i", "img", "kbd", "li", "ol", "p", "picture", "pre", "source", "span", "strong", "sub",
+    "summary", "sup", "table", "tbody", "td", "th", "thead", "tr", "ul",
+];
+
+/// First paragraph of cleaned up documentation, on one line.
+pub fn summary(docs: &str) -> Option<String> {
+    let docs = clean(docs);
+    let first = docs.split("\n\n").next()?.trim();
+    (!first.is_empty()).then(|| first.replace('\n', " "))
+}
+
+/// Documentation cleaned up for embedding and display: without raw HTML, images
+/// like badges, the hidden lines of doctests, and with long code blocks cut.
+pub fn clean(docs: &str) -> String {
+    let mut cleaned = String::new();
+    // Language of the code block the line is in, and how many lines were kept
+    let mut code: Option<(bool, usize)> = None;
+    let mut in_comment = false;
+    for line in docs.lines() {
+        let trimmed = line.trim_start();
+        if let Some(info) = trimmed.strip_prefix("```") {
+            code = match code {
+                Some((_, lines)) => {
+                    if lines > MAX_CODE_LINES {
+                        cleaned.push_str("// ...\n");
+                    }
+                    None
+                }
+                None => Some((is_rust(info), 0)),
+            };
+            cleaned.push_str(line);
+            cleaned.push('\n');
+            continue;
+        }
+        if let Some((rust, lines)) = &mut code {
+            // Lines of doctests starting with `#` are hidden from the documentation
+            if *rust && (trimmed == "#" || trimmed.starts_with("# ")) {
+                continue;
+            }
+            *lines += 1;
+            if *lines <= MAX_CODE_LINES {
+                cleaned.push_str(line);
+                cleaned.push('\n');
+            }
+            continue;
+        }
+        let line = strip_html(line, &mut in_comment);
+        let line = strip_images(&line);
+        // Lines that only held a badge or a tag, and the blank lines they leave
+        if line.trim().is_empty()
+            && (!trimmed.is_empty() || cleaned.is_empty() || cleaned.ends_with("\n\n"))
+        {
+            continue;
+        }
+        cleaned.push_str(&line);
+        cleaned.push('\n');
+    }
+    cleaned.truncate(cleaned.trim_end().len());
+    cleaned
+}
+
+/// Whether the info string of a code block makes it a Rust doctest, as an empty
+/// one does.
+fn is_rust(info: &str) -> bool {
+    info.split([',', ' '])
+        .map(str::trim)
+        .filter(|attribute| !attribute.is_empty())
+        .all(|attribute| {
+            matches!(
+                attribute,
+                "rust" | "ignore" | "no_run" | "should_panic" | "compile_fail" | "test_harness"
+            ) || attribute.starts_with("edition")
+        })
+}
+
+/// The line without HTML comments and tags of known elements, outside of code spans.
+fn strip_html(line: &str, in_comment: &mut bool) -> String {
+    let mut stripped = String::new();
+    let mut rest = line;
+    let mut in_code = false;
+    while !rest.is_empty() {
+        if *in_comment {
+            match rest.find("-->") {
+                Some(end) => {
+                    rest = &rest[end + 3..];
+                    *in_comment = false;
+                }
+                None => rest = "",
+            }
+            continue;
+        }
+        let c = rest.chars().next().unwrap();
+        if c == '`' {
+            in_code = !in_code;
+        } else if c == '<' && !in_code {
+            if let Some(comment) = rest.strip_prefix("<!--") {
+                rest = comment;
+                *in_comment = true;
+                continue;
+            }
+            if let Some(end) = html_tag(rest) {
+                rest = &rest[end..];
+                continue;
+            }
+        }
+        stripped.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    stripped
+}
+
+/// Length of the HTML tag of a known element the text starts with, if any.
+fn html_tag(text: &str) -> Option<usize> {
+    let name = text[1..].trim_start_matches('/');
+    let length = name
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(name.len());
+    if !HTML_TAGS.contains(&name[..length].to_ascii_lowercase().as_str()) {
+        return None;
+    }
+    let after = name[length..].chars().next()?;
+    if !matches!(after, '>' | ' ' | '/' | '\t') {
+        return None;
+    }
+    Some(text.find('>')? + 1)
+}
+
+/// The line without markdown images, also when they are links like most badges.
+fn strip_images(line: &str) -> String {
+    let mut stripped = line.to_string();
+    while let Some(start) = stripped.find("![") {
+        let Some(end) = image_end(&stripped[start..]) else {
+            break;
+        };
+        let mut end = start + end;
+        let mut start = start;
+        // `[![badge](image)](link)`
+        if stripped[..start].ends_with('[') && stripped[end..].starts_with("](") {
+            if let Some(link) = stripped[end..].find(')') {
+                start -= 1;
+                end += link + 1;
+            }
+        }
+        stripped.replace_range(start..end, "");
+    }
+    stripped
+}
+
+/// Length of the `![alt](url)` image the text starts with.
+fn image_end(text: &str) -> Option<usize> {
+    let alt = text.find("](")?;
+    let url = text[alt..].find(')')?;
+    Some(alt + url + 1)
+}
@@ -0,0 +1,132 @@
+use serde_json::{Map, Value};
+
+use crate::{
+    cache::content_hash, document::SCHEMA_VERSION, embedding::Prefixes, embedding::Provider,
+};
+
+/// Maximum length of a collection name accepted by Chroma.
+const MAX_NAME_LENGTH: usize = 63;
+
+/// Everything that influences the vectors stored in a collection.
+pub struct CollectionConfig<'a> {
+    pub project: &'a str,
+    /// Version the collection is pinned to, to keep several side by side
+    pub version: Option<&'a str>,
+    pub provider: Provider,
+    pub model: &'a str,
+    pub space: &'static str,
+    pub dimensions: Option<usize>,
+    pub prefixes: &'a Prefixes,
+    pub normalize: bool,
+    pub quantized: bool,
+    /// Whether the facets of documents have vectors of their own
+    pub multi_vector: bool,
+}
+
+impl CollectionConfig<'_> {
+    /// A readable `project--model--distance` name, with suffixes for non-default settings.
+    pub fn name(&self) -> String {
+        let mut parts = vec![self.project.to_string()];
+        if let Some(version) = self.version {
+            parts.push(format!("v{}", version));
+        }
+        if self.provider != Provider::Ollama {
+            parts.push(format!("{:?}", self.provider).to_lowercase());
+        }
+        parts.push(self.model.to_string());
+        parts.push(self.space.to_string());
+        if let Some(dimensions) = self.dimensions {
+            parts.push(format!("d{}", dimensions));
+        }
+        if self.quantized {
+            parts.push("int8".to_string());
+        }
+        if self.multi_vector {
+            parts.push("mv".to_string());
+        }
+        if self.prefixes.document != Prefixes::for_model(self.model).document
+            || self.prefixes.query != Prefixes::for_model(self.model).query
+        {
+            let prefixes = format!("{}\n{}", self.prefixes.document, self.prefixes.query);
+            parts.push(format!("p{}", &content_hash(&prefixes)[..8]));
+        }
+
+        let name = parts
+            .join("--")
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect::<String>()
+            .replace("..", "-");
+        let name = name.trim_matches(|c: char| !c.is_ascii_alphanumeric());
+        if name.len() <= MAX_NAME_LENGTH {
+            name.to_string()
+        } else {
+            format!(
+                "{}-{}",
+                name[..MAX_NAME_LENGTH - 9].trim_end_matches(|c: char| !c.is_ascii_alphanumeric()),
+                &content_hash(name)[..8]
+            )
+        }
+    }
+
+    pub fn metadata(&self) -> Map<String, Value> {
+        let mut metadata = Map::new();
+        metadata.insert("hnsw:space".to_string(), self.space.into());
+        metadata.insert("project".to_string(), self.project.into());
+        metadata.insert(
+            "provider".to_string(),
+            format!("{:?}", self.provider).to_lowercase().into(),
+        );
+        metadata.insert("model".to_string(), self.model.into());
+        metadata.insert(
+            "dimensions".to_string(),
+            self.dimensions.map(|d| d as i64).unwrap_or(0).into(),
+        );
+        metadata.insert(
+            "document_prefix".to_string(),
+            self.prefixes.document.clone().into(),
+        );
+        metadata.insert(
+            "query_prefix".to_string(),
+            self.prefixes.query.clone().into(),
+        );
+        metadata.insert("normalize".to_string(), self.normalize.into());
+        metadata.insert("multi_vector".to_string(), self.multi_vector.into());
+        metadata.insert("tool_version".to_string(), env!("CARGO_PKG_VERSION").into());
+        metadata.insert("document_schema".to_string(), SCHEMA_VERSION.into());
+        metadata
+    }
+
+    /// Settings of the stored collection that differ from this configuration.
+    pub fn mismatches(&self, stored: &Map<String, Value>) -> Vec<String> {
+        let expected = self.metadata();
+        [
+            "hnsw:space",
+            "provider",
+            "model",
+            "dimensions",
+            "document_prefix",
+            "query_prefix",
+            "normalize",
+            "multi_vector",
+        ]
+        .into_iter()
+        .filter_map(|key| {
+            let stored = stored.get(key)?;
+            let expected = &expected[key];
+            (stored != expected).then(|| {
+                format!(
+                    "{}: indexed with {}, but {} was requested",
+                    key, stored, expected
+                )
+            })
+        })
+        .collect()
+    }
+}
@@ -0,0 +1,157 @@
+use std::error::Error;
+
+use chromadb::v2::ChromaClient;
+use serde_json::{Map, Value};
+
+use rustdoc_rag::{
+    local_store::Quantization, manifest::Manifest, store::VectorStore, Options, StoreKind,
+};
+
+use crate::stats;
+
+/// Names of the collections in the store, sorted.
+async fn names(options: &Options, chroma: &ChromaClient) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut names = match options.store {
+        StoreKind::Chroma => chroma
+            .list_collections()
+            .await?
+            .iter()
+            .map(|collection| collection.name().to_string())
+            .collect(),
+        StoreKind::Local => std::fs::read_dir(options.out_dir.join("index"))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "bin"))
+            .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+            .collect::<Vec<_>>(),
+    };
+    names.sort();
+    Ok(names)
+}
+
+/// Open an existing collection, without creating it when it's missing.
+async fn open(
+    options: &Options,
+    chroma: &ChromaClient,
+    name: &str,
+) -> Result<Option<VectorStore>, Box<dyn Error>> {
+    if !names(options, chroma)
+        .await?
+        .iter()
+        .any(|known| known == name)
+    {
+        return Ok(None);
+    }
+    Ok(Some(match options.store {
+        StoreKind::Chroma => {
+            VectorStore::Chroma(chroma.get_collection(name).await?, options.retry())
+        }
+        StoreKind::Local => {
+            VectorStore::open_local(
+                options.local_index_path(name),
+                Map::new(),
+                Quantization::None,
+                false,
+            )?
+            .0
+        }
+    }))
+}
+
+fn missing(name: &str) -> ! {
+    eprintln!("Couldn't find collection {}", name);
+    eprintln!("List them with `rustdoc-rag collections list`");
+    std::process::exit(1);
+}
+
+/// A collection of the store, with the metadata it was created with.
+pub struct Summary {
+    pub name: String,
+    pub entries: usize,
+    pub metadata: Map<String, Value>,
+}
+
+pub async fn summaries(
+    options: &Options,
+    chroma: &ChromaClient,
+) -> Result<Vec<Summary>, Box<dyn Error>> {
+    let mut summaries = vec![];
+    for name in names(options, chroma).await? {
+        let Some(store) = open(options, chroma, &name).await? else {
+            continue;
+        };
+        summaries.push(Summary {
+            entries: store.ids().await?.len(),
+            metadata: store.metadata().unwrap_or_default(),
+            name,
+        });
+    }
+    Ok(summaries)
+}
+
+pub async fn list(options: &Options, chroma: &ChromaClient) -> Result<(), Box<dyn Error>> {
+    let summaries = summaries(options, chroma).await?;
+    if summaries.is_empty() {
+        println!("No collections");
+        return Ok(());
+    }
+    let width = summaries
+        .iter()
+        .map(|summary| summary.name.len())
+        .max()
+        .unwrap_or(0);
+    for summary in summaries {
+        println!(
+            "{:width$}  {:>7} entries  {}",
+            summary.name,
+            summary.entries,
+            console::style(
+                summary
+                    .metadata
+                    .get("model")
+                    .and_then(Value::as_str)
+                    .unwrap_or("(unknown model)")
+            )
+            .dim(),
+        );
+    }
+    Ok(())
+}
+
+pub async fn info(
+    options: &Options,
+    chroma: &ChromaClient,
+    name: &str,
+) -> Result<(), Box<dyn Error>> {
+    let Some(store) = open(options, chroma, name).await? else {
+        missing(name);
+    };
+    let size = match options.store {
+        StoreKind::Chroma => None,
+        StoreKind::Local => Some(std::fs::metadata(options.local_index_path(name))?.len()),
+    };
+    stats::print(name, &store, size).await
+}
+
+pub async fn delete(
+    options: &Options,
+    chroma: &ChromaClient,
+    name: &str,
+) -> Result<(), Box<dyn Error>> {
+    if !names(options, chroma)
+        .await?
+        .iter()
+        .any(|known| known == name)
+    {
+        missing(name);
+    }
+    match options.store {
+        StoreKind::Chroma => chroma.delete_collection(name).await?,
+        StoreKind::Local => std::fs::remove_file(options.local_index_path(name))?,
+    }
+    Manifest::load(&options.out_dir, name).delete()?;
+    println!("Deleted collection {}", name);
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use futures::StreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{answer, graph::Graph, llm::Llm};
+
+/// Smallest group of items worth summarizing
+const MIN_MEMBERS: usize = 3;
+
+/// Label propagation rounds at most, it usually settles in a few
+const MAX_ROUNDS: usize = 20;
+
+/// A group of items more connected to each other than to the rest of the graph,
+/// with a summary of what they do together written by the chat model.
+#[derive(Serialize, Deserialize)]
+pub struct Community {
+    pub title: String,
+    pub summary: String,
+    pub members: Vec<String>,
+}
+
+impl Community {
+    /// Id of the community among the documents given to the chat model.
+    pub fn id(&self) -> String {
+        format!("community:{}", self.title)
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = format!("{}\n\n", self.title);
+        if !self.summary.is_empty() {
+            text.push_str(&format!("{}\n\n", self.summary));
+        }
+        text.push_str(&format!("It involves {}.", self.members.join(", ")));
+        text
+    }
+}
+
+/// The communities of the item graph, for --graph-rag to answer questions about
+/// how parts of the project relate.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Communities {
+    communities: Vec<Community>,
+    /// Community of every member, by path
+    #[serde(skip)]
+    by_member: HashMap<String, usize>,
+}
+
+impl Communities {
+    pub fn path(out_dir: &Path, project: &str) -> PathBuf {
+        out_dir
+            .join("communities")
+            .join(format!("{}.json", project))
+    }
+
+    /// Detect the communities of the graph and summarize each from the documents of
+    /// its members, by path.
+    pub async fn build(
+        graph: &Graph,
+        documents: &BTreeMap<String, String>,
+        llm: &Llm,
+        project: &str,
+        context_tokens: usize,
+        concurrency: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        let groups = detect(graph)
+            .into_iter()
+            .filter(|members| members.len() >= MIN_MEMBERS)
+            .collect::<Vec<_>>();
+        let progress = ProgressBar::new(groups.len() as u64);
+        progress.set_style(
+            ProgressStyle::with_template("{msg:>10} [{bar:40}] {pos}/{len} ({per_sec}, ETA {eta})")
+                .unwrap()
+                .progress_chars("=> "),
+        );
+        progress.set_message("summaries");
+        let communities = futures::stream::iter(groups)
+            .map(|members| async {
+                let context = members
+                    .iter()
+                    .filter_map(|path| Some((path.clone(), documents.get(path)?.clone())))
+                    .collect();
+                let reply =
+                    summarize(llm, project, answer::pack_context(context, context_tokens)).await?;
+                progress.inc(1);
+                let (title, summary) = reply.trim().split_once('\n').unwrap_or((&reply, ""));
+                Ok::<_, Box<dyn Error>>(Community {
+                    title: title.trim().trim_matches(['#', '*', ' ']).to_string(),
+                    summary: summary.trim().to_string(),
+                    members,
+                })
+            })
+            .buffered(concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        progress.finish();
+        Ok(Communities::new(communities))
+    }
+
+    fn new(communities: Vec<Community>) -> Self {
+        let by_member = communities
+            .iter()
+            .enumerate()
+            .flat_map(|(i, community)| community.members.iter().map(move |path| (path.clone(), i)))
+            .collect();
+        Communities {
+            communities,
+            by_member,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Option<Self> {
+        let loaded: Communities =
+            serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()?;
+        Some(Communities::new(loaded.communities))
+    }
+
+    /// Community of the item with this path.
+    pub fn of(&self, path: &str) -> Option<&Community> {
+        self.by_member.get(path).map(|i| &self.communities[*i])
+    }
+}
+
+/// Groups of connected items found by label propagation: every item takes the
+/// most common label among its neighbours until labels stop changing. Items are
+/// visited in order and ties go to the smallest label, so that results are stable.
+fn detect(graph: &Graph) -> Vec<Vec<String>> {
+    let mut neighbours = BTreeMap::<&str, Vec<&str>>::new();
+    for (from, to, _) in graph.edges() {
+        neighbours.entry(from).or_default().push(to);
+        neighbours.entry(to).or_default().push(from);
+    }
+    let mut labels = neighbours
+        .keys()
+        .enumerate()
+        .map(|(i, node)| (*node, i))
+        .collect::<HashMap<_, _>>();
+    for _ in 0..MAX_ROUNDS {
+        let mut changed = false;
+        for (node, adjacent) in &neighbours {
+            let mut counts = BTreeMap::<usize, usize>::new();
+            for neighbour in adjacent {
+                *counts.entry(labels[neighbour]).or_default() += 1;
+            }
+            let Some(best) = counts
+                .iter()
+                .max_by(|(a, count_a), (b, count_b)| count_a.cmp(count_b).then(b.cmp(a)))
+                .map(|(label, _)| *label)
+            else {
+                continue;
+            };
+            if labels[node] != best {
+                labels.insert(node, best);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    let mut groups = BTreeMap::<usize, BTreeSet<String>>::new();
+    for (node, label) in labels {
+        groups.entry(label).or_default().insert(node.to_string());
+    }
+    groups
+        .into_values()
+        .map(|members| members.into_iter().collect())
+        .collect()
+}
+
+/// Ask for a title on the first line and a summary of a community.
+async fn summarize(
+    llm: &Llm,
+    project: &str,
+    documents: Vec<(String, String)>,
+) -> Result<String, Box<dyn Error>> {
+    let mut context = String::new();
+    for (path, text) in &documents {
+        context.push_str(&format!("## {}\n\n{}\n\n", path, text.trim()));
+    }
+    let messages = json!([
+        {
+            "role": "system",
+            "content": format!(
+                "You summarize parts of the API of the Rust project {}. Given the \
+                documentation of items that refer to each other, reply with a short title \
+                naming what they are about on the first line, then a paragraph explaining \
+                what they do together and how they relate. Only use the documentation \
+                provided.",
+                project
+            ),
+        },
+        { "role": "user", "content": context },
+    ]);
+    llm.chat(messages, |_| Ok(())).await
+}
@@ -0,0 +1,74 @@
+use std::collections::BTreeSet;
+
+use rustyline::{
+    completion::Completer, highlight::Highlighter, hint::Hinter, validate::Validator, Context,
+    Helper,
+};
+
+/// Commands of the interactive prompt.
+const COMMANDS: &[&str] = &[
+    ":copy", ":limit", ":next", ":open", ":prev", ":reindex", ":search", ":show", ":similar",
+];
+
+/// Most completions listed at once.
+const MAX_COMPLETIONS: usize = 100;
+
+/// Completes the names and paths of the indexed items, like `Commands` or
+/// `bevy_ecs::system::Commands`, and the commands of the prompt.
+pub struct Identifiers {
+    identifiers: BTreeSet<String>,
+}
+
+impl Identifiers {
+    pub fn new(identifiers: BTreeSet<String>) -> Self {
+        Identifiers { identifiers }
+    }
+}
+
+impl Completer for Identifiers {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let before = &line[..pos];
+        let start = before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !(c.is_alphanumeric() || *c == '_' || *c == ':'))
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let word = &before[start..];
+        if word.is_empty() {
+            return Ok((start, vec![]));
+        }
+        if start == 0 && word.starts_with(':') && !word.starts_with("::") {
+            let commands = COMMANDS
+                .iter()
+                .filter(|command| command.starts_with(word))
+                .map(|command| command.to_string())
+                .collect();
+            return Ok((start, commands));
+        }
+        let candidates = self
+            .identifiers
+            .range(word.to_string()..)
+            .take_while(|identifier| identifier.starts_with(word))
+            .take(MAX_COMPLETIONS)
+            .cloned()
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for Identifiers {
+    type Hint = String;
+}
+
+impl Highlighter for Identifiers {}
+
+impl Validator for Identifiers {}
+
+impl Helper for Identifiers {}
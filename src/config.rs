@@ -0,0 +1,100 @@
+use std::{error::Error, ffi::OsString, path::PathBuf};
+
+use clap::{parser::ValueSource, CommandFactory};
+use toml::{Table, Value};
+
+use crate::Args;
+
+/// Name of the configuration file looked up in the current directory.
+const PROJECT_CONFIG: &str = "rustdoc-rag.toml";
+
+/// Configuration files, the user-level one first so the project one overrides it.
+fn config_files() -> Vec<PathBuf> {
+    let mut files = vec![];
+    if let Some(dir) = dirs::config_dir() {
+        files.push(dir.join("rustdoc-rag").join("config.toml"));
+    }
+    files.push(PathBuf::from(PROJECT_CONFIG));
+    files
+}
+
+/// Add the options of the configuration files that aren't set on the command line
+/// to its arguments. Keys are the long names of the options, and a `[projects.<name>]`
+/// table overrides them for one project.
+pub fn with_config(args: Vec<OsString>) -> Result<Vec<OsString>, Box<dyn Error>> {
+    let mut config = Table::new();
+    let mut projects = Table::new();
+    for path in config_files() {
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let mut table = match content.parse::<Table>() {
+            Ok(table) => table,
+            Err(err) => {
+                eprintln!("Invalid configuration in {}", path.display());
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        };
+        if let Some(Value::Table(overrides)) = table.remove("projects") {
+            for (project, values) in overrides {
+                if let (Some(Value::Table(merged)), Value::Table(values)) =
+                    (projects.get_mut(&project), &values)
+                {
+                    merged.extend(values.clone());
+                } else {
+                    projects.insert(project, values);
+                }
+            }
+        }
+        config.extend(table);
+    }
+    if config.is_empty() && projects.is_empty() {
+        return Ok(args);
+    }
+
+    let command = Args::command();
+    let matches = command.clone().get_matches_from(&args);
+    let project = matches.get_one::<String>("project").unwrap();
+    let project = project
+        .strip_suffix(".json")
+        .map_or(project.as_str(), |path| {
+            path.rsplit('/').next().unwrap_or(path)
+        });
+    if let Some(Value::Table(overrides)) = projects.get(project) {
+        config.extend(overrides.clone());
+    }
+
+    let mut merged = vec![args[0].clone()];
+    for (key, value) in config {
+        let long = key.replace('_', "-");
+        let Some(arg) = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+        else {
+            eprintln!("Unknown option in configuration: {}", key);
+            std::process::exit(1);
+        };
+        // Flags and environment variables take precedence over the configuration
+        if matches!(
+            matches.value_source(arg.get_id().as_str()),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+        let values = match value {
+            Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                Value::Boolean(true) => merged.push(format!("--{}", long).into()),
+                Value::Boolean(false) => {}
+                Value::String(value) => merged.push(format!("--{}={}", long, value).into()),
+                value => merged.push(format!("--{}={}", long, value).into()),
+            }
+        }
+    }
+    merged.extend(args.into_iter().skip(1));
+    Ok(merged)
+}
@@ -0,0 +1,142 @@
+use std::{error::Error, path::PathBuf};
+
+use crate::error;
+
+/// Service the credentials are stored under in the OS keyring.
+const KEYRING_SERVICE: &str = "rustdoc-rag";
+
+/// Credentials of the remote services, by the environment variable giving them.
+pub const NAMES: &[&str] = &[
+    "OPENAI_API_KEY",
+    "COHERE_API_KEY",
+    "VOYAGE_API_KEY",
+    "CHROMA_TOKEN",
+    "DISCORD_TOKEN",
+    "DISCORD_PUBLIC_KEY",
+];
+
+/// File of credentials, a TOML table of names to values only its owner can read.
+pub fn file() -> Option<PathBuf> {
+    Some(
+        dirs::config_dir()?
+            .join("rustdoc-rag")
+            .join("credentials.toml"),
+    )
+}
+
+/// A credential from its environment variable, else from the credentials file,
+/// else from the OS keyring where `rustdoc-rag login` stores it.
+pub fn get(name: &str) -> Option<String> {
+    if let Ok(value) = std::env::var(name) {
+        return Some(value);
+    }
+    // An invalid file is reported when storing a credential in it
+    let table = read_file(true).unwrap_or_else(|err| {
+        tracing::warn!(%err, "ignoring the credentials file");
+        None
+    });
+    if let Some(value) = table.and_then(|mut table| table.remove(name)) {
+        return value.as_str().map(str::to_string);
+    }
+    keyring::Entry::new(KEYRING_SERVICE, name)
+        .ok()?
+        .get_password()
+        .ok()
+}
+
+/// Like [`get`], failing with how to give the credential when it's missing.
+pub fn require(name: &str, what: &str) -> error::Result<String> {
+    get(name).ok_or_else(|| error::Error::Credential {
+        name: name.to_string(),
+        what: what.to_string(),
+    })
+}
+
+/// Store a credential in the OS keyring, or in the credentials file.
+pub fn store(name: &str, value: &str, in_file: bool) -> Result<(), Box<dyn Error>> {
+    if !in_file {
+        keyring::Entry::new(KEYRING_SERVICE, name)?.set_password(value)?;
+        return Ok(());
+    }
+    let mut table = read_file(false)?.unwrap_or_default();
+    table.insert(name.to_string(), value.into());
+    write_file(&table)
+}
+
+/// Remove a credential from the OS keyring and the credentials file, returning
+/// whether it was in either.
+pub fn remove(name: &str) -> Result<bool, Box<dyn Error>> {
+    // Without a keyring, the credential can still be in the file
+    let mut removed = keyring::Entry::new(KEYRING_SERVICE, name)
+        .and_then(|entry| entry.delete_credential())
+        .is_ok();
+    if let Some(mut table) = read_file(false)? {
+        if table.remove(name).is_some() {
+            write_file(&table)?;
+            removed = true;
+        }
+    }
+    Ok(removed)
+}
+
+/// The credentials file, ignored when `private` and other users can read it.
+fn read_file(private: bool) -> error::Result<Option<toml::Table>> {
+    let Some(path) = file() else {
+        return Ok(None);
+    };
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::PermissionsExt;
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            return Ok(None);
+        };
+        let mode = metadata.permissions().mode();
+        if mode & 0o077 != 0 {
+            static WARNED: std::sync::Once = std::sync::Once::new();
+            WARNED.call_once(|| {
+                eprintln!(
+                    "Ignoring {} as other users can read it, restrict it with `chmod 600`",
+                    path.display()
+                )
+            });
+            return Ok(None);
+        }
+    }
+    content
+        .parse()
+        .map(Some)
+        .map_err(|source: toml::de::Error| error::Error::Invalid {
+            what: format!("credentials file {}", path.display()),
+            source: Box::new(source),
+        })
+}
+
+fn write_file(table: &toml::Table) -> Result<(), Box<dyn Error>> {
+    let Some(path) = file() else {
+        return Err(error::Error::Options(
+            "no configuration directory to write the credentials file to".to_string(),
+        )
+        .into());
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        if path.exists() {
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    std::io::Write::write_all(
+        &mut options.open(&path)?,
+        toml::to_string(table)?.as_bytes(),
+    )?;
+    Ok(())
+}
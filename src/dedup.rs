@@ -0,0 +1,73 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde_json::{Map, Value};
+
+use crate::{cache::content_hash, document::Document};
+
+/// Remove documents whose text is the same as another one once case, punctuation
+/// and whitespace are ignored, keeping the first by id. Returns the paths of the
+/// removed documents, by id of the document kept in their place.
+pub fn merge_duplicates(
+    documents: &mut BTreeMap<String, Document>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut representatives = HashMap::new();
+    let mut duplicates = BTreeMap::<String, Vec<String>>::new();
+    let mut removed = vec![];
+    for (id, document) in documents.iter() {
+        let key = (document.kind.clone(), fingerprint(&document.text));
+        match representatives.get(&key) {
+            Some(representative) => {
+                duplicates
+                    .entry(String::clone(representative))
+                    .or_default()
+                    .push(document.path.clone());
+                removed.push(id.clone());
+            }
+            None => {
+                representatives.insert(key, id.clone());
+            }
+        }
+    }
+    for id in removed {
+        documents.remove(&id);
+    }
+    duplicates
+}
+
+fn fingerprint(text: &str) -> String {
+    let normalized = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ");
+    content_hash(&normalized)
+}
+
+/// Keep only the best ranked result for each item, results being the same item
+/// when one's path is the path or a re-export of the other. Ids are `kind:path`,
+/// and re-export paths are read from the `aliases` metadata of the results.
+pub fn collapse_results(
+    results: Vec<(String, f32)>,
+    metadatas: &HashMap<String, Map<String, Value>>,
+) -> Vec<(String, f32)> {
+    let mut seen = HashSet::new();
+    results
+        .into_iter()
+        .filter(|(id, _)| {
+            let path = id.split_once(':').map_or(id.as_str(), |(_, path)| path);
+            let aliases = metadatas
+                .get(id)
+                .and_then(|metadata| metadata.get("aliases"))
+                .and_then(Value::as_str)
+                .into_iter()
+                .flat_map(|aliases| aliases.split(", "));
+            let paths = std::iter::once(path).chain(aliases).collect::<Vec<_>>();
+            if paths.iter().any(|path| seen.contains(*path)) {
+                return false;
+            }
+            seen.extend(paths.into_iter().map(str::to_string));
+            true
+        })
+        .collect()
+}
@@ -0,0 +1,125 @@
+use std::{collections::BTreeMap, error::Error, io::Write, path::Path};
+
+use serde_json::json;
+
+use rustdoc_rag::{answer, lexical::LexicalIndex, Options};
+
+/// Items added, removed and changed between two indexed versions of the project,
+/// from their generated documents.
+struct Diff {
+    added: Vec<(String, String)>,
+    removed: Vec<(String, String)>,
+    changed: Vec<(String, String, String)>,
+}
+
+impl Diff {
+    fn new(mut from: BTreeMap<String, String>, to: BTreeMap<String, String>) -> Self {
+        let mut diff = Diff {
+            added: vec![],
+            removed: vec![],
+            changed: vec![],
+        };
+        for (id, text) in to {
+            match from.remove(&id) {
+                None => diff.added.push((id, text)),
+                Some(previous) if previous != text => diff.changed.push((id, previous, text)),
+                Some(_) => {}
+            }
+        }
+        diff.removed = from.into_iter().collect();
+        diff
+    }
+}
+
+/// Documents of an indexed version of the project, by id.
+fn documents(options: &Options, version: &str) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+    let mut options = options.clone();
+    options.crate_version = Some(version.to_string());
+    options.pin_version(Path::new(""))?;
+    let Some(index) =
+        LexicalIndex::open(&LexicalIndex::path(&options.out_dir, &options.index_name()))?
+    else {
+        eprintln!(
+            "Couldn't find the keyword index of {}",
+            options.index_name()
+        );
+        eprintln!("Index it again with --crate-version {}", version);
+        std::process::exit(1);
+    };
+    index.documents()
+}
+
+/// Print the items that differ between two indexed versions, and with `summary`
+/// a migration guide written by the chat model from them.
+pub async fn run(
+    options: &Options,
+    from: &str,
+    to: &str,
+    summary: bool,
+) -> Result<(), Box<dyn Error>> {
+    let diff = Diff::new(documents(options, from)?, documents(options, to)?);
+    for (title, ids) in [
+        (
+            "Added",
+            diff.added.iter().map(|(id, _)| id).collect::<Vec<_>>(),
+        ),
+        ("Removed", diff.removed.iter().map(|(id, _)| id).collect()),
+        (
+            "Changed",
+            diff.changed.iter().map(|(id, _, _)| id).collect(),
+        ),
+    ] {
+        println!("{} ({})", console::style(title).bold(), ids.len());
+        for id in ids {
+            println!("  {}", id);
+        }
+        println!();
+    }
+    if !summary {
+        return Ok(());
+    }
+
+    // Removed and changed items are what users have to migrate from
+    let mut documents = vec![];
+    for (id, previous, text) in &diff.changed {
+        documents.push((
+            format!("changed {}", id),
+            format!("Before:\n{}\n\nAfter:\n{}", previous.trim(), text.trim()),
+        ));
+    }
+    for (id, text) in &diff.removed {
+        documents.push((format!("removed {}", id), text.clone()));
+    }
+    for (id, text) in &diff.added {
+        documents.push((format!("added {}", id), text.clone()));
+    }
+    let mut context = String::new();
+    for (id, text) in answer::pack_context(documents, options.context_tokens) {
+        context.push_str(&format!("## {}\n\n{}\n\n", id, text.trim()));
+    }
+    let messages = json!([
+        {
+            "role": "system",
+            "content": format!(
+                "You write migration guides for the Rust project {}. From the documentation \
+                of the items that changed between versions {} and {}, explain what users \
+                have to change in their code, grouped by area, with short before and after \
+                examples when the documentation allows it. Only mention changes that are in \
+                the documentation provided.",
+                options.project, from, to
+            ),
+        },
+        { "role": "user", "content": context },
+    ]);
+    let mut stdout = std::io::stdout();
+    options
+        .chat_model()?
+        .chat(messages, |token| {
+            print!("{}", token);
+            stdout.flush()?;
+            Ok(())
+        })
+        .await?;
+    println!();
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use std::{error::Error, rc::Rc};
+
+use bytes::Bytes;
+use hyper::{body::Incoming, Request, StatusCode};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use rustdoc_rag::{
+    answer::{self, Answerer},
+    credentials, docs_rs, Options, Retriever,
+};
+use serde_json::{json, Value};
+
+use crate::server;
+
+const API: &str = "https://discord.com/api/v10";
+
+/// Longest message Discord accepts
+const MAX_MESSAGE: usize = 2000;
+
+/// Results listed in a reply when not answering with a chat model
+const RESULTS: usize = 5;
+
+fn env(var: &str) -> rustdoc_rag::Result<String> {
+    credentials::require(var, "Discord credentials")
+}
+
+/// Answer the `/docs` command of a Discord application, registering it with the
+/// bot token of `DISCORD_TOKEN`. Discord sends the commands to the interactions
+/// endpoint of the application, which must point to the address listened on, and
+/// they are checked with the public key of `DISCORD_PUBLIC_KEY`.
+pub async fn serve(
+    options: Options,
+    retriever: Retriever,
+    addr: &str,
+) -> Result<(), Box<dyn Error>> {
+    let token = env("DISCORD_TOKEN")?;
+    let Some(public_key) = decode_hex(&env("DISCORD_PUBLIC_KEY")?) else {
+        eprintln!("DISCORD_PUBLIC_KEY isn't an hexadecimal key");
+        std::process::exit(1);
+    };
+
+    let client = reqwest::Client::new();
+    let application: Value = client
+        .get(format!("{}/applications/@me", API))
+        .header("authorization", format!("Bot {}", token))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let application_id = application["id"].as_str().unwrap_or_default().to_string();
+    client
+        .put(format!("{}/applications/{}/commands", API, application_id))
+        .header("authorization", format!("Bot {}", token))
+        .json(&json!([{
+            "name": "docs",
+            "description": format!("Search the documentation of {}", options.project),
+            "options": [{
+                "type": 3,
+                "name": "question",
+                "description": "What to look for",
+                "required": true,
+            }],
+        }]))
+        .send()
+        .await?
+        .error_for_status()?;
+    eprintln!("Registered /docs for application {}", application_id);
+
+    let state = Rc::new((options, retriever, client, public_key));
+    server::listen(addr, move |request| {
+        let state = state.clone();
+        async move {
+            match interaction(state, request).await {
+                Some(response) => server::json(StatusCode::OK, &response),
+                None => server::json(
+                    StatusCode::UNAUTHORIZED,
+                    &json!({ "error": "invalid request signature" }),
+                ),
+            }
+        }
+    })
+    .await
+}
+
+/// Reply to an interaction, or `None` if it isn't signed by Discord. Commands are
+/// acknowledged right away and answered by editing the reply once searched.
+async fn interaction(
+    state: Rc<(Options, Retriever, reqwest::Client, Vec<u8>)>,
+    request: Request<Incoming>,
+) -> Option<Value> {
+    let header = |name| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let signature = decode_hex(&header("x-signature-ed25519")?)?;
+    let timestamp = header("x-signature-timestamp")?;
+    let body: Bytes = server::read_bytes(request).await?;
+    let signed = [timestamp.as_bytes(), &body].concat();
+    UnparsedPublicKey::new(&ED25519, &state.3)
+        .verify(&signed, &signature)
+        .ok()?;
+
+    let interaction: Value = serde_json::from_slice(&body).ok()?;
+    match interaction["type"].as_u64() {
+        // Discord checking the endpoint
+        Some(1) => Some(json!({ "type": 1 })),
+        Some(2) => {
+            let question = interaction["data"]["options"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .find(|option| option["name"] == "question")
+                .and_then(|option| option["value"].as_str())
+                .unwrap_or_default()
+                .to_string();
+            let url = format!(
+                "{}/webhooks/{}/{}/messages/@original",
+                API,
+                interaction["application_id"].as_str().unwrap_or_default(),
+                interaction["token"].as_str().unwrap_or_default(),
+            );
+            // Members of a server, or users messaging the application directly
+            let user = interaction["member"]["user"]["id"]
+                .as_str()
+                .or(interaction["user"]["id"].as_str())
+                .map(|id| format!("discord:{}", id));
+            tokio::task::spawn_local(async move {
+                let (options, retriever, client, _) = &*state;
+                let content = match reply(options, retriever, &question, user.as_deref()).await {
+                    Ok(content) => content,
+                    Err(err) => format!("Couldn't search the documentation: {}", err),
+                };
+                if let Err(err) = client
+                    .patch(url)
+                    .json(&json!({ "content": content }))
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                {
+                    tracing::warn!(%err, "couldn't post the reply");
+                }
+            });
+            // Deferred reply, showing that the bot is thinking
+            Some(json!({ "type": 5 }))
+        }
+        _ => Some(json!({ "type": 4, "data": { "content": "Unsupported interaction" } })),
+    }
+}
+
+/// Message answering the question with docs.rs links to the documents used.
+async fn reply(
+    options: &Options,
+    retriever: &Retriever,
+    question: &str,
+    user: Option<&str>,
+) -> Result<String, Box<dyn Error>> {
+    let results = retriever
+        .search_from(user, question, retriever.filter())
+        .await?;
+    let link = |id: &str| {
+        let path = id.split_once(':').map_or(id, |(_, path)| path);
+        match results
+            .metadatas
+            .get(id)
+            .and_then(|metadata| docs_rs::item_url(id, metadata))
+        {
+            Some(url) => format!("[`{}`](<{}>)", path, url),
+            None => format!("`{}`", path),
+        }
+    };
+    let mut content = format!("> {}\n", question);
+    if options.answer {
+        let documents = retriever.answer_context(question, &results).await?;
+        let answer = Answerer::new(options.chat_model()?, &options.project)
+            .with_mode(options.answer_mode)
+            .answer_streaming(question, &documents, |_| Ok(()))
+            .await?;
+        let sources = answer::citations(&answer, &documents)
+            .into_iter()
+            .map(|(number, id)| format!("[{}] {}", number, link(id)))
+            .collect::<Vec<_>>();
+        content.push_str(&answer);
+        if !sources.is_empty() {
+            content.push_str(&format!("\n\n{}", sources.join("\n")));
+        }
+    } else if results.ranking.is_empty() {
+        content.push_str("Nothing found");
+    } else {
+        for (id, _) in results.ranking.iter().take(RESULTS) {
+            let summary = results
+                .texts
+                .get(id)
+                .and_then(|text| text.lines().map(str::trim).find(|line| !line.is_empty()))
+                .unwrap_or_default();
+            content.push_str(&format!("- {} {}\n", link(id), summary));
+        }
+    }
+    if content.len() > MAX_MESSAGE {
+        let end = rustdoc_rag::chunk::floor_char_boundary(&content, MAX_MESSAGE - 3);
+        content.truncate(end);
+        content.push_str("...");
+    }
+    Ok(content)
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.trim();
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
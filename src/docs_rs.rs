@@ -0,0 +1,219 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{Map, Value};
+
+use crate::{cargo, error, traversal, Options};
+
+/// Download the rustdoc JSON of a published crate from docs.rs, in the format version
+/// this tool reads, and point the options to it. Does nothing without `--from-docs-rs`.
+pub async fn use_docs_rs(options: &mut Options) -> Result<(), Box<dyn Error>> {
+    let Some(spec) = &options.from_docs_rs else {
+        return Ok(());
+    };
+    let (name, version) = spec.split_once('@').unwrap_or((spec, "latest"));
+    let Some(path) = download(name, version, &options.out_dir).await? else {
+        return Err(error::Error::Rustdoc(format!(
+            "docs.rs has no rustdoc JSON of {}, it only has them for recent builds, in format version {} when built by a nightly producing it",
+            spec,
+            rustdoc_types::FORMAT_VERSION
+        ))
+        .into());
+    };
+    options.jsons_dir = path.parent().map(PathBuf::from);
+    options.project = path.to_string_lossy().to_string();
+    Ok(())
+}
+
+/// Download from docs.rs the rustdoc JSON of the dependencies of the project that
+/// aren't in the jsons directory, in the version rustdoc links them to. Does
+/// nothing without `--fetch-missing`.
+pub async fn fetch_missing(options: &Options, project_json: &Path) -> Result<(), Box<dyn Error>> {
+    /// Just enough of a rustdoc JSON file to know its dependencies
+    #[derive(serde::Deserialize)]
+    struct Dependencies {
+        external_crates: HashMap<u32, rustdoc_types::ExternalCrate>,
+    }
+
+    if !options.fetch_missing {
+        return Ok(());
+    }
+    let roots = if project_json.is_dir() {
+        std::fs::read_dir(project_json)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "json")
+            })
+            .collect()
+    } else {
+        vec![project_json.to_path_buf()]
+    };
+    let jsons_dir = options.jsons_dir();
+    let mut fetched = vec![];
+    for root in roots {
+        let file = std::io::BufReader::new(std::fs::File::open(&root)?);
+        let Ok(dependencies) = serde_json::from_reader::<_, Dependencies>(file) else {
+            continue;
+        };
+        for krate in dependencies.external_crates.values() {
+            // Crates of the workspace aren't on docs.rs
+            let Some((name, version)) = release(krate).filter(|_| !is_std(krate)) else {
+                continue;
+            };
+            if fetched.contains(&name)
+                || options
+                    .exclude_crate
+                    .iter()
+                    .any(|pattern| traversal::matches_pattern(pattern, &krate.name))
+                || jsons_dir.join(format!("{}.json", krate.name)).exists()
+                || cached(&options.out_dir, &name, version.as_deref()).is_some()
+            {
+                continue;
+            }
+            let version = version.as_deref().unwrap_or("latest");
+            // The other dependencies are still worth fetching
+            if let Err(err) = download(&name, version, &options.out_dir).await {
+                eprintln!(
+                    "Couldn't download the rustdoc JSON of {}@{}: {}",
+                    name, version, err
+                );
+            }
+            fetched.push(name);
+        }
+    }
+    Ok(())
+}
+
+/// Whether a dependency is part of the standard library, or one of the crates it
+/// depends on which rustdoc links to the standard library documentation.
+pub fn is_std(krate: &rustdoc_types::ExternalCrate) -> bool {
+    cargo::STD_CRATES.contains(&krate.name.as_str())
+        || krate
+            .html_root_url
+            .as_deref()
+            .is_some_and(|url| url.starts_with("https://doc.rust-lang.org/"))
+}
+
+/// Name on docs.rs and version of a dependency, from the URL rustdoc links it to
+/// like `https://docs.rs/bevy_ecs/0.15.0/`. None for crates not on docs.rs.
+pub fn release(krate: &rustdoc_types::ExternalCrate) -> Option<(String, Option<String>)> {
+    let url = krate.html_root_url.as_deref()?;
+    let mut path = url.strip_prefix("https://docs.rs/")?.split('/');
+    let name = path.next().filter(|name| !name.is_empty())?;
+    let version = path
+        .next()
+        .filter(|version| !version.is_empty() && *version != "latest");
+    Some((name.to_string(), version.map(str::to_string)))
+}
+
+/// Path of the rustdoc JSON of a crate downloaded from docs.rs, in this version or
+/// the most recent one downloaded.
+pub fn cached(out_dir: &Path, name: &str, version: Option<&str>) -> Option<PathBuf> {
+    let file = format!("{}.json", name.replace('-', "_"));
+    let dir = out_dir.join("docs-rs").join(name);
+    match version {
+        Some(version) => Some(dir.join(version).join(file)).filter(|path| path.exists()),
+        None => std::fs::read_dir(&dir)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.path().join(&file))
+            .filter(|path| path.exists())
+            .max(),
+    }
+}
+
+/// Path of the rustdoc JSON of a crate release, downloading it unless already cached.
+/// The latest release is always looked up again. None when docs.rs doesn't have it.
+async fn download(
+    name: &str,
+    version: &str,
+    out_dir: &Path,
+) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let cache = |version: &str| {
+        out_dir
+            .join("docs-rs")
+            .join(name)
+            .join(version)
+            .join(format!("{}.json", name.replace('-', "_")))
+    };
+    if version != "latest" && cache(version).exists() {
+        tracing::debug!(name, version, "rustdoc JSON already downloaded");
+        return Ok(Some(cache(version)));
+    }
+
+    let url = format!(
+        "https://docs.rs/crate/{}/{}/json/{}",
+        name,
+        version,
+        rustdoc_types::FORMAT_VERSION
+    );
+    eprintln!("Downloading {}", url);
+    let response = reqwest::get(&url).await?;
+    if !response.status().is_success() {
+        eprintln!(
+            "Couldn't download the rustdoc JSON of {}@{}: {}",
+            name,
+            version,
+            response.status()
+        );
+        return Ok(None);
+    }
+    // Redirected to `/rustdoc-json/<name>/<version>/...` with the resolved version
+    let resolved = response
+        .url()
+        .path_segments()
+        .and_then(|mut segments| {
+            (segments.next() == Some("rustdoc-json")).then(|| segments.nth(1))?
+        })
+        .map_or(version.to_string(), str::to_string);
+    let compressed = response.bytes().await?;
+    let json = zstd::decode_all(compressed.as_ref())?;
+    let path = cache(&resolved);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(&path, json)?;
+    Ok(Some(path))
+}
+
+/// Page of the item on docs.rs, from the crate, version, module and name it was
+/// indexed with.
+pub fn item_url(id: &str, metadata: &Map<String, Value>) -> Option<String> {
+    let field = |name| metadata.get(name).and_then(Value::as_str);
+    let (kind, _) = id.split_once(':')?;
+    if kind == "source" {
+        return Some(format!(
+            "https://docs.rs/crate/{}/{}/source/{}#{}",
+            field("crate")?,
+            field("version").unwrap_or("latest"),
+            field("file")?,
+            metadata.get("line")?
+        ));
+    }
+    let name = field("name")?;
+    let module = field("module")?.replace("::", "/");
+    let page = match kind {
+        "module" => format!("{}/index.html", name),
+        "function" => format!("fn.{}.html", name),
+        "plugin" => format!("struct.{}.html", name),
+        "type alias" => format!("type.{}.html", name),
+        "trait alias" => format!("traitalias.{}.html", name),
+        "proc macro" | "macro" => format!("macro.{}.html", name),
+        kind => format!("{}.{}.html", kind, name),
+    };
+    let krate = field("crate")?;
+    // The standard library isn't on docs.rs
+    if cargo::STD_CRATES.contains(&krate) {
+        return Some(format!("https://doc.rust-lang.org/{}/{}", module, page));
+    }
+    Some(format!(
+        "https://docs.rs/{}/{}/{}/{}",
+        krate,
+        field("version").unwrap_or("latest"),
+        module,
+        page
+    ))
+}
@@ -0,0 +1,198 @@
+use std::path::Path;
+
+use chromadb::v2::ChromaClient;
+use serde::Deserialize;
+use serde_json::Value;
+
+use rustdoc_rag::{
+    embedding::Provider, llm::LlmProvider, traversal::MIN_FORMAT_VERSION, Options, StoreKind,
+};
+
+/// Just enough of a rustdoc JSON file to know if it can be read.
+#[derive(Deserialize)]
+struct FormatVersion {
+    format_version: u32,
+}
+
+/// Outcome of the checks, printed as they run with how to fix failures.
+#[derive(Default)]
+struct Checks {
+    failed: usize,
+}
+
+impl Checks {
+    fn pass(&self, check: &str) {
+        println!("{} {}", console::style("ok  ").green(), check);
+    }
+
+    fn fail(&mut self, check: &str, fixes: &[String]) {
+        self.failed += 1;
+        println!("{} {}", console::style("FAIL").red(), check);
+        for fix in fixes {
+            println!("     {}", fix);
+        }
+    }
+}
+
+/// Check that everything needed to index and query the project is available.
+/// Returns whether all checks passed.
+pub async fn run(options: &Options, chroma: &ChromaClient, project_json: &Path) -> bool {
+    let mut checks = Checks::default();
+
+    let ollama = match options.ollama() {
+        Ok(ollama) => Some(ollama),
+        Err(err) => {
+            checks.fail(
+                "the Ollama headers are valid",
+                &[format!("Fix --ollama-header: {}", err)],
+            );
+            None
+        }
+    };
+    let uses_ollama = options.provider == Provider::Ollama
+        || (options.llm == LlmProvider::Ollama && !options.offline);
+    if let Some(ollama) = ollama.filter(|_| uses_ollama) {
+        let models = match ollama
+            .client
+            .get(format!("{}api/tags", ollama.url))
+            .send()
+            .await
+        {
+            Ok(response) => response.json::<Value>().await.ok(),
+            Err(_) => None,
+        };
+        match &models {
+            Some(_) => checks.pass(&format!("Ollama is reachable at {}", ollama.url)),
+            None => checks.fail(
+                &format!("Ollama is reachable at {}", ollama.url),
+                &[
+                    "Start Ollama with `ollama serve`".to_string(),
+                    "or point to it with --ollama-url".to_string(),
+                ],
+            ),
+        }
+        let names = models
+            .iter()
+            .flat_map(|models| models["models"].as_array())
+            .flatten()
+            .filter_map(|model| model["name"].as_str())
+            .collect::<Vec<_>>();
+        let mut wanted = vec![];
+        if options.provider == Provider::Ollama {
+            wanted.extend(options.embedding.iter().map(String::as_str));
+        }
+        if options.llm == LlmProvider::Ollama && (options.answer || options.rerank || options.hyde)
+        {
+            wanted.push(&options.chat_model);
+        }
+        // Models can't be checked without Ollama
+        for model in wanted.into_iter().filter(|_| models.is_some()) {
+            let check = format!("model {} is available", model);
+            if names
+                .iter()
+                .any(|name| *name == model || *name == format!("{}:latest", model))
+            {
+                checks.pass(&check);
+            } else {
+                checks.fail(&check, &[format!("Pull it with `ollama pull {}`", model)]);
+            }
+        }
+    }
+
+    match options.store {
+        StoreKind::Chroma => {
+            let check = format!("Chroma is reachable at {}", options.chroma_url);
+            if chroma.heartbeat().await.is_ok() {
+                checks.pass(&check);
+            } else {
+                checks.fail(
+                    &check,
+                    &[
+                        "Start it with `docker run -p 8000:8000 chromadb/chroma`".to_string(),
+                        "or point to it with --chroma-url".to_string(),
+                    ],
+                );
+            }
+        }
+        StoreKind::Local => {
+            let check = format!("{} is writable", options.out_dir.display());
+            let probe = options.out_dir.join(".doctor");
+            let writable = std::fs::create_dir_all(&options.out_dir).is_ok()
+                && std::fs::write(&probe, b"").is_ok();
+            let _ = std::fs::remove_file(&probe);
+            if writable {
+                checks.pass(&check);
+            } else {
+                checks.fail(
+                    &check,
+                    &["Choose another directory with --out-dir".to_string()],
+                );
+            }
+        }
+    }
+
+    if project_json.exists() {
+        checks.pass(&format!("{} exists", project_json.display()));
+    } else {
+        checks.fail(
+            &format!("{} exists", project_json.display()),
+            &[
+                "Generate it in the project with".to_string(),
+                "RUSTDOCFLAGS=\"-Z unstable-options --output-format json\" cargo +nightly doc"
+                    .to_string(),
+            ],
+        );
+    }
+
+    let jsons_dir = options.jsons_dir();
+    let mut files = std::fs::read_dir(&jsons_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    let mut unsupported = vec![];
+    for path in &files {
+        let version = std::fs::File::open(path).ok().and_then(|file| {
+            serde_json::from_reader::<_, FormatVersion>(std::io::BufReader::new(file)).ok()
+        });
+        match version {
+            Some(version)
+                if (MIN_FORMAT_VERSION..=rustdoc_types::FORMAT_VERSION)
+                    .contains(&version.format_version) => {}
+            Some(version) => unsupported.push(format!(
+                "{} has format version {}",
+                path.display(),
+                version.format_version
+            )),
+            None => unsupported.push(format!("{} isn't a rustdoc JSON file", path.display())),
+        }
+    }
+    let check = format!(
+        "{} rustdoc JSON files in {} use format version {} or {}",
+        files.len(),
+        jsons_dir.display(),
+        MIN_FORMAT_VERSION,
+        rustdoc_types::FORMAT_VERSION
+    );
+    if unsupported.is_empty() {
+        checks.pass(&check);
+    } else {
+        unsupported.push(format!(
+            "Regenerate them with a nightly toolchain producing format version {}",
+            rustdoc_types::FORMAT_VERSION
+        ));
+        checks.fail(&check, &unsupported);
+    }
+
+    if checks.failed > 0 {
+        println!();
+        println!("{} checks failed", checks.failed);
+    }
+    checks.failed == 0
+}
@@ -0,0 +1,158 @@
+use std::{collections::BTreeSet, error::Error, fmt::Write, path::Path};
+
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use serde_json::{json, Map, Value};
+
+use crate::{cache::content_hash, chunk::floor_char_boundary};
+
+/// Longest file stem generated, well under the usual 255 bytes limit to leave
+/// room for the extension and chunk suffixes.
+const MAX_FILE_STEM_LENGTH: usize = 200;
+
+/// Version of the generated documents, bumped when their text or metadata change
+/// enough that collections built before should be indexed again.
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Formats documents are written in with --emit-docs, next to a JSON file of their
+/// metadata for the formats other than JSON.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum DocFormat {
+    /// The markdown text that is embedded
+    #[default]
+    Markdown,
+    /// The text without the markdown syntax
+    Text,
+    /// One object with the `id`, `path`, `kind`, `text` and `metadata` fields
+    Json,
+    /// The text rendered to an HTML fragment
+    Html,
+}
+
+/// A generated document, ready to be embedded.
+pub struct Document {
+    /// Fully-qualified path of the item, like `bevy_ecs::system::Query`
+    pub path: String,
+    pub kind: String,
+    pub text: String,
+    pub metadata: Map<String, Value>,
+}
+
+impl Document {
+    /// Id of the document in the vector store, unique across crates.
+    pub fn id(&self) -> String {
+        format!("{}:{}", self.kind, self.path)
+    }
+
+    /// Mention other public paths the item can be reached by, in the text and
+    /// metadata, with the names it is re-exported under like `Bar` for
+    /// `pub use Foo as Bar`.
+    pub fn add_aliases(&mut self, aliases: BTreeSet<String>) {
+        if aliases.is_empty() {
+            return;
+        }
+        let name = self.path.rsplit("::").next().unwrap_or_default();
+        let mut renames = aliases
+            .iter()
+            .filter_map(|alias| alias.rsplit("::").next())
+            .filter(|alias| *alias != name)
+            .collect::<Vec<_>>();
+        renames.sort();
+        renames.dedup();
+        let aliases = aliases
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(self.text, "It is also available as {}.\n\n", aliases).unwrap();
+        self.metadata.insert("aliases".to_string(), aliases.into());
+        if !renames.is_empty() {
+            let renames = renames.join(", ");
+            write!(self.text, "{} is re-exported as {}.\n\n", name, renames).unwrap();
+            self.metadata
+                .insert("exported_as".to_string(), renames.into());
+        }
+    }
+
+    /// Name usable as a file name: characters that aren't valid in filenames are
+    /// replaced, and long names are truncated with a hash of the full name to keep
+    /// them unique. The original name stays in the text and metadata.
+    pub fn file_stem(&self) -> String {
+        let stem = self
+            .path
+            .replace("::", ".")
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        let stem = stem.trim_start_matches('.');
+        if stem.len() <= MAX_FILE_STEM_LENGTH {
+            stem.to_string()
+        } else {
+            format!(
+                "{}-{}",
+                &stem[..floor_char_boundary(stem, MAX_FILE_STEM_LENGTH - 9)],
+                &content_hash(&self.path)[..8]
+            )
+        }
+    }
+
+    /// Write the document and its metadata under `<out_dir>/<kind>s/`, for debugging.
+    pub fn emit(&self, out_dir: &Path, format: DocFormat) -> Result<(), Box<dyn Error>> {
+        let dir = out_dir.join(format!("{}s", self.kind));
+        std::fs::create_dir_all(&dir)?;
+        let stem = self.file_stem();
+        let (extension, content) = match format {
+            DocFormat::Markdown => ("md", self.text.clone()),
+            DocFormat::Text => ("txt", plain_text(&self.text)),
+            DocFormat::Html => {
+                let mut html = String::new();
+                pulldown_cmark::html::push_html(&mut html, Parser::new(&self.text));
+                ("html", html)
+            }
+            DocFormat::Json => {
+                let document = json!({
+                    "id": self.id(),
+                    "path": self.path,
+                    "kind": self.kind,
+                    "text": self.text,
+                    "metadata": self.metadata,
+                });
+                std::fs::write(
+                    dir.join(format!("{}.json", stem)),
+                    serde_json::to_string_pretty(&document)?,
+                )?;
+                return Ok(());
+            }
+        };
+        std::fs::write(dir.join(format!("{}.{}", stem, extension)), content)?;
+        std::fs::write(
+            dir.join(format!("{}.json", stem)),
+            serde_json::to_string(&self.metadata)?,
+        )?;
+        Ok(())
+    }
+}
+
+/// Text of a markdown document without its syntax, keeping the paragraphs, list
+/// items and code blocks on their own lines.
+fn plain_text(markdown: &str) -> String {
+    let mut text = String::new();
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(content) | Event::Code(content) => text.push_str(&content),
+            Event::Start(Tag::Item) => text.push_str("- "),
+            Event::SoftBreak | Event::HardBreak | Event::End(TagEnd::Item) => text.push('\n'),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::CodeBlock) => {
+                text.push_str("\n\n")
+            }
+            Event::End(TagEnd::List(_)) => text.push('\n'),
+            _ => {}
+        }
+    }
+    text
+}
@@ -0,0 +1,218 @@
+use std::fmt::Write;
+
+use rustdoc_types::{GenericBound, GenericParamDefKind, Item, ItemEnum, Type, WherePredicate};
+
+use crate::{
+    cleanup,
+    document::Document,
+    generator::Context,
+    signature::type_name,
+    traversal::{item_metadata, item_path, CrateCatalog},
+};
+
+/// Most types an extension trait is implemented for, more is a trait types share.
+const MAX_EXTENDED_TYPES: usize = 3;
+
+/// A type receiving the methods of an extension trait.
+struct Extended {
+    /// How the text names it, like `Commands` or `types implementing Bundle`
+    name: String,
+    /// Canonical path of the type, when known
+    path: Option<String>,
+}
+
+/// Document of an extension trait like `EntityCommandsExt`, saying which methods it
+/// adds to which types since users look for them by the type. Traits named like
+/// `*Ext` or only implemented for a few types of other crates are extension
+/// traits, other traits aren't documented.
+pub fn document_extension(
+    item: &Item,
+    trait_: &rustdoc_types::Trait,
+    context: &Context,
+) -> Option<Document> {
+    let (current_crate, crates) = (context.current_crate, context.crates);
+    let krate = &crates[current_crate].as_ref().unwrap().1;
+    let name = item.name.clone().unwrap_or_default();
+    let impls = implementations(trait_, krate);
+    let named =
+        name.ends_with("Ext") || name.ends_with("Extension") || name.ends_with("Extensions");
+    let foreign = |ty: &Type| match ty {
+        Type::ResolvedPath(path) => krate
+            .paths
+            .get(&path.id)
+            .is_some_and(|summary| summary.crate_id != 0),
+        _ => false,
+    };
+    let extends_foreign = !impls.is_empty()
+        && impls.len() <= MAX_EXTENDED_TYPES
+        && impls.iter().all(|imp| foreign(&imp.for_));
+    if !(named || extends_foreign) || impls.is_empty() {
+        return None;
+    }
+    let mut extended = impls
+        .iter()
+        .map(|imp| extended(&imp.for_, &imp.generics, krate))
+        .map(|extended| Extended {
+            name: context.simplify(&extended.name),
+            ..extended
+        })
+        .collect::<Vec<_>>();
+    extended.dedup_by(|a, b| a.name == b.name);
+
+    let methods = trait_
+        .items
+        .iter()
+        .filter_map(|id| krate.index.get(id))
+        .filter(|method| matches!(method.inner, ItemEnum::Function(_)))
+        .filter_map(|method| {
+            Some((
+                method.name.clone()?,
+                method.docs.as_deref().and_then(cleanup::summary),
+            ))
+        })
+        .collect::<Vec<_>>();
+    if methods.is_empty() {
+        return None;
+    }
+    let names = methods
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+    let types = extended
+        .iter()
+        .map(|extended| extended.name.as_str())
+        .collect::<Vec<_>>();
+
+    let mut text = String::new();
+    write!(
+        text,
+        "{} is an extension trait. It adds the {} {} to {}.\n\n",
+        name,
+        if names.len() == 1 {
+            "method"
+        } else {
+            "methods"
+        },
+        names.join(", "),
+        types.join(", ")
+    )
+    .unwrap();
+    if let Some(docs) = &item.docs {
+        write!(text, "{}\n\n", cleanup::clean(docs)).unwrap();
+    }
+    writeln!(text, "Methods it adds to {}:", types.join(", ")).unwrap();
+    for (method, summary) in &methods {
+        match summary {
+            Some(summary) => writeln!(text, "- {}: {}", method, summary).unwrap(),
+            None => writeln!(text, "- {}", method).unwrap(),
+        }
+    }
+    text.push('\n');
+
+    let mut metadata = item_metadata(item, "trait", current_crate, crates);
+    metadata.insert("role".to_string(), "extension".into());
+    metadata.insert("extends".to_string(), types.join(", ").into());
+    metadata.insert("methods".to_string(), names.join(", ").into());
+    Some(Document {
+        path: item_path(item, current_crate, crates),
+        kind: "trait".to_string(),
+        text,
+        metadata,
+    })
+}
+
+/// Canonical paths of the types a trait is implemented for, which get its methods.
+pub fn extended_paths(
+    trait_: &rustdoc_types::Trait,
+    current_crate: usize,
+    crates: &CrateCatalog,
+) -> Vec<String> {
+    let krate = &crates[current_crate].as_ref().unwrap().1;
+    let mut paths = implementations(trait_, krate)
+        .into_iter()
+        .filter_map(|imp| extended(&imp.for_, &imp.generics, krate).path)
+        .collect::<Vec<_>>();
+    paths.dedup();
+    paths
+}
+
+fn implementations<'a>(
+    trait_: &rustdoc_types::Trait,
+    krate: &'a rustdoc_types::Crate,
+) -> Vec<&'a rustdoc_types::Impl> {
+    trait_
+        .implementations
+        .iter()
+        .filter_map(|id| match &krate.index.get(id)?.inner {
+            ItemEnum::Impl(imp) => Some(imp),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The type an impl is for, or the bounds of its generic parameter for blanket
+/// impls like `impl<T: Bundle> BundleExt for T`.
+fn extended(
+    ty: &Type,
+    generics: &rustdoc_types::Generics,
+    krate: &rustdoc_types::Crate,
+) -> Extended {
+    match ty {
+        Type::ResolvedPath(path) => Extended {
+            name: path
+                .name
+                .rsplit("::")
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            path: krate
+                .paths
+                .get(&path.id)
+                .map(|summary| summary.path.join("::")),
+        },
+        Type::Generic(param) => {
+            let mut bounds = generics
+                .params
+                .iter()
+                .filter(|def| def.name == *param)
+                .flat_map(|def| match &def.kind {
+                    GenericParamDefKind::Type { bounds, .. } => bounds.as_slice(),
+                    _ => &[],
+                })
+                .chain(
+                    generics
+                        .where_predicates
+                        .iter()
+                        .flat_map(|predicate| match predicate {
+                            WherePredicate::BoundPredicate {
+                                type_: Type::Generic(name),
+                                bounds,
+                                ..
+                            } if name == param => bounds.as_slice(),
+                            _ => &[],
+                        }),
+                )
+                .filter_map(|bound| match bound {
+                    GenericBound::TraitBound { trait_, .. } => {
+                        Some(trait_.name.rsplit("::").next().unwrap_or_default())
+                    }
+                    _ => None,
+                })
+                .filter(|bound| !matches!(*bound, "Sized" | "Send" | "Sync"))
+                .collect::<Vec<_>>();
+            bounds.dedup();
+            Extended {
+                name: if bounds.is_empty() {
+                    "every type".to_string()
+                } else {
+                    format!("types implementing {}", bounds.join(" and "))
+                },
+                path: None,
+            }
+        }
+        ty => Extended {
+            name: type_name(ty),
+            path: None,
+        },
+    }
+}
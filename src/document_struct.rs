@@ -1,30 +1,138 @@
-use std::io::Write;
+use std::fmt::Write;
 
-use crate::CrateCatalog;
+use serde_json::{Map, Value};
+
+use crate::{
+    cleanup,
+    document::Document,
+    generator::Context,
+    signature,
+    traversal::{find_path, item_metadata, item_path, CrateCatalog},
+};
 
 struct StructDocument {
     name: String,
+    path: String,
     docs: Option<String>,
     fields: Vec<Field>,
+    /// Roles in Bevy, from the traits it implements
+    roles: Vec<&'static str>,
+    /// Traits implemented with a derive macro, like `Clone`
+    derives: Vec<String>,
+    /// Other traits implemented in the source, like `Display`
+    traits: Vec<String>,
+    /// Traits from blanket impls and auto traits, with --blanket-impls
+    blanket_traits: Vec<String>,
+    methods: Vec<Method>,
+    /// Methods of each trait implemented
+    trait_methods: Vec<(String, Vec<Method>)>,
+    metadata: Map<String, Value>,
 }
 
+/// Bevy traits giving a struct its role, by name.
+const BEVY_ROLES: &[(&str, &str)] = &[
+    ("Plugin", "plugin"),
+    ("Resource", "resource"),
+    ("Component", "component"),
+    ("Event", "event"),
+];
+
+/// What derived traits allow doing with a type, by name.
+const CAPABILITIES: &[(&str, &str)] = &[
+    ("Clone", "cloned"),
+    ("Copy", "copied implicitly"),
+    ("Debug", "formatted with `{:?}`"),
+    ("Default", "created with default values"),
+    ("Deserialize", "deserialized with serde"),
+    ("Eq", "compared for equality"),
+    ("Hash", "hashed"),
+    ("Ord", "ordered"),
+    ("PartialEq", "compared for equality"),
+    ("PartialOrd", "compared for ordering"),
+    ("Reflect", "inspected and edited through reflection"),
+    ("Serialize", "serialized with serde"),
+];
+
 struct Field {
     name: String,
     docs: Option<String>,
 }
 
+struct Method {
+    name: String,
+    /// First paragraph of its documentation
+    summary: Option<String>,
+    /// Whether the trait provides it, the impl not overriding it
+    provided: bool,
+}
+
 pub fn document_struct(
     item: &rustdoc_types::Item,
     stru: &rustdoc_types::Struct,
-    current_crate: usize,
-    crates: &CrateCatalog,
-) {
-    std::fs::create_dir_all("out/structs").unwrap();
+    context: &Context,
+) -> Document {
+    let (current_crate, crates, blanket_impls) =
+        (context.current_crate, context.crates, context.blanket_impls);
+    let derives = derives(item, stru, current_crate, crates);
+    // Traits are named with their generic arguments, shortened like signatures
+    let simplified = |names: Vec<String>| {
+        names
+            .iter()
+            .map(|name| context.simplify(name))
+            .collect::<Vec<_>>()
+    };
+    let (traits, blanket_traits) = implemented_traits(stru, &derives, current_crate, crates);
+    let (traits, blanket_traits) = (simplified(traits), simplified(blanket_traits));
+    let (methods, trait_methods) = methods(stru, blanket_impls, current_crate, crates);
+    let trait_methods = trait_methods
+        .into_iter()
+        .map(|(trait_, methods)| (context.simplify(&trait_), methods))
+        .collect();
     let mut doc = StructDocument {
         name: item.name.as_ref().unwrap().to_string(),
-        docs: item.docs.clone(),
+        path: item_path(item, current_crate, crates),
+        docs: item.docs.as_deref().map(cleanup::clean),
         fields: vec![],
+        roles: bevy_roles(stru, current_crate, crates),
+        derives,
+        traits,
+        blanket_traits: if blanket_impls {
+            blanket_traits
+        } else {
+            vec![]
+        },
+        methods,
+        trait_methods,
+        metadata: item_metadata(item, "struct", current_crate, crates),
     };
+    if let Some(role) = doc.roles.first() {
+        doc.metadata.insert("role".to_string(), (*role).into());
+    }
+    if !doc.derives.is_empty() {
+        doc.metadata
+            .insert("derives".to_string(), doc.derives.join(", ").into());
+    }
+    let mut method_names = doc
+        .methods
+        .iter()
+        .chain(doc.trait_methods.iter().flat_map(|(_, methods)| methods))
+        .map(|method| method.name.as_str())
+        .collect::<Vec<_>>();
+    method_names.sort();
+    method_names.dedup();
+    if !method_names.is_empty() {
+        doc.metadata
+            .insert("methods".to_string(), method_names.join(", ").into());
+    }
+    let traits = [&doc.traits[..], &doc.blanket_traits[..]].concat();
+    if !traits.is_empty() {
+        doc.metadata
+            .insert("traits".to_string(), traits.join(", ").into());
+    }
+    doc.metadata.insert(
+        "signature".to_string(),
+        signature::struct_signature(item, stru, current_crate, crates).into(),
+    );
 
     match &stru.kind {
         rustdoc_types::StructKind::Unit => {}
@@ -33,9 +141,7 @@ pub fn document_struct(
             doc.fields = fields
                 .iter()
                 .map(|field| {
-                    let field = crates
-                        .get(current_crate)
-                        .unwrap()
+                    let field = crates[current_crate]
                         .as_ref()
                         .unwrap()
                         .1
@@ -44,29 +150,319 @@ pub fn document_struct(
                         .unwrap();
                     Field {
                         name: field.name.as_ref().unwrap().to_string(),
-                        docs: field.docs.clone(),
+                        docs: field.docs.as_deref().map(cleanup::clean),
                     }
                 })
                 .collect();
         }
     }
-    doc.write();
+    doc.into_document()
+}
+
+/// Roles of a struct in Bevy, from its implementations of Bevy traits like
+/// `Component`, most specific first.
+fn bevy_roles(
+    stru: &rustdoc_types::Struct,
+    current_crate: usize,
+    crates: &CrateCatalog,
+) -> Vec<&'static str> {
+    let krate = &crates[current_crate].as_ref().unwrap().1;
+    let implemented = stru
+        .impls
+        .iter()
+        .filter_map(|id| match &krate.index.get(id)?.inner {
+            rustdoc_types::ItemEnum::Impl(imp) => imp.trait_.as_ref(),
+            _ => None,
+        })
+        .filter(|trait_| is_bevy_trait(trait_, krate))
+        .map(|trait_| trait_.name.rsplit("::").next().unwrap_or_default())
+        .collect::<Vec<_>>();
+    BEVY_ROLES
+        .iter()
+        .filter(|(name, _)| implemented.contains(name))
+        .map(|(_, role)| *role)
+        .collect()
+}
+
+/// Traits derived by a struct, from its `#[derive]` attributes and the impls
+/// generated by derive macros.
+fn derives(
+    item: &rustdoc_types::Item,
+    stru: &rustdoc_types::Struct,
+    current_crate: usize,
+    crates: &CrateCatalog,
+) -> Vec<String> {
+    let krate = &crates[current_crate].as_ref().unwrap().1;
+    let mut derives = item
+        .attrs
+        .iter()
+        .filter_map(|attr| attr.strip_prefix("#[derive(")?.strip_suffix(")]"))
+        .flat_map(|names| names.split(','))
+        .map(|name| {
+            name.trim()
+                .rsplit("::")
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    derives.extend(
+        stru.impls
+            .iter()
+            .filter_map(|id| krate.index.get(id))
+            .filter(|imp| {
+                imp.attrs
+                    .iter()
+                    .any(|attr| attr == "#[automatically_derived]")
+            })
+            .filter_map(|imp| match &imp.inner {
+                rustdoc_types::ItemEnum::Impl(imp) => imp.trait_.as_ref(),
+                _ => None,
+            })
+            .map(|trait_| {
+                trait_
+                    .name
+                    .rsplit("::")
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            }),
+    );
+    // Marker implemented by the compiler along with `PartialEq`
+    derives.retain(|name| !name.is_empty() && name != "StructuralPartialEq");
+    derives.sort();
+    derives.dedup();
+    derives
+}
+
+/// Traits a struct implements other than the derived ones, keeping apart the ones
+/// from blanket impls and the auto traits the compiler implements.
+fn implemented_traits(
+    stru: &rustdoc_types::Struct,
+    derives: &[String],
+    current_crate: usize,
+    crates: &CrateCatalog,
+) -> (Vec<String>, Vec<String>) {
+    let krate = &crates[current_crate].as_ref().unwrap().1;
+    let mut traits = vec![];
+    let mut blanket_traits = vec![];
+    for imp in stru.impls.iter().filter_map(|id| krate.index.get(id)) {
+        let rustdoc_types::ItemEnum::Impl(imp) = &imp.inner else {
+            continue;
+        };
+        let Some(trait_) = &imp.trait_ else {
+            continue;
+        };
+        let name = trait_.name.rsplit("::").next().unwrap_or_default();
+        if name == "StructuralPartialEq" || derives.iter().any(|derive| derive == name) {
+            continue;
+        }
+        let name = trait_name(trait_);
+        let names = if imp.is_synthetic || imp.blanket_impl.is_some() {
+            &mut blanket_traits
+        } else {
+            &mut traits
+        };
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    traits.sort();
+    blanket_traits.sort();
+    (traits, blanket_traits)
+}
+
+/// Last segment of the path of a trait with its generic arguments, like `From<f32>`.
+fn trait_name(trait_: &rustdoc_types::Path) -> String {
+    format!(
+        "{}{}",
+        trait_.name.rsplit("::").next().unwrap_or_default(),
+        &signature::path_name(trait_)[trait_.name.len()..]
+    )
+}
+
+/// Methods of the inherent impls of a struct, and the methods of each trait it
+/// implements. Methods of traits without their own documentation take the one of
+/// the trait, and the provided methods the impl doesn't override are listed with
+/// it. Derived impls, blanket impls and auto traits are left out unless
+/// `blanket_impls`.
+fn methods(
+    stru: &rustdoc_types::Struct,
+    blanket_impls: bool,
+    current_crate: usize,
+    crates: &CrateCatalog,
+) -> (Vec<Method>, Vec<(String, Vec<Method>)>) {
+    let krate = &crates[current_crate].as_ref().unwrap().1;
+    let mut inherent = vec![];
+    let mut traits = vec![];
+    for item in stru.impls.iter().filter_map(|id| krate.index.get(id)) {
+        let rustdoc_types::ItemEnum::Impl(imp) = &item.inner else {
+            continue;
+        };
+        let method = |item: &rustdoc_types::Item, docs: Option<&str>, provided| {
+            Some(Method {
+                name: item.name.clone()?,
+                summary: item.docs.as_deref().or(docs).and_then(cleanup::summary),
+                provided,
+            })
+        };
+        let functions = imp
+            .items
+            .iter()
+            .filter_map(|id| krate.index.get(id))
+            .filter(|item| matches!(item.inner, rustdoc_types::ItemEnum::Function(_)));
+        let Some(trait_) = &imp.trait_ else {
+            inherent.extend(functions.filter_map(|item| method(item, None, false)));
+            continue;
+        };
+        let derived = item
+            .attrs
+            .iter()
+            .any(|attr| attr == "#[automatically_derived]");
+        if !blanket_impls && (derived || imp.is_synthetic || imp.blanket_impl.is_some()) {
+            continue;
+        }
+        let trait_methods = trait_methods(trait_, current_crate, crates);
+        let trait_docs = |name: &str| {
+            trait_methods
+                .iter()
+                .find(|item| item.name.as_deref() == Some(name))
+                .and_then(|item| item.docs.as_deref())
+        };
+        let mut methods = functions
+            .filter_map(|item| method(item, trait_docs(item.name.as_deref()?), false))
+            .collect::<Vec<_>>();
+        for name in &imp.provided_trait_methods {
+            if let Some(item) = trait_methods
+                .iter()
+                .find(|item| item.name.as_ref() == Some(name))
+            {
+                methods.extend(method(item, None, true));
+            }
+        }
+        if !methods.is_empty() {
+            traits.push((trait_name(trait_), methods));
+        }
+    }
+    inherent.sort_by(|a, b| a.name.cmp(&b.name));
+    traits.sort_by(|(a, _), (b, _)| a.cmp(b));
+    (inherent, traits)
+}
+
+/// Methods declared by a trait, from the crate defining it.
+fn trait_methods<'a>(
+    trait_: &rustdoc_types::Path,
+    current_crate: usize,
+    crates: &'a CrateCatalog,
+) -> Vec<&'a rustdoc_types::Item> {
+    let krate = &crates[current_crate].as_ref().unwrap().1;
+    let found = match krate.index.get(&trait_.id) {
+        Some(_) => Some((current_crate, trait_.id)),
+        None => krate.paths.get(&trait_.id).and_then(|summary| {
+            let path = summary.path.iter().map(String::as_str).collect::<Vec<_>>();
+            find_path(&path, crates)
+        }),
+    };
+    let Some((trait_crate, id)) = found else {
+        return vec![];
+    };
+    let trait_krate = &crates[trait_crate].as_ref().unwrap().1;
+    match trait_krate.index.get(&id).map(|item| &item.inner) {
+        Some(rustdoc_types::ItemEnum::Trait(trait_)) => trait_
+            .items
+            .iter()
+            .filter_map(|id| trait_krate.index.get(id))
+            .filter(|item| matches!(item.inner, rustdoc_types::ItemEnum::Function(_)))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Bullet list of methods with their summary, mentioning the ones a trait provides.
+fn write_methods(file: &mut String, methods: &[Method], trait_: Option<&str>) {
+    for method in methods {
+        write!(file, "- {}", method.name).unwrap();
+        if let (true, Some(trait_)) = (method.provided, trait_) {
+            write!(file, ", provided by {}", trait_).unwrap();
+        }
+        match &method.summary {
+            Some(summary) => writeln!(file, ": {}", summary).unwrap(),
+            None => file.push('\n'),
+        }
+    }
+    file.push('\n');
+}
+
+/// Items of a list in a sentence, like `a, b and c`.
+fn sentence_list<S: AsRef<str>>(items: &[S]) -> String {
+    let items = items.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+    match items.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+/// Whether a trait is from a Bevy crate. Traits not in the paths of the crate can't
+/// be told apart and aren't.
+pub fn is_bevy_trait(trait_: &rustdoc_types::Path, krate: &rustdoc_types::Crate) -> bool {
+    krate.paths.get(&trait_.id).is_some_and(|summary| {
+        summary
+            .path
+            .first()
+            .is_some_and(|krate| krate.starts_with("bevy"))
+    })
 }
 
 impl StructDocument {
-    pub fn write(&self) {
-        let mut file = std::fs::File::create(format!("out/structs/{}.md", self.name)).unwrap();
+    pub fn into_document(self) -> Document {
+        let mut file = String::new();
 
         write!(file, "{} is a struct.\n\n", self.name).unwrap();
+        if !self.roles.is_empty() {
+            write!(file, "It is a Bevy {}.\n\n", self.roles.join(" and ")).unwrap();
+        }
+        if !self.derives.is_empty() {
+            let mut capabilities = vec![];
+            for (name, capability) in CAPABILITIES {
+                if self.derives.iter().any(|derive| derive == name)
+                    && !capabilities.contains(capability)
+                {
+                    capabilities.push(*capability);
+                }
+            }
+            write!(file, "It derives {}", sentence_list(&self.derives)).unwrap();
+            if !capabilities.is_empty() {
+                write!(file, ", so it can be {}", sentence_list(&capabilities)).unwrap();
+            }
+            file.push_str(".\n\n");
+        }
+        if !self.traits.is_empty() {
+            write!(file, "It implements {}.\n\n", sentence_list(&self.traits)).unwrap();
+        }
+        if !self.blanket_traits.is_empty() {
+            write!(
+                file,
+                "Through blanket impls and auto traits, it also implements {}.\n\n",
+                sentence_list(&self.blanket_traits)
+            )
+            .unwrap();
+        }
         if let Some(docs) = &self.docs {
             write!(file, "{}\n\n", docs).unwrap();
         }
         if !self.fields.is_empty() {
-            write!(file, "It has the following fields: ").unwrap();
-            for field in &self.fields {
-                write!(file, "{}, ", field.name).unwrap();
-            }
-            write!(file, "\n\n").unwrap();
+            let names = self
+                .fields
+                .iter()
+                .map(|field| field.name.as_str())
+                .collect::<Vec<_>>();
+            write!(
+                file,
+                "It has the following fields: {}.\n\n",
+                names.join(", ")
+            )
+            .unwrap();
 
             for field in &self.fields {
                 if let Some(docs) = &field.docs {
@@ -75,5 +471,20 @@ impl StructDocument {
                 }
             }
         }
+        if !self.methods.is_empty() {
+            file.push_str("It has the following methods:\n");
+            write_methods(&mut file, &self.methods, None);
+        }
+        for (trait_, methods) in &self.trait_methods {
+            writeln!(file, "From its implementation of {}, it has:", trait_).unwrap();
+            write_methods(&mut file, methods, Some(trait_));
+        }
+
+        Document {
+            path: self.path,
+            kind: "struct".to_string(),
+            text: file,
+            metadata: self.metadata,
+        }
     }
 }
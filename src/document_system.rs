@@ -0,0 +1,287 @@
+use std::fmt::Write;
+
+use rustdoc_types::{GenericArg, GenericArgs, Type};
+
+use crate::{
+    cleanup,
+    document::Document,
+    signature::type_name,
+    traversal::{item_metadata, item_path, CrateCatalog},
+};
+
+/// Parameters of Bevy systems, by the name of their type.
+const SYSTEM_PARAMS: &[&str] = &[
+    "Query",
+    "Single",
+    "Populated",
+    "Res",
+    "ResMut",
+    "NonSend",
+    "NonSendMut",
+    "Commands",
+    "EventReader",
+    "EventWriter",
+    "MessageReader",
+    "MessageWriter",
+    "Local",
+    "ParamSet",
+    "Trigger",
+    "On",
+];
+
+/// What a system does with the world through its parameters.
+#[derive(Default)]
+struct Access {
+    reads: Vec<String>,
+    writes: Vec<String>,
+    lines: Vec<String>,
+}
+
+impl Access {
+    fn read(&mut self, name: String) {
+        if !self.reads.contains(&name) {
+            self.reads.push(name);
+        }
+    }
+
+    fn write(&mut self, name: String) {
+        if !self.writes.contains(&name) {
+            self.writes.push(name);
+        }
+    }
+}
+
+/// Document of a function taking Bevy system parameters, saying what it accesses.
+/// Other functions aren't documented.
+pub fn document_system(
+    item: &rustdoc_types::Item,
+    function: &rustdoc_types::Function,
+    current_crate: usize,
+    crates: &CrateCatalog,
+) -> Option<Document> {
+    let params = function
+        .sig
+        .inputs
+        .iter()
+        .filter(|(_, ty)| system_param(ty).is_some())
+        .collect::<Vec<_>>();
+    if params.is_empty() {
+        return None;
+    }
+    let name = item.name.clone().unwrap_or_default();
+    let mut access = Access::default();
+    for (_, ty) in &params {
+        describe_param(ty, &mut access);
+    }
+
+    let mut text = String::new();
+    write!(text, "{} is a Bevy system.\n\n", name).unwrap();
+    if let Some(docs) = &item.docs {
+        write!(text, "{}\n\n", cleanup::clean(docs)).unwrap();
+    }
+    text.push_str("This system accesses:\n");
+    for line in &access.lines {
+        writeln!(text, "- {}", line).unwrap();
+    }
+    text.push('\n');
+
+    let inputs = function
+        .sig
+        .inputs
+        .iter()
+        .map(|(name, ty)| format!("{}: {}", name, type_name(ty)))
+        .collect::<Vec<_>>();
+    let mut metadata = item_metadata(item, "function", current_crate, crates);
+    metadata.insert("role".to_string(), "system".into());
+    metadata.insert(
+        "signature".to_string(),
+        format!("fn {}({})", name, inputs.join(", ")).into(),
+    );
+    if !access.reads.is_empty() {
+        metadata.insert("reads".to_string(), access.reads.join(", ").into());
+    }
+    if !access.writes.is_empty() {
+        metadata.insert("writes".to_string(), access.writes.join(", ").into());
+    }
+    Some(Document {
+        path: item_path(item, current_crate, crates),
+        kind: "function".to_string(),
+        text,
+        metadata,
+    })
+}
+
+/// Name of the system parameter type, with its generic arguments.
+fn system_param(ty: &Type) -> Option<(&str, Vec<&Type>)> {
+    let Type::ResolvedPath(path) = ty else {
+        return None;
+    };
+    let name = path.name.rsplit("::").next().unwrap_or_default();
+    SYSTEM_PARAMS.contains(&name).then(|| (name, type_args(ty)))
+}
+
+fn type_args(ty: &Type) -> Vec<&Type> {
+    let Type::ResolvedPath(path) = ty else {
+        return vec![];
+    };
+    match path.args.as_deref() {
+        Some(GenericArgs::AngleBracketed { args, .. }) => args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArg::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Last segment of the path of a type, like `Transform`.
+fn base_name(ty: &Type) -> String {
+    match ty {
+        Type::ResolvedPath(path) => path
+            .name
+            .rsplit("::")
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        ty => type_name(ty),
+    }
+}
+
+fn describe_param(ty: &Type, access: &mut Access) {
+    let Some((name, args)) = system_param(ty) else {
+        return;
+    };
+    let first = args.first().map(|ty| base_name(ty)).unwrap_or_default();
+    match name {
+        "Query" | "Single" | "Populated" => {
+            let mut data = vec![];
+            if let Some(ty) = args.first() {
+                query_data(ty, access, &mut data);
+            }
+            let mut line = format!("queries entities for {}", data.join(", "));
+            let mut filters = vec![];
+            if let Some(ty) = args.get(1) {
+                query_filter(ty, &mut filters);
+            }
+            if !filters.is_empty() {
+                write!(line, " {}", filters.join(", ")).unwrap();
+            }
+            if name == "Single" {
+                line.push_str(", expecting exactly one");
+            }
+            access.lines.push(line);
+        }
+        "Res" | "NonSend" => {
+            access.lines.push(format!("reads the resource {}", first));
+            access.read(first);
+        }
+        "ResMut" | "NonSendMut" => {
+            access.lines.push(format!("writes the resource {}", first));
+            access.write(first);
+        }
+        "Commands" => access
+            .lines
+            .push("queues commands to spawn, despawn or change entities".to_string()),
+        "EventReader" | "MessageReader" => {
+            access.lines.push(format!("reads the events {}", first));
+            access.read(first);
+        }
+        "EventWriter" | "MessageWriter" => {
+            access.lines.push(format!("sends the events {}", first));
+            access.write(first);
+        }
+        "Trigger" | "On" => {
+            access
+                .lines
+                .push(format!("observes the event {} when it is triggered", first));
+            access.read(first);
+        }
+        "Local" => access
+            .lines
+            .push(format!("keeps local state of type {}", first)),
+        "ParamSet" => {
+            access
+                .lines
+                .push("uses conflicting parameters one at a time:".to_string());
+            for ty in args
+                .first()
+                .map(|ty| match ty {
+                    Type::Tuple(types) => types.iter().collect(),
+                    ty => vec![*ty],
+                })
+                .unwrap_or_default()
+            {
+                describe_param(ty, access);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// What a query fetches from its data, like `mut Transform`, recording the
+/// components it reads and writes.
+fn query_data(ty: &Type, access: &mut Access, data: &mut Vec<String>) {
+    match ty {
+        Type::Tuple(types) => {
+            for ty in types {
+                query_data(ty, access, data);
+            }
+        }
+        Type::BorrowedRef {
+            is_mutable, type_, ..
+        } => {
+            let name = base_name(type_);
+            if *is_mutable {
+                data.push(format!("{} (written)", name));
+                access.write(name);
+            } else {
+                data.push(format!("{} (read)", name));
+                access.read(name);
+            }
+        }
+        Type::ResolvedPath(path) if path.name.ends_with("Option") => {
+            if let Some(ty) = type_args(ty).first() {
+                let mut optional = vec![];
+                query_data(ty, access, &mut optional);
+                data.extend(
+                    optional
+                        .into_iter()
+                        .map(|name| format!("optionally {}", name)),
+                );
+            }
+        }
+        ty => data.push(base_name(ty)),
+    }
+}
+
+/// Conditions of a query filter, like `with Player`.
+fn query_filter(ty: &Type, filters: &mut Vec<String>) {
+    match ty {
+        Type::Tuple(types) => {
+            for ty in types {
+                query_filter(ty, filters);
+            }
+        }
+        Type::ResolvedPath(path) => {
+            let args = type_args(ty);
+            let first = args.first().map(|ty| base_name(ty)).unwrap_or_default();
+            match path.name.rsplit("::").next().unwrap_or_default() {
+                "With" => filters.push(format!("with {}", first)),
+                "Without" => filters.push(format!("without {}", first)),
+                "Changed" => filters.push(format!("when {} changed", first)),
+                "Added" => filters.push(format!("when {} was added", first)),
+                "Or" => {
+                    let mut alternatives = vec![];
+                    for ty in args {
+                        query_filter(ty, &mut alternatives);
+                    }
+                    filters.push(format!("either {}", alternatives.join(" or ")));
+                }
+                _ => filters.push(type_name(ty)),
+            }
+        }
+        ty => filters.push(type_name(ty)),
+    }
+}
@@ -0,0 +1,605 @@
+use std::{error::Error, io::Write};
+
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+use crate::{
+    cache::EmbeddingCache,
+    credentials,
+    endpoint::OllamaEndpoint,
+    error,
+    indexer::{Callback, Event},
+    lexical::split_identifiers,
+    retry::Retry,
+};
+
+/// Dimensions of the hashing embeddings when the model name doesn't give them
+pub const HASHING_DIMENSIONS: usize = 768;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, ValueEnum)]
+pub enum Provider {
+    Ollama,
+    Cohere,
+    Voyage,
+    /// Hashes the words of the text in process, needing no model or service. Much
+    /// less relevant than a model, the model name sets the dimensions like `hashing-768`
+    Hashing,
+}
+
+/// What the text being embedded will be used for. Some providers embed
+/// documents and queries differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputType {
+    Document,
+    Query,
+}
+
+/// Prefixes prepended to the text before embedding, for instruction-tuned models
+/// that expect to be told whether they're embedding a document or a query.
+#[derive(Debug, Clone, Default, Hash)]
+pub struct Prefixes {
+    pub document: String,
+    pub query: String,
+}
+
+impl Prefixes {
+    pub fn for_model(model: &str) -> Self {
+        let name = model.rsplit('/').next().unwrap_or(model);
+        let name = name.split(':').next().unwrap_or(name);
+        let (document, query) = if name.starts_with("nomic-embed-text") {
+            ("search_document: ", "search_query: ")
+        } else if name.starts_with("mxbai-embed-large")
+            || name.starts_with("snowflake-arctic-embed")
+            || name.starts_with("bge-")
+        {
+            (
+                "",
+                "Represent this sentence for searching relevant passages: ",
+            )
+        } else if name.contains("e5-") || name.starts_with("e5") {
+            ("passage: ", "query: ")
+        } else {
+            ("", "")
+        };
+        Prefixes {
+            document: document.to_string(),
+            query: query.to_string(),
+        }
+    }
+}
+
+/// Options of the embedding requests to Ollama, left to its defaults when unset.
+#[derive(Debug, Clone, Default)]
+pub struct OllamaOptions {
+    pub keep_alive: Option<String>,
+    pub num_ctx: Option<u32>,
+    pub truncate: Option<bool>,
+}
+
+pub struct Embedder {
+    backend: Backend,
+    prefixes: Prefixes,
+    dimensions: Option<usize>,
+    normalize: bool,
+    cache: Option<EmbeddingCache>,
+    retry: Option<Retry>,
+}
+
+enum Backend {
+    Ollama(SimpleOllama),
+    Cohere(Cohere),
+    Voyage(Voyage),
+    Hashing(Hashing),
+}
+
+impl Embedder {
+    pub fn new(
+        provider: Provider,
+        model: &str,
+        prefixes: Prefixes,
+        dimensions: Option<usize>,
+        normalize: bool,
+        ollama: &OllamaEndpoint,
+        ollama_options: OllamaOptions,
+    ) -> error::Result<Self> {
+        let backend = match provider {
+            Provider::Ollama => Backend::Ollama(SimpleOllama {
+                url: ollama.url.clone(),
+                client: ollama.client.clone(),
+                embedding_model: model.to_string(),
+                options: ollama_options,
+                events: None,
+            }),
+            Provider::Cohere => Backend::Cohere(Cohere {
+                client: reqwest::Client::new(),
+                api_key: credentials::require("COHERE_API_KEY", "Cohere API key")?,
+                model: model.to_string(),
+            }),
+            Provider::Voyage => Backend::Voyage(Voyage {
+                client: reqwest::Client::new(),
+                api_key: credentials::require("VOYAGE_API_KEY", "Voyage API key")?,
+                model: model.to_string(),
+            }),
+            Provider::Hashing => Backend::Hashing(Hashing {
+                dimensions: model
+                    .rsplit('-')
+                    .next()
+                    .and_then(|dimensions| dimensions.parse().ok())
+                    .filter(|dimensions| *dimensions > 0)
+                    .unwrap_or(HASHING_DIMENSIONS),
+            }),
+        };
+        Ok(Embedder {
+            backend,
+            prefixes,
+            dimensions,
+            normalize,
+            cache: None,
+            retry: None,
+        })
+    }
+
+    pub fn with_cache(mut self, cache: EmbeddingCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Retry the calls to the service that fail.
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Send the download progress of the model as events rather than to stderr.
+    pub(crate) fn with_events(mut self, events: Callback) -> Self {
+        if let Backend::Ollama(ollama) = &mut self.backend {
+            ollama.events = Some(events);
+        }
+        self
+    }
+
+    /// Make a call to the service, retried when it fails with a retry policy.
+    async fn call<T, F>(&self, mut call: impl FnMut() -> F) -> Result<T, Box<dyn Error>>
+    where
+        F: std::future::Future<Output = Result<T, Box<dyn Error>>>,
+    {
+        match &self.retry {
+            Some(retry) => retry.run("embedding", call).await,
+            None => call().await,
+        }
+        .map_err(|err| error::Error::embedding(err).into())
+    }
+
+    pub async fn prepare(&self) -> Result<(), Box<dyn Error>> {
+        match &self.backend {
+            Backend::Ollama(ollama) => ollama
+                .download_model()
+                .await
+                .map_err(|err| error::Error::embedding(err).into()),
+            Backend::Cohere(_) | Backend::Voyage(_) | Backend::Hashing(_) => Ok(()),
+        }
+    }
+
+    pub async fn embeddings(
+        &self,
+        document: &str,
+        input_type: InputType,
+    ) -> Result<Vec<f32>, Box<dyn Error>> {
+        let prefix = match input_type {
+            InputType::Document => &self.prefixes.document,
+            InputType::Query => &self.prefixes.query,
+        };
+        let document = format!("{}{}", prefix, document);
+        let cache = self
+            .cache
+            .as_ref()
+            .filter(|_| input_type == InputType::Document);
+        let mut embedding = match cache.and_then(|cache| cache.get(&document)) {
+            Some(embedding) => {
+                tracing::trace!("embedding found in cache");
+                embedding
+            }
+            None => {
+                let start = std::time::Instant::now();
+                let embedding = match &self.backend {
+                    Backend::Ollama(ollama) => self.call(|| ollama.embeddings(&document)).await?,
+                    Backend::Cohere(cohere) => {
+                        self.call(|| cohere.embeddings(&document, input_type))
+                            .await?
+                    }
+                    Backend::Voyage(voyage) => {
+                        self.call(|| voyage.embeddings(&document, input_type))
+                            .await?
+                    }
+                    Backend::Hashing(hashing) => hashing.embeddings(&document),
+                };
+                tracing::debug!(
+                    length = document.len(),
+                    seconds = start.elapsed().as_secs_f64(),
+                    "embedded"
+                );
+                if let Some(cache) = cache {
+                    cache.put(&document, &embedding);
+                }
+                embedding
+            }
+        };
+        if let Some(dimensions) = self.dimensions {
+            if dimensions < embedding.len() {
+                embedding.truncate(dimensions);
+                normalize(&mut embedding);
+            }
+        }
+        if self.normalize {
+            normalize(&mut embedding);
+        }
+        Ok(embedding)
+    }
+
+    /// Number of tokens the model splits the document in, prefix included.
+    pub async fn count_tokens(&self, document: &str) -> Result<usize, Box<dyn Error>> {
+        let document = format!("{}{}", self.prefixes.document, document);
+        match &self.backend {
+            Backend::Ollama(ollama) => self.call(|| ollama.count_tokens(&document)).await,
+            Backend::Cohere(cohere) => self.call(|| cohere.count_tokens(&document)).await,
+            Backend::Voyage(voyage) => self.call(|| voyage.count_tokens(&document)).await,
+            Backend::Hashing(_) => Ok(hashing_terms(&document).len()),
+        }
+    }
+
+    /// Maximum number of tokens the model embeds, the rest of the input is ignored.
+    pub async fn context_length(&self) -> Result<usize, Box<dyn Error>> {
+        match &self.backend {
+            Backend::Ollama(ollama) => ollama
+                .context_length()
+                .await
+                .map_err(|err| error::Error::embedding(err).into()),
+            Backend::Cohere(_) => Ok(512),
+            Backend::Voyage(_) => Ok(32000),
+            // Every word is hashed
+            Backend::Hashing(_) => Ok(usize::MAX),
+        }
+    }
+}
+
+pub fn normalize(embedding: &mut [f32]) {
+    let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        embedding.iter_mut().for_each(|v| *v /= norm);
+    }
+}
+
+struct SimpleOllama {
+    url: String,
+    client: reqwest::Client,
+    embedding_model: String,
+    options: OllamaOptions,
+    /// Where the download progress of the model goes instead of stderr
+    events: Option<Callback>,
+}
+
+impl SimpleOllama {
+    /// Body of an embedding request, with the configured options.
+    fn embed_request(&self, document: &str, truncate: Option<bool>) -> Value {
+        let mut request = json!({ "model": self.embedding_model, "input": document });
+        if let Some(keep_alive) = &self.options.keep_alive {
+            // Durations are strings, seconds like `-1` numbers
+            request["keep_alive"] = match keep_alive.parse::<i64>() {
+                Ok(seconds) => seconds.into(),
+                Err(_) => keep_alive.as_str().into(),
+            };
+        }
+        if let Some(num_ctx) = self.options.num_ctx {
+            request["options"] = json!({ "num_ctx": num_ctx });
+        }
+        if let Some(truncate) = truncate.or(self.options.truncate) {
+            request["truncate"] = truncate.into();
+        }
+        request
+    }
+
+    async fn download_model(&self) -> Result<(), Box<dyn Error>> {
+        let models = match self
+            .client
+            .get(format!("{}api/tags", self.url))
+            .send()
+            .await
+        {
+            Ok(response) => response.error_for_status()?.json::<Value>().await?,
+            Err(err) => return Err(format!("Ollama at {}: {}", self.url, err).into()),
+        };
+
+        for model in models["models"].as_array().into_iter().flatten() {
+            let name = model["name"].as_str().unwrap_or_default();
+            if name == self.embedding_model || name == format!("{}:latest", self.embedding_model) {
+                return Ok(());
+            }
+        }
+
+        self.pull_model().await
+    }
+
+    async fn pull_model(&self) -> Result<(), Box<dyn Error>> {
+        if self.events.is_none() {
+            eprintln!("downloading model {}", self.embedding_model);
+        }
+        let mut response = self
+            .client
+            .post(format!("{}api/pull", self.url))
+            .json(&json!({ "model": self.embedding_model, "stream": true }))
+            .send()
+            .await?
+            .error_for_status()?;
+        let mut stderr = std::io::stderr();
+        let mut buffer = vec![];
+        while let Some(chunk) = response.chunk().await? {
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                let line = buffer.drain(..=end).collect::<Vec<_>>();
+                let Ok(status) = serde_json::from_slice::<Value>(&line) else {
+                    continue;
+                };
+                if let Some(error) = status["error"].as_str() {
+                    return Err(error.into());
+                }
+                let message = status["status"].as_str().unwrap_or_default();
+                if let Some(events) = &self.events {
+                    events(&Event::ModelDownloading {
+                        model: &self.embedding_model,
+                        status: message,
+                        completed: status["completed"].as_u64(),
+                        total: status["total"].as_u64(),
+                    });
+                    continue;
+                }
+                match (status["completed"].as_u64(), status["total"].as_u64()) {
+                    (Some(completed), Some(total)) if total > 0 => eprint!(
+                        "\r{}: {:>5.1}% ({} / {} MB)",
+                        message,
+                        completed as f64 * 100.0 / total as f64,
+                        completed / 1_000_000,
+                        total / 1_000_000
+                    ),
+                    _ => eprint!("\r{:<60}", message),
+                }
+                stderr.flush()?;
+            }
+        }
+        if self.events.is_none() {
+            eprintln!();
+        }
+
+        Ok(())
+    }
+
+    async fn embed(&self, document: &str) -> Result<reqwest::Response, Box<dyn Error>> {
+        Ok(self
+            .client
+            .post(format!("{}api/embed", self.url))
+            .json(&self.embed_request(document, None))
+            .send()
+            .await?)
+    }
+
+    async fn embeddings(&self, document: &str) -> Result<Vec<f32>, Box<dyn Error>> {
+        let mut response = self.embed(document).await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            self.pull_model().await?;
+            response = self.embed(document).await?;
+        }
+        let res: Value = response.error_for_status()?.json().await?;
+        res["embeddings"][0]
+            .as_array()
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(Value::as_f64)
+                    .map(|value| value as f32)
+                    .collect()
+            })
+            .ok_or_else(|| "Unexpected embedding response".into())
+    }
+
+    /// Ollama has no tokenize endpoint but reports the number of tokens evaluated
+    /// when embedding. Inputs longer than the context are truncated, so the count
+    /// is capped at the context length.
+    async fn count_tokens(&self, document: &str) -> Result<usize, Box<dyn Error>> {
+        let res: Value = self
+            .client
+            .post(format!("{}api/embed", self.url))
+            .json(&self.embed_request(document, Some(true)))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        res["prompt_eval_count"]
+            .as_u64()
+            .map(|count| count as usize)
+            .ok_or_else(|| "Unexpected embedding response".into())
+    }
+
+    async fn context_length(&self) -> Result<usize, Box<dyn Error>> {
+        let res: Value = self
+            .client
+            .post(format!("{}api/show", self.url))
+            .json(&json!({ "model": self.embedding_model }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        res["model_info"]
+            .as_object()
+            .and_then(|info| {
+                info.iter()
+                    .find(|(key, _)| key.ends_with(".context_length"))
+                    .and_then(|(_, value)| value.as_u64())
+            })
+            .map(|length| match self.options.num_ctx {
+                Some(num_ctx) => (length as usize).min(num_ctx as usize),
+                None => length as usize,
+            })
+            .ok_or_else(|| "Couldn't find the context length of the model".into())
+    }
+}
+
+/// Feature hashing of the words and identifier parts of a text, with a sign from
+/// the hash so that collisions tend to cancel out.
+struct Hashing {
+    dimensions: usize,
+}
+
+impl Hashing {
+    fn embeddings(&self, document: &str) -> Vec<f32> {
+        let mut embedding = vec![0.0f32; self.dimensions];
+        for term in hashing_terms(document) {
+            // FNV-1a, stable across runs and platforms unlike the std hasher
+            let hash = term.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            });
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            embedding[(hash % self.dimensions as u64) as usize] += sign;
+        }
+        // Dampen frequent words
+        for value in &mut embedding {
+            *value = value.signum() * value.abs().ln_1p();
+        }
+        normalize(&mut embedding);
+        embedding
+    }
+}
+
+/// Lowercased words of a text, with identifiers followed by their parts.
+fn hashing_terms(document: &str) -> Vec<String> {
+    split_identifiers(document)
+        .split(' ')
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+struct Cohere {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+}
+
+impl Cohere {
+    async fn embeddings(
+        &self,
+        document: &str,
+        input_type: InputType,
+    ) -> Result<Vec<f32>, Box<dyn Error>> {
+        let input_type = match input_type {
+            InputType::Document => "search_document",
+            InputType::Query => "search_query",
+        };
+        let res: Value = self
+            .client
+            .post("https://api.cohere.com/v2/embed")
+            .bearer_auth(&self.api_key)
+            .json(&json!({
+                "model": self.model,
+                "texts": [document],
+                "input_type": input_type,
+                "embedding_types": ["float"],
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        first_embedding(&res["embeddings"]["float"])
+    }
+
+    async fn count_tokens(&self, document: &str) -> Result<usize, Box<dyn Error>> {
+        let res: Value = self
+            .client
+            .post("https://api.cohere.com/v1/tokenize")
+            .bearer_auth(&self.api_key)
+            .json(&json!({
+                "model": self.model,
+                "text": document,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        res["tokens"]
+            .as_array()
+            .map(Vec::len)
+            .ok_or_else(|| "Unexpected tokenize response".into())
+    }
+}
+
+struct Voyage {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+}
+
+impl Voyage {
+    async fn embeddings(
+        &self,
+        document: &str,
+        input_type: InputType,
+    ) -> Result<Vec<f32>, Box<dyn Error>> {
+        let input_type = match input_type {
+            InputType::Document => "document",
+            InputType::Query => "query",
+        };
+        let res: Value = self
+            .client
+            .post("https://api.voyageai.com/v1/embeddings")
+            .bearer_auth(&self.api_key)
+            .json(&json!({
+                "model": self.model,
+                "input": [document],
+                "input_type": input_type,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        first_embedding(&res["data"][0]["embedding"])
+    }
+
+    /// Voyage has no tokenize endpoint, embed the document and read the usage.
+    async fn count_tokens(&self, document: &str) -> Result<usize, Box<dyn Error>> {
+        let res: Value = self
+            .client
+            .post("https://api.voyageai.com/v1/embeddings")
+            .bearer_auth(&self.api_key)
+            .json(&json!({
+                "model": self.model,
+                "input": [document],
+                "input_type": "document",
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        res["usage"]["total_tokens"]
+            .as_u64()
+            .map(|count| count as usize)
+            .ok_or_else(|| "Unexpected embedding response".into())
+    }
+}
+
+fn first_embedding(value: &Value) -> Result<Vec<f32>, Box<dyn Error>> {
+    let value = match value {
+        Value::Array(values) if values.first().is_some_and(Value::is_array) => &values[0],
+        value => value,
+    };
+    let Some(values) = value.as_array() else {
+        return Err("Unexpected embedding response".into());
+    };
+    Ok(values
+        .iter()
+        .map(|v| v.as_f64().unwrap_or_default() as f32)
+        .collect())
+}
@@ -0,0 +1,46 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::error;
+
+/// Where to reach Ollama, with a client sending the configured headers, like the
+/// credentials of a proxy in front of a remote server.
+#[derive(Clone)]
+pub struct OllamaEndpoint {
+    /// Base URL, always ending with a `/`
+    pub url: String,
+    pub client: reqwest::Client,
+}
+
+impl OllamaEndpoint {
+    /// Headers are given as `Name: value`. Like Ollama's own `OLLAMA_HOST`, the URL
+    /// can omit the scheme.
+    pub fn new(url: &str, headers: &[String]) -> error::Result<Self> {
+        let mut url = if url.contains("://") {
+            url.to_string()
+        } else {
+            format!("http://{}", url)
+        };
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        let mut header_map = HeaderMap::new();
+        for header in headers {
+            let invalid = |source: Box<dyn std::error::Error>| error::Error::Invalid {
+                what: format!("header {}", header),
+                source,
+            };
+            let Some((name, value)) = header.split_once(':') else {
+                return Err(invalid("headers are given as `Name: value`".into()));
+            };
+            let name = HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|err| invalid(err.into()))?;
+            let value = HeaderValue::from_str(value.trim()).map_err(|err| invalid(err.into()))?;
+            header_map.insert(name, value);
+        }
+        let client = reqwest::Client::builder()
+            .default_headers(header_map)
+            .build()
+            .unwrap();
+        Ok(OllamaEndpoint { url, client })
+    }
+}
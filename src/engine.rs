@@ -0,0 +1,222 @@
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Description of an exported index, the `index.json` of a site.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteIndex {
+    pub project: String,
+    pub model: Option<String>,
+    /// Distance function, `l2`, `ip` or `cosine`
+    pub space: Option<String>,
+    pub query_prefix: Option<String>,
+    #[serde(default)]
+    pub normalize: bool,
+    pub dimensions: usize,
+    /// Ollama endpoint queries are embedded with
+    pub embedding_url: Option<String>,
+    /// Position of the document of every vector, as chunks share a document
+    pub vectors: Vec<usize>,
+}
+
+/// A document of an exported index, in `documents.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteDocument {
+    pub id: String,
+    pub name: Option<String>,
+    pub kind: String,
+    #[serde(rename = "crate")]
+    pub krate: Option<String>,
+    pub url: Option<String>,
+    pub text: String,
+}
+
+/// Quantize a vector to one byte per dimension, returning the scale to multiply
+/// them back by.
+pub fn quantize(embedding: &[f32]) -> (f32, Vec<i8>) {
+    let max = embedding.iter().fold(0.0f32, |max, v| max.max(v.abs()));
+    let scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+    let values = embedding
+        .iter()
+        .map(|v| (v / scale).round().clamp(-127.0, 127.0) as i8)
+        .collect();
+    (scale, values)
+}
+
+/// Append a quantized vector to `vectors.bin`, its scale first.
+pub fn encode(vectors: &mut Vec<u8>, scale: f32, values: &[i8]) {
+    vectors.extend(scale.to_le_bytes());
+    vectors.extend(values.iter().map(|v| *v as u8));
+}
+
+/// Queries an exported index without a vector store or any native dependency, so
+/// it also runs in browsers once compiled to wasm32 without default features.
+pub struct Engine {
+    index: SiteIndex,
+    documents: Vec<SiteDocument>,
+    scales: Vec<f32>,
+    values: Vec<i8>,
+    client: reqwest::Client,
+}
+
+impl Engine {
+    /// Load the contents of `index.json`, `documents.json` and `vectors.bin`.
+    pub fn load(index: &str, documents: &str, vectors: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let index: SiteIndex = serde_json::from_str(index)?;
+        let documents: Vec<SiteDocument> = serde_json::from_str(documents)?;
+        let stride = index.dimensions + 4;
+        if vectors.len() != index.vectors.len() * stride {
+            return Err("vectors.bin doesn't match index.json".into());
+        }
+        if index
+            .vectors
+            .iter()
+            .any(|document| *document >= documents.len())
+        {
+            return Err("documents.json doesn't match index.json".into());
+        }
+        let mut scales = Vec::with_capacity(index.vectors.len());
+        let mut values = Vec::with_capacity(index.vectors.len() * index.dimensions);
+        for vector in vectors.chunks(stride) {
+            scales.push(f32::from_le_bytes(vector[..4].try_into()?));
+            values.extend(vector[4..].iter().map(|v| *v as i8));
+        }
+        Ok(Engine {
+            index,
+            documents,
+            scales,
+            values,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    pub fn index(&self) -> &SiteIndex {
+        &self.index
+    }
+
+    pub fn documents(&self) -> &[SiteDocument] {
+        &self.documents
+    }
+
+    /// Embed a query with the endpoint of the index, or another Ollama-compatible one.
+    pub async fn embed(&self, query: &str, url: Option<&str>) -> Result<Vec<f32>, Box<dyn Error>> {
+        let Some(url) = url.or(self.index.embedding_url.as_deref()) else {
+            return Err("the index has no embedding endpoint".into());
+        };
+        let input = format!(
+            "{}{}",
+            self.index.query_prefix.as_deref().unwrap_or_default(),
+            query
+        );
+        let response: Value = self
+            .client
+            .post(format!("{}/api/embed", url.trim_end_matches('/')))
+            .json(&json!({ "model": self.index.model, "input": input }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let Some(embedding) = response["embeddings"][0].as_array() else {
+            return Err("no embedding in the response".into());
+        };
+        let mut embedding = embedding
+            .iter()
+            .filter_map(|v| v.as_f64().map(|v| v as f32))
+            .collect::<Vec<_>>();
+        let truncated = embedding.len() > self.index.dimensions;
+        embedding.truncate(self.index.dimensions);
+        if truncated || self.index.normalize {
+            let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                embedding.iter_mut().for_each(|v| *v /= norm);
+            }
+        }
+        Ok(embedding)
+    }
+
+    /// Positions of the documents closest to an embedding with their distance,
+    /// closest first, keeping the closest chunk of every document.
+    pub fn nearest(&self, embedding: &[f32], n_results: usize) -> Vec<(usize, f32)> {
+        let dimensions = self.index.dimensions;
+        let query_norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+        let mut best = vec![f32::INFINITY; self.documents.len()];
+        for (i, document) in self.index.vectors.iter().enumerate() {
+            let scale = self.scales[i];
+            let stored = &self.values[i * dimensions..(i + 1) * dimensions];
+            let (dot, norm, sq) =
+                embedding
+                    .iter()
+                    .zip(stored)
+                    .fold((0.0, 0.0, 0.0), |(dot, norm, sq), (q, s)| {
+                        let s = *s as f32 * scale;
+                        (dot + q * s, norm + s * s, sq + (q - s) * (q - s))
+                    });
+            let distance = match self.index.space.as_deref() {
+                Some("ip") => 1.0 - dot,
+                Some("cosine") if norm == 0.0 || query_norm == 0.0 => 1.0,
+                Some("cosine") => 1.0 - dot / (norm.sqrt() * query_norm),
+                _ => sq,
+            };
+            best[*document] = best[*document].min(distance);
+        }
+        let mut nearest = best
+            .into_iter()
+            .enumerate()
+            .filter(|(_, distance)| distance.is_finite())
+            .collect::<Vec<_>>();
+        nearest.sort_by(|a, b| a.1.total_cmp(&b.1));
+        nearest.truncate(n_results);
+        nearest
+    }
+
+    /// Documents closest to a query, embedded with the endpoint of the index.
+    pub async fn search(
+        &self,
+        query: &str,
+        n_results: usize,
+    ) -> Result<Vec<(&SiteDocument, f32)>, Box<dyn Error>> {
+        let embedding = self.embed(query, None).await?;
+        Ok(self
+            .nearest(&embedding, n_results)
+            .into_iter()
+            .map(|(document, distance)| (&self.documents[document], distance))
+            .collect())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod bindings {
+    use wasm_bindgen::prelude::*;
+
+    /// [`Engine`](super::Engine) for JavaScript, returning results as JSON.
+    #[wasm_bindgen]
+    pub struct WasmEngine(super::Engine);
+
+    #[wasm_bindgen]
+    impl WasmEngine {
+        #[wasm_bindgen(constructor)]
+        pub fn new(index: &str, documents: &str, vectors: &[u8]) -> Result<WasmEngine, JsError> {
+            super::Engine::load(index, documents, vectors)
+                .map(WasmEngine)
+                .map_err(|err| JsError::new(&err.to_string()))
+        }
+
+        /// Documents closest to a query, as a JSON array of `{ document, distance }`.
+        pub async fn search(&self, query: &str, n_results: usize) -> Result<String, JsError> {
+            let results = self
+                .0
+                .search(query, n_results)
+                .await
+                .map_err(|err| JsError::new(&err.to_string()))?;
+            let results = results
+                .into_iter()
+                .map(|(document, distance)| {
+                    serde_json::json!({ "document": document, "distance": distance })
+                })
+                .collect::<Vec<_>>();
+            Ok(serde_json::Value::from(results).to_string())
+        }
+    }
+}
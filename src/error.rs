@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+
+/// Failures of the library, by the stage they happened in, for applications to
+/// tell them apart. Functions returning `Box<dyn Error>` box them, and
+/// `downcast_ref::<rustdoc_rag::Error>()` finds them again. Messages include the
+/// one of their source, as they are shown on their own.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// A rustdoc JSON couldn't be read
+    #[error("couldn't read the rustdoc JSON {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// A rustdoc JSON is in a format version that can't be read
+    #[error("{} has rustdoc JSON format version {version}, which can't be read", path.display())]
+    Format {
+        path: PathBuf,
+        version: u32,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// The rustdoc JSON of the project couldn't be found or generated
+    #[error("{0}")]
+    Rustdoc(String),
+    /// Generating the documents of the project failed
+    #[error("couldn't generate the documents: {0}")]
+    Traversal(#[source] Box<dyn std::error::Error>),
+    /// The embedding provider failed
+    #[error("embedding failed: {0}")]
+    Embedding(#[source] Box<dyn std::error::Error>),
+    /// The vector store failed
+    #[error("vector store request failed: {0}")]
+    Store(#[source] Box<dyn std::error::Error>),
+    /// The chat model failed
+    #[error("chat model request failed: {0}")]
+    Llm(#[source] Box<dyn std::error::Error>),
+    /// An index was built with other settings than the requested ones
+    #[error("{index} doesn't match the requested configuration: {reason}")]
+    Mismatch { index: String, reason: String },
+    /// The rustdoc JSON is of another version than the one the index is pinned to
+    #[error("{index} is pinned to version {pinned}, but the rustdoc JSON is of version {found}")]
+    Version {
+        index: String,
+        pinned: String,
+        found: String,
+    },
+    /// A file built while indexing is missing, like the keyword index
+    #[error("couldn't find the {what} of {index}")]
+    Missing { what: &'static str, index: String },
+    /// A credential of a remote service isn't set
+    #[error("missing the {what}")]
+    Credential { name: String, what: String },
+    /// A file given in the options can't be used, like the glossary
+    #[error("invalid {what}: {source}")]
+    Invalid {
+        what: String,
+        #[source]
+        source: Box<dyn std::error::Error>,
+    },
+    /// Options that can't be used, like flags needing a service --offline doesn't
+    /// use or paths without anything to index
+    #[error("{0}")]
+    Options(String),
+    /// Anything else, like reading or writing the output directory
+    #[error(transparent)]
+    Other(Box<dyn std::error::Error>),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Wrap an error of the embedding provider, keeping one that already says where
+    /// it happened.
+    pub(crate) fn embedding(err: Box<dyn std::error::Error>) -> Self {
+        Self::classify(err, Error::Embedding)
+    }
+
+    pub(crate) fn store(err: Box<dyn std::error::Error>) -> Self {
+        Self::classify(err, Error::Store)
+    }
+
+    pub(crate) fn llm(err: Box<dyn std::error::Error>) -> Self {
+        Self::classify(err, Error::Llm)
+    }
+
+    pub(crate) fn traversal(err: Box<dyn std::error::Error>) -> Self {
+        Self::classify(err, Error::Traversal)
+    }
+
+    fn classify(
+        err: Box<dyn std::error::Error>,
+        wrap: fn(Box<dyn std::error::Error>) -> Self,
+    ) -> Self {
+        match err.downcast::<Error>() {
+            Ok(err) => *err,
+            Err(err) => wrap(err),
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for Error {
+    fn from(err: Box<dyn std::error::Error>) -> Self {
+        Self::classify(err, Error::Other)
+    }
+}
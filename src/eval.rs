@@ -0,0 +1,359 @@
+use std::{collections::BTreeMap, error::Error, path::PathBuf};
+
+use futures::StreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use rustdoc_rag::{
+    answer, cache::content_hash, Distance, Index, Indexer, Options, Retrieval, Retriever,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct EvalArgs {
+    /// YAML list of `query` with the `expected` paths of the items it should find
+    file: PathBuf,
+    /// Number of results the metrics look at
+    #[arg(short, default_value_t = 10)]
+    k: usize,
+    /// Retrieval methods to compare, like `dense,hybrid`, instead of --retrieval
+    #[arg(long, value_delimiter = ',')]
+    methods: Vec<Retrieval>,
+    /// Also list the expected items each query missed
+    #[arg(long)]
+    misses: bool,
+}
+
+/// A query of the evaluation file with the paths of the items it should find.
+#[derive(Deserialize, Serialize)]
+struct Case {
+    query: String,
+    expected: Vec<String>,
+}
+
+/// Metrics of a configuration, averaged over the queries.
+#[derive(Default, Clone, Copy)]
+struct Scores {
+    recall: f32,
+    reciprocal_rank: f32,
+    ndcg: f32,
+}
+
+/// Run the queries of the evaluation file with each embedding model and retrieval
+/// method, and report how well each finds the expected items in its top `k`.
+pub async fn run(options: &Options, args: &EvalArgs) -> Result<(), Box<dyn Error>> {
+    evaluate(std::slice::from_ref(options), args, false).await?;
+    Ok(())
+}
+
+/// Like [`run`], for every combination of the models and distances, indexing the
+/// project for the ones that weren't yet.
+pub async fn compare(
+    indexer: &Indexer,
+    args: &EvalArgs,
+    models: &[String],
+    distances: &[Distance],
+) -> Result<(), Box<dyn Error>> {
+    let mut combinations = vec![];
+    let options = indexer.options();
+    let distances = if distances.is_empty() {
+        vec![options.distance.clone()]
+    } else {
+        distances.to_vec()
+    };
+    for distance in distances {
+        let mut options = options.clone();
+        options.distance = distance;
+        if !models.is_empty() {
+            options.embedding = models.to_vec();
+        }
+        let indexer = indexer.with_options(options);
+        indexer.open().await?;
+        combinations.push(indexer.options().clone());
+    }
+    evaluate(&combinations, args, true).await?;
+    Ok(())
+}
+
+/// Like [`compare`] with every distance, reporting the best for each embedding
+/// model and retrieval method by nDCG.
+pub async fn bench_distances(indexer: &Indexer, args: &EvalArgs) -> Result<(), Box<dyn Error>> {
+    let distances = [
+        Distance::SquaredL2,
+        Distance::InnerProduct,
+        Distance::Cosine,
+    ];
+    let mut combinations = vec![];
+    for distance in distances {
+        let mut options = indexer.options().clone();
+        options.distance = distance;
+        let indexer = indexer.with_options(options);
+        indexer.open().await?;
+        combinations.push(indexer.options().clone());
+    }
+    let evaluated = evaluate(&combinations, args, true).await?;
+    let mut best = BTreeMap::<&str, (&str, Scores)>::new();
+    for evaluation in &evaluated {
+        let Some((configuration, space)) = &evaluation.distance else {
+            continue;
+        };
+        match best.get(configuration.as_str()) {
+            Some((_, scores)) if scores.ndcg >= evaluation.scores.ndcg => {}
+            _ => {
+                best.insert(configuration, (space, evaluation.scores));
+            }
+        }
+    }
+    println!();
+    for (configuration, (space, scores)) in best {
+        println!(
+            "Best distance for {}: {} (nDCG@{} {:.3})",
+            configuration, space, args.k, scores.ndcg
+        );
+    }
+    Ok(())
+}
+
+/// Metrics of a configuration, with the configuration without its distance and
+/// the distance for dense configurations.
+struct Evaluation {
+    scores: Scores,
+    distance: Option<(String, &'static str)>,
+}
+
+async fn evaluate(
+    combinations: &[Options],
+    args: &EvalArgs,
+    with_distance: bool,
+) -> Result<Vec<Evaluation>, Box<dyn Error>> {
+    let cases = read_cases(&args.file)?;
+    let k = args.k;
+    let mut configurations = vec![];
+    let mut lexical = false;
+    for options in combinations {
+        let retrievals = if args.methods.is_empty() {
+            vec![options.retrieval]
+        } else {
+            args.methods.clone()
+        };
+        let chroma = options.chroma_client();
+        for retrieval in retrievals {
+            // Keyword search doesn't depend on the embedding model nor the distance
+            let models = match retrieval {
+                Retrieval::Lexical if lexical => continue,
+                Retrieval::Lexical => &options.embedding[..1],
+                _ => &options.embedding[..],
+            };
+            lexical |= retrieval == Retrieval::Lexical;
+            for model in models {
+                let mut options = options.clone();
+                options.retrieval = retrieval;
+                options.nb_results = k;
+                // Evaluation queries aren't asked by users
+                options.query_log = None;
+                let mut name = match retrieval {
+                    Retrieval::Lexical => "lexical".to_string(),
+                    Retrieval::Dense => format!("{} dense", model),
+                    Retrieval::Hybrid => format!("{} hybrid", model),
+                };
+                let distance = (retrieval != Retrieval::Lexical)
+                    .then(|| (name.clone(), options.distance.space()));
+                if with_distance && retrieval != Retrieval::Lexical {
+                    name = name.replacen(' ', &format!(" {} ", options.distance.space()), 1);
+                }
+                let index = Index::open(&chroma, &options, model).await?;
+                configurations.push((name, distance, Retriever::new(options, vec![index])?));
+            }
+        }
+    }
+
+    let width = configurations
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("configuration".len());
+    let mut report = vec![];
+    for (name, distance, retriever) in &configurations {
+        let mut scores = Scores::default();
+        for case in &cases {
+            let results = retriever.search(&case.query).await?;
+            let ranks = case
+                .expected
+                .iter()
+                .map(|expected| {
+                    results
+                        .ranking
+                        .iter()
+                        .take(k)
+                        .position(|(id, _)| matches(id, expected))
+                })
+                .collect::<Vec<_>>();
+            let found = ranks.iter().flatten().count();
+            scores.recall += found as f32 / case.expected.len().max(1) as f32;
+            if let Some(first) = ranks.iter().flatten().min() {
+                scores.reciprocal_rank += 1.0 / (*first + 1) as f32;
+            }
+            let dcg = ranks
+                .iter()
+                .flatten()
+                .map(|rank| 1.0 / (*rank as f32 + 2.0).log2())
+                .sum::<f32>();
+            let ideal = (0..case.expected.len().min(k))
+                .map(|rank| 1.0 / (rank as f32 + 2.0).log2())
+                .sum::<f32>();
+            if ideal > 0.0 {
+                scores.ndcg += dcg / ideal;
+            }
+            if args.misses && found < case.expected.len() {
+                let missing = case
+                    .expected
+                    .iter()
+                    .zip(&ranks)
+                    .filter(|(_, rank)| rank.is_none())
+                    .map(|(expected, _)| expected.as_str())
+                    .collect::<Vec<_>>();
+                println!("{}: {:?} missed {}", name, case.query, missing.join(", "));
+            }
+        }
+        let count = cases.len().max(1) as f32;
+        scores.recall /= count;
+        scores.reciprocal_rank /= count;
+        scores.ndcg /= count;
+        report.push((name, distance, scores));
+    }
+
+    if args.misses {
+        println!();
+    }
+    println!(
+        "{:width$}  {:>9}  {:>6}  {:>7}  ({} queries)",
+        "configuration",
+        format!("recall@{}", k),
+        "MRR",
+        format!("nDCG@{}", k),
+        cases.len()
+    );
+    let mut evaluated = vec![];
+    for (name, distance, scores) in report {
+        println!(
+            "{:width$}  {:>9.3}  {:>6.3}  {:>7.3}",
+            name, scores.recall, scores.reciprocal_rank, scores.ndcg
+        );
+        evaluated.push(Evaluation {
+            scores,
+            distance: distance.clone(),
+        });
+    }
+    Ok(evaluated)
+}
+
+/// Write an evaluation file of questions asked to the chat model about a sample of
+/// the documents, each expecting the item of its document. The sample is the same
+/// for the same seed and documents.
+pub async fn generate(
+    indexer: &Indexer,
+    count: usize,
+    seed: u64,
+    output: &std::path::Path,
+) -> Result<(), Box<dyn Error>> {
+    let options = indexer.options();
+    if options.offline {
+        eprintln!("Generating queries needs a chat model, which --offline doesn't use");
+        std::process::exit(1);
+    }
+    let documents = indexer.documents()?.documents;
+    let mut sample = documents
+        .values()
+        // Sources repeat the item they are from
+        .filter(|document| document.kind != "source")
+        .map(|document| {
+            (
+                content_hash(&format!("{}{}", seed, document.id())),
+                document,
+            )
+        })
+        .collect::<Vec<_>>();
+    sample.sort_by(|(a, _), (b, _)| a.cmp(b));
+    sample.truncate(count);
+
+    let llm = options.chat_model()?;
+    let system = format!(
+        "You write evaluation questions for a search engine over the API documentation \
+        of the Rust project {}. Given the documentation of an item, write one question \
+        a user could ask that it answers, in the words of someone who doesn't know the \
+        name of the item. Reply with the question only.",
+        options.project
+    );
+    let progress = ProgressBar::new(sample.len() as u64);
+    progress.set_style(
+        ProgressStyle::with_template("{msg:>10} [{bar:40}] {pos}/{len} ({per_sec}, ETA {eta})")
+            .unwrap()
+            .progress_chars("=> "),
+    );
+    progress.set_message("queries");
+    let mut replies = futures::stream::iter(sample)
+        .map(|(_, document)| {
+            let llm = &llm;
+            let system = &system;
+            async move {
+                let text = answer::pack_context(
+                    vec![(String::new(), document.text.clone())],
+                    options.context_tokens,
+                )
+                .pop()
+                .map(|(_, text)| text)
+                .unwrap_or_default();
+                let messages = json!([
+                    { "role": "system", "content": system },
+                    { "role": "user", "content": text },
+                ]);
+                let query = llm.chat(messages, |_| Ok(())).await?;
+                Ok::<_, Box<dyn Error>>((document.path.clone(), query))
+            }
+        })
+        .buffer_unordered(options.concurrency.max(1));
+    let mut cases = vec![];
+    while let Some(reply) = replies.next().await {
+        let (path, query) = reply?;
+        progress.inc(1);
+        let query = query.trim().trim_matches('"').trim();
+        if !query.is_empty() {
+            cases.push(Case {
+                query: query.to_string(),
+                expected: vec![path],
+            });
+        }
+    }
+    progress.finish();
+    cases.sort_by(|a, b| a.expected.cmp(&b.expected));
+    std::fs::write(output, serde_yaml::to_string(&cases)?)?;
+    println!("Wrote {} queries to {}", cases.len(), output.display());
+    Ok(())
+}
+
+fn read_cases(path: &std::path::Path) -> Result<Vec<Case>, Box<dyn Error>> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        eprintln!("Couldn't read the evaluation file {}", path.display());
+        std::process::exit(1);
+    };
+    match serde_yaml::from_str::<Vec<Case>>(&content) {
+        Ok(cases) if !cases.is_empty() => Ok(cases),
+        Ok(_) => {
+            eprintln!("The evaluation file {} has no queries", path.display());
+            std::process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("Invalid evaluation file {}", path.display());
+            eprintln!("{}", err);
+            eprintln!("Write a list of entries like `- query: ...` with `expected: [path, ...]`");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Whether a result is the expected item, given by its path like
+/// `bevy::prelude::Cuboid` or by its id like `struct:bevy::prelude::Cuboid`.
+fn matches(id: &str, expected: &str) -> bool {
+    id == expected || id.split_once(':').is_some_and(|(_, path)| path == expected)
+}
@@ -0,0 +1,283 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Map;
+
+use crate::{document::Document, error};
+
+/// Documents of the items of the example files given with --examples, files or
+/// directories searched recursively like the `examples` directory of a package.
+pub fn example_documents(paths: &[PathBuf]) -> Result<Vec<Document>, Box<dyn Error>> {
+    let mut documents = vec![];
+    for path in paths {
+        // Files are named from the directory they are in, like `examples/basic.rs`
+        let path = std::path::absolute(path)?;
+        let base = if path.is_dir() {
+            path.parent()
+        } else {
+            path.parent().and_then(Path::parent)
+        }
+        .unwrap_or(Path::new(""));
+        let mut files = vec![];
+        rust_files(&path, &mut files)?;
+        if files.is_empty() {
+            return Err(error::Error::Options(format!(
+                "couldn't find any Rust file in {}",
+                path.display()
+            ))
+            .into());
+        }
+        for file in files {
+            let name = file
+                .strip_prefix(base)
+                .unwrap_or(&file)
+                .to_string_lossy()
+                .replace('\\', "/");
+            documents.extend(items(&name, &std::fs::read_to_string(&file)?));
+        }
+    }
+    Ok(documents)
+}
+
+fn rust_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = std::fs::read_dir(path)?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    entries.sort();
+    for entry in entries {
+        let hidden = entry
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if entry.is_dir() && !hidden {
+            rust_files(&entry, files)?;
+        } else if entry.extension().is_some_and(|extension| extension == "rs") {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Name of the example a file is part of: its stem, or its directory for the
+/// `main.rs` of a multi-file example.
+fn example_name(file: &str) -> &str {
+    let mut parts = file.rsplit('/');
+    let stem = parts.next().unwrap_or(file).trim_end_matches(".rs");
+    match (stem, parts.next()) {
+        ("main", Some(dir)) if dir != "examples" => dir,
+        _ => stem,
+    }
+}
+
+/// One document per top-level item of an example, with the comments and attributes
+/// above it. The text of an item starts with the description of the example from
+/// its `//!` comments, and shows the `use` declarations of the file with the item.
+fn items(file: &str, content: &str) -> Vec<Document> {
+    let example = example_name(file);
+    let mut description = String::new();
+    let mut uses = String::new();
+    let mut items = vec![];
+    let mut current = String::new();
+    let mut has_code = false;
+    let mut scanner = Scanner::default();
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if scanner.depth == 0 && !scanner.in_string && !has_code {
+            if let Some(doc) = trimmed.strip_prefix("//!") {
+                description.push_str(doc.strip_prefix(' ').unwrap_or(doc));
+                description.push('\n');
+                continue;
+            }
+            // Comments separated from an item by a blank line don't describe it
+            if trimmed.is_empty() || trimmed.starts_with("#!") {
+                current.clear();
+                continue;
+            }
+        }
+        current.push_str(line);
+        current.push('\n');
+        has_code |= !trimmed.starts_with("//") && !trimmed.starts_with("#[");
+        scanner.scan(line);
+        if has_code
+            && scanner.depth == 0
+            && !scanner.in_string
+            && (trimmed.ends_with('}') || trimmed.ends_with(';'))
+        {
+            let item = std::mem::take(&mut current);
+            has_code = false;
+            match signature(&item) {
+                Some(("use" | "extern", _)) => uses.push_str(&item),
+                Some((keyword, name)) => items.push((keyword, name, item)),
+                None => {}
+            }
+        }
+    }
+
+    let mut names = HashMap::<String, usize>::new();
+    items
+        .into_iter()
+        .map(|(keyword, name, item)| {
+            // Like the several impl blocks of a type
+            let count = names.entry(name.clone()).or_default();
+            let mut path = format!("{}#{}", file, name.replace(' ', "-"));
+            if *count > 0 {
+                path.push_str(&format!("-{}", count));
+            }
+            *count += 1;
+            let mut text = format!("# Example {}: {}\n\n", example, name);
+            if !description.trim().is_empty() {
+                text.push_str(description.trim());
+                text.push_str("\n\n");
+            }
+            text.push_str("```rust\n");
+            if !uses.is_empty() {
+                text.push_str(&uses);
+                text.push('\n');
+            }
+            text.push_str(&item);
+            text.push_str("```\n");
+            let mut metadata = Map::new();
+            metadata.insert("kind".to_string(), "example".into());
+            metadata.insert("source".to_string(), "example".into());
+            metadata.insert("file".to_string(), file.into());
+            metadata.insert("example".to_string(), example.into());
+            metadata.insert("item".to_string(), keyword.into());
+            metadata.insert("name".to_string(), name.into());
+            Document {
+                path,
+                kind: "example".to_string(),
+                text,
+                metadata,
+            }
+        })
+        .collect()
+}
+
+/// Keyword and name of an item from its source, like `("fn", "main")` or
+/// `("impl", "Plugin for MyPlugin")`.
+fn signature(item: &str) -> Option<(&'static str, String)> {
+    const KEYWORDS: &[&str] = &[
+        "fn",
+        "struct",
+        "enum",
+        "union",
+        "trait",
+        "impl",
+        "mod",
+        "const",
+        "static",
+        "type",
+        "macro_rules!",
+        "use",
+        "extern",
+    ];
+    let code = item
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("//") && !line.starts_with("#["))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut words = code.split_whitespace().peekable();
+    while let Some(word) = words.next() {
+        // A `const fn`, not a constant
+        if word == "const" && words.peek() == Some(&"fn") {
+            continue;
+        }
+        // Like `impl<T>`
+        let (word, generics) = word.split_at(word.find('<').unwrap_or(word.len()));
+        let Some(keyword) = KEYWORDS.iter().find(|keyword| **keyword == word) else {
+            continue;
+        };
+        let name = if *keyword == "impl" {
+            let rest = [generics]
+                .into_iter()
+                .chain(words)
+                .collect::<Vec<_>>()
+                .join(" ");
+            let rest = rest.split('{').next().unwrap_or_default();
+            let rest = rest.split(" where ").next().unwrap_or_default().trim();
+            // Skip the generic parameters of the impl itself
+            match rest.strip_prefix('<') {
+                Some(generics) => skip_generics(generics).trim().to_string(),
+                None => rest.to_string(),
+            }
+        } else {
+            words
+                .next()
+                .unwrap_or_default()
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        };
+        return Some((keyword, name));
+    }
+    None
+}
+
+/// What follows the generic parameters, given the text after their opening `<`.
+fn skip_generics(generics: &str) -> &str {
+    let mut depth = 1;
+    for (i, c) in generics.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return &generics[i + 1..];
+        }
+    }
+    ""
+}
+
+/// Nesting of braces through the lines of a file, ignoring them in comments,
+/// strings and characters.
+#[derive(Default)]
+struct Scanner {
+    depth: usize,
+    in_string: bool,
+}
+
+impl Scanner {
+    fn scan(&mut self, line: &str) {
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if self.in_string {
+                match c {
+                    '\\' => {
+                        chars.next();
+                    }
+                    '"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match c {
+                '/' if chars.peek() == Some(&'/') => return,
+                '"' => self.in_string = true,
+                // A character literal like '{', not a lifetime
+                '\'' => {
+                    let length = match chars.peek() {
+                        Some('\\') => 3,
+                        Some(_) => 2,
+                        None => 0,
+                    };
+                    if length > 0 && chars.clone().nth(length - 1) == Some('\'') {
+                        chars.nth(length - 1);
+                    }
+                }
+                '{' => self.depth += 1,
+                '}' => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+}
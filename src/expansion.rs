@@ -0,0 +1,125 @@
+use std::{error::Error, path::Path};
+
+use serde_json::json;
+
+use crate::{error, llm::Llm};
+
+/// Words of users mapped to the names the API uses for them, read from a TOML file
+/// of entries like `"spawn a cube" = ["Mesh3d", "Cuboid", "Commands::spawn"]`.
+pub struct Glossary {
+    entries: Vec<(Vec<String>, Vec<String>)>,
+}
+
+impl Glossary {
+    pub fn load(path: &Path) -> error::Result<Self> {
+        let invalid = |source: Box<dyn Error>| error::Error::Invalid {
+            what: format!("glossary {}", path.display()),
+            source,
+        };
+        let content = std::fs::read_to_string(path).map_err(|err| invalid(err.into()))?;
+        let table = content
+            .parse::<toml::Table>()
+            .map_err(|err| invalid(err.into()))?;
+        let entries = table
+            .into_iter()
+            .map(|(phrase, names)| {
+                let names = match names {
+                    toml::Value::String(name) => vec![name],
+                    toml::Value::Array(names) => names
+                        .into_iter()
+                        .filter_map(|name| name.as_str().map(str::to_string))
+                        .collect(),
+                    _ => {
+                        return Err(invalid(
+                            format!("the entry {:?} should be a name or a list of names", phrase)
+                                .into(),
+                        ))
+                    }
+                };
+                Ok((words(&phrase), names))
+            })
+            .collect::<error::Result<_>>()?;
+        Ok(Glossary { entries })
+    }
+
+    /// The query followed by the names of the entries whose words it all uses.
+    pub fn expand(&self, query: &str) -> String {
+        let used = words(query);
+        let mut expanded = query.to_string();
+        for (phrase, names) in &self.entries {
+            if phrase.is_empty() || !phrase.iter().all(|word| used.contains(word)) {
+                continue;
+            }
+            for name in names {
+                if !expanded.contains(name.as_str()) {
+                    expanded.push(' ');
+                    expanded.push_str(name);
+                }
+            }
+        }
+        expanded
+    }
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Ask the model to write the documentation that would answer the question, to
+/// search with text that reads like the indexed documents rather than like a
+/// question.
+pub async fn hypothetical_document(
+    llm: &Llm,
+    project: &str,
+    question: &str,
+) -> Result<String, Box<dyn Error>> {
+    let messages = json!([
+        {
+            "role": "system",
+            "content": format!(
+                "You write the API documentation of the Rust project {}. Write a short \
+                documentation paragraph for the item that answers the question, naming the \
+                types and functions it would use. Reply with the paragraph only.",
+                project
+            ),
+        },
+        { "role": "user", "content": question },
+    ]);
+    llm.chat(messages, |_| Ok(())).await
+}
+
+/// Ask the model for other ways to phrase the question, using the project's
+/// vocabulary where it can.
+pub async fn paraphrases(
+    llm: &Llm,
+    project: &str,
+    question: &str,
+    count: usize,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let messages = json!([
+        {
+            "role": "system",
+            "content": format!(
+                "You help search the API documentation of the Rust project {}. Rewrite the \
+                question in {} different ways, using the names the project would use for \
+                the concepts involved. Reply with one rewrite per line and nothing else.",
+                project, count
+            ),
+        },
+        { "role": "user", "content": question },
+    ]);
+    let reply = llm.chat(messages, |_| Ok(())).await?;
+    Ok(reply
+        .lines()
+        .map(|line| {
+            line.trim()
+                .trim_start_matches(|c: char| c.is_ascii_digit() || "-*.) ".contains(c))
+                .to_string()
+        })
+        .filter(|line| !line.is_empty())
+        .take(count)
+        .collect())
+}
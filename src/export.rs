@@ -0,0 +1,180 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+use rustdoc_rag::{docs_rs, document::Document, graph::Graph};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON object per line with `id`, `path`, `kind`, `text` and `metadata`,
+    /// as ingested by LlamaIndex or uploaded to OpenAI vector stores
+    Jsonl,
+    /// An `llms.txt` index of the items by module and an `llms-full.txt` with every
+    /// document, written to the output directory or the current one
+    LlmsTxt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GraphFormat {
+    /// The DOT language of Graphviz
+    Dot,
+    /// GraphML, read by Gephi, yEd or networkx
+    Graphml,
+}
+
+/// Write the item graph, to a file or to stdout without one.
+pub fn export_graph(
+    graph: &Graph,
+    format: GraphFormat,
+    output: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let content = match format {
+        GraphFormat::Dot => graph.to_dot(),
+        GraphFormat::Graphml => graph.to_graphml(),
+    };
+    let mut writer = writer(output)?;
+    writer.write_all(content.as_bytes())?;
+    writer.flush()?;
+    if let Some(path) = output {
+        println!(
+            "{} edges written to {}",
+            graph.edges().count(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Writer to the output file, creating its directory, or to stdout without one.
+fn writer(output: Option<&Path>) -> Result<Box<dyn Write>, Box<dyn Error>> {
+    Ok(match output {
+        Some(path) => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            Box::new(BufWriter::new(std::fs::File::create(path)?))
+        }
+        None => Box::new(BufWriter::new(std::io::stdout().lock())),
+    })
+}
+
+/// Write the generated documents for retrieval stacks other than this one.
+pub fn export(
+    project: &str,
+    documents: &BTreeMap<String, Document>,
+    format: ExportFormat,
+    output: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        ExportFormat::Jsonl => jsonl(documents, output),
+        ExportFormat::LlmsTxt => llms_txt(project, documents, output.unwrap_or(Path::new("."))),
+    }
+}
+
+/// Write the documents as JSON lines to a file, or to stdout without one. The
+/// docs.rs URL of the item is added to its metadata when known.
+fn jsonl(
+    documents: &BTreeMap<String, Document>,
+    output: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let mut writer = writer(output)?;
+    for (id, document) in documents {
+        let mut metadata = document.metadata.clone();
+        if let Some(url) = docs_rs::item_url(id, &metadata) {
+            metadata.insert("url".to_string(), url.into());
+        }
+        let record = json!({
+            "id": id,
+            "path": document.path,
+            "kind": document.kind,
+            "text": document.text,
+            "metadata": metadata,
+        });
+        writeln!(writer, "{}", record)?;
+    }
+    writer.flush()?;
+    if let Some(path) = output {
+        println!(
+            "{} documents written to {}",
+            documents.len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Write `llms.txt`, linking every item by module with the first sentence of its
+/// documentation, and `llms-full.txt` with the documents themselves, following
+/// <https://llmstxt.org>.
+fn llms_txt(
+    project: &str,
+    documents: &BTreeMap<String, Document>,
+    dir: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let description = format!(
+        "> Documentation of the public API of {}, generated from its rustdoc JSON.",
+        project
+    );
+    let mut modules = BTreeMap::<&str, Vec<String>>::new();
+    let mut full = format!("# {}\n\n{}\n\n", project, description);
+    for (id, document) in documents {
+        let url = docs_rs::item_url(id, &document.metadata);
+        let module = document
+            .metadata
+            .get("module")
+            .and_then(Value::as_str)
+            .unwrap_or(&document.path);
+        let title = match &url {
+            Some(url) => format!("[{}]({})", document.path, url),
+            None => format!("`{}`", document.path),
+        };
+        let entry = match summary(&document.text) {
+            Some(summary) => format!("- {}: {}", title, summary),
+            None => format!("- {}", title),
+        };
+        modules.entry(module).or_default().push(entry);
+
+        full.push_str(&format!("## {}\n\n", document.path));
+        if let Some(url) = &url {
+            full.push_str(&format!("Source: {}\n\n", url));
+        }
+        full.push_str(document.text.trim());
+        full.push_str("\n\n");
+    }
+
+    let mut index = format!("# {}\n\n{}\n\n", project, description);
+    index.push_str("The documentation of every item is in [llms-full.txt](llms-full.txt).\n\n");
+    for (module, entries) in modules {
+        index.push_str(&format!("## {}\n\n{}\n\n", module, entries.join("\n")));
+    }
+
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join("llms.txt"), index)?;
+    std::fs::write(dir.join("llms-full.txt"), full)?;
+    println!(
+        "{} documents written to {} and {}",
+        documents.len(),
+        dir.join("llms.txt").display(),
+        dir.join("llms-full.txt").display()
+    );
+    Ok(())
+}
+
+/// First sentence of the documentation in a generated document, which always
+/// starts with a paragraph of its own saying what kind of item it is.
+fn summary(text: &str) -> Option<String> {
+    let paragraph = text.split("\n\n").nth(1)?.trim();
+    // Undocumented items go straight to their fields or methods
+    if paragraph.is_empty() || paragraph.starts_with("It has ") || paragraph.starts_with('#') {
+        return None;
+    }
+    let line = paragraph.lines().next()?;
+    let sentence = line.find(". ").map_or(line, |end| &line[..=end]);
+    Some(sentence.trim().to_string())
+}
@@ -0,0 +1,174 @@
+use serde_json::{json, Map, Value};
+
+/// Features given as a comma separated list, like `bevy_ui,bevy_pbr`.
+pub fn features(list: Option<String>) -> Option<Vec<String>> {
+    list.map(|list| {
+        list.split(',')
+            .map(|feature| feature.trim().to_string())
+            .filter(|feature| !feature.is_empty())
+            .collect()
+    })
+}
+
+/// Restrictions on the items a query can return, matched against the metadata
+/// stored with each document.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub kind: Option<String>,
+    pub krate: Option<String>,
+    pub path_prefix: Option<String>,
+    /// Role of the item in the framework, like a Bevy component
+    pub role: Option<String>,
+    /// Cargo features enabled in the project of the user, leaving out items that
+    /// need other ones
+    pub features: Option<Vec<String>>,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.kind.is_none()
+            && self.krate.is_none()
+            && self.path_prefix.is_none()
+            && self.role.is_none()
+            && self.features.is_none()
+    }
+
+    /// Chroma `where` clause for the conditions it can evaluate. Path prefixes and
+    /// features can't be expressed and are checked with [`Filter::matches`] afterwards.
+    pub fn where_clause(&self) -> Option<Value> {
+        let mut conditions = vec![];
+        if let Some(kind) = &self.kind {
+            conditions.push(json!({ "kind": kind }));
+        }
+        if let Some(krate) = &self.krate {
+            conditions.push(json!({ "crate": krate }));
+        }
+        if let Some(role) = &self.role {
+            conditions.push(json!({ "role": role }));
+        }
+        match conditions.len() {
+            0 => None,
+            1 => conditions.pop(),
+            _ => Some(json!({ "$and": conditions })),
+        }
+    }
+
+    pub fn matches(&self, metadata: &Map<String, Value>) -> bool {
+        let field = |key: &str| metadata.get(key).and_then(Value::as_str);
+        if self
+            .kind
+            .as_deref()
+            .is_some_and(|kind| field("kind") != Some(kind))
+        {
+            return false;
+        }
+        if self
+            .krate
+            .as_deref()
+            .is_some_and(|krate| field("crate") != Some(krate))
+        {
+            return false;
+        }
+        if self
+            .role
+            .as_deref()
+            .is_some_and(|role| field("role") != Some(role))
+        {
+            return false;
+        }
+        if let (Some(enabled), Some(needed)) = (&self.features, field("features")) {
+            if !needed
+                .split(", ")
+                .all(|feature| enabled.iter().any(|enabled| enabled == feature))
+            {
+                return false;
+            }
+        }
+        let Some(prefix) = &self.path_prefix else {
+            return true;
+        };
+        let path = match (field("module"), field("name")) {
+            (Some(module), Some(name)) => format!("{}::{}", module, name),
+            (module, _) => module.unwrap_or_default().to_string(),
+        };
+        std::iter::once(path.as_str())
+            .chain(
+                field("aliases")
+                    .into_iter()
+                    .flat_map(|aliases| aliases.split(", ")),
+            )
+            .any(|path| under(path, prefix))
+    }
+}
+
+/// Whether a path is the prefix or an item inside it, `bevy::ecs` containing
+/// `bevy::ecs::world` but not `bevy::ecs_macros`.
+fn under(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches("::");
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_prefixes_stop_at_segments() {
+        assert!(under("bevy::ecs", "bevy::ecs"));
+        assert!(under("bevy::ecs::world::World", "bevy::ecs"));
+        assert!(under("bevy::ecs::world::World", "bevy::ecs::"));
+        assert!(!under("bevy::ecs_macros::Component", "bevy::ecs"));
+        assert!(!under("bevy", "bevy::ecs"));
+    }
+
+    fn metadata(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn filters_match_the_metadata() {
+        let item = metadata(json!({
+            "kind": "struct",
+            "crate": "bevy_ecs",
+            "module": "bevy_ecs::world",
+            "name": "World",
+            "aliases": "bevy::ecs::world::World, bevy::prelude::World",
+            "features": "multi_threaded",
+        }));
+        assert!(Filter::default().matches(&item));
+        let filter = |filter: Filter| filter.matches(&item);
+        assert!(filter(Filter {
+            kind: Some("struct".into()),
+            krate: Some("bevy_ecs".into()),
+            ..Filter::default()
+        }));
+        assert!(!filter(Filter {
+            kind: Some("trait".into()),
+            ..Filter::default()
+        }));
+        assert!(!filter(Filter {
+            role: Some("component".into()),
+            ..Filter::default()
+        }));
+        assert!(filter(Filter {
+            path_prefix: Some("bevy::prelude".into()),
+            ..Filter::default()
+        }));
+        assert!(!filter(Filter {
+            path_prefix: Some("bevy_ecs::system".into()),
+            ..Filter::default()
+        }));
+        assert!(filter(Filter {
+            features: Some(vec!["multi_threaded".into(), "bevy_ui".into()]),
+            ..Filter::default()
+        }));
+        assert!(!filter(Filter {
+            features: Some(vec!["bevy_ui".into()]),
+            ..Filter::default()
+        }));
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+/// Constant from the original reciprocal rank fusion paper, dampens the
+/// impact of the top ranks.
+const RRF_K: f32 = 60.0;
+
+/// Merge several rankings of the same documents into one, scoring each
+/// document by the sum of `1 / (k + rank)` over the rankings it appears in.
+pub fn reciprocal_rank_fusion(rankings: &[Vec<(String, f32)>], limit: usize) -> Vec<(String, f32)> {
+    let mut scores = HashMap::<&str, f32>::new();
+    for ranking in rankings {
+        for (rank, (id, _)) in ranking.iter().enumerate() {
+            *scores.entry(id).or_default() += 1.0 / (RRF_K + rank as f32 + 1.0);
+        }
+    }
+    let mut fused = scores
+        .into_iter()
+        .map(|(id, score)| (id.to_string(), score))
+        .collect::<Vec<_>>();
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    fused.truncate(limit);
+    fused
+}
+
+/// Multiply the score of each result by the boost of its kind, taken from the
+/// `kind:` prefix of its id, and sort again. Distances are divided instead so a
+/// boost above 1 always moves a result up.
+pub fn apply_boosts(results: &mut [(String, f32)], boosts: &HashMap<String, f32>, distances: bool) {
+    for (id, score) in results.iter_mut() {
+        let kind = id.split_once(':').map_or("", |(kind, _)| kind);
+        if let Some(boost) = boosts.get(kind) {
+            if distances {
+                *score /= boost;
+            } else {
+                *score *= boost;
+            }
+        }
+    }
+    sort(results, distances);
+}
+
+/// Favor results many items refer to, multiplying their score by
+/// `1 + weight * ln(1 + references)`, and sort again. Distances are divided instead.
+pub fn apply_popularity(
+    results: &mut [(String, f32)],
+    references: impl Fn(&str) -> usize,
+    weight: f32,
+    distances: bool,
+) {
+    rescale(
+        results,
+        |id| 1.0 + weight * (1.0 + references(id) as f32).ln(),
+        distances,
+    );
+}
+
+/// Lower the score of results shorter than `min_tokens`, in proportion to how much
+/// shorter they are down to half, so that documents saying little like undocumented
+/// unit structs don't come first for being close to any query. Kept results like
+/// exact matches of the query aren't changed.
+pub fn apply_length_penalty(
+    results: &mut [(String, f32)],
+    tokens: impl Fn(&str) -> usize,
+    min_tokens: usize,
+    kept: &[String],
+    distances: bool,
+) {
+    rescale(
+        results,
+        |id| {
+            if kept.iter().any(|kept| kept == id) {
+                return 1.0;
+            }
+            let length = tokens(id) as f32 / min_tokens.max(1) as f32;
+            0.5 + 0.5 * length.min(1.0)
+        },
+        distances,
+    );
+}
+
+/// Multiply the score of each result by a factor, dividing distances instead so a
+/// factor above 1 always moves a result up, and sort again.
+fn rescale(results: &mut [(String, f32)], factor: impl Fn(&str) -> f32, distances: bool) {
+    for (id, score) in results.iter_mut() {
+        let factor = factor(id);
+        if distances {
+            *score /= factor;
+        } else {
+            *score *= factor;
+        }
+    }
+    sort(results, distances);
+}
+
+fn sort(results: &mut [(String, f32)], distances: bool) {
+    if distances {
+        results.sort_by(|a, b| a.1.total_cmp(&b.1));
+    } else {
+        results.sort_by(|a, b| b.1.total_cmp(&a.1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranking(ids: &[&str]) -> Vec<(String, f32)> {
+        ids.iter().map(|id| (id.to_string(), 0.0)).collect()
+    }
+
+    fn ids(results: &[(String, f32)]) -> Vec<&str> {
+        results.iter().map(|(id, _)| id.as_str()).collect()
+    }
+
+    #[test]
+    fn documents_in_several_rankings_come_first() {
+        let fused =
+            reciprocal_rank_fusion(&[ranking(&["a", "b", "c"]), ranking(&["c", "d", "b"])], 10);
+        assert_eq!(ids(&fused), ["c", "b", "a", "d"]);
+        assert_eq!(fused[2].1, 1.0 / 61.0);
+        assert_eq!(fused[1].1, 1.0 / 62.0 + 1.0 / 63.0);
+    }
+
+    #[test]
+    fn fusion_keeps_the_limit() {
+        let fused = reciprocal_rank_fusion(&[ranking(&["a", "b", "c"])], 2);
+        assert_eq!(ids(&fused), ["a", "b"]);
+    }
+
+    #[test]
+    fn boosts_move_results_up_for_scores_and_distances() {
+        let boosts = HashMap::from([("struct".to_string(), 2.0)]);
+
+        let mut scores = vec![("fn:a".to_string(), 0.8), ("struct:b".to_string(), 0.5)];
+        apply_boosts(&mut scores, &boosts, false);
+        assert_eq!(ids(&scores), ["struct:b", "fn:a"]);
+        assert_eq!(scores[0].1, 1.0);
+
+        let mut distances = vec![("fn:a".to_string(), 0.3), ("struct:b".to_string(), 0.4)];
+        apply_boosts(&mut distances, &boosts, true);
+        assert_eq!(ids(&distances), ["struct:b", "fn:a"]);
+        assert_eq!(distances[0].1, 0.2);
+    }
+
+    #[test]
+    fn short_documents_are_penalized_unless_kept() {
+        let tokens = |id: &str| if id == "short" { 10 } else { 100 };
+        let mut results = vec![("short".to_string(), 1.0), ("long".to_string(), 0.9)];
+        apply_length_penalty(&mut results, tokens, 20, &[], false);
+        assert_eq!(ids(&results), ["long", "short"]);
+        assert_eq!(results[1].1, 0.75);
+
+        let mut results = vec![("short".to_string(), 1.0), ("long".to_string(), 0.9)];
+        apply_length_penalty(&mut results, tokens, 20, &["short".to_string()], false);
+        assert_eq!(ids(&results), ["short", "long"]);
+    }
+}
@@ -0,0 +1,198 @@
+use std::{collections::HashMap, sync::Arc};
+
+use rustdoc_types::{Item, ItemEnum, ItemKind};
+
+use crate::{
+    document::Document,
+    document_extension, document_struct, document_system, signature,
+    traversal::{document_primitive, CrateCatalog},
+};
+
+/// What a generator knows of the project besides the item.
+pub struct Context<'a> {
+    /// Id of the crate of the item in `crates`
+    pub current_crate: usize,
+    pub crates: &'a CrateCatalog,
+    /// Whether documents of structs list the traits of blanket impls
+    pub blanket_impls: bool,
+    /// Elide generic arguments of signatures nested deeper than this, 0 to keep them
+    pub signature_depth: usize,
+    /// Keep only this many bounds of the lists of bounds of signatures, 0 to keep
+    /// them all
+    pub signature_bounds: usize,
+}
+
+impl Context<'_> {
+    /// A signature or a type written in a document, shortened like the
+    /// `signature` metadata with --signature-depth and --signature-bounds.
+    pub fn simplify(&self, signature: &str) -> String {
+        signature::simplify(signature, self.signature_depth, self.signature_bounds)
+    }
+}
+
+/// Writes the documents of the items of one kind, registered for it with
+/// [`Options::with_generator`](crate::Options::with_generator).
+///
+/// ```no_run
+/// use rustdoc_rag::{
+///     document::Document,
+///     generator::{Context, DocumentGenerator, Generated},
+///     traversal::{item_metadata, item_path},
+///     Options,
+/// };
+/// use rustdoc_types::{Item, ItemKind};
+///
+/// struct Fields;
+///
+/// impl DocumentGenerator for Fields {
+///     fn generate(&self, item: &Item, context: &Context) -> Generated {
+///         let Some(name) = item.name.as_deref() else {
+///             return Ok(None);
+///         };
+///         Ok(Some(Document {
+///             path: item_path(item, context.current_crate, context.crates),
+///             kind: "struct".to_string(),
+///             text: format!("{} is a struct.\n\n", name),
+///             metadata: item_metadata(item, "struct", context.current_crate, context.crates),
+///         }))
+///     }
+/// }
+///
+/// let options = Options::new("jsons/bevy.json").with_generator(ItemKind::Struct, Fields);
+/// ```
+pub trait DocumentGenerator: Send + Sync {
+    /// Document of the item, `None` when it isn't documented.
+    fn generate(&self, item: &Item, context: &Context) -> Generated;
+}
+
+/// What a generator returns. Its error stops the traversal, and is sent across the
+/// threads generating documents in parallel.
+pub type Generated = Result<Option<Document>, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Document generators by kind of item, with the built-in ones for structs,
+/// functions, traits and primitives. Items of kinds without one aren't documented.
+#[derive(Clone)]
+pub struct Generators {
+    generators: HashMap<ItemKind, Arc<dyn DocumentGenerator>>,
+}
+
+impl Generators {
+    /// Use `generator` for the items of this kind, replacing the previous one.
+    pub fn insert(&mut self, kind: ItemKind, generator: impl DocumentGenerator + 'static) {
+        self.generators.insert(kind, Arc::new(generator));
+    }
+
+    pub fn remove(&mut self, kind: ItemKind) -> Option<Arc<dyn DocumentGenerator>> {
+        self.generators.remove(&kind)
+    }
+
+    pub fn get(&self, kind: ItemKind) -> Option<&dyn DocumentGenerator> {
+        self.generators.get(&kind).map(|generator| &**generator)
+    }
+}
+
+impl Default for Generators {
+    fn default() -> Self {
+        let mut generators = Generators {
+            generators: HashMap::new(),
+        };
+        generators.insert(ItemKind::Struct, Structs);
+        generators.insert(ItemKind::Function, Functions);
+        generators.insert(ItemKind::Trait, Traits);
+        generators.insert(ItemKind::Primitive, Primitives);
+        generators
+    }
+}
+
+impl std::fmt::Debug for Generators {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.generators.keys()).finish()
+    }
+}
+
+/// Kind of an item, to find its generator.
+pub fn kind(inner: &ItemEnum) -> ItemKind {
+    match inner {
+        ItemEnum::Module(_) => ItemKind::Module,
+        ItemEnum::ExternCrate { .. } => ItemKind::ExternCrate,
+        ItemEnum::Use(_) => ItemKind::Use,
+        ItemEnum::Union(_) => ItemKind::Union,
+        ItemEnum::Struct(_) => ItemKind::Struct,
+        ItemEnum::StructField(_) => ItemKind::StructField,
+        ItemEnum::Enum(_) => ItemKind::Enum,
+        ItemEnum::Variant(_) => ItemKind::Variant,
+        ItemEnum::Function(_) => ItemKind::Function,
+        ItemEnum::Trait(_) => ItemKind::Trait,
+        ItemEnum::TraitAlias(_) => ItemKind::TraitAlias,
+        ItemEnum::Impl(_) => ItemKind::Impl,
+        ItemEnum::TypeAlias(_) => ItemKind::TypeAlias,
+        ItemEnum::Constant { .. } => ItemKind::Constant,
+        ItemEnum::Static(_) => ItemKind::Static,
+        ItemEnum::ExternType => ItemKind::ExternType,
+        ItemEnum::Macro(_) => ItemKind::Macro,
+        ItemEnum::ProcMacro(proc_macro) => match proc_macro.kind {
+            rustdoc_types::MacroKind::Bang => ItemKind::Macro,
+            rustdoc_types::MacroKind::Attr => ItemKind::ProcAttribute,
+            rustdoc_types::MacroKind::Derive => ItemKind::ProcDerive,
+        },
+        ItemEnum::Primitive(_) => ItemKind::Primitive,
+        ItemEnum::AssocConst { .. } => ItemKind::AssocConst,
+        ItemEnum::AssocType { .. } => ItemKind::AssocType,
+    }
+}
+
+struct Structs;
+
+impl DocumentGenerator for Structs {
+    fn generate(&self, item: &Item, context: &Context) -> Generated {
+        let ItemEnum::Struct(stru) = &item.inner else {
+            return Ok(None);
+        };
+        Ok(Some(document_struct::document_struct(item, stru, context)))
+    }
+}
+
+struct Functions;
+
+impl DocumentGenerator for Functions {
+    fn generate(&self, item: &Item, context: &Context) -> Generated {
+        let ItemEnum::Function(function) = &item.inner else {
+            return Ok(None);
+        };
+        Ok(document_system::document_system(
+            item,
+            function,
+            context.current_crate,
+            context.crates,
+        ))
+    }
+}
+
+struct Traits;
+
+impl DocumentGenerator for Traits {
+    fn generate(&self, item: &Item, context: &Context) -> Generated {
+        let ItemEnum::Trait(trait_) = &item.inner else {
+            return Ok(None);
+        };
+        Ok(document_extension::document_extension(
+            item, trait_, context,
+        ))
+    }
+}
+
+struct Primitives;
+
+impl DocumentGenerator for Primitives {
+    fn generate(&self, item: &Item, context: &Context) -> Generated {
+        let ItemEnum::Primitive(primitive) = &item.inner else {
+            return Ok(None);
+        };
+        Ok(Some(document_primitive(
+            item,
+            primitive,
+            context.current_crate,
+            context.crates,
+        )))
+    }
+}
@@ -0,0 +1,67 @@
+use std::{collections::BTreeMap, error::Error, path::Path};
+
+use rustdoc_rag::document::{DocFormat, Document};
+
+/// Compare documents with a golden directory produced by a previous run,
+/// creating it if missing. Returns whether everything matched.
+pub fn check(dir: &Path, documents: &BTreeMap<String, Document>) -> Result<bool, Box<dyn Error>> {
+    if !dir.exists() {
+        for document in documents.values() {
+            document.emit(dir, DocFormat::Markdown)?;
+        }
+        println!(
+            "Wrote {} golden documents to {}",
+            documents.len(),
+            dir.display()
+        );
+        return Ok(true);
+    }
+
+    let mut expected = BTreeMap::new();
+    for kind_dir in std::fs::read_dir(dir)? {
+        let kind_dir = kind_dir?.path();
+        if !kind_dir.is_dir() {
+            continue;
+        }
+        for file in std::fs::read_dir(&kind_dir)? {
+            let file = file?.path();
+            if file.extension().is_some_and(|ext| ext == "md") {
+                let name = file.file_stem().unwrap().to_string_lossy().to_string();
+                expected.insert(
+                    (
+                        kind_dir.file_name().unwrap().to_string_lossy().to_string(),
+                        name,
+                    ),
+                    std::fs::read_to_string(&file)?,
+                );
+            }
+        }
+    }
+
+    let mut mismatches = 0;
+    for document in documents.values() {
+        let key = (format!("{}s", document.kind), document.file_stem());
+        match expected.remove(&key) {
+            Some(text) if text == document.text => {}
+            Some(_) => {
+                println!("changed: {}/{}", key.0, key.1);
+                mismatches += 1;
+            }
+            None => {
+                println!("added: {}/{}", key.0, key.1);
+                mismatches += 1;
+            }
+        }
+    }
+    for (kind, name) in expected.keys() {
+        println!("removed: {}/{}", kind, name);
+        mismatches += 1;
+    }
+
+    if mismatches == 0 {
+        println!("All {} documents match the golden output", documents.len());
+    } else {
+        println!("{} documents differ from the golden output", mismatches);
+    }
+    Ok(mismatches == 0)
+}
@@ -0,0 +1,287 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use rustdoc_types::{
+    GenericArg, GenericArgs, GenericBound, GenericParamDefKind, Generics, Id, ItemEnum, Type,
+    WherePredicate,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::traversal::CrateCatalog;
+
+/// How an item refers to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Relation {
+    /// A module contains the item
+    Contains,
+    /// The item has a field of this type
+    Field,
+    /// The item implements this trait
+    Implements,
+    /// A generic parameter of the item is bound by this trait
+    Bound,
+    /// A method of the item takes or returns this type
+    Signature,
+}
+
+impl Relation {
+    pub fn name(self) -> &'static str {
+        match self {
+            Relation::Contains => "contains",
+            Relation::Field => "field",
+            Relation::Implements => "implements",
+            Relation::Bound => "bound",
+            Relation::Signature => "signature",
+        }
+    }
+}
+
+/// Paths of the items each documented item refers to, through its fields, the
+/// signatures of its methods and the traits it implements or is bound by, and of
+/// the items each module contains.
+#[derive(Default)]
+pub struct Graph {
+    edges: BTreeMap<String, BTreeMap<String, Relation>>,
+}
+
+impl Graph {
+    pub fn path(out_dir: &Path, project: &str) -> PathBuf {
+        out_dir.join("graphs").join(format!("{}.json", project))
+    }
+
+    /// Add edges from an item, keeping the first relation in [`Relation`] order
+    /// when it refers to an item in several ways.
+    pub fn add(&mut self, from: &str, references: BTreeMap<String, Relation>) {
+        for (reference, relation) in references {
+            if reference == from {
+                continue;
+            }
+            self.edges
+                .entry(from.to_string())
+                .or_default()
+                .entry(reference)
+                .and_modify(|known| *known = (*known).min(relation))
+                .or_insert(relation);
+        }
+    }
+
+    /// Every edge, as `(from, to, relation)`.
+    pub fn edges(&self) -> impl Iterator<Item = (&str, &str, Relation)> {
+        self.edges.iter().flat_map(|(from, references)| {
+            references
+                .iter()
+                .map(move |(to, relation)| (from.as_str(), to.as_str(), *relation))
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&self.edges)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Option<Self> {
+        let edges = serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()?;
+        Some(Graph { edges })
+    }
+
+    /// Number of items referring to each item, leaving out the modules containing it.
+    pub fn inbound(&self) -> BTreeMap<&str, usize> {
+        let mut inbound = BTreeMap::new();
+        for (_, to, relation) in self.edges() {
+            if relation != Relation::Contains {
+                *inbound.entry(to).or_default() += 1;
+            }
+        }
+        inbound
+    }
+
+    /// Items the given one refers to or is referred to by, leaving out modules.
+    pub fn neighbours(&self, path: &str) -> BTreeSet<&str> {
+        self.edges()
+            .filter(|(_, _, relation)| *relation != Relation::Contains)
+            .filter_map(|(from, to, _)| match (from == path, to == path) {
+                (true, _) => Some(to),
+                (_, true) => Some(from),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The graph in the DOT language of Graphviz, with edges labelled by relation.
+    pub fn to_dot(&self) -> String {
+        let mut dot = "digraph items {\n    node [shape=box];\n".to_string();
+        for (from, to, relation) in self.edges() {
+            dot.push_str(&format!(
+                "    {:?} -> {:?} [label={:?}];\n",
+                from,
+                to,
+                relation.name()
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The graph in GraphML, with the relation as an edge attribute.
+    pub fn to_graphml(&self) -> String {
+        let escape = |text: &str| {
+            text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        };
+        let nodes = self
+            .edges()
+            .flat_map(|(from, to, _)| [from, to])
+            .collect::<BTreeSet<_>>();
+        let mut graphml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="relation" for="edge" attr.name="relation" attr.type="string"/>
+  <graph id="items" edgedefault="directed">
+"#
+        .to_string();
+        for node in nodes {
+            graphml.push_str(&format!("    <node id=\"{}\"/>\n", escape(node)));
+        }
+        for (from, to, relation) in self.edges() {
+            graphml.push_str(&format!(
+                "    <edge source=\"{}\" target=\"{}\"><data key=\"relation\">{}</data></edge>\n",
+                escape(from),
+                escape(to),
+                relation.name()
+            ));
+        }
+        graphml.push_str("  </graph>\n</graphml>\n");
+        graphml
+    }
+}
+
+/// Paths of the items a struct refers to, with how.
+pub fn struct_references(
+    stru: &rustdoc_types::Struct,
+    current_crate: usize,
+    crates: &CrateCatalog,
+) -> BTreeMap<String, Relation> {
+    let krate = &crates[current_crate].as_ref().unwrap().1;
+    let mut references = BTreeMap::new();
+    let mut add = |ids: Vec<Id>, relation| {
+        for path in ids
+            .iter()
+            .filter_map(|id| krate.paths.get(id))
+            .map(|summary| summary.path.join("::"))
+        {
+            references.entry(path).or_insert(relation);
+        }
+    };
+    let fields = match &stru.kind {
+        rustdoc_types::StructKind::Unit => vec![],
+        rustdoc_types::StructKind::Tuple(fields) => fields.iter().flatten().collect(),
+        rustdoc_types::StructKind::Plain { fields, .. } => fields.iter().collect(),
+    };
+    let mut ids = vec![];
+    for field in fields {
+        if let Some(ItemEnum::StructField(ty)) = krate.index.get(field).map(|item| &item.inner) {
+            type_references(ty, &mut ids);
+        }
+    }
+    add(ids, Relation::Field);
+    add(bound_references(&stru.generics), Relation::Bound);
+    let mut traits = vec![];
+    let mut ids = vec![];
+    for id in &stru.impls {
+        let Some(ItemEnum::Impl(imp)) = krate.index.get(id).map(|item| &item.inner) else {
+            continue;
+        };
+        if imp.is_synthetic || imp.blanket_impl.is_some() {
+            continue;
+        }
+        if let Some(trait_) = &imp.trait_ {
+            traits.push(trait_.id);
+            continue;
+        }
+        for method in &imp.items {
+            if let Some(ItemEnum::Function(function)) =
+                krate.index.get(method).map(|item| &item.inner)
+            {
+                for (_, ty) in &function.sig.inputs {
+                    type_references(ty, &mut ids);
+                }
+                if let Some(output) = &function.sig.output {
+                    type_references(output, &mut ids);
+                }
+            }
+        }
+    }
+    add(traits, Relation::Implements);
+    add(ids, Relation::Signature);
+    references
+}
+
+/// Traits bounding the generic parameters, in their declaration or where clause.
+fn bound_references(generics: &Generics) -> Vec<Id> {
+    let bounds = generics
+        .params
+        .iter()
+        .filter_map(|param| match &param.kind {
+            GenericParamDefKind::Type { bounds, .. } => Some(bounds),
+            _ => None,
+        })
+        .chain(
+            generics
+                .where_predicates
+                .iter()
+                .filter_map(|predicate| match predicate {
+                    WherePredicate::BoundPredicate { bounds, .. } => Some(bounds),
+                    _ => None,
+                }),
+        );
+    bounds
+        .flatten()
+        .filter_map(|bound| match bound {
+            GenericBound::TraitBound { trait_, .. } => Some(trait_.id),
+            _ => None,
+        })
+        .collect()
+}
+
+fn type_references(ty: &Type, ids: &mut Vec<Id>) {
+    match ty {
+        Type::ResolvedPath(path) => {
+            ids.push(path.id);
+            if let Some(GenericArgs::AngleBracketed { args, .. }) = path.args.as_deref() {
+                for arg in args {
+                    if let GenericArg::Type(ty) = arg {
+                        type_references(ty, ids);
+                    }
+                }
+            }
+        }
+        Type::DynTrait(dyn_trait) => {
+            ids.extend(dyn_trait.traits.iter().map(|poly| poly.trait_.id));
+        }
+        Type::ImplTrait(bounds) => {
+            for bound in bounds {
+                if let GenericBound::TraitBound { trait_, .. } = bound {
+                    ids.push(trait_.id);
+                }
+            }
+        }
+        Type::Tuple(types) => {
+            for ty in types {
+                type_references(ty, ids);
+            }
+        }
+        Type::Slice(ty)
+        | Type::Array { type_: ty, .. }
+        | Type::RawPointer { type_: ty, .. }
+        | Type::BorrowedRef { type_: ty, .. } => type_references(ty, ids),
+        _ => {}
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Map;
+
+use crate::{cleanup, document::Document, error};
+
+/// Documents of the sections of the markdown files given with --extra-docs, files
+/// or directories searched recursively, like a README or migration guides. An
+/// mdBook directory, with a `book.toml`, is read in the order of its summary.
+pub fn guide_documents(paths: &[PathBuf]) -> Result<Vec<Document>, Box<dyn Error>> {
+    let mut documents = vec![];
+    for path in paths {
+        if path.join("book.toml").is_file() {
+            documents.extend(book_documents(path)?);
+            continue;
+        }
+        // Files are named relative to the directory given, or by their file name
+        let base = if path.is_dir() {
+            path.as_path()
+        } else {
+            path.parent().unwrap_or(Path::new(""))
+        };
+        let mut files = vec![];
+        markdown_files(path, &mut files)?;
+        if files.is_empty() {
+            return Err(error::Error::Options(format!(
+                "couldn't find any markdown file in {}",
+                path.display()
+            ))
+            .into());
+        }
+        for file in files {
+            let name = file
+                .strip_prefix(base)
+                .unwrap_or(&file)
+                .to_string_lossy()
+                .replace('\\', "/");
+            documents.extend(sections(&name, &std::fs::read_to_string(&file)?));
+        }
+    }
+    Ok(documents)
+}
+
+/// A chapter listed in the `SUMMARY.md` of an mdBook.
+struct Chapter {
+    title: String,
+    /// Like `1.2.`, prefix and suffix chapters have none
+    number: Option<String>,
+    /// Title of the part of the book the chapter is in
+    part: Option<String>,
+    /// Markdown file of the chapter, relative to the source directory
+    file: String,
+}
+
+/// Documents of the sections of the chapters of an mdBook, with the book, part,
+/// chapter and section they are in as metadata.
+fn book_documents(dir: &Path) -> Result<Vec<Document>, Box<dyn Error>> {
+    let config = std::fs::read_to_string(dir.join("book.toml"))?.parse::<toml::Table>()?;
+    let book = config.get("book").and_then(toml::Value::as_table);
+    let field = |key| book.and_then(|book| book.get(key)?.as_str());
+    let dir_name = std::path::absolute(dir)?
+        .file_name()
+        .map_or("book".to_string(), |name| {
+            name.to_string_lossy().to_string()
+        });
+    let title = field("title").map_or(dir_name.clone(), str::to_string);
+    let src = dir.join(field("src").unwrap_or("src"));
+    let Ok(summary) = std::fs::read_to_string(src.join("SUMMARY.md")) else {
+        return Err(error::Error::Options(format!(
+            "couldn't find the SUMMARY.md of the book in {}",
+            src.display()
+        ))
+        .into());
+    };
+
+    let mut documents = vec![];
+    for chapter in summary_chapters(&summary) {
+        let Ok(content) = std::fs::read_to_string(src.join(&chapter.file)) else {
+            tracing::warn!(file = chapter.file, "missing chapter of the book");
+            continue;
+        };
+        let name = format!("{}/{}", dir_name, chapter.file);
+        let numbered = match &chapter.number {
+            Some(number) => format!("{} {}", number, chapter.title),
+            None => chapter.title.clone(),
+        };
+        for mut document in sections(&name, &content) {
+            document.text = format!(
+                "From the book {}, chapter {}.\n\n{}",
+                title, numbered, document.text
+            );
+            let metadata = &mut document.metadata;
+            let section = metadata.get("name").cloned();
+            metadata.insert("book".to_string(), title.clone().into());
+            metadata.insert("chapter".to_string(), chapter.title.clone().into());
+            if let Some(number) = &chapter.number {
+                metadata.insert("chapter_number".to_string(), number.clone().into());
+            }
+            if let Some(part) = &chapter.part {
+                metadata.insert("part".to_string(), part.clone().into());
+            }
+            if let Some(section) = section {
+                metadata.insert("section".to_string(), section);
+            }
+            documents.push(document);
+        }
+    }
+    Ok(documents)
+}
+
+/// Chapters of an mdBook summary in order: links in nested lists are numbered
+/// chapters, links outside of them prefix or suffix chapters, and headings after
+/// the first title parts. Draft chapters, without a file, are skipped.
+fn summary_chapters(summary: &str) -> Vec<Chapter> {
+    let mut chapters = vec![];
+    let mut part = None;
+    let mut seen_title = false;
+    // Indentation and number of the list levels the current line is nested in
+    let mut levels: Vec<(usize, usize)> = vec![];
+    for line in summary.lines() {
+        let trimmed = line.trim_start();
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            // The first heading is the title of the summary itself
+            if seen_title {
+                part = Some(heading.trim_start_matches('#').trim().to_string());
+                levels.clear();
+            }
+            seen_title = true;
+            continue;
+        }
+        // A separator ends the last part, before the suffix chapters
+        if trimmed.starts_with("---") {
+            part = None;
+            levels.clear();
+            continue;
+        }
+        let (item, listed) = match trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            Some(item) => (item.trim(), true),
+            None => (trimmed, false),
+        };
+        let Some((title, file)) = item
+            .strip_prefix('[')
+            .and_then(|item| item.split_once("]("))
+            .and_then(|(title, rest)| Some((title, rest.split_once(')')?.0)))
+        else {
+            continue;
+        };
+        let number = if listed {
+            let indent = line.len() - trimmed.len();
+            while levels.last().is_some_and(|(level, _)| *level > indent) {
+                levels.pop();
+            }
+            match levels.last_mut() {
+                Some((level, count)) if *level == indent => *count += 1,
+                _ => levels.push((indent, 1)),
+            }
+            Some(
+                levels
+                    .iter()
+                    .map(|(_, count)| format!("{}.", count))
+                    .collect::<String>(),
+            )
+        } else {
+            levels.clear();
+            None
+        };
+        if file.trim().is_empty() {
+            continue;
+        }
+        chapters.push(Chapter {
+            title: title.to_string(),
+            number,
+            part: part.clone(),
+            file: file.trim().trim_start_matches("./").to_string(),
+        });
+    }
+    chapters
+}
+
+fn markdown_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = std::fs::read_dir(path)?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    entries.sort();
+    for entry in entries {
+        let hidden = entry
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if entry.is_dir() && !hidden {
+            markdown_files(&entry, files)?;
+        } else if entry
+            .extension()
+            .is_some_and(|extension| extension == "md" || extension == "markdown")
+        {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// One document per section of a markdown file, starting at each heading outside of
+/// code blocks. The text of a section starts with the headings it is nested in.
+fn sections(file: &str, content: &str) -> Vec<Document> {
+    let mut documents = vec![];
+    let mut headings: Vec<(usize, String)> = vec![];
+    let mut body = String::new();
+    let mut in_code = false;
+    let mut slugs = HashMap::<String, usize>::new();
+    let mut flush = |headings: &[(usize, String)], body: &mut String| {
+        let text = std::mem::take(body);
+        if text.trim().is_empty() {
+            return;
+        }
+        let heading = headings.last().map(|(_, heading)| heading.as_str());
+        let mut path = file.to_string();
+        if let Some(heading) = heading {
+            // Repeated headings get a number, like GitHub anchors
+            let slug = slug(heading);
+            let count = slugs.entry(slug.clone()).or_default();
+            path.push('#');
+            path.push_str(&slug);
+            if *count > 0 {
+                path.push_str(&format!("-{}", count));
+            }
+            *count += 1;
+        }
+        let mut title = headings
+            .iter()
+            .map(|(_, heading)| heading.as_str())
+            .collect::<Vec<_>>()
+            .join(" > ");
+        if title.is_empty() {
+            title = file.to_string();
+        }
+        let mut metadata = Map::new();
+        metadata.insert("kind".to_string(), "guide".into());
+        metadata.insert("source".to_string(), "guide".into());
+        metadata.insert("file".to_string(), file.into());
+        metadata.insert("name".to_string(), heading.unwrap_or(file).into());
+        documents.push(Document {
+            path,
+            kind: "guide".to_string(),
+            text: format!("# {}\n\n{}\n", title, cleanup::clean(text.trim())),
+            metadata,
+        });
+    };
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        let level = line.chars().take_while(|c| *c == '#').count();
+        if !in_code && (1..=6).contains(&level) && line[level..].starts_with(' ') {
+            flush(&headings, &mut body);
+            headings.retain(|(parent, _)| *parent < level);
+            headings.push((level, line[level..].trim().to_string()));
+            continue;
+        }
+        body.push_str(line);
+        body.push('\n');
+    }
+    flush(&headings, &mut body);
+    documents
+}
+
+/// Anchor of a heading as GitHub and mdBook generate it.
+fn slug(heading: &str) -> String {
+    heading
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,373 @@
+use std::{cell::RefCell, collections::BTreeMap, error::Error, fmt::Write, rc::Rc, time::Instant};
+
+use hyper::{body::Incoming, Method, Request, Response, StatusCode};
+use rustdoc_rag::{
+    answer::{self, Answerer},
+    docs_rs,
+    filter::{features, Filter},
+    Options, Results, Retriever,
+};
+use serde_json::{json, Map, Value};
+use tokio::sync::mpsc;
+
+use crate::{
+    collections,
+    server::{self, Body},
+};
+
+/// Page searching and asking questions from a browser, served at `/`.
+const INDEX_HTML: &str = include_str!("http/index.html");
+
+/// Requests served and the time spent on them, for `/metrics`.
+#[derive(Default)]
+struct Metrics {
+    /// Number of requests and their seconds, by endpoint
+    requests: BTreeMap<String, (u64, f64)>,
+    /// Number of queries and their seconds, by stage like `embedding`
+    stages: BTreeMap<&'static str, (u64, f64)>,
+}
+
+impl Metrics {
+    fn request(&mut self, endpoint: String, start: Instant) {
+        let (count, seconds) = self.requests.entry(endpoint).or_default();
+        *count += 1;
+        *seconds += start.elapsed().as_secs_f64();
+    }
+
+    fn stages(&mut self, results: &Results) {
+        for (stage, elapsed) in &results.timings {
+            let (count, seconds) = self.stages.entry(stage).or_default();
+            *count += 1;
+            *seconds += elapsed;
+        }
+    }
+
+    /// The metrics in the text format of Prometheus.
+    fn prometheus(&self) -> String {
+        let mut text = String::new();
+        for (name, help, label, values) in [
+            (
+                "rustdoc_rag_request_seconds",
+                "Time spent answering requests, by endpoint",
+                "endpoint",
+                self.requests
+                    .iter()
+                    .map(|(endpoint, value)| (endpoint.as_str(), *value))
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                "rustdoc_rag_stage_seconds",
+                "Time spent in each stage of the queries",
+                "stage",
+                self.stages
+                    .iter()
+                    .map(|(stage, value)| (*stage, *value))
+                    .collect(),
+            ),
+        ] {
+            writeln!(text, "# HELP {} {}", name, help).unwrap();
+            writeln!(text, "# TYPE {} summary", name).unwrap();
+            for (value, (count, seconds)) in values {
+                writeln!(text, "{}_sum{{{}=\"{}\"}} {}", name, label, value, seconds).unwrap();
+                writeln!(text, "{}_count{{{}=\"{}\"}} {}", name, label, value, count).unwrap();
+            }
+        }
+        text
+    }
+}
+
+/// Serve the JSON API: `/query` and `/ask` with a `q` parameter and optional
+/// filters, `/items/<path>` and `/collections`. `/ask` streams the answer as
+/// Server-Sent Events when they are accepted. `/metrics` reports the requests
+/// served and the time spent in each stage for Prometheus. `/` is a page using
+/// them from a browser.
+pub async fn serve(
+    options: Options,
+    retriever: Retriever,
+    addr: &str,
+) -> Result<(), Box<dyn Error>> {
+    let state = Rc::new((options, retriever, RefCell::new(Metrics::default())));
+    server::listen(addr, move |request| {
+        let state = state.clone();
+        async move {
+            let start = Instant::now();
+            let endpoint = match request.uri().path() {
+                path @ ("/" | "/query" | "/ask" | "/collections" | "/metrics") => path.to_string(),
+                path if path.starts_with("/items/") => "/items".to_string(),
+                _ => "other".to_string(),
+            };
+            let streaming = request.uri().path() == "/ask"
+                && request
+                    .headers()
+                    .get("accept")
+                    .and_then(|accept| accept.to_str().ok())
+                    .is_some_and(|accept| accept.contains("text/event-stream"));
+            let (options, retriever, metrics) = &*state;
+            let response = if streaming {
+                ask_stream(state.clone(), request).await
+            } else {
+                route(options, retriever, metrics, request).await
+            };
+            // Streamed answers are only timed until their first event
+            metrics.borrow_mut().request(endpoint, start);
+            match response {
+                Ok(response) => response,
+                Err(err) => server::json(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    &json!({ "error": err.to_string() }),
+                ),
+            }
+        }
+    })
+    .await
+}
+
+/// Answer with a `sources` event listing the documents given to the model, a
+/// `token` event for each token generated, then a `citations` event with the
+/// sources the answer cites and a final `done` event, or an `error` event.
+async fn ask_stream(
+    state: Rc<(Options, Retriever, RefCell<Metrics>)>,
+    request: Request<Incoming>,
+) -> Result<Response<Body>, Box<dyn Error>> {
+    let Some(query) = query(&state.1, request).await? else {
+        return Ok(server::json(
+            StatusCode::BAD_REQUEST,
+            &json!({ "error": "missing q parameter" }),
+        ));
+    };
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::task::spawn_local(async move {
+        let (options, retriever, metrics) = &*state;
+        let send = |name: &str, data: Value| -> Result<(), Box<dyn Error>> {
+            sender
+                .send(server::event(name, &data.to_string()))
+                .map_err(|_| "the client closed the event stream".into())
+        };
+        let answered: Result<(), Box<dyn Error>> = async {
+            let mut results = retriever
+                .search_from(query.client.as_deref(), &query.query, &query.filter)
+                .await?;
+            if let Some(limit) = query.limit {
+                results.ranking.truncate(limit);
+            }
+            let documents = retriever.answer_context(&query.query, &results).await?;
+            let sources = documents
+                .iter()
+                .enumerate()
+                .map(|(i, (id, _))| {
+                    let metadata = results.metadatas.get(id).cloned().unwrap_or_default();
+                    json!({
+                        "number": i + 1,
+                        "id": id,
+                        "url": docs_rs::item_url(id, &metadata),
+                        "metadata": metadata,
+                    })
+                })
+                .collect::<Vec<_>>();
+            send("sources", json!({ "sources": sources }))?;
+            let start = Instant::now();
+            let answer = Answerer::new(options.chat_model()?, &options.project)
+                .with_mode(options.answer_mode)
+                .answer_streaming(&query.query, &documents, |token| match token {
+                    "" => Ok(()),
+                    token => send("token", json!({ "token": token })),
+                })
+                .await?;
+            results.time("answer", start);
+            metrics.borrow_mut().stages(&results);
+            let cited = answer::citations(&answer, &documents)
+                .into_iter()
+                .map(|(number, id)| json!({ "number": number, "id": id }))
+                .collect::<Vec<_>>();
+            send("citations", json!({ "citations": cited }))?;
+            send("done", json!({ "answer": answer }))
+        }
+        .await;
+        if let Err(err) = answered {
+            let _ = send("error", json!({ "error": err.to_string() }));
+        }
+    });
+    Ok(server::event_stream(receiver))
+}
+
+/// Parameters of a query, from the query string or a JSON body.
+struct Query {
+    query: String,
+    filter: Filter,
+    limit: Option<usize>,
+    /// Who asked, from the `X-Client-Id` header, for --query-log
+    client: Option<String>,
+}
+
+async fn query(
+    retriever: &Retriever,
+    request: Request<Incoming>,
+) -> Result<Option<Query>, Box<dyn Error>> {
+    let client = request
+        .headers()
+        .get("x-client-id")
+        .and_then(|client| client.to_str().ok())
+        .map(str::to_string);
+    let params = if request.method() == Method::POST {
+        server::read_json(request).await.unwrap_or_default()
+    } else {
+        let mut params = Map::new();
+        for name in [
+            "q",
+            "kind",
+            "crate",
+            "path_prefix",
+            "role",
+            "features",
+            "limit",
+        ] {
+            if let Some(value) = server::query_param(&request, name) {
+                params.insert(name.to_string(), value.into());
+            }
+        }
+        Value::Object(params)
+    };
+    let param = |name: &str| params[name].as_str().map(str::to_string);
+    let Some(query) = param("q").or_else(|| param("query")) else {
+        return Ok(None);
+    };
+    let defaults = retriever.filter();
+    Ok(Some(Query {
+        query,
+        filter: Filter {
+            kind: param("kind").or(defaults.kind.clone()),
+            krate: param("crate").or(defaults.krate.clone()),
+            path_prefix: param("path_prefix").or(defaults.path_prefix.clone()),
+            role: param("role").or(defaults.role.clone()),
+            features: features(param("features")).or(defaults.features.clone()),
+        },
+        limit: params["limit"]
+            .as_u64()
+            .or_else(|| params["limit"].as_str()?.parse().ok())
+            .map(|limit| limit as usize),
+        client,
+    }))
+}
+
+async fn route(
+    options: &Options,
+    retriever: &Retriever,
+    metrics: &RefCell<Metrics>,
+    request: Request<Incoming>,
+) -> Result<Response<Body>, Box<dyn Error>> {
+    let path = request.uri().path().to_string();
+    let missing_query = || {
+        server::json(
+            StatusCode::BAD_REQUEST,
+            &json!({ "error": "missing q parameter" }),
+        )
+    };
+    Ok(match path.as_str() {
+        "/query" => {
+            let Some(query) = query(retriever, request).await? else {
+                return Ok(missing_query());
+            };
+            let mut results = retriever
+                .search_from(query.client.as_deref(), &query.query, &query.filter)
+                .await?;
+            metrics.borrow_mut().stages(&results);
+            if let Some(limit) = query.limit {
+                results.ranking.truncate(limit);
+            }
+            server::json(StatusCode::OK, &results_json(&results))
+        }
+        "/ask" => {
+            let Some(query) = query(retriever, request).await? else {
+                return Ok(missing_query());
+            };
+            let mut results = retriever
+                .search_from(query.client.as_deref(), &query.query, &query.filter)
+                .await?;
+            if let Some(limit) = query.limit {
+                results.ranking.truncate(limit);
+            }
+            let documents = retriever.answer_context(&query.query, &results).await?;
+            let start = Instant::now();
+            let answer = Answerer::new(options.chat_model()?, &options.project)
+                .with_mode(options.answer_mode)
+                .answer_streaming(&query.query, &documents, |_| Ok(()))
+                .await?;
+            results.time("answer", start);
+            metrics.borrow_mut().stages(&results);
+            let sources = answer::citations(&answer, &documents)
+                .into_iter()
+                .map(|(number, id)| json!({ "number": number, "id": id }))
+                .collect::<Vec<_>>();
+            server::json(
+                StatusCode::OK,
+                &json!({ "answer": answer, "sources": sources }),
+            )
+        }
+        "/" => Response::builder()
+            .header("content-type", "text/html; charset=utf-8")
+            .body(server::full(
+                INDEX_HTML.replace("{project}", &options.project),
+            ))
+            .unwrap(),
+        "/metrics" => Response::builder()
+            .header("content-type", "text/plain; version=0.0.4")
+            .body(server::full(metrics.borrow().prometheus()))
+            .unwrap(),
+        "/collections" => {
+            let summaries = collections::summaries(options, &options.chroma_client()).await?;
+            let collections = summaries
+                .into_iter()
+                .map(|summary| {
+                    json!({
+                        "name": summary.name,
+                        "entries": summary.entries,
+                        "metadata": summary.metadata,
+                    })
+                })
+                .collect::<Vec<_>>();
+            server::json(StatusCode::OK, &json!({ "collections": collections }))
+        }
+        path => match path.strip_prefix("/items/") {
+            Some(item) => match retriever.item(&server::percent_decode(item))? {
+                Some(hit) => server::json(
+                    StatusCode::OK,
+                    &json!({
+                        "id": hit.id,
+                        "text": hit.text,
+                        "url": docs_rs::item_url(&hit.id, &hit.metadata),
+                        "metadata": hit.metadata,
+                    }),
+                ),
+                None => server::json(StatusCode::NOT_FOUND, &json!({ "error": "no such item" })),
+            },
+            None => server::json(StatusCode::NOT_FOUND, &json!({ "error": "not found" })),
+        },
+    })
+}
+
+fn results_json(results: &Results) -> Value {
+    let result = |id: &String, score: Option<f32>| {
+        let metadata = results.metadatas.get(id).cloned().unwrap_or_default();
+        json!({
+            "id": id,
+            "score": score,
+            "text": results.texts.get(id),
+            "url": docs_rs::item_url(id, &metadata),
+            "project": results.projects.get(id),
+            "metadata": metadata,
+        })
+    };
+    json!({
+        "results": results
+            .ranking
+            .iter()
+            .map(|(id, score)| result(id, Some(*score)))
+            .collect::<Vec<_>>(),
+        "related": results
+            .related
+            .iter()
+            .map(|id| result(id, None))
+            .collect::<Vec<_>>(),
+        "suggestions": results.suggestions,
+    })
+}
@@ -0,0 +1,554 @@
+use std::{
+    collections::HashSet,
+    error::Error,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use chromadb::v2::ChromaClient;
+use futures::StreamExt;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde_json::Value;
+
+use crate::{
+    cache::{self, EmbeddingCache},
+    catalog::ItemCatalog,
+    chroma, chunk,
+    communities::Communities,
+    document::{Document, SCHEMA_VERSION},
+    embedding::{Embedder, InputType},
+    error,
+    graph::Graph,
+    lexical::LexicalIndex,
+    manifest::Manifest,
+    store::{Entry, StoreKind, VectorStore},
+    summary,
+    traversal::{generate_documents, Traversal},
+    Options,
+};
+
+/// The vectors of one embedding model, with the model to embed queries.
+pub struct Index {
+    pub name: String,
+    pub embedder: Embedder,
+    pub store: VectorStore,
+    /// Whether the collection existed before being opened
+    pub exist: bool,
+    /// Length of the vectors the collection was indexed with
+    pub dimensions: Option<usize>,
+    /// Version of the document generator the collection was built by
+    schema: u64,
+}
+
+impl Index {
+    /// Version of the document generator an existing collection was built by, when
+    /// it's older than the current one and its documents may be missing what newer
+    /// ones have. [`Indexer::open`] doesn't index it again unless asked to.
+    pub fn stale(&self) -> Option<u64> {
+        (self.exist && self.schema < SCHEMA_VERSION).then_some(self.schema)
+    }
+
+    /// Open the collection of a model, creating it if needed, and check that it was
+    /// indexed with the same settings.
+    pub async fn open(
+        chroma: &ChromaClient,
+        options: &Options,
+        model: &str,
+    ) -> Result<Index, Box<dyn Error>> {
+        let prefixes = options.prefixes(model);
+        let mut embedder = options.embedder(model)?;
+        if !options.no_cache {
+            embedder = embedder.with_cache(EmbeddingCache::new(
+                options.out_dir.join("cache"),
+                options.provider,
+                model,
+            ));
+        }
+
+        let config = options.collection_config(model, &prefixes);
+        let collection_name = config.name();
+        let collection_meta = config.metadata();
+        let (store, exist) = match options.store {
+            StoreKind::Chroma => {
+                VectorStore::open_chroma(
+                    chroma,
+                    &collection_name,
+                    collection_meta.clone(),
+                    options.retry(),
+                )
+                .await?
+            }
+            StoreKind::Local => VectorStore::open_local(
+                options.local_index_path(&collection_name),
+                collection_meta.clone(),
+                options.quantize,
+                options.binary_prefilter,
+            )?,
+        };
+
+        let mut dimensions = None;
+        let mut schema = SCHEMA_VERSION;
+        if exist {
+            let stored = store.metadata().unwrap_or_default();
+            let mismatches = config.mismatches(&stored);
+            if options.recompute {
+                store.set_metadata(&collection_meta).await?;
+            } else if !mismatches.is_empty() {
+                return Err(error::Error::Mismatch {
+                    index: collection_name,
+                    reason: mismatches.join(", "),
+                }
+                .into());
+            }
+            if !options.recompute {
+                schema = stored
+                    .get("document_schema")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(0);
+                dimensions = stored
+                    .get("embedding_dimensions")
+                    .and_then(Value::as_u64)
+                    .map(|dimensions| dimensions as usize);
+            }
+        }
+
+        Ok(Index {
+            name: collection_name,
+            embedder,
+            store,
+            exist,
+            dimensions,
+            schema,
+        })
+    }
+
+    /// Fail when an embedding doesn't have the length of the vectors of the
+    /// collection, which a change of model or of its version gives under the same
+    /// name, instead of letting the store reject it.
+    pub fn check_dimensions(&self, embedding: &[f32]) -> error::Result<()> {
+        match self.dimensions {
+            Some(dimensions) if dimensions != embedding.len() => Err(error::Error::Mismatch {
+                index: self.name.clone(),
+                reason: format!(
+                    "its vectors have {} dimensions, but the embedding model gives {}",
+                    dimensions,
+                    embedding.len()
+                ),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Texts of the facets of a document embedded on their own with --multi-vector: its
+/// name with its path and aliases, and the signature of the item when known.
+fn facets(document: &Document) -> impl Iterator<Item = (&'static str, String)> + '_ {
+    let field = |key| document.metadata.get(key).and_then(|value| value.as_str());
+    let name = match (field("name"), field("aliases")) {
+        (Some(name), Some(aliases)) => {
+            format!("{} {} {}, {}", document.kind, name, document.path, aliases)
+        }
+        (Some(name), None) => format!("{} {} {}", document.kind, name, document.path),
+        _ => format!("{} {}", document.kind, document.path),
+    };
+    std::iter::once(("name", name))
+        .chain(field("signature").map(|signature| ("signature", signature.to_string())))
+}
+
+/// What indexing is doing, for applications showing their own progress with
+/// [`Indexer::with_events`].
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// The items of the project were traversed
+    ItemsDiscovered { items: usize, documents: usize },
+    /// A document is ready to be embedded, after its description and summary
+    DocumentGenerated { id: &'a str },
+    /// The documents that changed start being embedded for the index
+    EmbeddingStarted { index: &'a str, pending: usize },
+    /// A batch of entries was embedded for the index
+    BatchEmbedded { index: &'a str, size: usize },
+    /// A batch of entries was written to the vector store of the index
+    UpsertCompleted { index: &'a str, size: usize },
+    /// Ollama is downloading the embedding model, with the bytes downloaded out
+    /// of the total of the layer being downloaded when known
+    ModelDownloading {
+        model: &'a str,
+        status: &'a str,
+        completed: Option<u64>,
+        total: Option<u64>,
+    },
+    /// Something the project or the index is missing, indexing goes on
+    Warning { message: String },
+}
+
+pub(crate) type Callback = Arc<dyn Fn(&Event) + Send + Sync>;
+
+/// Generates the documents of a project and keeps its indexes up to date.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// use rustdoc_rag::{Indexer, Options, Retriever};
+///
+/// let indexer = Indexer::new(Options::new("jsons/bevy.json").with_update(true))?;
+/// let indexes = indexer.open().await?;
+/// let retriever = Retriever::new(indexer.options().clone(), indexes)?;
+/// for (id, _) in retriever.search("spawn an entity").await?.ranking {
+///     println!("{}", id);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Indexer {
+    options: Options,
+    project_json: PathBuf,
+    events: Option<Callback>,
+}
+
+impl Indexer {
+    /// Apply --offline and find the rustdoc JSON of the project, failing on
+    /// options that can't be used together or a version nothing matches.
+    pub fn new(mut options: Options) -> error::Result<Self> {
+        options.use_offline()?;
+        let project_json = options.project_json()?;
+        options.pin_version(&project_json)?;
+        Ok(Indexer {
+            options,
+            project_json,
+            events: None,
+        })
+    }
+
+    /// Call `callback` with what indexing is doing, instead of showing progress
+    /// bars.
+    pub fn with_events(mut self, callback: impl Fn(&Event) + Send + Sync + 'static) -> Self {
+        self.events = Some(Arc::new(callback));
+        self
+    }
+
+    fn emit(&self, event: Event) {
+        if let Some(callback) = &self.events {
+            callback(&event);
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub fn project_json(&self) -> &Path {
+        &self.project_json
+    }
+
+    /// Indexer of the same project with other options, like another distance.
+    pub fn with_options(&self, options: Options) -> Indexer {
+        Indexer {
+            options,
+            project_json: self.project_json.clone(),
+            events: self.events.clone(),
+        }
+    }
+
+    /// Generate the documents of the project, without embedding them.
+    pub fn documents(&self) -> Result<Traversal, Box<dyn Error>> {
+        Ok(self.generate()?)
+    }
+
+    /// Generate the documents, showing the progress and the missing dependencies
+    /// unless they are sent as events.
+    fn generate(&self) -> error::Result<Traversal> {
+        let traversal =
+            generate_documents(&self.options, &self.project_json, self.events.is_none())?;
+        for name in traversal.report.missing_crates.keys() {
+            self.emit(Event::Warning {
+                message: format!("missing the rustdoc JSON of {}", name),
+            });
+        }
+        Ok(traversal)
+    }
+
+    /// Open the index of every embedding model, embedding the documents when
+    /// they are missing, or the ones that changed with `update`.
+    pub async fn open(&self) -> error::Result<Vec<Index>> {
+        let options = &self.options;
+        let chroma = options.chroma_client();
+        if options.store == StoreKind::Chroma {
+            chroma::ensure_running(options, &chroma).await?;
+        }
+        let mut indexes = vec![];
+        for model in &options.embedding {
+            let mut index = Index::open(&chroma, options, model).await?;
+            if let Some(events) = &self.events {
+                index.embedder = index.embedder.with_events(events.clone());
+            }
+            indexes.push(index);
+        }
+
+        let mut rebuilt = false;
+        if indexes.iter().any(|index| !index.exist) || options.recompute || options.update {
+            let mut traversal = self.generate()?;
+            // Documents of another version would be stored under the pinned one
+            if let (Some(pinned), Some(found)) = (&options.pinned_version, &traversal.version) {
+                if pinned != found {
+                    return Err(error::Error::Version {
+                        index: options.index_name(),
+                        pinned: pinned.clone(),
+                        found: found.clone(),
+                    });
+                }
+            }
+            if options.describe_undocumented {
+                let start = Instant::now();
+                summary::describe_undocumented(
+                    options,
+                    &mut traversal.documents,
+                    &traversal.undocumented,
+                )
+                .await?;
+                traversal.report.time("descriptions", start);
+            }
+            if options.summarize {
+                let start = Instant::now();
+                summary::summarize_documents(options, &mut traversal.documents).await?;
+                traversal.report.time("summaries", start);
+            }
+            self.emit(Event::ItemsDiscovered {
+                items: traversal.report.visited.values().sum(),
+                documents: traversal.documents.len(),
+            });
+            for id in traversal.documents.keys() {
+                self.emit(Event::DocumentGenerated { id });
+            }
+            LexicalIndex::build(
+                &LexicalIndex::path(&options.out_dir, &options.index_name()),
+                &traversal.documents,
+            )?;
+            traversal
+                .graph
+                .save(&Graph::path(&options.out_dir, &options.index_name()))?;
+            ItemCatalog::write(
+                &ItemCatalog::path(&options.out_dir, &options.index_name()),
+                &traversal.documents,
+            )?;
+
+            for index in &indexes {
+                if index.exist && !options.recompute && !options.update {
+                    continue;
+                }
+                let start = Instant::now();
+                index.embedder.prepare().await?;
+                let mut manifest = Manifest::load(&options.out_dir, &index.name);
+                if !index.exist || options.recompute {
+                    manifest.hashes.clear();
+                }
+                let previous = std::mem::take(&mut manifest.hashes);
+
+                let mut current = HashSet::new();
+                let mut pending = vec![];
+                for (id, document) in &traversal.documents {
+                    let hash = cache::content_hash(&document.text);
+                    let unchanged = previous.get(id).is_some_and(|stored| *stored == hash);
+                    manifest.hashes.insert(id.clone(), hash);
+                    let chunks =
+                        chunk::chunk(&document.text, options.chunk_size, options.chunk_overlap);
+                    let chunked = chunks.len() > 1;
+                    for (i, text) in chunks.into_iter().enumerate() {
+                        let mut metadata = document.metadata.clone();
+                        let chunk_id = if chunked {
+                            metadata.insert("parent".to_string(), id.clone().into());
+                            metadata.insert("chunk".to_string(), i.into());
+                            chunk::chunk_id(id, i)
+                        } else {
+                            id.clone()
+                        };
+                        current.insert(chunk_id.clone());
+                        if !unchanged {
+                            pending.push((chunk_id, text.clone(), text, metadata));
+                        }
+                    }
+                    // Facets embed their own text, but keep the document for results
+                    for (facet, text) in facets(document).filter(|_| options.multi_vector) {
+                        let mut metadata = document.metadata.clone();
+                        metadata.insert("parent".to_string(), id.clone().into());
+                        metadata.insert("facet".to_string(), facet.into());
+                        let facet_id = chunk::facet_id(id, facet);
+                        current.insert(facet_id.clone());
+                        if !unchanged {
+                            pending.push((facet_id, text, document.text.clone(), metadata));
+                        }
+                    }
+                }
+                if index.exist && options.recompute {
+                    // Vectors of another model can't stay next to the new ones
+                    let ids = index.store.ids().await?;
+                    if !ids.is_empty() {
+                        index.store.delete(ids).await?;
+                    }
+                }
+                let stale = if index.exist && !options.recompute {
+                    index
+                        .store
+                        .ids()
+                        .await?
+                        .into_iter()
+                        .filter(|id| !current.contains(id))
+                        .collect::<Vec<_>>()
+                } else {
+                    vec![]
+                };
+
+                self.emit(Event::EmbeddingStarted {
+                    index: &index.name,
+                    pending: pending.len(),
+                });
+                let bars = if self.events.is_some() {
+                    MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+                } else {
+                    MultiProgress::new()
+                };
+                let style = ProgressStyle::with_template(
+                    "{msg:>10} [{bar:40}] {pos}/{len} ({per_sec}, ETA {eta})",
+                )
+                .unwrap()
+                .progress_chars("=> ");
+                let embedding_bar = bars.add(ProgressBar::new(pending.len() as u64));
+                embedding_bar.set_style(style.clone());
+                embedding_bar.set_message("embedding");
+                let upsert_bar = bars.add(ProgressBar::new(pending.len() as u64));
+                upsert_bar.set_style(style);
+                upsert_bar.set_message("upsert");
+
+                let mut embedded = futures::stream::iter(pending)
+                    .map(|(id, text, document, metadata)| async move {
+                        Ok::<_, Box<dyn Error>>(Entry {
+                            id,
+                            embedding: index
+                                .embedder
+                                .embeddings(&text, InputType::Document)
+                                .await?,
+                            document,
+                            metadata: Some(metadata),
+                        })
+                    })
+                    .buffer_unordered(options.concurrency.max(1));
+                let mut batch = vec![];
+                let mut dimensions = None;
+                while let Some(entry) = embedded.next().await {
+                    let entry = entry?;
+                    index.check_dimensions(&entry.embedding)?;
+                    dimensions = Some(entry.embedding.len());
+                    batch.push(entry);
+                    embedding_bar.inc(1);
+                    if batch.len() >= options.batch_size.max(1) {
+                        self.upsert(index, std::mem::take(&mut batch), &upsert_bar)
+                            .await?;
+                    }
+                }
+                if !batch.is_empty() {
+                    self.upsert(index, batch, &upsert_bar).await?;
+                }
+                embedding_bar.finish();
+                upsert_bar.finish();
+                if !stale.is_empty() {
+                    if self.events.is_some() {
+                        self.emit(Event::Warning {
+                            message: format!(
+                                "removing {} entries of {} for items that no longer exist",
+                                stale.len(),
+                                index.name
+                            ),
+                        });
+                    } else {
+                        eprintln!(
+                            "removing {} entries for items that no longer exist",
+                            stale.len()
+                        );
+                    }
+                    index.store.delete(stale).await?;
+                }
+                let mut metadata = index.store.metadata().unwrap_or_default();
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                metadata.entry("created_at").or_insert(now.into());
+                metadata.insert("updated_at".to_string(), now.into());
+                if let Some(dimensions) = dimensions {
+                    metadata.insert("embedding_dimensions".to_string(), dimensions.into());
+                }
+                metadata.insert("document_schema".to_string(), SCHEMA_VERSION.into());
+                metadata.insert("tool_version".to_string(), env!("CARGO_PKG_VERSION").into());
+                if let Some(version) = &traversal.version {
+                    metadata.insert("crate_version".to_string(), version.clone().into());
+                }
+                index.store.set_metadata(&metadata).await?;
+                index.store.flush()?;
+                manifest.save()?;
+                traversal
+                    .report
+                    .time(&format!("embedding {}", index.name), start);
+            }
+            traversal.report.write(&options.out_dir)?;
+            rebuilt = true;
+            if options.recompute || options.update {
+                for index in &mut indexes {
+                    index.schema = SCHEMA_VERSION;
+                }
+            }
+        }
+
+        let communities = Communities::path(&options.out_dir, &options.index_name());
+        if options.graph_rag && (rebuilt || !communities.exists()) {
+            let graph = Graph::load(&Graph::path(&options.out_dir, &options.index_name()));
+            let lexical =
+                LexicalIndex::open(&LexicalIndex::path(&options.out_dir, &options.index_name()))?;
+            let (Some(graph), Some(lexical)) = (graph, lexical) else {
+                return Err(error::Error::Missing {
+                    what: "item graph",
+                    index: options.index_name(),
+                });
+            };
+            let documents = lexical
+                .documents()?
+                .into_iter()
+                .map(|(id, text)| {
+                    let path = id
+                        .split_once(':')
+                        .map_or(id.clone(), |(_, path)| path.to_string());
+                    (path, text)
+                })
+                .collect();
+            Communities::build(
+                &graph,
+                &documents,
+                &options.chat_model()?,
+                &options.project,
+                options.context_tokens,
+                options.concurrency,
+            )
+            .await?
+            .save(&communities)?;
+        }
+        Ok(indexes)
+    }
+
+    async fn upsert(
+        &self,
+        index: &Index,
+        batch: Vec<Entry>,
+        bar: &ProgressBar,
+    ) -> Result<(), Box<dyn Error>> {
+        let size = batch.len();
+        self.emit(Event::BatchEmbedded {
+            index: &index.name,
+            size,
+        });
+        index.store.upsert(batch).await?;
+        bar.inc(size as u64);
+        self.emit(Event::UpsertCompleted {
+            index: &index.name,
+            size,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use tantivy::{
+    collector::TopDocs,
+    directory::MmapDirectory,
+    doc,
+    query::{FuzzyTermQuery, QueryParser, TermQuery},
+    schema::{Field, IndexRecordOption, Schema, Value as _, STORED, STRING, TEXT},
+    Index, IndexReader, IndexWriter, TantivyDocument, Term,
+};
+
+use crate::{document::Document, filter::Filter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Retrieval {
+    /// Nearest neighbours of the query embedding
+    Dense,
+    /// Keyword search over the documents
+    Lexical,
+    /// Both, merged with reciprocal rank fusion
+    Hybrid,
+}
+
+/// Memory budget of the tantivy writer, in bytes
+const WRITER_MEMORY: usize = 50_000_000;
+
+/// Edits allowed between an identifier and the names suggested for it, a swap of
+/// two letters counting as one
+const FUZZY_DISTANCE: u8 = 2;
+
+/// How many more hits than requested are fetched before applying filters
+const FILTER_OVERSAMPLING: usize = 10;
+
+/// A tantivy full-text index of the generated documents, stored next to the vector
+/// indexes.
+pub struct LexicalIndex {
+    index: Index,
+    reader: IndexReader,
+    id: Field,
+    text: Field,
+    /// Metadata of the document, as JSON
+    metadata: Field,
+    /// Identifiers split in words, so that `TextureAtlasLayout` also matches `atlas`
+    terms: Field,
+    /// Name of the item, for exact lookups
+    name: Field,
+    /// Canonical and re-export paths of the item, for exact lookups
+    paths: Field,
+}
+
+pub struct LexicalHit {
+    pub id: String,
+    pub score: f32,
+    pub text: String,
+    pub metadata: Map<String, Value>,
+}
+
+impl LexicalIndex {
+    pub fn path(out_dir: &Path, project: &str) -> PathBuf {
+        out_dir.join("fulltext").join(project)
+    }
+
+    fn open_or_create(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut schema = Schema::builder();
+        let id = schema.add_text_field("id", STRING | STORED);
+        let text = schema.add_text_field("text", TEXT | STORED);
+        let metadata = schema.add_text_field("metadata", STORED);
+        let terms = schema.add_text_field("terms", TEXT);
+        let name = schema.add_text_field("name", STRING | STORED);
+        let paths = schema.add_text_field("paths", STRING);
+        std::fs::create_dir_all(path)?;
+        let index = Index::open_or_create(MmapDirectory::open(path)?, schema.build())?;
+        Ok(LexicalIndex {
+            reader: index.reader()?,
+            index,
+            id,
+            text,
+            metadata,
+            terms,
+            name,
+            paths,
+        })
+    }
+
+    /// Replace the content of the index at `path` with the given documents.
+    pub fn build(
+        path: &Path,
+        documents: &BTreeMap<String, Document>,
+    ) -> Result<(), Box<dyn Error>> {
+        let index = match Self::open_or_create(path) {
+            Ok(index) => index,
+            // Written by a version with a different schema, start over
+            Err(_) => {
+                std::fs::remove_dir_all(path)?;
+                Self::open_or_create(path)?
+            }
+        };
+        let mut writer: IndexWriter = index.index.writer(WRITER_MEMORY)?;
+        writer.delete_all_documents()?;
+        for (id, document) in documents {
+            let field = |key: &str| document.metadata.get(key).and_then(Value::as_str);
+            let mut entry = doc!(
+                index.id => id.as_str(),
+                index.text => document.text.as_str(),
+                index.metadata => serde_json::to_string(&document.metadata)?,
+                index.terms => split_identifiers(&document.text),
+                index.name => field("name").unwrap_or_default(),
+                index.paths => document.path.as_str(),
+            );
+            for alias in field("aliases")
+                .into_iter()
+                .flat_map(|aliases| aliases.split(", "))
+            {
+                entry.add_text(index.paths, alias);
+            }
+            // Names of `pub use Foo as Bar` re-exports, to look the item up by them
+            for name in field("exported_as")
+                .into_iter()
+                .flat_map(|names| names.split(", "))
+            {
+                entry.add_text(index.name, name);
+            }
+            writer.add_document(entry)?;
+        }
+        writer.commit()?;
+        Ok(())
+    }
+
+    /// Text of every document of the index, by id.
+    pub fn documents(&self) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+        let searcher = self.reader.searcher();
+        let mut documents = BTreeMap::new();
+        for segment in searcher.segment_readers() {
+            let store = segment.get_store_reader(1)?;
+            for doc_id in segment.doc_ids_alive() {
+                let document: TantivyDocument = store.get(doc_id)?;
+                let field = |field| {
+                    document
+                        .get_first(field)
+                        .and_then(|value| value.as_str())
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                documents.insert(field(self.id), field(self.text));
+            }
+        }
+        Ok(documents)
+    }
+
+    /// Names and paths of the items of the index, their re-export paths included.
+    pub fn identifiers(&self) -> Result<BTreeSet<String>, Box<dyn Error>> {
+        let searcher = self.reader.searcher();
+        let mut identifiers = BTreeSet::new();
+        for segment in searcher.segment_readers() {
+            let store = segment.get_store_reader(1)?;
+            for doc_id in segment.doc_ids_alive() {
+                let document: TantivyDocument = store.get(doc_id)?;
+                for name in document
+                    .get_all(self.name)
+                    .filter_map(|value| value.as_str())
+                {
+                    identifiers.insert(name.to_string());
+                }
+                if let Some((_, path)) = document
+                    .get_first(self.id)
+                    .and_then(|value| value.as_str())
+                    .and_then(|id| id.split_once(':'))
+                {
+                    identifiers.insert(path.to_string());
+                }
+                let metadata = document
+                    .get_first(self.metadata)
+                    .and_then(|value| value.as_str())
+                    .and_then(|metadata| serde_json::from_str::<Map<String, Value>>(metadata).ok());
+                if let Some(aliases) = metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.get("aliases")?.as_str())
+                {
+                    identifiers.extend(aliases.split(", ").map(str::to_string));
+                }
+            }
+        }
+        identifiers.remove("");
+        Ok(identifiers)
+    }
+
+    pub fn open(path: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        if !path.join("meta.json").exists() {
+            return Ok(None);
+        }
+        // An index with an outdated schema is rebuilt on the next update
+        Ok(Self::open_or_create(path).ok())
+    }
+
+    /// Documents of items with exactly this name, or this path if it has several
+    /// segments.
+    pub fn lookup(
+        &self,
+        identifier: &str,
+        n_results: usize,
+        filter: &Filter,
+    ) -> Result<Vec<LexicalHit>, Box<dyn Error>> {
+        let field = if identifier.contains("::") {
+            self.paths
+        } else {
+            self.name
+        };
+        let query = TermQuery::new(
+            Term::from_field_text(field, identifier),
+            IndexRecordOption::Basic,
+        );
+        let mut hits = self.run(&query, n_results * FILTER_OVERSAMPLING)?;
+        hits.retain(|hit| filter.matches(&hit.metadata));
+        hits.truncate(n_results);
+        Ok(hits)
+    }
+
+    /// Best matching documents for a free text query, best first.
+    pub fn search(
+        &self,
+        query: &str,
+        n_results: usize,
+        filter: &Filter,
+    ) -> Result<Vec<LexicalHit>, Box<dyn Error>> {
+        let parser = QueryParser::for_index(&self.index, vec![self.text, self.terms]);
+        let query = split_identifiers(query)
+            .split_whitespace()
+            .filter(|word| word.chars().all(char::is_alphanumeric))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let (query, _) = parser.parse_query_lenient(&query);
+        if filter.is_empty() {
+            return self.run(&*query, n_results);
+        }
+        let mut hits = self.run(&*query, n_results * FILTER_OVERSAMPLING)?;
+        hits.retain(|hit| filter.matches(&hit.metadata));
+        hits.truncate(n_results);
+        Ok(hits)
+    }
+
+    /// Search with tantivy's query syntax, supporting `"exact phrases"`, `+required`
+    /// and `-excluded` terms.
+    pub fn exact_search(
+        &self,
+        query: &str,
+        n_results: usize,
+    ) -> Result<Vec<LexicalHit>, Box<dyn Error>> {
+        let parser = QueryParser::for_index(&self.index, vec![self.text]);
+        let query = parser.parse_query(query)?;
+        self.run(&*query, n_results)
+    }
+
+    /// Names of items within a couple of typos of the last segment of the identifier.
+    pub fn similar_names(
+        &self,
+        identifier: &str,
+        n_results: usize,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let name = identifier.rsplit("::").next().unwrap_or(identifier);
+        let query =
+            FuzzyTermQuery::new(Term::from_field_text(self.name, name), FUZZY_DISTANCE, true);
+        let searcher = self.reader.searcher();
+        let mut names = vec![];
+        for (_, address) in searcher.search(&query, &TopDocs::with_limit(n_results * 4))? {
+            let document: TantivyDocument = searcher.doc(address)?;
+            if let Some(similar) = document
+                .get_first(self.name)
+                .and_then(|value| value.as_str())
+            {
+                if similar != name && !names.iter().any(|known| known == similar) {
+                    names.push(similar.to_string());
+                }
+            }
+        }
+        names.truncate(n_results);
+        Ok(names)
+    }
+
+    fn run(
+        &self,
+        query: &dyn tantivy::query::Query,
+        n_results: usize,
+    ) -> Result<Vec<LexicalHit>, Box<dyn Error>> {
+        let searcher = self.reader.searcher();
+        let mut hits = vec![];
+        for (score, address) in searcher.search(query, &TopDocs::with_limit(n_results))? {
+            let document: TantivyDocument = searcher.doc(address)?;
+            let field = |field| {
+                document
+                    .get_first(field)
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            hits.push(LexicalHit {
+                id: field(self.id),
+                score,
+                text: field(self.text),
+                metadata: serde_json::from_str(&field(self.metadata)).unwrap_or_default(),
+            });
+        }
+        Ok(hits)
+    }
+}
+
+/// The text with camel case and snake case identifiers followed by their parts.
+pub(crate) fn split_identifiers(text: &str) -> String {
+    let mut terms = vec![];
+    for word in text
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+    {
+        terms.push(word.to_string());
+        let mut parts = vec![];
+        let mut current = String::new();
+        for c in word.chars() {
+            if c == '_' || (c.is_uppercase() && !current.is_empty()) {
+                if !current.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+                if c == '_' {
+                    continue;
+                }
+            }
+            current.push(c);
+        }
+        if !current.is_empty() {
+            parts.push(current);
+        }
+        if parts.len() > 1 {
+            terms.extend(parts);
+        }
+    }
+    terms.join(" ")
+}
+
+/// Whether the query is a bare or path-qualified Rust identifier, like `Transform`
+/// or `bevy_ecs::system::Commands`.
+pub fn is_identifier(query: &str) -> bool {
+    let query = query.trim();
+    !query.is_empty()
+        && query.split("::").all(|segment| {
+            segment
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_alphabetic() || c == '_')
+                && segment.chars().all(|c| c.is_alphanumeric() || c == '_')
+        })
+}
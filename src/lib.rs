@@ -0,0 +1,113 @@
+//! Retrieval of Rust documentation for prompts, from the rustdoc JSON of a project
+//! and of its dependencies.
+//!
+//! An [`Indexer`] generates a markdown document for every public item and embeds
+//! them in a vector store, and a [`Retriever`] finds the documents answering a
+//! prompt. Both are configured with [`Options`], which are also the options of
+//! the `rustdoc-rag` command line.
+//!
+//! Without the default `native` feature, only the [`engine`] querying an index
+//! exported with `rustdoc-rag export-site` is built, which also compiles to wasm32.
+
+#[cfg(feature = "native")]
+pub mod answer;
+#[cfg(feature = "native")]
+pub mod cache;
+#[cfg(feature = "native")]
+pub mod cargo;
+#[cfg(feature = "native")]
+pub mod catalog;
+#[cfg(feature = "native")]
+pub mod chroma;
+pub mod chunk;
+#[cfg(feature = "native")]
+mod cleanup;
+#[cfg(feature = "native")]
+pub mod collection;
+#[cfg(feature = "native")]
+pub mod communities;
+#[cfg(feature = "native")]
+pub mod credentials;
+#[cfg(feature = "native")]
+pub mod dedup;
+#[cfg(feature = "native")]
+pub mod docs_rs;
+#[cfg(feature = "native")]
+pub mod document;
+#[cfg(feature = "native")]
+mod document_extension;
+#[cfg(feature = "native")]
+mod document_struct;
+#[cfg(feature = "native")]
+mod document_system;
+#[cfg(feature = "native")]
+pub mod embedding;
+#[cfg(feature = "native")]
+pub mod endpoint;
+pub mod engine;
+#[cfg(feature = "native")]
+pub mod error;
+#[cfg(feature = "native")]
+pub mod example;
+#[cfg(feature = "native")]
+pub mod expansion;
+pub mod filter;
+pub mod fusion;
+#[cfg(feature = "native")]
+pub mod generator;
+#[cfg(feature = "native")]
+pub mod graph;
+#[cfg(feature = "native")]
+pub mod guide;
+#[cfg(feature = "native")]
+mod indexer;
+#[cfg(feature = "native")]
+pub mod lexical;
+#[cfg(feature = "native")]
+pub mod llm;
+#[cfg(feature = "native")]
+pub mod local_store;
+#[cfg(feature = "native")]
+pub mod manifest;
+pub mod mmr;
+#[cfg(feature = "native")]
+mod options;
+#[cfg(feature = "native")]
+mod plugin;
+#[cfg(feature = "native")]
+pub mod query_log;
+#[cfg(feature = "native")]
+pub mod report;
+#[cfg(feature = "native")]
+pub mod rerank;
+#[cfg(feature = "native")]
+pub mod retry;
+#[cfg(feature = "native")]
+pub mod search;
+#[cfg(feature = "native")]
+mod signature;
+#[cfg(feature = "native")]
+mod source;
+#[cfg(feature = "native")]
+pub mod store;
+#[cfg(feature = "native")]
+mod summary;
+#[cfg(feature = "native")]
+mod template;
+#[cfg(feature = "native")]
+pub mod traversal;
+#[cfg(feature = "native")]
+pub mod watch;
+
+#[cfg(feature = "native")]
+pub use error::{Error, Result};
+#[cfg(feature = "native")]
+pub use indexer::{Event, Index, Indexer};
+#[cfg(feature = "native")]
+pub use lexical::Retrieval;
+#[cfg(feature = "native")]
+pub use options::{Distance, Options, TokenHeader};
+#[cfg(feature = "native")]
+pub use search::{Results, Retriever};
+#[cfg(feature = "native")]
+pub use store::StoreKind;
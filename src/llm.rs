@@ -0,0 +1,134 @@
+use std::error::Error;
+
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+use crate::{credentials, endpoint::OllamaEndpoint, error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LlmProvider {
+    Ollama,
+    /// Any server implementing OpenAI's chat completions API
+    Openai,
+}
+
+/// A chat model, used to answer questions and rerank results.
+pub struct Llm {
+    provider: LlmProvider,
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+impl Llm {
+    /// Ollama is reached at `base_url` when given, at the configured endpoint
+    /// otherwise.
+    pub fn new(
+        provider: LlmProvider,
+        base_url: Option<&str>,
+        model: &str,
+        ollama: &OllamaEndpoint,
+    ) -> Self {
+        let (url, api_key) = match provider {
+            LlmProvider::Ollama => (
+                format!(
+                    "{}/api/chat",
+                    base_url.unwrap_or(&ollama.url).trim_end_matches('/')
+                ),
+                None,
+            ),
+            LlmProvider::Openai => (
+                format!(
+                    "{}/chat/completions",
+                    base_url
+                        .unwrap_or("https://api.openai.com/v1")
+                        .trim_end_matches('/')
+                ),
+                credentials::get("OPENAI_API_KEY"),
+            ),
+        };
+        Llm {
+            provider,
+            client: match provider {
+                LlmProvider::Ollama => ollama.client.clone(),
+                LlmProvider::Openai => reqwest::Client::new(),
+            },
+            url,
+            api_key,
+            model: model.to_string(),
+        }
+    }
+
+    /// Send the messages to the model, calling `on_token` with every piece of the
+    /// reply as it's generated. Returns the complete reply.
+    pub async fn chat(
+        &self,
+        messages: Value,
+        on_token: impl FnMut(&str) -> Result<(), Box<dyn Error>>,
+    ) -> Result<String, Box<dyn Error>> {
+        self.stream(messages, on_token)
+            .await
+            .map_err(|err| error::Error::llm(err).into())
+    }
+
+    async fn stream(
+        &self,
+        messages: Value,
+        mut on_token: impl FnMut(&str) -> Result<(), Box<dyn Error>>,
+    ) -> Result<String, Box<dyn Error>> {
+        let mut request = self.client.post(&self.url).json(&json!({
+            "model": self.model,
+            "messages": messages,
+            "stream": true,
+        }));
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let mut response = match request
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+        {
+            Ok(response) => response,
+            Err(err) => {
+                return Err(format!("{} with the model {}: {}", self.url, self.model, err).into())
+            }
+        };
+
+        // Both APIs stream one event per line, that can be split across chunks
+        let mut reply = String::new();
+        let mut pending = vec![];
+        while let Some(chunk) = response.chunk().await? {
+            pending.extend_from_slice(&chunk);
+            while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+                let line = pending.drain(..=end).collect::<Vec<_>>();
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                if let Some(content) = self.parse_event(&line)? {
+                    on_token(&content)?;
+                    reply.push_str(&content);
+                }
+            }
+        }
+        Ok(reply)
+    }
+
+    /// Text added to the reply by a streamed event, if any.
+    fn parse_event(&self, line: &[u8]) -> Result<Option<String>, Box<dyn Error>> {
+        let line = std::str::from_utf8(line)?.trim();
+        let event: Value = match self.provider {
+            LlmProvider::Ollama => serde_json::from_str(line)?,
+            LlmProvider::Openai => match line.strip_prefix("data:").map(str::trim) {
+                Some("[DONE]") | None => return Ok(None),
+                Some(data) => serde_json::from_str(data)?,
+            },
+        };
+        let content = match self.provider {
+            LlmProvider::Ollama => &event["message"]["content"],
+            LlmProvider::Openai => &event["choices"][0]["delta"]["content"],
+        };
+        Ok(content.as_str().map(str::to_string))
+    }
+}
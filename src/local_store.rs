@@ -0,0 +1,404 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::{
+    error,
+    filter::Filter,
+    store::{Entry, Hit},
+};
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum Quantization {
+    None,
+    Int8,
+}
+
+/// How many more candidates than requested the binary prefilter keeps for
+/// re-scoring with the full vectors.
+const PREFILTER_OVERSAMPLING: usize = 10;
+
+/// A brute-force vector index kept in memory and persisted to a single file.
+pub struct LocalIndex {
+    path: PathBuf,
+    data: Mutex<LocalData>,
+    binary_prefilter: bool,
+    /// Modification time of the file when it was last read or written, to reload
+    /// it when another process updates it
+    modified: Mutex<Option<SystemTime>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LocalData {
+    /// Collection metadata, serialized as JSON as bincode can't handle `Value`
+    metadata: String,
+    quantization: Quantization,
+    entries: BTreeMap<String, StoredVector>,
+    /// Sign bits of every vector, used to cheaply discard most candidates
+    /// before computing full distances
+    binary: Option<BTreeMap<String, Vec<u64>>>,
+    /// Metadata of every entry, parsed once when the index is read
+    #[serde(with = "json_strings")]
+    entry_metadata: BTreeMap<String, Map<String, Value>>,
+    documents: BTreeMap<String, String>,
+}
+
+/// Metadata of entries stored as JSON strings, as bincode can't handle `Value`.
+mod json_strings {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        metadata: &BTreeMap<String, Map<String, Value>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut strings = BTreeMap::new();
+        for (id, metadata) in metadata {
+            let json = serde_json::to_string(metadata).map_err(serde::ser::Error::custom)?;
+            strings.insert(id, json);
+        }
+        strings.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<String, Map<String, Value>>, D::Error> {
+        BTreeMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(id, json)| {
+                let metadata = serde_json::from_str(&json).map_err(serde::de::Error::custom)?;
+                Ok((id, metadata))
+            })
+            .collect()
+    }
+}
+
+impl LocalData {
+    /// Build the sign bits of an index stored without them when the prefilter is
+    /// asked for, and drop them otherwise.
+    fn use_binary_prefilter(&mut self, binary_prefilter: bool) {
+        match (&self.binary, binary_prefilter) {
+            (None, true) => {
+                self.binary = Some(
+                    self.entries
+                        .iter()
+                        .map(|(id, vector)| (id.clone(), binarize(&vector.to_f32())))
+                        .collect(),
+                )
+            }
+            (Some(_), false) => self.binary = None,
+            _ => {}
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum StoredVector {
+    F32(Vec<f32>),
+    Int8 { scale: f32, values: Vec<i8> },
+}
+
+impl StoredVector {
+    fn new(embedding: Vec<f32>, quantization: Quantization) -> Self {
+        match quantization {
+            Quantization::None => StoredVector::F32(embedding),
+            Quantization::Int8 => {
+                let max = embedding.iter().fold(0.0f32, |max, v| max.max(v.abs()));
+                let scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+                StoredVector::Int8 {
+                    scale,
+                    values: embedding
+                        .iter()
+                        .map(|v| (v / scale).round().clamp(-127.0, 127.0) as i8)
+                        .collect(),
+                }
+            }
+        }
+    }
+
+    fn to_f32(&self) -> Vec<f32> {
+        match self {
+            StoredVector::F32(values) => values.clone(),
+            StoredVector::Int8 { scale, values } => {
+                values.iter().map(|v| *v as f32 * scale).collect()
+            }
+        }
+    }
+
+    fn distance(&self, query: &[f32], space: &str) -> f32 {
+        let (dot, norm, sq) = match self {
+            StoredVector::F32(values) => components(query, values.iter().copied()),
+            StoredVector::Int8 { scale, values } => {
+                components(query, values.iter().map(|v| *v as f32 * scale))
+            }
+        };
+        match space {
+            "ip" => 1.0 - dot,
+            "cosine" => {
+                let query_norm = query.iter().map(|v| v * v).sum::<f32>().sqrt();
+                if norm == 0.0 || query_norm == 0.0 {
+                    1.0
+                } else {
+                    1.0 - dot / (norm.sqrt() * query_norm)
+                }
+            }
+            _ => sq,
+        }
+    }
+}
+
+fn binarize(embedding: &[f32]) -> Vec<u64> {
+    embedding
+        .chunks(64)
+        .map(|chunk| {
+            chunk.iter().enumerate().fold(
+                0u64,
+                |bits, (i, v)| if *v > 0.0 { bits | 1 << i } else { bits },
+            )
+        })
+        .collect()
+}
+
+fn hamming(a: &[u64], b: &[u64]) -> u32 {
+    a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum()
+}
+
+/// Dot product, squared norm of the stored vector, and squared L2 distance.
+fn components(query: &[f32], stored: impl Iterator<Item = f32>) -> (f32, f32, f32) {
+    query
+        .iter()
+        .zip(stored)
+        .fold((0.0, 0.0, 0.0), |(dot, norm, sq), (q, s)| {
+            (dot + q * s, norm + s * s, sq + (q - s) * (q - s))
+        })
+}
+
+impl LocalIndex {
+    pub fn open(
+        path: PathBuf,
+        metadata: Map<String, Value>,
+        quantization: Quantization,
+        binary_prefilter: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut data = if path.exists() {
+            let data: LocalData = bincode::deserialize(&std::fs::read(&path)?)?;
+            if data.quantization != quantization {
+                return Err(error::Error::Mismatch {
+                    index: path.display().to_string(),
+                    reason: format!(
+                        "its vectors were quantized with {:?}, but {:?} was requested",
+                        data.quantization, quantization
+                    ),
+                }
+                .into());
+            }
+            data
+        } else {
+            LocalData {
+                metadata: serde_json::to_string(&metadata)?,
+                quantization,
+                entries: BTreeMap::new(),
+                binary: binary_prefilter.then(BTreeMap::new),
+                entry_metadata: BTreeMap::new(),
+                documents: BTreeMap::new(),
+            }
+        };
+        data.use_binary_prefilter(binary_prefilter);
+        Ok(LocalIndex {
+            modified: Mutex::new(modified(&path)),
+            path,
+            data: Mutex::new(data),
+            binary_prefilter,
+        })
+    }
+
+    /// Read the file again if it was written since it was last read, as when
+    /// `--watch` updates the index of a running `serve`.
+    fn reload(&self) {
+        let current = modified(&self.path);
+        let mut last = self.modified.lock().unwrap();
+        if current == *last {
+            return;
+        }
+        match std::fs::read(&self.path)
+            .ok()
+            .and_then(|bytes| bincode::deserialize::<LocalData>(&bytes).ok())
+        {
+            Some(mut data) => {
+                data.use_binary_prefilter(self.binary_prefilter);
+                *self.data.lock().unwrap() = data;
+                *last = current;
+            }
+            // Possibly still being written, tried again on the next query
+            None => tracing::debug!("couldn't reload the local index"),
+        }
+    }
+
+    pub fn metadata(&self) -> Map<String, Value> {
+        serde_json::from_str(&self.data.lock().unwrap().metadata).unwrap_or_default()
+    }
+
+    pub fn set_metadata(&self, metadata: Map<String, Value>) {
+        self.data.lock().unwrap().metadata = serde_json::to_string(&metadata).unwrap();
+    }
+
+    pub fn upsert(&self, entries: Vec<Entry>) {
+        let mut data = self.data.lock().unwrap();
+        let quantization = data.quantization;
+        for entry in entries {
+            if let Some(binary) = &mut data.binary {
+                binary.insert(entry.id.clone(), binarize(&entry.embedding));
+            }
+            match entry.metadata {
+                Some(metadata) => data.entry_metadata.insert(entry.id.clone(), metadata),
+                None => data.entry_metadata.remove(&entry.id),
+            };
+            data.documents.insert(entry.id.clone(), entry.document);
+            data.entries
+                .insert(entry.id, StoredVector::new(entry.embedding, quantization));
+        }
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.data.lock().unwrap().entries.keys().cloned().collect()
+    }
+
+    pub fn entry_metadatas(&self) -> Vec<Map<String, Value>> {
+        let data = self.data.lock().unwrap();
+        data.entries
+            .keys()
+            .map(|id| data.entry_metadata.get(id).cloned().unwrap_or_default())
+            .collect()
+    }
+
+    pub fn entries(&self) -> Vec<Entry> {
+        let data = self.data.lock().unwrap();
+        data.entries
+            .iter()
+            .map(|(id, vector)| Entry {
+                id: id.clone(),
+                embedding: vector.to_f32(),
+                document: data.documents.get(id).cloned().unwrap_or_default(),
+                metadata: data.entry_metadata.get(id).cloned(),
+            })
+            .collect()
+    }
+
+    pub fn delete(&self, ids: &[String]) {
+        let mut data = self.data.lock().unwrap();
+        for id in ids {
+            data.entries.remove(id);
+            if let Some(binary) = &mut data.binary {
+                binary.remove(id);
+            }
+            data.entry_metadata.remove(id);
+            data.documents.remove(id);
+        }
+    }
+
+    pub fn query(&self, embedding: &[f32], n_results: usize, filter: &Filter) -> Vec<Hit> {
+        self.reload();
+        let space = self
+            .metadata()
+            .get("hnsw:space")
+            .and_then(Value::as_str)
+            .unwrap_or("l2")
+            .to_string();
+        let data = self.data.lock().unwrap();
+        let allowed = |id: &String| {
+            filter.is_empty()
+                || data
+                    .entry_metadata
+                    .get(id)
+                    .is_some_and(|metadata| filter.matches(metadata))
+        };
+        let candidates = match &data.binary {
+            Some(binary) => {
+                let query_bits = binarize(embedding);
+                let mut candidates = binary
+                    .iter()
+                    .filter(|(id, _)| allowed(id))
+                    .map(|(id, bits)| (hamming(&query_bits, bits), id))
+                    .collect::<Vec<_>>();
+                candidates.sort();
+                candidates.truncate(n_results * PREFILTER_OVERSAMPLING);
+                candidates
+                    .into_iter()
+                    .filter_map(|(_, id)| data.entries.get_key_value(id))
+                    .collect::<Vec<_>>()
+            }
+            None => data.entries.iter().filter(|(id, _)| allowed(id)).collect(),
+        };
+        let mut hits = candidates
+            .into_iter()
+            .map(|(id, vector)| Hit {
+                id: id.clone(),
+                distance: vector.distance(embedding, &space),
+                document: data.documents.get(id).cloned(),
+                embedding: None,
+                metadata: None,
+            })
+            .collect::<Vec<_>>();
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits.truncate(n_results);
+        for hit in &mut hits {
+            hit.embedding = data.entries.get(&hit.id).map(StoredVector::to_f32);
+            hit.metadata = data.entry_metadata.get(&hit.id).cloned();
+        }
+        hits
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let bytes = bincode::serialize(&*self.data.lock().unwrap())?;
+        std::fs::write(&self.path, bytes)?;
+        *self.modified.lock().unwrap() = modified(&self.path);
+        Ok(())
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).ok()?.modified().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn int8_vectors_round_trip_within_a_step() {
+        let embedding = vec![0.5, -1.0, 0.25, 0.0, 0.999];
+        let stored = StoredVector::new(embedding.clone(), Quantization::Int8);
+        let step = 1.0 / 127.0;
+        for (restored, original) in stored.to_f32().iter().zip(&embedding) {
+            assert!((restored - original).abs() <= step / 2.0 + f32::EPSILON);
+        }
+    }
+
+    #[test]
+    fn int8_zero_vectors_stay_zero() {
+        let stored = StoredVector::new(vec![0.0; 4], Quantization::Int8);
+        assert_eq!(stored.to_f32(), vec![0.0; 4]);
+    }
+
+    #[test]
+    fn quantized_distances_are_close() {
+        let embedding = vec![0.6, 0.8, 0.0];
+        let query = [0.8, 0.6, 0.0];
+        let exact = StoredVector::new(embedding.clone(), Quantization::None);
+        let quantized = StoredVector::new(embedding, Quantization::Int8);
+        for space in ["cosine", "ip", "l2"] {
+            let difference = exact.distance(&query, space) - quantized.distance(&query, space);
+            assert!(difference.abs() < 0.01, "{}", space);
+        }
+    }
+}
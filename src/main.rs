@@ -1,317 +1,1380 @@
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, BTreeSet},
     error::Error,
-    hash::{DefaultHasher, Hash, Hasher},
-    io::BufRead,
-    str::FromStr,
+    io::{IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    time::Instant,
 };
 
-use chromadb::v2::{
-    client::ChromaClient,
-    collection::{CollectionEntries, QueryOptions},
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use completion::Identifiers;
+use eval::EvalArgs;
+use export::{ExportFormat, GraphFormat};
+use futures::StreamExt;
+use rustdoc_rag::{
+    answer::{self, Answerer, Conversation},
+    cargo, chroma, chunk, credentials, docs_rs,
+    local_store::Quantization,
+    report::TokenStats,
+    store, watch, Distance, Index, Indexer, Options, Results, Retriever, StoreKind,
 };
-use clap::{Parser, ValueEnum};
-use ollama_rs::{generation::embeddings::request::GenerateEmbeddingsRequest, Ollama};
-use serde_json::Map;
+use rustyline::{error::ReadlineError, history::DefaultHistory, CompletionType, Config, Editor};
+use serde_json::{Map, Value};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+use transcript::{Report, Transcript};
 
-mod document_struct;
+mod analytics;
+mod collections;
+mod completion;
+mod config;
+mod diff;
+mod discord;
+mod doctor;
+mod eval;
+mod export;
+mod golden;
+mod http;
+mod markdown;
+mod mcp;
+mod server;
+mod site;
+mod stats;
+mod transcript;
+mod tui;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Ollama model to use for embedding
-    #[arg(short, long, default_value = "nomic-embed-text:latest")]
-    embedding: String,
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    /// Name of the project being documented
-    #[arg(short, long, default_value = "bevy")]
-    project: String,
+    #[command(flatten)]
+    options: Options,
 
-    /// Distance function to use for finding neighbours
-    #[arg(short, long, default_value = "squared-l2")]
-    distance: Distance,
+    /// Compare generated documents with the ones in this directory instead of indexing,
+    /// writing them there if it doesn't exist yet
+    #[arg(long)]
+    golden: Option<PathBuf>,
 
-    /// Force recompute of everything from scratch
-    #[arg(short, long)]
-    recompute: bool,
+    /// Count the tokens of generated documents with each embedding model and report
+    /// the ones exceeding its context, instead of indexing
+    #[arg(long)]
+    count_tokens: bool,
 
-    /// Number of results to return
-    #[arg(short, long, default_value_t = 10)]
-    nb_results: usize,
+    /// Generate documents and print what would be embedded, without calling the
+    /// embedding model or the vector store
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Answer this prompt and exit instead of reading prompts interactively,
+    /// which is also the case when they are piped in
+    #[arg(long)]
+    query: Option<String>,
+
+    /// Explore results in a terminal UI with a preview of the selected document
+    #[arg(long)]
+    tui: bool,
+
+    /// Append every query with its results and answer to this markdown file
+    #[arg(long)]
+    transcript: Option<PathBuf>,
+
+    /// Write every query with its results, their docs.rs links and the answer to
+    /// this markdown file, to share them
+    #[arg(long)]
+    report: Option<PathBuf>,
+
+    /// Log more details on stderr, twice for debug logs and three times for traces
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Also write debug logs to this file, as JSON lines
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    /// Open the docs.rs page of the best result, for one-shot queries
+    #[arg(long)]
+    open: bool,
+
+    /// Also query every other project indexed in the vector store
+    #[arg(long)]
+    all_projects: bool,
+
+    /// Print the full document of the best result, for one-shot queries
+    #[arg(long)]
+    show_full: bool,
+
+    /// Print how long each stage of a query took, like embedding the query, searching
+    /// the vector store, reranking and answering
+    #[arg(long)]
+    timing: bool,
+
+    /// Answer every query of this file, one per line or as a YAML list, and exit
+    #[arg(long)]
+    queries_file: Option<PathBuf>,
+
+    /// Number of results listed at once interactively, see the following ones with :next
+    #[arg(long, default_value_t = 10)]
+    page_size: usize,
+
+    /// Cluster listed results under headers of their crate or module
+    #[arg(long, default_value = "none")]
+    group_by: GroupBy,
+}
+
+/// Log to stderr at the level chosen with -v, or the one of `RUST_LOG`, and at
+/// debug level to the log file if there is one.
+fn init_logging(args: &Args) -> Result<(), Box<dyn Error>> {
+    let level = match args.verbose {
+        0 => "warn",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("rustdoc_rag={}", level)));
+    let stderr = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_filter(filter);
+    let file = match &args.log_file {
+        Some(path) => Some(
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_writer(std::sync::Mutex::new(std::fs::File::create(path)?))
+                .with_filter(EnvFilter::new("rustdoc_rag=debug")),
+        ),
+        None => None,
+    };
+    tracing_subscriber::registry()
+        .with(stderr)
+        .with(file)
+        .init();
+    Ok(())
 }
 
-#[derive(Debug, Clone, Hash, ValueEnum)]
-enum Distance {
-    SquaredL2,
-    InnerProduct,
-    Cosine,
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Check that Ollama, the vector store and the rustdoc JSON files are usable
+    Doctor,
+    /// Show what is stored in the collections of the project
+    Stats,
+    /// Summarize the queries logged with --query-log: the most asked ones, what
+    /// they were about and the ones without results
+    Analytics {
+        /// Query log to summarize, instead of the --query-log one
+        file: Option<PathBuf>,
+        /// Number of entries of each list
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
+    /// Answer queries from other programs instead of reading prompts
+    Serve {
+        /// Speak the Model Context Protocol instead of serving the JSON API, on stdin
+        /// and stdout unless --http is given
+        #[arg(long)]
+        mcp: bool,
+
+        /// Listen for HTTP requests on this address, like `:8080` or `127.0.0.1:8080`
+        #[arg(long)]
+        http: Option<String>,
+
+        /// Answer the /docs command of a Discord application sent to the --http address,
+        /// with the credentials of DISCORD_TOKEN and DISCORD_PUBLIC_KEY
+        #[arg(long, conflicts_with = "mcp", requires = "http")]
+        discord: bool,
+    },
+    /// Write a static site searching the documentation in the browser, that can be
+    /// hosted anywhere like GitHub Pages
+    ExportSite {
+        /// Directory to write the site to
+        #[arg(default_value = "site")]
+        dir: PathBuf,
+    },
+    /// List the items added, removed and changed between two versions of the project
+    /// indexed with --crate-version
+    Diff {
+        /// Version to compare from, like `0.13`
+        from: String,
+        /// Version to compare to
+        to: String,
+        /// Also ask the chat model for a migration guide from the changes
+        #[arg(long)]
+        summary: bool,
+    },
+    /// Write the generated documents for other retrieval pipelines, without embedding
+    /// them
+    Export {
+        /// Format of the exported documents
+        #[arg(long, default_value = "jsonl")]
+        format: ExportFormat,
+        /// File to write the documents to, instead of stdout, or directory with
+        /// --format llms-txt
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Write the graph of how items refer to each other, through module containment,
+    /// fields, implemented traits, trait bounds and method signatures
+    ExportGraph {
+        /// Format of the graph
+        #[arg(long, default_value = "dot")]
+        format: GraphFormat,
+        /// File to write the graph to, instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Measure how well retrieval finds the expected items of the queries of a YAML
+    /// file, with each embedding model, reporting recall, MRR and nDCG
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Eval {
+        #[command(subcommand)]
+        command: Option<EvalCommand>,
+        #[command(flatten)]
+        args: Option<EvalArgs>,
+    },
+    /// Evaluate the queries of a YAML file with every distance, indexing the project
+    /// with the ones it wasn't yet, and report the best one for the embedding model
+    BenchDistances {
+        #[command(flatten)]
+        args: EvalArgs,
+    },
+    /// Chat about the project: answer questions interactively, with follow-up
+    /// questions like "what about 2D?" understood from the previous ones
+    Chat,
+    /// Manage the collections of every project in the vector store
+    Collections {
+        #[command(subcommand)]
+        command: CollectionsCommand,
+    },
+    /// Store the credential of a remote service, read from stdin, in the OS keyring
+    /// or the credentials file. Environment variables still take precedence
+    Login {
+        /// Environment variable the credential would be given with, like
+        /// `OPENAI_API_KEY`
+        name: String,
+        /// Store it in the credentials file of the configuration directory, only
+        /// readable by the user, instead of the OS keyring
+        #[arg(long)]
+        file: bool,
+    },
+    /// Remove a stored credential
+    Logout { name: String },
 }
 
-impl FromStr for Distance {
-    type Err = String;
+#[derive(Debug, Subcommand)]
+enum EvalCommand {
+    /// Evaluate every combination of embedding models and distances side by side,
+    /// indexing the project with the ones it wasn't yet
+    Compare {
+        #[command(flatten)]
+        args: EvalArgs,
+        /// Embedding models to compare, like `nomic-embed-text,mxbai-embed-large`,
+        /// instead of --embedding
+        #[arg(long, value_delimiter = ',')]
+        models: Vec<String>,
+        /// Distances to compare, like `cosine,squared-l2`, instead of --distance
+        #[arg(long, value_delimiter = ',')]
+        distances: Vec<Distance>,
+    },
+    /// Write an evaluation file of questions the chat model asks about a sample of
+    /// the documents, expecting the item of each document
+    Generate {
+        /// File to write the queries to
+        output: PathBuf,
+        /// Number of documents to ask about
+        #[arg(short = 'n', long, default_value_t = 50)]
+        count: usize,
+        /// Seed of the sample, to ask about other documents
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "squared-l2" => Ok(Self::SquaredL2),
-            "l2" => Ok(Self::SquaredL2),
-            "inner-product" => Ok(Self::InnerProduct),
-            "ip" => Ok(Self::InnerProduct),
-            "cosine" => Ok(Self::Cosine),
-            _ => Err("Invalid distance metric".to_string()),
-        }
-    }
+#[derive(Debug, Subcommand)]
+enum CollectionsCommand {
+    /// List the collections with their model and number of entries
+    List,
+    /// Show what is stored in a collection
+    Info { name: String },
+    /// Delete a collection and its manifest
+    Delete { name: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum GroupBy {
+    None,
+    Crate,
+    Module,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let args = Args::parse();
-
-    let chroma: ChromaClient = ChromaClient::new(Default::default());
-    let ollama = SimpleOllama {
-        ollama: Ollama::default(),
-        embedding_model: args.embedding.clone(),
+    let Err(err) = run().await else {
+        return Ok(());
     };
-
-    let mut hash = DefaultHasher::new();
-    args.embedding.hash(&mut hash);
-    args.distance.hash(&mut hash);
-    args.project.hash(&mut hash);
-    let collection_name = hash.finish().to_string();
-
-    let mut collection_meta = Map::new();
-    collection_meta.insert(
-        "hnsw:space".to_string(),
-        match args.distance {
-            Distance::SquaredL2 => "l2",
-            Distance::InnerProduct => "ip",
-            Distance::Cosine => "cosine",
-        }
-        .into(),
-    );
-    let exist = chroma.get_collection(&collection_name).await.is_ok();
-    let Ok(collection) = chroma
-        .get_or_create_collection(&collection_name, Some(collection_meta))
-        .await
-    else {
-        println!("Error creating collection in Chroma");
-        println!("Is the database running?");
-        println!("> docker run -p 8000:8000 chromadb/chroma");
-        panic!();
+    let Some(failure) = err.downcast_ref::<rustdoc_rag::Error>() else {
+        return Err(err);
     };
+    let hint = match failure {
+        rustdoc_rag::Error::Llm(_) => Some(
+            "Is Ollama running with the chat model pulled, or the OpenAI endpoint reachable with OPENAI_API_KEY set?".to_string(),
+        ),
+        rustdoc_rag::Error::Embedding(_) => Some(
+            "Is the embedding provider running and reachable, like Ollama at --ollama-url?".to_string(),
+        ),
+        rustdoc_rag::Error::Mismatch { .. } => {
+            Some("Use the same settings, or add --recompute to index again".to_string())
+        }
+        rustdoc_rag::Error::Version { .. } => Some(
+            "Give the rustdoc JSON of the pinned version with --json-path, or a --crate-version matching it".to_string(),
+        ),
+        rustdoc_rag::Error::Missing { .. } => Some("Add --update to build it".to_string()),
+        rustdoc_rag::Error::Store(_) => Some(
+            "With the Chroma store, is the database running? > docker run -p 8000:8000 chromadb/chroma, or add --start-chroma".to_string(),
+        ),
+        rustdoc_rag::Error::Rustdoc(_) => Some(
+            "Generate it in the project to document with > RUSTDOCFLAGS=\"-Z unstable-options --output-format json\" cargo +nightly doc, or add --manifest-path path/to/Cargo.toml to let rustdoc-rag generate it".to_string(),
+        ),
+        rustdoc_rag::Error::Format { version, .. } => Some(format!(
+            "Regenerate it with a nightly toolchain producing format version {}{}",
+            rustdoc_types::FORMAT_VERSION,
+            if *version > rustdoc_types::FORMAT_VERSION {
+                format!(
+                    ", or use a version of rustdoc-rag reading format version {}",
+                    version
+                )
+            } else {
+                String::new()
+            }
+        )),
+        rustdoc_rag::Error::Credential { name, .. } => Some(format!(
+            "Set the {} environment variable, or store it with `rustdoc-rag login {}`",
+            name, name
+        )),
+        rustdoc_rag::Error::Other(_) => return Err(err),
+        _ => None,
+    };
+    eprintln!("{}", failure);
+    // Messages include their source, but not what caused it
+    let mut shown = failure.to_string();
+    let mut source = failure.source();
+    while let Some(cause) = source {
+        let message = cause.to_string();
+        if !shown.contains(&message) {
+            eprintln!("{}", message);
+            shown.push_str(&message);
+        }
+        source = cause.source();
+    }
+    if let Some(hint) = hint {
+        eprintln!("{}", hint);
+    }
+    std::process::exit(1);
+}
 
-    if !exist || args.recompute {
-        std::fs::create_dir_all("out")?;
-        let Ok(json_string) = std::fs::read_to_string(format!("./jsons/{}.json", args.project))
-        else {
-            println!("Couldn't find {}.json", args.project);
-            println!(
-                "You should generate all jsons from rustdoc and place them in the jsons directory by running the following commands:"
-            );
-            println!("You can run the following command in the project you want to document:");
-            println!();
-            println!(
-                "> RUSTDOCFLAGS=\"-Z unstable-options --output-format json\" cargo +nightly doc"
-            );
-            println!();
-            println!(
-                "then move the generated jsons from target/doc/ to the jsons directory in the rustdoc-rag project"
-            );
-            panic!()
+async fn run() -> Result<(), Box<dyn Error>> {
+    let matches =
+        Args::command().get_matches_from(config::with_config(std::env::args_os().collect())?);
+    let args = Args::from_arg_matches(&matches)?;
+    init_logging(&args)?;
+    if let Some(Command::Login { name, file }) = &args.command {
+        return login(name, *file);
+    }
+    if let Some(Command::Logout { name }) = &args.command {
+        known_credential(name);
+        if credentials::remove(name)? {
+            println!("Removed {}", name);
+        } else {
+            println!("No stored {}", name);
+        }
+        return Ok(());
+    }
+    // Only the first --project is kept in the options
+    let other_projects = matches
+        .get_many::<String>("project")
+        .into_iter()
+        .flatten()
+        .skip(1)
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut options = args.options.clone();
+    // Subcommands only read what was already generated
+    cargo::use_manifest(&mut options, args.command.is_none())?;
+    docs_rs::use_docs_rs(&mut options).await?;
+    options.update |= options.watch;
+    let indexer = Indexer::new(options)?;
+    let options = indexer.options();
+    let project_json = indexer.project_json();
+    docs_rs::fetch_missing(options, project_json).await?;
+
+    if options.store == StoreKind::Chroma
+        && (options.quantize != Quantization::None || options.binary_prefilter)
+    {
+        eprintln!("Quantization is only supported with the local store");
+        eprintln!("Add --store local to use it");
+        std::process::exit(1);
+    }
+
+    if let Some(Command::Analytics { file, top }) = &args.command {
+        let Some(path) = file.as_ref().or(options.query_log.as_ref()) else {
+            eprintln!("No query log to summarize");
+            eprintln!("Give its path, or add the --query-log the queries were logged to");
+            std::process::exit(1);
         };
-        let krate: rustdoc_types::Crate = serde_json::from_str(&json_string)?;
+        return analytics::run(path, *top);
+    }
 
-        let mut loaded_crates = vec![None; krate.external_crates.len() + 1];
+    if let Some(Command::Doctor) = &args.command {
+        if !doctor::run(options, &options.chroma_client(), project_json).await {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-        for ext_krate in &krate.external_crates {
-            let Ok(json_string) =
-                std::fs::read_to_string(format!("./jsons/{}.json", ext_krate.1.name))
-            else {
+    if let Some(Command::Stats) = &args.command {
+        let chroma = options.chroma_client();
+        for model in &options.embedding {
+            let prefixes = options.prefixes(model);
+            let name = options.collection_config(model, &prefixes).name();
+            // Opening a missing Chroma collection would create it empty
+            let exist = match options.store {
+                StoreKind::Chroma => chroma.get_collection(&name).await.is_ok(),
+                StoreKind::Local => options.local_index_path(&name).exists(),
+            };
+            if !exist {
+                eprintln!("Collection {} doesn't exist", name);
+                eprintln!("Add --update to index the project");
                 continue;
+            }
+            let index = Index::open(&chroma, options, model).await?;
+            let size = match options.store {
+                StoreKind::Chroma => None,
+                StoreKind::Local => Some(std::fs::metadata(options.local_index_path(&name))?.len()),
             };
-            let krate: rustdoc_types::Crate = serde_json::from_str(&json_string)?;
-            loaded_crates[*ext_krate.0 as usize] = Some((ext_krate.1.name.clone(), krate));
+            stats::print(&index.name, &index.store, size).await?;
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Collections { command }) = &args.command {
+        let chroma = options.chroma_client();
+        match command {
+            CollectionsCommand::List => collections::list(options, &chroma).await?,
+            CollectionsCommand::Info { name } => collections::info(options, &chroma, name).await?,
+            CollectionsCommand::Delete { name } => {
+                collections::delete(options, &chroma, name).await?
+            }
         }
-        loaded_crates[0] = Some(("bevy".to_string(), krate));
+        return Ok(());
+    }
 
-        let mut visited = HashSet::<(usize, rustdoc_types::Id)>::new();
-        start_krate(&loaded_crates, &mut visited);
+    if let Some(golden) = &args.golden {
+        let traversal = indexer.documents()?;
+        if !golden::check(golden, &traversal.documents)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-        ollama.download_model().await?;
+    if args.dry_run {
+        let traversal = indexer.documents()?;
+        let mut kinds = BTreeMap::<&str, usize>::new();
+        let mut crates = BTreeMap::<&str, usize>::new();
+        let mut chunks = 0;
+        let mut tokens = 0;
+        for document in traversal.documents.values() {
+            *kinds.entry(&document.kind).or_default() += 1;
+            let krate = document
+                .metadata
+                .get("crate")
+                .and_then(Value::as_str)
+                .unwrap_or("(unknown)");
+            *crates.entry(krate).or_default() += 1;
+            chunks += chunk::chunk(&document.text, options.chunk_size, options.chunk_overlap).len();
+            tokens += answer::estimate_tokens(&document.text);
+        }
+        println!("{} documents would be embedded:", traversal.documents.len());
+        for (kind, count) in kinds {
+            println!("  {:>6} {}", count, kind);
+        }
+        println!("by crate:");
+        for (krate, count) in crates {
+            println!("  {:>6} {}", count, krate);
+        }
+        println!("{} vectors per model, about {} tokens", chunks, tokens);
+        return Ok(());
+    }
 
-        let dir = std::fs::read_dir("./out/structs")?;
-        for (i, entry) in dir.enumerate() {
-            if i % 100 == 0 {
-                println!("{} entries processed", i);
-            }
-            let entry = entry.unwrap();
-            let path = entry.path();
-            let file_name = path.file_name().unwrap().to_str().unwrap();
-            let entries = CollectionEntries {
-                ids: vec![file_name],
-                embeddings: Some(vec![
-                    ollama.embeddings(&std::fs::read_to_string(&path)?).await?,
-                ]),
-                ..Default::default()
-            };
-            collection.upsert(entries, None).await?;
+    if args.count_tokens {
+        let mut traversal = indexer.documents()?;
+        for model in &options.embedding {
+            let embedder = options.embedder(model)?;
+            embedder.prepare().await?;
+            let context_length = embedder.context_length().await?;
+            let counts = futures::stream::iter(&traversal.documents)
+                .map(|(id, document)| async {
+                    Ok::<_, Box<dyn Error>>((
+                        id.clone(),
+                        embedder.count_tokens(&document.text).await?,
+                    ))
+                })
+                .buffer_unordered(options.concurrency.max(1))
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .collect::<Result<Vec<_>, _>>()?;
+            let stats = TokenStats::new(context_length, &counts);
+            stats.print(model);
+            traversal.report.tokens.insert(model.clone(), stats);
         }
+        traversal.report.write(&options.out_dir)?;
+        return Ok(());
+    }
+
+    if let Some(Command::Export { format, output }) = &args.command {
+        let documents = indexer.documents()?.documents;
+        return export::export(&options.project, &documents, *format, output.as_deref());
+    }
+
+    if let Some(Command::ExportGraph { format, output }) = &args.command {
+        let graph = indexer.documents()?.graph;
+        return export::export_graph(&graph, *format, output.as_deref());
     }
 
+    if let Some(Command::Diff { from, to, summary }) = &args.command {
+        return diff::run(options, from, to, *summary).await;
+    }
+
+    if options.watch {
+        return watch::watch(&indexer).await;
+    }
+    if let Some(Command::BenchDistances { args }) = &args.command {
+        return eval::bench_distances(&indexer, args).await;
+    }
+    if let Some(Command::Eval {
+        command:
+            Some(EvalCommand::Compare {
+                args,
+                models,
+                distances,
+            }),
+        ..
+    }) = &args.command
+    {
+        return eval::compare(&indexer, args, models, distances).await;
+    }
+    if let Some(Command::Eval {
+        command:
+            Some(EvalCommand::Generate {
+                output,
+                count,
+                seed,
+            }),
+        ..
+    }) = &args.command
+    {
+        return eval::generate(&indexer, *count, *seed, output).await;
+    }
+    let indexes = open_indexes(&indexer).await?;
+    if let Some(Command::Eval {
+        args: Some(args), ..
+    }) = &args.command
+    {
+        // Each configuration opens its own index
+        drop(indexes);
+        return eval::run(options, args).await;
+    }
+    if let Some(Command::ExportSite { dir }) = &args.command {
+        let documents = indexer.documents()?.documents;
+        return site::export(options, &documents, &indexes[0], dir).await;
+    }
+
+    let answerer = options
+        .answer
+        .then(|| options.chat_model())
+        .transpose()?
+        .map(|llm| Answerer::new(llm, &options.project).with_mode(options.answer_mode));
+    let mut retriever = Retriever::new(options.clone(), indexes)?
+        .with_projects(open_projects(options, other_projects, args.all_projects).await?);
+    if let Some(Command::Chat) = &args.command {
+        return chat(options, &retriever).await;
+    }
+    if args.tui {
+        return tui::run(&retriever).await;
+    }
+    if let Some(Command::Serve { mcp, http, discord }) = &args.command {
+        if let (true, Some(addr)) = (discord, http) {
+            return discord::serve(options.clone(), retriever, addr).await;
+        }
+        return match (mcp, http) {
+            (true, None) => mcp::serve_stdio(&retriever).await,
+            (true, Some(addr)) => mcp::serve_sse(retriever, addr).await,
+            (false, Some(addr)) => http::serve(options.clone(), retriever, addr).await,
+            (false, None) => {
+                eprintln!("Choose what to serve");
+                eprintln!(
+                    "Add --http :8080 to serve a JSON API, or --mcp for the Model Context Protocol"
+                );
+                std::process::exit(1);
+            }
+        };
+    }
+    let mut previous = Results::default();
+    // Prompts given on the command line, in a file or piped in are answered once,
+    // for scripts
     let stdin = std::io::stdin();
-    println!();
-    println!("Enter a prompt:");
-    for line in stdin.lock().lines() {
-        let query = QueryOptions {
-            query_embeddings: Some(vec![ollama.embeddings(&line?).await?]),
-            n_results: Some(args.nb_results as usize),
-            include: Some(vec!["distances"]),
-            ..Default::default()
+    let batch = match (&args.query, &args.queries_file) {
+        (Some(query), _) => Some(vec![query.clone()]),
+        (None, Some(path)) => Some(read_queries(path)?),
+        (None, None) if !stdin.is_terminal() => {
+            let mut query = String::new();
+            stdin.lock().read_to_string(&mut query)?;
+            Some(vec![query.trim().to_string()])
+        }
+        (None, None) => None,
+    };
+    let interactive = batch.is_none();
+    let many = batch.as_ref().is_some_and(|queries| queries.len() > 1);
+    let prompt = || {
+        if interactive {
+            println!();
+            println!("Enter a prompt:");
+        }
+    };
+    let lines: Box<dyn Iterator<Item = Result<String, Box<dyn Error>>>> = match batch {
+        Some(queries) => Box::new(queries.into_iter().map(Ok)),
+        None => Box::new(read_lines(retriever.identifiers()?)?),
+    };
+    // Everything is listed at once for scripts
+    let page_size = if interactive {
+        args.page_size.max(1)
+    } else {
+        usize::MAX
+    };
+    let transcript = args.transcript.clone().map(Transcript::new);
+    let report = args.report.clone().map(Report::create).transpose()?;
+    let mut page = 0;
+    let mut missing = false;
+    let mut clarifying = None;
+    let mut limit = options.nb_results;
+    prompt();
+    for line in lines {
+        let line = line?;
+        let command = line.trim();
+        if command == ":next" || command == ":prev" {
+            let pages = previous.ranking.len().div_ceil(page_size);
+            if command == ":next" && page + 1 < pages {
+                page += 1;
+            } else if command == ":prev" && page > 0 {
+                page -= 1;
+            } else {
+                println!("No more results in that direction");
+                prompt();
+                continue;
+            }
+            print_results(&previous, args.group_by, page, page_size);
+            prompt();
+            continue;
+        }
+        if let Some((command, number)) = line
+            .split_once(' ')
+            .filter(|(command, _)| [":similar", ":show", ":open", ":copy"].contains(command))
+        {
+            let Some(id) = number
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| previous.ranking.get(number.wrapping_sub(1)))
+                .map(|(id, _)| id.clone())
+            else {
+                println!("No result with that number in the previous list");
+                prompt();
+                continue;
+            };
+            if command == ":open" {
+                open_docs(&id, &previous);
+                prompt();
+                continue;
+            }
+            if command == ":copy" {
+                copy_import(&id, &previous);
+                prompt();
+                continue;
+            }
+            if command == ":show" {
+                println!(
+                    "{}",
+                    markdown::render(previous.texts.get(&id).map_or("", String::as_str))
+                );
+                prompt();
+                continue;
+            }
+            let similar = retriever.similar(&id, &previous).await?;
+            println!("Items similar to {}:", id);
+            page = 0;
+            print_results(&similar, args.group_by, page, page_size);
+            previous = similar;
+            prompt();
+            continue;
+        }
+        if command == ":reindex" {
+            match reindex(&indexer, &mut retriever).await {
+                Ok(()) => println!("Index updated"),
+                Err(err) => eprintln!("Couldn't index the project again: {}", err),
+            }
+            prompt();
+            continue;
+        }
+        if let Some(count) = line.strip_prefix(":limit ") {
+            match count.trim().parse::<usize>() {
+                Ok(count) if count > 0 => {
+                    limit = count;
+                    retriever.set_nb_results(limit);
+                    println!("Showing {} results per query", limit);
+                }
+                _ => println!("Give the number of results, like `:limit 25`"),
+            }
+            prompt();
+            continue;
+        }
+        if let Some(query) = line.strip_prefix(":search ") {
+            let Some(lexical) = &retriever.lexical else {
+                eprintln!("No keyword index, add --update to build it");
+                prompt();
+                continue;
+            };
+            match lexical.exact_search(query, limit) {
+                Ok(hits) => {
+                    let width = hits
+                        .iter()
+                        .map(|hit| row_width(&hit.id, Some(&hit.metadata)))
+                        .max();
+                    for (i, hit) in hits.iter().enumerate() {
+                        let score = format!("{:.3}", hit.score);
+                        let metadata = Some(&hit.metadata);
+                        print_row(i, &hit.id, metadata, width.unwrap_or_default(), &score);
+                        println!("    {}", snippet(&hit.text));
+                    }
+                }
+                Err(err) => eprintln!("Invalid query: {}", err),
+            }
+            prompt();
+            continue;
+        }
+        // The reply to a clarifying question completes the question it was about
+        let (line, clarified) = match clarifying.take() {
+            Some(question) => (format!("{} {}", question, line.trim()), true),
+            None => (line, false),
         };
-        let result = collection.query(query, None).await?;
-        for (i, doc) in result.ids[0].iter().enumerate() {
-            let mut doc = doc.clone();
-            let _ = doc.split_off(doc.len() - 3);
+        let (line, count) = result_count(&line);
+        if many {
+            println!("{}", console::style(format!("Query: {}", line)).bold());
+        }
+        if let Some(count) = count {
+            retriever.set_nb_results(count);
+        }
+        let mut results = retriever.search(&line).await?;
+        if count.is_some() {
+            retriever.set_nb_results(limit);
+        }
+        if !results.suggestions.is_empty() {
+            println!("Did you mean: {}?", results.suggestions.join(", "));
+        }
+        if results.ranking.is_empty() {
+            println!("No sufficiently relevant results");
+            missing = true;
+            if args.timing {
+                println!("{}", timings(&results));
+            }
+            if let Some(transcript) = &transcript {
+                transcript.record(&line, &results, None)?;
+            }
+            if let Some(report) = &report {
+                report.record(&line, &results, None, &[])?;
+            }
+            prompt();
+            continue;
+        }
+        let mut answer = None;
+        let mut sources = vec![];
+        if let Some(answerer) = &answerer {
+            let documents = retriever.answer_context(&line, &results).await?;
+            if options.clarify && interactive && !clarified && results.is_ambiguous() {
+                let start = Instant::now();
+                let question = answerer.clarifying_question(&line, &documents).await?;
+                results.time("clarify", start);
+                if let Some(question) = question {
+                    println!("{}", question);
+                    clarifying = Some(line);
+                    prompt();
+                    continue;
+                }
+            }
+            let start = Instant::now();
+            let text = answerer.answer(&line, &documents).await?;
+            results.time("answer", start);
+            let citations = answer::citations(&text, &documents);
+            if !citations.is_empty() {
+                println!();
+                println!("Sources:");
+                for (i, id) in &citations {
+                    println!("[{}] {}", i, id);
+                }
+            }
+            sources = citations
+                .into_iter()
+                .map(|(i, id)| (i, id.to_string()))
+                .collect();
+            answer = Some(text);
+        } else {
+            page = 0;
+            print_results(&results, args.group_by, page, page_size);
+            if !results.related.is_empty() {
+                println!("Related: {}", results.related.join(", "));
+            }
+            for id in &results.communities {
+                println!();
+                println!("{}", results.texts[id]);
+            }
+        }
+        if let Some(transcript) = &transcript {
+            transcript.record(&line, &results, answer.as_deref())?;
+        }
+        if let Some(report) = &report {
+            report.record(&line, &results, answer.as_deref(), &sources)?;
+        }
+        if args.show_full && !interactive {
+            let best = &results.ranking[0].0;
+            println!();
             println!(
-                "{:02}. {:<40} {:.3}",
-                i + 1,
-                doc,
-                result.distances.as_ref().unwrap()[0][i]
+                "{}",
+                markdown::render(results.texts.get(best).map_or("", String::as_str))
             );
         }
-        println!();
-        println!("Enter a prompt:");
+        if args.open && !interactive {
+            open_docs(&results.ranking[0].0, &results);
+        }
+        if args.timing {
+            println!("{}", timings(&results));
+        }
+        previous = results;
+        prompt();
     }
 
+    if !interactive && missing {
+        std::process::exit(1);
+    }
     Ok(())
 }
 
-struct SimpleOllama {
-    ollama: Ollama,
-    embedding_model: String,
+/// Time spent in each stage of a query, like `Timing: embedding 12.3 ms, store 1.0 ms`.
+fn timings(results: &Results) -> String {
+    let duration = |seconds: f64| {
+        if seconds < 1.0 {
+            format!("{:.1} ms", seconds * 1000.0)
+        } else {
+            format!("{:.2} s", seconds)
+        }
+    };
+    let mut stages = results
+        .timings
+        .iter()
+        .map(|(stage, seconds)| format!("{} {}", stage, duration(*seconds)))
+        .collect::<Vec<_>>();
+    stages.push(format!(
+        "total {}",
+        duration(results.timings.values().sum())
+    ));
+    format!("Timing: {}", stages.join(", "))
 }
 
-impl SimpleOllama {
-    async fn download_model(&self) -> Result<(), Box<dyn Error>> {
-        let Ok(models) = self.ollama.list_local_models().await else {
-            println!("Error generating embeddings");
-            println!("Is Ollama running?");
-            panic!();
-        };
+/// Queries of a file, one per line, or the items of a list of strings when it's YAML.
+fn read_queries(path: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let content = std::fs::read_to_string(path)?;
+    let yaml = path
+        .extension()
+        .is_some_and(|extension| extension == "yaml" || extension == "yml");
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            if !yaml {
+                return Some(line.to_string());
+            }
+            let item = line.strip_prefix('-')?.trim();
+            let unquoted = item
+                .strip_prefix('"')
+                .and_then(|item| item.strip_suffix('"'))
+                .or_else(|| item.strip_prefix('\'')?.strip_suffix('\''));
+            Some(unquoted.unwrap_or(item).to_string())
+        })
+        .collect())
+}
 
-        for model in models {
-            if model.name == self.embedding_model {
-                return Ok(());
+/// The query and the number of results asked for it, given after a `|` like
+/// `spawn an entity | 25`.
+fn result_count(line: &str) -> (String, Option<usize>) {
+    if let Some((query, count)) = line.rsplit_once('|') {
+        if let Ok(count) = count.trim().parse::<usize>() {
+            if count > 0 {
+                return (query.trim_end().to_string(), Some(count));
             }
         }
+    }
+    (line.to_string(), None)
+}
 
-        println!("downloading model {}", self.embedding_model);
-        self.ollama
-            .pull_model(self.embedding_model.clone(), false)
-            .await?;
+/// Prompts read interactively with line editing and tab completion of these
+/// identifiers, with a history kept across runs in the user's data directory.
+/// Ends on Ctrl-C or Ctrl-D.
+fn read_lines(
+    identifiers: BTreeSet<String>,
+) -> Result<impl Iterator<Item = Result<String, Box<dyn Error>>>, Box<dyn Error>> {
+    let config = Config::builder()
+        .completion_type(CompletionType::List)
+        .build();
+    let mut editor = Editor::<Identifiers, DefaultHistory>::with_config(config)?;
+    editor.set_helper(Some(Identifiers::new(identifiers)));
+    let history = dirs::data_dir().map(|dir| dir.join("rustdoc-rag").join("history.txt"));
+    if let Some(history) = &history {
+        let _ = editor.load_history(history);
+    }
+    Ok(std::iter::from_fn(move || match editor.readline("> ") {
+        Ok(line) => {
+            if !line.trim().is_empty() {
+                let _ = editor.add_history_entry(&line);
+                if let Some(history) = &history {
+                    let _ = std::fs::create_dir_all(history.parent().unwrap());
+                    let _ = editor.save_history(history);
+                }
+            }
+            Some(Ok(line))
+        }
+        Err(ReadlineError::Eof | ReadlineError::Interrupted) => None,
+        Err(err) => Some(Err(err.into())),
+    }))
+}
 
-        Ok(())
+/// Answer questions read interactively, rewriting each follow-up question into a
+/// standalone one to retrieve documents for, and answering from the conversation
+/// and the documents retrieved so far. With --clarify, ambiguous questions get a
+/// clarifying question back instead of an answer.
+async fn chat(options: &Options, retriever: &Retriever) -> Result<(), Box<dyn Error>> {
+    if options.offline {
+        eprintln!("Chatting needs a chat model, which --offline doesn't use");
+        std::process::exit(1);
     }
+    let answerer =
+        Answerer::new(options.chat_model()?, &options.project).with_mode(options.answer_mode);
+    let mut conversation = Conversation::default();
+    let mut clarified = false;
+    println!("Ask a question, Ctrl-D to quit:");
+    for line in read_lines(retriever.identifiers()?)? {
+        let line = line?;
+        let question = line.trim();
+        if question.is_empty() {
+            continue;
+        }
+        let query = answerer
+            .standalone_question(&conversation, question)
+            .await?;
+        if query != question {
+            println!("{}", console::style(format!("Searching: {}", query)).dim());
+        }
+        let results = retriever.search(&query).await?;
+        if options.clarify && !clarified && results.is_ambiguous() {
+            let documents = retriever.answer_context(&query, &results).await?;
+            if let Some(clarification) = answerer.clarifying_question(&query, &documents).await? {
+                println!("{}", clarification);
+                // The reply is rewritten with the question into a standalone one
+                conversation.record(question, &clarification);
+                clarified = true;
+                println!();
+                continue;
+            }
+        }
+        clarified = false;
+        let documents = retriever.answer_context(&query, &results).await?;
+        let documents = conversation.context(documents, options.context_tokens);
+        let answer = answerer
+            .answer_in(&conversation, question, &documents)
+            .await?;
+        let citations = answer::citations(&answer, &documents);
+        if !citations.is_empty() {
+            println!();
+            println!("Sources:");
+            for (i, id) in citations {
+                println!("[{}] {}", i, id);
+            }
+        }
+        conversation.record(question, &answer);
+        println!();
+    }
+    Ok(())
+}
 
-    async fn embeddings(&self, document: &str) -> Result<Vec<f32>, Box<dyn Error>> {
-        let request = GenerateEmbeddingsRequest::new(self.embedding_model.clone(), document.into());
-        let mut res = self.ollama.generate_embeddings(request).await?;
-        Ok(res.embeddings.remove(0))
+/// Update the index of the project with the documents that changed, regenerating
+/// its rustdoc JSON first with --manifest-path, and search the updated index.
+async fn reindex(indexer: &Indexer, retriever: &mut Retriever) -> Result<(), Box<dyn Error>> {
+    let options = indexer.options();
+    if let Some(manifest_path) = &options.manifest_path {
+        let package = cargo::package(Some(manifest_path), None)?;
+        if !package.run_rustdoc(&options.toolchain)? {
+            return Err("the rustdoc JSON couldn't be generated".into());
+        }
     }
+    let indexer = indexer.with_options(options.clone().with_update(true));
+    retriever.reload(indexer.open().await?)?;
+    Ok(())
 }
 
-type CrateCatalog = [Option<(String, rustdoc_types::Crate)>];
+/// Open the indexes of the project, offering to index again the ones built by an
+/// older version of rustdoc-rag.
+async fn open_indexes(indexer: &Indexer) -> Result<Vec<Index>, Box<dyn Error>> {
+    let options = indexer.options();
+    let starting;
+    let indexer = if options.store == StoreKind::Chroma
+        && !options.start_chroma
+        && chroma::local_port(&options.chroma_url).is_some()
+        && options.chroma_client().heartbeat().await.is_err()
+        && confirm(&format!(
+            "Chroma isn't reachable at {}. Start it locally?",
+            options.chroma_url
+        )) {
+        let mut options = options.clone();
+        options.start_chroma = true;
+        starting = indexer.with_options(options);
+        &starting
+    } else {
+        indexer
+    };
+    let indexes = indexer.open().await?;
+    let stale = indexes
+        .iter()
+        .filter(|index| index.stale().is_some())
+        .map(|index| index.name.as_str())
+        .collect::<Vec<_>>();
+    if stale.is_empty() {
+        return Ok(indexes);
+    }
+    for name in &stale {
+        eprintln!(
+            "Collection {} was built by an older version of rustdoc-rag, its documents may be missing what newer ones have",
+            name
+        );
+    }
+    if !confirm("Index it again now?") {
+        eprintln!("Add --update to index it again");
+        return Ok(indexes);
+    }
+    drop(indexes);
+    let mut options = indexer.options().clone();
+    options.recompute = true;
+    Ok(indexer.with_options(options).open().await?)
+}
 
-fn start_krate(crates: &CrateCatalog, visited: &mut HashSet<(usize, rustdoc_types::Id)>) {
-    let krate = &crates[0].as_ref().unwrap().1;
-    item_explorer(krate.root, 0, crates, visited, 0);
+/// Ask a yes or no question, defaulting to yes, and to no when not interactive.
+fn confirm(question: &str) -> bool {
+    if !std::io::stdin().is_terminal() {
+        return false;
+    }
+    eprint!("{} [Y/n] ", question);
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "" | "y" | "yes")
 }
 
-fn item_explorer(
-    id: rustdoc_types::Id,
-    current_crate: usize,
-    crates: &CrateCatalog,
-    visited: &mut HashSet<(usize, rustdoc_types::Id)>,
-    depth: u32,
-) {
-    if !visited.insert((current_crate, id)) {
-        return;
+/// Retrievers of the other projects to query, indexing the ones that are missing.
+async fn open_projects(
+    options: &Options,
+    mut projects: Vec<String>,
+    all_projects: bool,
+) -> Result<Vec<Retriever>, Box<dyn Error>> {
+    if all_projects {
+        for summary in collections::summaries(options, &options.chroma_client()).await? {
+            if let Some(project) = summary.metadata.get("project").and_then(Value::as_str) {
+                if project != options.project && !projects.iter().any(|known| known == project) {
+                    projects.push(project.to_string());
+                }
+            }
+        }
     }
-    let krate = crates[current_crate].as_ref().unwrap();
-    let item = if let Some(item) = krate.1.index.get(&id) {
-        item
-    } else {
-        krate.1.index.get(&krate.1.root).unwrap()
+    let mut retrievers = vec![];
+    for project in projects {
+        let mut other = options.clone();
+        other.project = project;
+        other.json_path = None;
+        other.crate_version = None;
+        let indexer = Indexer::new(other)?;
+        let indexes = open_indexes(&indexer).await?;
+        retrievers.push(Retriever::new(indexer.options().clone(), indexes)?);
+    }
+    Ok(retrievers)
+}
+
+/// List a page of results, numbered by rank even when they are grouped. Scores
+/// are shown with their similarity when they are distances.
+fn print_results(results: &Results, group_by: GroupBy, page: usize, page_size: usize) {
+    let key = match group_by {
+        GroupBy::None => None,
+        GroupBy::Crate => Some("crate"),
+        GroupBy::Module => Some("module"),
+    };
+    let group_of = |doc: &String| {
+        key.map(|key| {
+            results
+                .metadatas
+                .get(doc)
+                .and_then(|metadata| metadata.get(key))
+                .and_then(Value::as_str)
+                .unwrap_or("(unknown)")
+                .to_string()
+        })
     };
-    match &item.inner {
-        rustdoc_types::ItemEnum::Module(module) => {
-            module_explorer(module, current_crate, crates, visited, depth);
-        }
-        rustdoc_types::ItemEnum::ExternCrate { .. } => todo!(),
-        rustdoc_types::ItemEnum::Use(used) => {
-            let crate_name = used.source.split("::").next().unwrap();
-            if crate_name == "crate" || crate_name == "super" {
-                return item_explorer(used.id.unwrap(), current_crate, crates, visited, depth + 1);
+    let mut groups: Vec<(Option<String>, Vec<usize>)> = vec![];
+    let start = page.saturating_mul(page_size);
+    let end = start.saturating_add(page_size).min(results.ranking.len());
+    for (i, (doc, _)) in results.ranking.iter().enumerate().take(end).skip(start) {
+        let group = group_of(doc);
+        match groups.iter_mut().find(|(name, _)| *name == group) {
+            Some((_, members)) => members.push(i),
+            None => groups.push((group, vec![i])),
+        }
+    }
+    let width = results
+        .ranking
+        .iter()
+        .map(|(doc, _)| row_width(doc, results.metadatas.get(doc)))
+        .max()
+        .unwrap_or_default();
+    for (group, members) in groups {
+        if let Some(group) = group {
+            println!("{}", console::style(group).bold());
+        }
+        for i in members {
+            let (doc, score) = &results.ranking[i];
+            let score = if results.pinned.contains(doc) {
+                "exact".to_string()
+            } else if let Some(space) = results.space {
+                format!("{:.3} ({:.0}%)", score, store::similarity(*score, space))
+            } else {
+                format!("{:.3}", score)
+            };
+            let score = match results.projects.get(doc) {
+                Some(project) => format!("{} {}", score, console::style(project).dim()),
+                None => score,
+            };
+            print_row(i, doc, results.metadatas.get(doc), width, &score);
+            if let Some(import) = results
+                .metadatas
+                .get(doc)
+                .and_then(|metadata| metadata.get("import"))
+                .and_then(Value::as_str)
+            {
+                println!("    {}", console::style(import).dim());
             }
-            for (crate_index, krate) in crates.iter().enumerate() {
-                if let Some(krate) = krate {
-                    if krate.0 == crate_name {
-                        return item_explorer(
-                            rustdoc_types::Id(u32::MAX),
-                            crate_index,
-                            crates,
-                            visited,
-                            depth + 1,
+            match (results.passages.get(doc), results.texts.get(doc)) {
+                (Some(passages), _) if !passages.is_empty() => {
+                    let matching = passages.iter().filter(|(_, score)| *score > 0.0);
+                    for (passage, score) in matching.take(EXPLAINED_PASSAGES) {
+                        println!(
+                            "    {} {}",
+                            console::style(snippet(passage)).yellow(),
+                            console::style(format!("({:.3})", score)).dim()
                         );
                     }
                 }
+                (_, Some(text)) => println!("    {}", snippet(text)),
+                _ => {}
+            }
+        }
+    }
+    if end < results.ranking.len() {
+        println!(
+            "{}",
+            console::style(format!(
+                "Page {}/{}, :next for more",
+                page + 1,
+                results.ranking.len().div_ceil(page_size)
+            ))
+            .dim()
+        );
+    }
+}
+
+fn open_docs(id: &str, results: &Results) {
+    match results
+        .metadatas
+        .get(id)
+        .and_then(|metadata| docs_rs::item_url(id, metadata))
+    {
+        Some(url) => {
+            println!("Opening {}", url);
+            if let Err(err) = open::that(&url) {
+                eprintln!("Couldn't open a browser: {}", err);
             }
-            return item_explorer(used.id.unwrap(), current_crate, crates, visited, depth + 1);
-        }
-        rustdoc_types::ItemEnum::Union(_union) => todo!(),
-        rustdoc_types::ItemEnum::Struct(stru) => {
-            document_struct::document_struct(item, stru, current_crate, crates);
-        }
-        rustdoc_types::ItemEnum::StructField(_strufi) => {}
-        rustdoc_types::ItemEnum::Enum(enume) => {
-            enum_explorer(enume, current_crate, crates, visited, depth);
-        }
-        rustdoc_types::ItemEnum::Variant(_) => {}
-        rustdoc_types::ItemEnum::Function(_) => {}
-        rustdoc_types::ItemEnum::Trait(_) => {}
-        rustdoc_types::ItemEnum::TraitAlias(_) => todo!(),
-        rustdoc_types::ItemEnum::Impl(_) => {}
-        rustdoc_types::ItemEnum::TypeAlias(_) => {}
-        rustdoc_types::ItemEnum::Constant { .. } => {}
-        rustdoc_types::ItemEnum::Static(_) => {}
-        rustdoc_types::ItemEnum::ExternType => todo!(),
-        rustdoc_types::ItemEnum::Macro(_) => {}
-        rustdoc_types::ItemEnum::ProcMacro(_proc_macro) => {}
-        rustdoc_types::ItemEnum::Primitive(_primitive) => todo!(),
-        rustdoc_types::ItemEnum::AssocConst { .. } => todo!(),
-        rustdoc_types::ItemEnum::AssocType { .. } => {}
+        }
+        None => println!("No docs.rs page known for {}", id),
+    }
+}
+
+fn known_credential(name: &str) {
+    if !credentials::NAMES.contains(&name) {
+        eprintln!("Unknown credential {}", name);
+        eprintln!("Use one of {}", credentials::NAMES.join(", "));
+        std::process::exit(1);
+    }
+}
+
+fn login(name: &str, file: bool) -> Result<(), Box<dyn Error>> {
+    known_credential(name);
+    let value = if std::io::stdin().is_terminal() {
+        rpassword::prompt_password(format!("{}: ", name))?
+    } else {
+        let mut value = String::new();
+        std::io::stdin().read_to_string(&mut value)?;
+        value
+    };
+    let value = value.trim();
+    if value.is_empty() {
+        eprintln!("No {} given", name);
+        std::process::exit(1);
+    }
+    if let Err(err) = credentials::store(name, value, file) {
+        if file {
+            return Err(err);
+        }
+        eprintln!("Couldn't store {} in the OS keyring: {}", name, err);
+        eprintln!("Add --file to store it in the credentials file instead");
+        std::process::exit(1);
+    }
+    match credentials::file().filter(|_| file) {
+        Some(path) => println!("Stored {} in {}", name, path.display()),
+        None => println!("Stored {} in the OS keyring", name),
     }
+    Ok(())
 }
 
-fn module_explorer(
-    module: &rustdoc_types::Module,
-    current_crate: usize,
-    crates: &CrateCatalog,
-    visited: &mut HashSet<(usize, rustdoc_types::Id)>,
-    depth: u32,
-) {
-    for item in &module.items {
-        item_explorer(*item, current_crate, crates, visited, depth + 1);
+/// Commands putting their input on the clipboard, tried in order.
+const CLIPBOARDS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// Put the `use` statement importing a result on the clipboard.
+fn copy_import(id: &str, results: &Results) {
+    let Some(import) = results
+        .metadatas
+        .get(id)
+        .and_then(|metadata| metadata.get("import"))
+        .and_then(Value::as_str)
+    else {
+        println!(
+            "No import path known for {}, index it again with --update",
+            id
+        );
+        return;
+    };
+    for (program, args) in CLIPBOARDS {
+        let Ok(mut child) = std::process::Command::new(program)
+            .args(*args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        let written = child
+            .stdin
+            .take()
+            .is_some_and(|mut stdin| stdin.write_all(import.as_bytes()).is_ok());
+        if written && child.wait().is_ok_and(|status| status.success()) {
+            println!("Copied {}", import);
+            return;
+        }
     }
+    println!("Couldn't reach a clipboard, copy it from here: {}", import);
 }
 
-fn enum_explorer(
-    enumeration: &rustdoc_types::Enum,
-    current_crate: usize,
-    crates: &CrateCatalog,
-    visited: &mut HashSet<(usize, rustdoc_types::Id)>,
-    depth: u32,
-) {
-    enumeration.variants.iter().for_each(|variant| {
-        item_explorer(*variant, current_crate, crates, visited, depth + 1);
-    });
+/// Print a numbered result with the label of its kind colored and its score dimmed,
+/// the scores aligned after rows up to `width` characters. Colors are disabled when
+/// stdout isn't a terminal.
+fn print_row(i: usize, id: &str, metadata: Option<&Map<String, Value>>, width: usize, score: &str) {
+    let (kind, path) = id.split_once(':').unwrap_or(("", id));
+    let color = match kind {
+        "struct" | "plugin" => console::Color::Green,
+        "enum" => console::Color::Yellow,
+        "trait" => console::Color::Magenta,
+        "function" => console::Color::Blue,
+        _ => console::Color::Cyan,
+    };
+    let padding = width.saturating_sub(row_width(id, metadata));
+    println!(
+        "{:02}. {} {}{} {}",
+        i + 1,
+        console::style(kind_label(id, metadata)).fg(color),
+        path,
+        " ".repeat(padding),
+        console::style(score).dim()
+    );
+}
+
+/// Characters of a row before its score.
+fn row_width(id: &str, metadata: Option<&Map<String, Value>>) -> usize {
+    let (_, path) = id.split_once(':').unwrap_or(("", id));
+    kind_label(id, metadata).chars().count() + 1 + path.chars().count()
+}
+
+/// Label of the kind of a result from its metadata, like `[fn]`, or
+/// `[struct, component]` with its role in Bevy.
+fn kind_label(id: &str, metadata: Option<&Map<String, Value>>) -> String {
+    let field = |key| metadata?.get(key)?.as_str();
+    let short = |kind: &str| match kind {
+        "function" => "fn".to_string(),
+        "method" => "method".to_string(),
+        "type alias" => "type".to_string(),
+        "proc macro" => "macro".to_string(),
+        kind => kind.to_string(),
+    };
+    let (kind, _) = id.split_once(':').unwrap_or(("", id));
+    let mut label = match (kind, field("item_kind")) {
+        ("source", Some(item_kind)) => format!("{} source", short(item_kind)),
+        (kind, _) => short(kind),
+    };
+    if let Some(role) = field("role").filter(|role| *role != kind) {
+        label = format!("{}, {}", label, role);
+    }
+    format!("[{}]", label)
+}
+
+/// Passages shown under a query result with --explain, best matching first
+const EXPLAINED_PASSAGES: usize = 2;
+
+/// Longest snippet shown under a query result, in characters
+const SNIPPET_LENGTH: usize = 160;
+
+/// First paragraph of a document after its introduction line, on a single line.
+fn snippet(document: &str) -> String {
+    let mut paragraphs = document
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty());
+    let first = paragraphs.next().unwrap_or_default();
+    let paragraph = paragraphs.next().unwrap_or(first);
+    let line = paragraph.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() > SNIPPET_LENGTH {
+        format!(
+            "{}...",
+            line.chars().take(SNIPPET_LENGTH).collect::<String>()
+        )
+    } else {
+        line
+    }
 }
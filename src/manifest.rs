@@ -0,0 +1,40 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+/// Hashes of the documents currently stored in a collection, used to only
+/// embed what changed when updating an index.
+pub struct Manifest {
+    path: PathBuf,
+    pub hashes: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    pub fn load(out_dir: &Path, collection_name: &str) -> Self {
+        let path = out_dir
+            .join("manifests")
+            .join(format!("{}.json", collection_name));
+        let hashes = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Manifest { path, hashes }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&self.hashes)?)?;
+        Ok(())
+    }
+
+    pub fn delete(self) -> Result<(), Box<dyn Error>> {
+        if self.path.exists() {
+            std::fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
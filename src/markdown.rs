@@ -0,0 +1,58 @@
+use console::style;
+
+/// A markdown document styled for the terminal, with headings in bold, code in
+/// cyan and the markup removed.
+pub fn render(text: &str) -> String {
+    let mut in_code = false;
+    let mut lines = vec![];
+    for line in text.trim_end().lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            lines.push(format!("    {}", style(line).cyan()));
+        } else if let Some(heading) = line.strip_prefix('#') {
+            let heading = heading.trim_start_matches('#').trim();
+            lines.push(style(inline(heading)).bold().underlined().to_string());
+        } else {
+            lines.push(inline(line));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Style the inline code spans and bold text of a line.
+fn inline(line: &str) -> String {
+    let mut rendered = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find(['`', '*']) {
+        rendered.push_str(&rest[..start]);
+        let (marker, styled) = if rest[start..].starts_with('`') {
+            ("`", true)
+        } else if rest[start..].starts_with("**") {
+            ("**", false)
+        } else {
+            rendered.push('*');
+            rest = &rest[start + 1..];
+            continue;
+        };
+        let after = &rest[start + marker.len()..];
+        match after.find(marker) {
+            Some(end) if styled => {
+                rendered.push_str(&style(&after[..end]).cyan().to_string());
+                rest = &after[end + marker.len()..];
+            }
+            Some(end) => {
+                rendered.push_str(&style(&after[..end]).bold().to_string());
+                rest = &after[end + marker.len()..];
+            }
+            None => {
+                rendered.push_str(marker);
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
@@ -0,0 +1,220 @@
+use std::{cell::RefCell, collections::HashMap, error::Error, rc::Rc};
+
+use hyper::{Method, Response, StatusCode};
+use rustdoc_rag::{
+    filter::{features, Filter},
+    Retriever,
+};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt},
+    sync::mpsc,
+};
+
+use crate::server;
+
+/// Version of the Model Context Protocol answered to clients that don't ask for one
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Answer MCP requests read from stdin, one JSON-RPC message per line, on stdout.
+pub async fn serve_stdio(retriever: &Retriever) -> Result<(), Box<dyn Error>> {
+    let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_message(retriever, &line).await {
+            stdout
+                .write_all(format!("{}\n", response).as_bytes())
+                .await?;
+            stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+/// Answer MCP requests with the SSE transport: clients open an event stream on
+/// `/sse`, post requests to the endpoint it announces, and get responses as events.
+pub async fn serve_sse(retriever: Retriever, addr: &str) -> Result<(), Box<dyn Error>> {
+    let retriever = Rc::new(retriever);
+    let sessions = Rc::new(RefCell::new(
+        HashMap::<String, mpsc::UnboundedSender<String>>::new(),
+    ));
+    let next_session = Rc::new(RefCell::new(0u64));
+    server::listen(addr, move |request| {
+        let retriever = retriever.clone();
+        let sessions = sessions.clone();
+        let next_session = next_session.clone();
+        async move {
+            match (request.method(), request.uri().path()) {
+                (&Method::GET, "/sse") => {
+                    let id = {
+                        let mut next = next_session.borrow_mut();
+                        *next += 1;
+                        next.to_string()
+                    };
+                    let (sender, receiver) = mpsc::unbounded_channel();
+                    let _ = sender.send(server::event(
+                        "endpoint",
+                        &format!("/messages?session_id={}", id),
+                    ));
+                    sessions.borrow_mut().insert(id, sender);
+                    server::event_stream(receiver)
+                }
+                (&Method::POST, "/messages") => {
+                    let id = server::query_param(&request, "session_id").unwrap_or_default();
+                    let session = sessions.borrow().get(&id).cloned();
+                    let Some(session) = session else {
+                        return server::json(
+                            StatusCode::NOT_FOUND,
+                            &json!({ "error": "unknown session" }),
+                        );
+                    };
+                    let Some(message) = server::read_json(request).await else {
+                        return server::json(
+                            StatusCode::BAD_REQUEST,
+                            &json!({ "error": "invalid JSON" }),
+                        );
+                    };
+                    if let Some(response) = handle(&retriever, message).await {
+                        // The event stream was closed by the client
+                        if session
+                            .send(server::event("message", &response.to_string()))
+                            .is_err()
+                        {
+                            sessions.borrow_mut().remove(&id);
+                        }
+                    }
+                    Response::builder()
+                        .status(StatusCode::ACCEPTED)
+                        .body(server::full(""))
+                        .unwrap()
+                }
+                _ => server::json(StatusCode::NOT_FOUND, &json!({ "error": "not found" })),
+            }
+        }
+    })
+    .await
+}
+
+async fn handle_message(retriever: &Retriever, line: &str) -> Option<Value> {
+    match serde_json::from_str(line) {
+        Ok(message) => handle(retriever, message).await,
+        Err(err) => Some(json!({
+            "jsonrpc": "2.0",
+            "id": null,
+            "error": { "code": -32700, "message": err.to_string() },
+        })),
+    }
+}
+
+/// Response to a JSON-RPC message, none for notifications.
+async fn handle(retriever: &Retriever, message: Value) -> Option<Value> {
+    let id = message.get("id")?.clone();
+    let params = &message["params"];
+    let result = match message["method"].as_str().unwrap_or_default() {
+        "initialize" => Ok(json!({
+            "protocolVersion": params["protocolVersion"].as_str().unwrap_or(PROTOCOL_VERSION),
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "rustdoc-rag", "version": env!("CARGO_PKG_VERSION") },
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tools() })),
+        "tools/call" => Ok(call_tool(retriever, params).await),
+        method => Err(json!({ "code": -32601, "message": format!("unknown method {}", method) })),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    })
+}
+
+fn tools() -> Value {
+    json!([
+        {
+            "name": "search_docs",
+            "description": "Search the documentation of the indexed Rust project, returning the documents of the most relevant items",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Question or keywords to search for" },
+                    "kind": { "type": "string", "description": "Only return items of this kind, like struct or trait" },
+                    "crate": { "type": "string", "description": "Only return items from this crate" },
+                    "path_prefix": { "type": "string", "description": "Only return items at this path or under it, like bevy::ecs" },
+                    "role": { "type": "string", "description": "Only return items with this role: component, resource, event, plugin or system in Bevy, or extension for extension traits" },
+                    "features": { "type": "string", "description": "Only return items available with these comma separated Cargo features enabled" },
+                    "limit": { "type": "integer", "description": "Number of results to return" },
+                },
+                "required": ["query"],
+            },
+        },
+        {
+            "name": "get_item",
+            "description": "Get the documentation of an item of the indexed Rust project by its path, like bevy_ecs::system::Commands",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Path or unique name of the item" },
+                },
+                "required": ["path"],
+            },
+        },
+    ])
+}
+
+async fn call_tool(retriever: &Retriever, params: &Value) -> Value {
+    let arguments = &params["arguments"];
+    let argument = |name: &str| arguments[name].as_str().map(str::to_string);
+    let text = match params["name"].as_str().unwrap_or_default() {
+        "search_docs" => {
+            let Some(query) = argument("query") else {
+                return tool_error("missing query");
+            };
+            let defaults = retriever.filter();
+            let filter = Filter {
+                kind: argument("kind").or(defaults.kind.clone()),
+                krate: argument("crate").or(defaults.krate.clone()),
+                path_prefix: argument("path_prefix").or(defaults.path_prefix.clone()),
+                role: argument("role").or(defaults.role.clone()),
+                features: features(argument("features")).or(defaults.features.clone()),
+            };
+            let mut results = match retriever.search_with(&query, &filter).await {
+                Ok(results) => results,
+                Err(err) => return tool_error(&err.to_string()),
+            };
+            if let Some(limit) = arguments["limit"].as_u64() {
+                results.ranking.truncate(limit as usize);
+            }
+            if results.ranking.is_empty() {
+                "No sufficiently relevant results".to_string()
+            } else {
+                results
+                    .ranking
+                    .iter()
+                    .map(|(id, _)| {
+                        let text = results.texts.get(id).map_or("", |text| text.trim());
+                        format!("## {}\n\n{}", id, text)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            }
+        }
+        "get_item" => {
+            let Some(path) = argument("path") else {
+                return tool_error("missing path");
+            };
+            match retriever.item(&path) {
+                Ok(Some(hit)) => hit.text,
+                Ok(None) => return tool_error(&format!("no item found at {}", path)),
+                Err(err) => return tool_error(&err.to_string()),
+            }
+        }
+        name => return tool_error(&format!("unknown tool {}", name)),
+    };
+    json!({ "content": [{ "type": "text", "text": text }], "isError": false })
+}
+
+fn tool_error(message: &str) -> Value {
+    json!({ "content": [{ "type": "text", "text": message }], "isError": true })
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+/// Reorder ranked results with maximal marginal relevance, picking at each step the
+/// result that best balances its relevance with its similarity to the ones already
+/// picked. `lambda` goes from 0 (only diversity) to 1 (only relevance). Relevance
+/// comes from the rank, so rankings from any retrieval method can be diversified.
+pub fn maximal_marginal_relevance(
+    ranking: Vec<(String, f32)>,
+    embeddings: &HashMap<String, Vec<f32>>,
+    lambda: f32,
+    limit: usize,
+) -> Vec<(String, f32)> {
+    let count = ranking.len() as f32;
+    let mut remaining = ranking
+        .into_iter()
+        .enumerate()
+        .map(|(rank, result)| (1.0 - rank as f32 / count, result))
+        .collect::<Vec<_>>();
+    let mut selected: Vec<(String, f32)> = vec![];
+    while selected.len() < limit && !remaining.is_empty() {
+        let (best, _) = remaining
+            .iter()
+            .enumerate()
+            .map(|(i, (relevance, (id, _)))| {
+                let redundancy = selected
+                    .iter()
+                    .filter_map(|(other, _)| {
+                        Some(cosine_similarity(
+                            embeddings.get(id)?,
+                            embeddings.get(other)?,
+                        ))
+                    })
+                    .fold(0.0f32, f32::max);
+                (i, lambda * relevance - (1.0 - lambda) * redundancy)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+            .unwrap();
+        selected.push(remaining.remove(best).1);
+    }
+    selected
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let (dot, norm_a, norm_b) = a
+        .iter()
+        .zip(b)
+        .fold((0.0, 0.0, 0.0), |(dot, norm_a, norm_b), (a, b)| {
+            (dot + a * b, norm_a + a * a, norm_b + b * b)
+        });
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranking(ids: &[&str]) -> Vec<(String, f32)> {
+        ids.iter().map(|id| (id.to_string(), 0.0)).collect()
+    }
+
+    #[test]
+    fn near_duplicates_are_pushed_down() {
+        let embeddings = HashMap::from([
+            ("a".to_string(), vec![1.0, 0.0]),
+            ("a2".to_string(), vec![1.0, 0.01]),
+            ("b".to_string(), vec![0.0, 1.0]),
+        ]);
+        let selected = maximal_marginal_relevance(ranking(&["a", "a2", "b"]), &embeddings, 0.5, 3);
+        let ids = selected
+            .iter()
+            .map(|(id, _)| id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["a", "b", "a2"]);
+    }
+
+    #[test]
+    fn only_relevance_keeps_the_ranking() {
+        let embeddings = HashMap::from([
+            ("a".to_string(), vec![1.0, 0.0]),
+            ("a2".to_string(), vec![1.0, 0.0]),
+            ("b".to_string(), vec![0.0, 1.0]),
+        ]);
+        let selected = maximal_marginal_relevance(ranking(&["a", "a2", "b"]), &embeddings, 1.0, 2);
+        let ids = selected
+            .iter()
+            .map(|(id, _)| id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["a", "a2"]);
+    }
+
+    #[test]
+    fn cosine_similarity_of_zero_vectors() {
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]), 1.0);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+}
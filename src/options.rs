@@ -0,0 +1,973 @@
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use chromadb::v2::client::{
+    ChromaAuthMethod, ChromaClient, ChromaClientOptions, ChromaTokenHeader,
+};
+use clap::{Parser, ValueEnum};
+use rustdoc_types::ItemKind;
+
+use crate::{
+    answer::AnswerMode,
+    cargo,
+    collection::CollectionConfig,
+    credentials,
+    document::DocFormat,
+    embedding::{Embedder, OllamaOptions, Prefixes, Provider, HASHING_DIMENSIONS},
+    endpoint::OllamaEndpoint,
+    error,
+    generator::{DocumentGenerator, Generators},
+    lexical::Retrieval,
+    llm::{Llm, LlmProvider},
+    local_store::Quantization,
+    retry::Retry,
+    store::StoreKind,
+};
+
+/// Everything that configures indexing and retrieval. The fields are also the
+/// options of the command line, and the setters cover the common ones for
+/// building it in code:
+///
+/// ```no_run
+/// use rustdoc_rag::{Options, StoreKind};
+///
+/// let options = Options::new("bevy")
+///     .with_out_dir("target/rustdoc-rag")
+///     .with_store(StoreKind::Local)
+///     .with_nb_results(5);
+/// ```
+#[derive(clap::Args, Debug, Clone)]
+#[command(about = None, long_about = None)]
+pub struct Options {
+    /// Embedding provider to use
+    #[arg(long, default_value = "ollama")]
+    pub provider: Provider,
+
+    /// Work without any service: embed with the hashing provider and keep the vectors
+    /// in the local store. Features needing a chat model aren't available
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Model to use for embedding, can be given several times to fuse results from multiple models
+    #[arg(short, long, default_value = "nomic-embed-text:latest")]
+    pub embedding: Vec<String>,
+
+    /// Prefix added to documents before embedding, defaults to the one expected by the model
+    #[arg(long)]
+    pub document_prefix: Option<String>,
+
+    /// Prefix added to queries before embedding, defaults to the one expected by the model
+    #[arg(long)]
+    pub query_prefix: Option<String>,
+
+    /// Truncate embeddings to this many dimensions, for Matryoshka-trained models
+    #[arg(long)]
+    pub embedding_dims: Option<usize>,
+
+    /// L2-normalize embeddings before storing and querying them
+    #[arg(long)]
+    pub normalize: bool,
+
+    /// Name of the project being documented, path to its rustdoc JSON file, or to a
+    /// directory like `target/doc` to index every rustdoc JSON file in it. The
+    /// command line queries every project given when there are several
+    #[arg(short, long, default_value = "bevy", action = clap::ArgAction::Append)]
+    pub project: String,
+
+    /// Path to the rustdoc JSON file of the project, or `-` to read it from stdin,
+    /// naming the project after its crate. Prompts are then given with --query
+    #[arg(long, visible_alias = "json", conflicts_with_all = ["manifest_path", "from_docs_rs"])]
+    pub json_path: Option<PathBuf>,
+
+    /// Directory containing the rustdoc JSON files, defaults to `jsons` or the directory of the project JSON file
+    #[arg(long)]
+    pub jsons_dir: Option<PathBuf>,
+
+    /// Only index the crates of a project directory whose name matches one of these
+    /// patterns, where `*` matches anything
+    #[arg(long)]
+    pub include_crate: Vec<String>,
+
+    /// Skip the crates whose name matches one of these patterns, among the crates of
+    /// a project directory and the dependencies the project re-exports items from
+    #[arg(long)]
+    pub exclude_crate: Vec<String>,
+
+    /// Only index the items of modules nested at most this deep under their crate
+    /// root, where 0 only indexes the items of the root modules
+    #[arg(long)]
+    pub max_depth: Option<usize>,
+
+    /// Only index the items whose path matches one of these patterns, like
+    /// `bevy_render::*`, where `*` matches anything
+    #[arg(long)]
+    pub include_path: Vec<String>,
+
+    /// Skip the items and modules whose path matches one of these patterns, like
+    /// `*::prelude`
+    #[arg(long)]
+    pub exclude_path: Vec<String>,
+
+    /// Also index the sections of the markdown files in this file or directory, like
+    /// a README or migration guides, with `source: guide` in their metadata. The
+    /// chapters of an mdBook directory are read from its SUMMARY.md
+    #[arg(long)]
+    pub extra_docs: Vec<PathBuf>,
+
+    /// Also index the items of the Rust files in this directory of examples, or this
+    /// file, with `source: example` in their metadata. The `examples` directory of the
+    /// package is indexed with --manifest-path
+    #[arg(long)]
+    pub examples: Vec<PathBuf>,
+
+    /// Also index the source of functions and methods, read from the crate sources
+    /// with the spans of the rustdoc JSON, to answer questions about how they work
+    #[arg(long)]
+    pub include_source: bool,
+
+    /// Ask the chat model for a short plain-English summary of each document, added
+    /// before it when indexing so questions worded like a newcomer's find it
+    #[arg(long)]
+    pub summarize: bool,
+
+    /// Ask the chat model for a short description of the items without documentation,
+    /// marked as generated, so they can still be found
+    #[arg(long)]
+    pub describe_undocumented: bool,
+
+    /// Don't index items without documentation, instead of indexing what is known of
+    /// them like their fields
+    #[arg(long, conflicts_with = "describe_undocumented")]
+    pub skip_undocumented: bool,
+
+    /// Also list in the documents of structs the traits they get from blanket impls,
+    /// like `From<T> for T`, and the auto traits like `Send`
+    #[arg(long)]
+    pub blanket_impls: bool,
+
+    /// Also embed the name and path, and the signature, of each item as vectors of
+    /// their own, for queries naming an item as well as describing it
+    #[arg(long)]
+    pub multi_vector: bool,
+
+    /// Directory where generated documents, caches and local indexes are written
+    #[arg(long, default_value = "out")]
+    pub out_dir: PathBuf,
+
+    /// Distance function to use for finding neighbours
+    #[arg(short, long, default_value = "squared-l2")]
+    pub distance: Distance,
+
+    /// Vector store to keep embeddings in
+    #[arg(long, default_value = "chroma")]
+    pub store: StoreKind,
+
+    /// Quantization of vectors kept in the local store
+    #[arg(long, default_value = "none")]
+    pub quantize: Quantization,
+
+    /// Keep binary-quantized vectors in the local store to prefilter candidates by Hamming distance
+    #[arg(long)]
+    pub binary_prefilter: bool,
+
+    /// Also write the generated documents to the output directory, for debugging
+    #[arg(long)]
+    pub emit_docs: bool,
+
+    /// Format of the documents written with --emit-docs
+    #[arg(long, default_value = "markdown")]
+    pub doc_format: DocFormat,
+
+    /// Directory of Tera templates named after a kind of item, like `struct.tera`,
+    /// writing the text of the documents of that kind from the data of the item
+    #[arg(long)]
+    pub templates: Option<PathBuf>,
+
+    /// Parse crates one at a time straight from disk and drop what the traversal
+    /// doesn't use, slower but needs much less memory on large projects
+    #[arg(long)]
+    pub low_memory: bool,
+
+    /// Generate the rustdoc JSON of the package of this Cargo manifest and of its
+    /// dependencies before indexing, instead of reading them from the jsons directory
+    #[arg(long)]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Toolchain used to generate rustdoc JSON with --manifest-path, it must produce
+    /// the format version this tool reads
+    #[arg(long, default_value = "nightly")]
+    pub toolchain: String,
+
+    /// Download the rustdoc JSON of a published crate from docs.rs, like `serde@1.0.210`
+    /// or `serde` for its latest release, instead of generating it
+    #[arg(long, conflicts_with = "manifest_path")]
+    pub from_docs_rs: Option<String>,
+
+    /// Download from docs.rs the rustdoc JSON of the dependencies missing from the
+    /// jsons directory, in the version the project links to
+    #[arg(long)]
+    pub fetch_missing: bool,
+
+    /// Also document the standard library, and resolve references to it, from the
+    /// rust-docs-json component of the --toolchain
+    #[arg(long)]
+    pub with_std: bool,
+
+    /// Version of the crate, like `0.14`, to keep each version in its own indexes.
+    /// Indexing checks that the rustdoc JSON matches it, and queries read the newest
+    /// indexed version matching it
+    #[arg(long)]
+    pub crate_version: Option<String>,
+
+    /// Version the indexes were pinned to with --crate-version
+    #[arg(skip)]
+    pub pinned_version: Option<String>,
+
+    /// Document generators by kind of item, replaced with [`Options::with_generator`]
+    #[arg(skip)]
+    pub generators: Generators,
+
+    /// Force recompute of everything from scratch
+    #[arg(short, long)]
+    pub recompute: bool,
+
+    /// Don't reuse or store embeddings in the on-disk cache
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Regenerate documents and only embed the ones that changed since the last indexing
+    #[arg(short, long)]
+    pub update: bool,
+
+    /// Keep running and update the index whenever the rustdoc JSON changes, or the
+    /// sources of the package with --manifest-path
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Number of documents embedded concurrently
+    #[arg(long, default_value_t = 8)]
+    pub concurrency: usize,
+
+    /// Times a failed call to the embedding service or Chroma is retried before
+    /// giving up
+    #[arg(long, default_value_t = 3)]
+    pub retries: u32,
+
+    /// Seconds to wait before retrying a failed call, doubled after every failure
+    #[arg(long, default_value_t = 1.0)]
+    pub retry_backoff: f64,
+
+    /// Seconds after which a call to the embedding service or Chroma fails
+    #[arg(long, default_value_t = 300)]
+    pub request_timeout: u64,
+
+    /// Elide the generic arguments of signatures nested deeper than this as `<..>`,
+    /// 0 to keep them all
+    #[arg(long, default_value_t = 3)]
+    pub signature_depth: usize,
+
+    /// Keep only this many bounds of the lists of bounds of signatures, 0 to keep
+    /// them all
+    #[arg(long, default_value_t = 3)]
+    pub signature_bounds: usize,
+
+    /// Number of entries sent to the vector store in each upsert
+    #[arg(long, default_value_t = 256)]
+    pub batch_size: usize,
+
+    /// Split documents longer than this many bytes into overlapping chunks, 0 to disable
+    #[arg(long, default_value_t = 4000)]
+    pub chunk_size: usize,
+
+    /// Number of bytes shared between consecutive chunks
+    #[arg(long, default_value_t = 400)]
+    pub chunk_overlap: usize,
+
+    /// Number of results to return
+    #[arg(short, long, default_value_t = 10)]
+    pub nb_results: usize,
+
+    /// How to find the documents matching a query
+    #[arg(long, default_value = "dense")]
+    pub retrieval: Retrieval,
+
+    /// Drop vector search results further than this from the query
+    #[arg(long)]
+    pub max_distance: Option<f32>,
+
+    /// Drop vector search results with a similarity to the query below this, from 0 to 100
+    #[arg(long)]
+    pub min_similarity: Option<f32>,
+
+    /// Drop keyword search results scoring less than this
+    #[arg(long)]
+    pub min_score: Option<f32>,
+
+    /// Only return items of this kind, like struct or trait
+    #[arg(long)]
+    pub kind: Option<String>,
+
+    /// Only return items from this crate
+    #[arg(long = "crate")]
+    pub krate: Option<String>,
+
+    /// Only return items at this path or under it, like `bevy::ecs`, by their path or
+    /// one of its re-exports
+    #[arg(long)]
+    pub path_prefix: Option<String>,
+
+    /// Only return items with this role: component, resource, event, plugin or system in Bevy, or extension for extension traits
+    #[arg(long)]
+    pub role: Option<String>,
+
+    /// Only return items available with these Cargo features enabled, like
+    /// `bevy_ui,bevy_pbr`, listing default features too
+    #[arg(long, value_delimiter = ',')]
+    pub features: Option<Vec<String>>,
+
+    /// TOML file mapping the words of users to the names of the API, like
+    /// `"spawn a cube" = ["Mesh3d", "Cuboid", "Commands::spawn"]`, whose names are
+    /// added to the queries using all the words of an entry. Set it per project in
+    /// the configuration
+    #[arg(long)]
+    pub glossary: Option<PathBuf>,
+
+    /// Search with a hypothetical documentation paragraph drafted by the chat model
+    /// instead of the question itself
+    #[arg(long)]
+    pub hyde: bool,
+
+    /// Also search with this many rewrites of the question by the chat model, and
+    /// merge the results
+    #[arg(long)]
+    pub multi_query: Option<usize>,
+
+    /// Diversify results with maximal marginal relevance, to avoid listing many
+    /// near identical items
+    #[arg(long)]
+    pub mmr: bool,
+
+    /// Balance between relevance (1) and diversity (0) of results with --mmr
+    #[arg(long, default_value_t = 0.5)]
+    pub mmr_lambda: f32,
+
+    /// Ask the chat model to score retrieved candidates and reorder them
+    #[arg(long)]
+    pub rerank: bool,
+
+    /// Number of candidates retrieved for reranking
+    #[arg(long, default_value_t = 50)]
+    pub rerank_candidates: usize,
+
+    /// Answer questions with a chat model from the retrieved documents, instead of
+    /// listing them
+    #[arg(long)]
+    pub answer: bool,
+
+    /// What answers are made of: an explanation from the documentation, or a working
+    /// snippet from the examples with links to the items it uses
+    #[arg(long, default_value = "docs")]
+    pub answer_mode: AnswerMode,
+
+    /// With --answer or chat, ask a clarifying question first when the results are
+    /// weak or about unrelated parts of the project, like `Do you mean UI text or 3D text?`
+    #[arg(long)]
+    pub clarify: bool,
+
+    /// Estimated tokens of documentation given to the chat model at most when answering
+    #[arg(long, default_value_t = 4000)]
+    pub context_tokens: usize,
+
+    /// Add items connected to the top results by their fields, method signatures
+    /// or implemented traits, to answer questions about how items relate
+    #[arg(long)]
+    pub expand_graph: bool,
+
+    /// Also answer from summaries of the groups of related items the top results
+    /// belong to, for questions about how parts of the project fit together. The
+    /// summaries are written by the chat model when indexing
+    #[arg(long)]
+    pub graph_rag: bool,
+
+    /// Score the passages of every result against the query, and show the best
+    /// matching ones instead of the start of the document
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Append every query with its filters and top results to this JSONL file, to
+    /// find documentation gaps with the `analytics` command
+    #[arg(long)]
+    pub query_log: Option<PathBuf>,
+
+    /// Backend of the chat model used to answer questions
+    #[arg(long, default_value = "ollama")]
+    pub llm: LlmProvider,
+
+    /// Base URL of the chat model API, defaults to a local Ollama or to OpenAI
+    #[arg(long)]
+    pub llm_base_url: Option<String>,
+
+    /// Chat model used to answer questions
+    #[arg(long, default_value = "llama3.2")]
+    pub chat_model: String,
+
+    /// URL of the Ollama server
+    #[arg(long, env = "OLLAMA_HOST", default_value = "http://127.0.0.1:11434")]
+    pub ollama_url: String,
+
+    /// Header sent with every request to Ollama, like `Authorization: Bearer <token>`
+    #[arg(long, env = "OLLAMA_HEADERS", value_delimiter = ',')]
+    pub ollama_header: Vec<String>,
+
+    /// How long Ollama keeps the embedding model loaded after a request, like `10m`,
+    /// or `-1` to keep it loaded
+    #[arg(long)]
+    pub ollama_keep_alive: Option<String>,
+
+    /// Context length Ollama loads the embedding model with, lower to fit small GPUs.
+    /// Longer inputs are truncated to it
+    #[arg(long)]
+    pub ollama_num_ctx: Option<u32>,
+
+    /// Whether Ollama truncates inputs longer than the context of the embedding
+    /// model, or fails on them with false
+    #[arg(long)]
+    pub ollama_truncate: Option<bool>,
+
+    /// URL of the Chroma server
+    #[arg(long, env = "CHROMA_URL", default_value = "http://localhost:8000")]
+    pub chroma_url: String,
+
+    /// Start Chroma without asking when it isn't reachable at a local --chroma-url
+    #[arg(long)]
+    pub start_chroma: bool,
+
+    /// Token sent to Chroma in the Authorization header
+    #[arg(long, env = "CHROMA_TOKEN")]
+    pub chroma_token: Option<String>,
+
+    /// Header to send the --chroma-token in, `x-chroma-token` for Chroma Cloud
+    #[arg(long, default_value = "authorization")]
+    pub chroma_token_header: TokenHeader,
+
+    /// Chroma tenant owning the database, on shared or hosted deployments
+    #[arg(long, env = "CHROMA_TENANT")]
+    pub chroma_tenant: Option<String>,
+
+    /// Chroma database holding the collections, instead of the default one
+    #[arg(long, env = "CHROMA_DATABASE")]
+    pub chroma_database: Option<String>,
+
+    /// Multiply scores of results by kind after retrieval, like `struct=1.2,function=0.9`
+    #[arg(long, value_parser = parse_boosts)]
+    pub boost: Option<HashMap<String, f32>>,
+
+    /// Weight of how many items refer to a result in its score, so that core types
+    /// come before rarely used ones. 0 ranks by relevance only
+    #[arg(long, default_value_t = 0.1)]
+    pub popularity: f32,
+
+    /// Lower the score of documents shorter than this many tokens, like undocumented
+    /// unit structs, unless they exactly match the query
+    #[arg(long)]
+    pub min_tokens: Option<usize>,
+}
+
+/// Options with nothing set, so that every field has its default value.
+#[derive(Parser)]
+struct Defaults {
+    #[command(flatten)]
+    options: Options,
+}
+
+/// Whether a version matches a requested one, like `0.14.2` matches `0.14`.
+fn version_matches(requested: &str, version: &str) -> bool {
+    let mut version = version.split('.');
+    requested
+        .trim_start_matches(['=', '^', 'v'])
+        .split('.')
+        .all(|part| version.next() == Some(part))
+}
+
+/// Numeric parts of a version, to find the newest one.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(['.', '-', '+'])
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+impl Default for Options {
+    /// The defaults of the command line, including the ones read from the
+    /// environment like `OLLAMA_HOST`.
+    fn default() -> Self {
+        Defaults::parse_from(["rustdoc-rag"]).options
+    }
+}
+
+fn parse_boosts(boosts: &str) -> Result<HashMap<String, f32>, String> {
+    boosts
+        .split(',')
+        .map(|boost| {
+            let (kind, factor) = boost
+                .split_once('=')
+                .ok_or_else(|| format!("expected kind=factor, got `{}`", boost))?;
+            let factor = factor
+                .trim()
+                .parse::<f32>()
+                .map_err(|_| format!("invalid factor for {}: `{}`", kind, factor))?;
+            Ok((kind.trim().to_string(), factor))
+        })
+        .collect()
+}
+
+impl Options {
+    /// Default options for a project, given by name or as a path to its rustdoc JSON file.
+    pub fn new(project: impl Into<String>) -> Self {
+        Options {
+            project: project.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Embedding models to use, replacing the default one.
+    pub fn with_embedding<S: Into<String>>(mut self, models: impl IntoIterator<Item = S>) -> Self {
+        self.embedding = models.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_provider(mut self, provider: Provider) -> Self {
+        self.provider = provider;
+        self
+    }
+
+    pub fn with_jsons_dir(mut self, jsons_dir: impl Into<PathBuf>) -> Self {
+        self.jsons_dir = Some(jsons_dir.into());
+        self
+    }
+
+    pub fn with_out_dir(mut self, out_dir: impl Into<PathBuf>) -> Self {
+        self.out_dir = out_dir.into();
+        self
+    }
+
+    pub fn with_distance(mut self, distance: Distance) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    pub fn with_store(mut self, store: StoreKind) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Write the documents of the items of this kind with `generator`, instead of the
+    /// built-in one when there is one.
+    pub fn with_generator(
+        mut self,
+        kind: ItemKind,
+        generator: impl DocumentGenerator + 'static,
+    ) -> Self {
+        self.generators.insert(kind, generator);
+        self
+    }
+
+    /// Embed the documents that changed since the last indexing when opening indexes.
+    pub fn with_update(mut self, update: bool) -> Self {
+        self.update = update;
+        self
+    }
+
+    pub fn with_retrieval(mut self, retrieval: Retrieval) -> Self {
+        self.retrieval = retrieval;
+        self
+    }
+
+    pub fn with_nb_results(mut self, nb_results: usize) -> Self {
+        self.nb_results = nb_results;
+        self
+    }
+
+    pub fn with_chat_model(mut self, llm: LlmProvider, model: impl Into<String>) -> Self {
+        self.llm = llm;
+        self.chat_model = model.into();
+        self
+    }
+
+    pub fn with_ollama_url(mut self, url: impl Into<String>) -> Self {
+        self.ollama_url = url.into();
+        self
+    }
+
+    pub fn with_chroma_url(mut self, url: impl Into<String>) -> Self {
+        self.chroma_url = url.into();
+        self
+    }
+
+    /// Path of the rustdoc JSON file of the project, or of the directory of all its
+    /// crates. When the project was given as a path, its name and the directory of
+    /// the JSON files are set from it.
+    pub fn project_json(&mut self) -> error::Result<PathBuf> {
+        if let Some(path) = &self.json_path {
+            // Read once, later projects of the same options use the copy
+            let path = if path == Path::new("-") {
+                let path = self.read_stdin_json()?;
+                self.json_path = Some(path.clone());
+                path
+            } else {
+                if self.jsons_dir.is_none() {
+                    self.jsons_dir = path.parent().map(Path::to_path_buf);
+                }
+                path.clone()
+            };
+            self.project = path.file_stem().unwrap().to_string_lossy().to_string();
+            return Ok(path);
+        }
+        let path = Path::new(&self.project);
+        if path.is_dir() {
+            let path = path.canonicalize().unwrap_or(path.to_path_buf());
+            // `target/doc` is named after the workspace
+            let named = match (path.file_name(), path.parent()) {
+                (Some(doc), Some(target)) if doc == "doc" && target.ends_with("target") => {
+                    target.parent().unwrap_or(target)
+                }
+                _ => &path,
+            };
+            self.project = named.file_name().map_or("workspace".into(), |name| {
+                name.to_string_lossy().to_string()
+            });
+            if self.jsons_dir.is_none() {
+                self.jsons_dir = Some(path.clone());
+            }
+            Ok(path)
+        } else if self.project.ends_with(".json") {
+            let path = PathBuf::from(&self.project);
+            self.project = path.file_stem().unwrap().to_string_lossy().to_string();
+            if self.jsons_dir.is_none() {
+                self.jsons_dir = path.parent().map(Path::to_path_buf);
+            }
+            Ok(path)
+        } else {
+            let path = self.jsons_dir().join(format!("{}.json", self.project));
+            // The standard library is read from the toolchain unless it was copied
+            if !path.exists() && cargo::STD_CRATES.contains(&self.project.as_str()) {
+                let dir = cargo::require_std_json_dir(&self.toolchain)?;
+                if self.jsons_dir.is_none() {
+                    self.jsons_dir = Some(dir.clone());
+                }
+                return Ok(dir.join(format!("{}.json", self.project)));
+            }
+            Ok(path)
+        }
+    }
+
+    /// Copy the rustdoc JSON read from stdin to the output directory, named after
+    /// its crate, as it's read again when indexing.
+    fn read_stdin_json(&self) -> error::Result<PathBuf> {
+        /// Just enough of a rustdoc JSON file to find the name of its crate
+        #[derive(serde::Deserialize)]
+        struct Root {
+            root: rustdoc_types::Id,
+            index: HashMap<rustdoc_types::Id, Named>,
+        }
+        #[derive(serde::Deserialize)]
+        struct Named {
+            name: Option<String>,
+        }
+
+        let stdin_error = |source| error::Error::Parse {
+            path: PathBuf::from("stdin"),
+            source,
+        };
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|err| stdin_error(serde_json::Error::io(err)))?;
+        let mut krate = serde_json::from_str::<Root>(&content).map_err(stdin_error)?;
+        let Some(name) = krate.index.remove(&krate.root).and_then(|root| root.name) else {
+            return Err(error::Error::Rustdoc(
+                "the rustdoc JSON from stdin has no root crate".to_string(),
+            ));
+        };
+        let dir = self.out_dir.join("stdin");
+        let path = dir.join(format!("{}.json", name));
+        std::fs::create_dir_all(&dir)
+            .and_then(|_| std::fs::write(&path, content))
+            .map_err(|err| {
+                error::Error::Other(format!("couldn't write {}: {}", path.display(), err).into())
+            })?;
+        Ok(path)
+    }
+
+    /// Pin the indexes to the newest version matching --crate-version, between the
+    /// ones already indexed and the one of the project rustdoc JSON.
+    pub fn pin_version(&mut self, project_json: &Path) -> error::Result<()> {
+        /// Just enough of a rustdoc JSON file to know the version of its crate
+        #[derive(serde::Deserialize)]
+        struct CrateVersion {
+            crate_version: Option<String>,
+        }
+
+        let Some(requested) = &self.crate_version else {
+            return Ok(());
+        };
+        let prefix = format!("{}@", self.project);
+        let mut versions = std::fs::read_dir(self.out_dir.join("fulltext"))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                Some(name.strip_prefix(&prefix)?.to_string())
+            })
+            .collect::<Vec<_>>();
+        let indexed = versions.clone();
+        if project_json.is_file() {
+            let json_version = std::fs::File::open(project_json).ok().and_then(|file| {
+                serde_json::from_reader::<_, CrateVersion>(std::io::BufReader::new(file))
+                    .ok()?
+                    .crate_version
+            });
+            // A JSON without version is taken to be the requested one
+            versions.push(json_version.unwrap_or(requested.clone()));
+        }
+        let pinned = versions
+            .into_iter()
+            .filter(|version| version_matches(requested, version))
+            .max_by_key(|version| version_key(version));
+        let Some(pinned) = pinned else {
+            let indexed = if indexed.is_empty() {
+                String::new()
+            } else {
+                format!(", indexed versions: {}", indexed.join(", "))
+            };
+            return Err(error::Error::Options(format!(
+                "couldn't find the rustdoc JSON or an index of {} matching version {}{}",
+                self.project, requested, indexed
+            )));
+        };
+        self.pinned_version = Some(pinned);
+        Ok(())
+    }
+
+    /// Apply --offline, replacing the settings that need a service.
+    pub fn use_offline(&mut self) -> error::Result<()> {
+        if !self.offline {
+            return Ok(());
+        }
+        for (flag, enabled) in [
+            ("--answer", self.answer),
+            ("--rerank", self.rerank),
+            ("--hyde", self.hyde),
+            ("--multi-query", self.multi_query.is_some()),
+            ("--summarize", self.summarize),
+            ("--describe-undocumented", self.describe_undocumented),
+        ] {
+            if enabled {
+                return Err(error::Error::Options(format!(
+                    "{} needs a chat model, which --offline doesn't use",
+                    flag
+                )));
+            }
+        }
+        self.store = StoreKind::Local;
+        if self.provider != Provider::Hashing {
+            self.provider = Provider::Hashing;
+            self.embedding = vec![format!("hashing-{}", HASHING_DIMENSIONS)];
+        }
+        Ok(())
+    }
+
+    /// Name of the indexes of the project, with the version they are pinned to.
+    pub fn index_name(&self) -> String {
+        match &self.pinned_version {
+            Some(version) => format!("{}@{}", self.project, version),
+            None => self.project.clone(),
+        }
+    }
+
+    pub fn jsons_dir(&self) -> PathBuf {
+        self.jsons_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("jsons"))
+    }
+
+    pub fn ollama(&self) -> error::Result<OllamaEndpoint> {
+        OllamaEndpoint::new(&self.ollama_url, &self.ollama_header)
+    }
+
+    pub fn retry(&self) -> Retry {
+        Retry {
+            retries: self.retries,
+            backoff: Duration::from_secs_f64(self.retry_backoff.max(0.0)),
+            timeout: Duration::from_secs(self.request_timeout.max(1)),
+        }
+    }
+
+    pub fn chat_model(&self) -> error::Result<Llm> {
+        Ok(Llm::new(
+            self.llm,
+            self.llm_base_url.as_deref(),
+            &self.chat_model,
+            &self.ollama()?,
+        ))
+    }
+
+    pub fn chroma_client(&self) -> ChromaClient {
+        ChromaClient::new(ChromaClientOptions {
+            url: self.chroma_url.clone(),
+            auth: match self
+                .chroma_token
+                .clone()
+                .or_else(|| credentials::get("CHROMA_TOKEN"))
+            {
+                Some(token) => ChromaAuthMethod::TokenAuth {
+                    token,
+                    header: match self.chroma_token_header {
+                        TokenHeader::Authorization => ChromaTokenHeader::Authorization,
+                        TokenHeader::XChromaToken => ChromaTokenHeader::XChromaToken,
+                    },
+                },
+                None => ChromaAuthMethod::None,
+            },
+            // The client only adds the database to the query of its requests, where
+            // Chroma also reads the tenant
+            database: match (&self.chroma_database, &self.chroma_tenant) {
+                (database, Some(tenant)) => Some(format!(
+                    "{}&tenant={}",
+                    database.as_deref().unwrap_or("default_database"),
+                    tenant
+                )),
+                (database, None) => database.clone(),
+            },
+        })
+    }
+
+    pub fn prefixes(&self, model: &str) -> Prefixes {
+        let mut prefixes = match self.provider {
+            Provider::Ollama => Prefixes::for_model(model),
+            Provider::Cohere | Provider::Voyage | Provider::Hashing => Prefixes::default(),
+        };
+        if let Some(prefix) = &self.document_prefix {
+            prefix.clone_into(&mut prefixes.document);
+        }
+        if let Some(prefix) = &self.query_prefix {
+            prefix.clone_into(&mut prefixes.query);
+        }
+        prefixes
+    }
+
+    pub fn embedder(&self, model: &str) -> error::Result<Embedder> {
+        Ok(Embedder::new(
+            self.provider,
+            model,
+            self.prefixes(model),
+            self.embedding_dims,
+            self.normalize,
+            &self.ollama()?,
+            OllamaOptions {
+                keep_alive: self.ollama_keep_alive.clone(),
+                num_ctx: self.ollama_num_ctx,
+                truncate: self.ollama_truncate,
+            },
+        )?
+        .with_retry(self.retry()))
+    }
+
+    pub fn collection_config<'a>(
+        &'a self,
+        model: &'a str,
+        prefixes: &'a Prefixes,
+    ) -> CollectionConfig<'a> {
+        CollectionConfig {
+            project: &self.project,
+            version: self.pinned_version.as_deref(),
+            provider: self.provider,
+            model,
+            space: self.distance.space(),
+            dimensions: self.embedding_dims,
+            prefixes,
+            normalize: self.normalize,
+            quantized: self.quantize != Quantization::None,
+            multi_vector: self.multi_vector,
+        }
+    }
+
+    pub fn local_index_path(&self, collection_name: &str) -> PathBuf {
+        self.out_dir
+            .join("index")
+            .join(format!("{}.bin", collection_name))
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum Distance {
+    SquaredL2,
+    InnerProduct,
+    Cosine,
+}
+
+impl FromStr for Distance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "squared-l2" => Ok(Self::SquaredL2),
+            "l2" => Ok(Self::SquaredL2),
+            "inner-product" => Ok(Self::InnerProduct),
+            "ip" => Ok(Self::InnerProduct),
+            "cosine" => Ok(Self::Cosine),
+            _ => Err("Invalid distance metric".to_string()),
+        }
+    }
+}
+
+/// Header a token is sent to Chroma in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TokenHeader {
+    /// `Authorization: Bearer <token>`
+    Authorization,
+    /// `X-Chroma-Token: <token>`
+    XChromaToken,
+}
+
+impl Distance {
+    /// Name of the distance function in Chroma and the local store
+    pub fn space(&self) -> &'static str {
+        match self {
+            Distance::SquaredL2 => "l2",
+            Distance::InnerProduct => "ip",
+            Distance::Cosine => "cosine",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_match_by_prefix_of_parts() {
+        assert!(version_matches("0.14", "0.14.1"));
+        assert!(version_matches("=0.14.1", "0.14.1"));
+        assert!(version_matches("^0.14", "0.14.0"));
+        assert!(version_matches("v0.14", "0.14.2"));
+        assert!(!version_matches("0.14", "0.15.0"));
+        assert!(!version_matches("0.1", "0.14.0"));
+        assert!(!version_matches("0.14.1", "0.14"));
+    }
+
+    #[test]
+    fn newest_version_has_the_largest_key() {
+        let newest = ["0.14.1", "0.9.0", "0.14.10", "0.14.2"]
+            .into_iter()
+            .max_by_key(|version| version_key(version));
+        assert_eq!(newest, Some("0.14.10"));
+        assert!(version_key("0.15.0-rc.1") > version_key("0.14.9"));
+    }
+}
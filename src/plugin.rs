@@ -0,0 +1,312 @@
+use std::fmt::Write;
+
+use rustdoc_types::{Item, ItemEnum};
+
+use crate::{
+    document::Document,
+    document_struct::is_bevy_trait,
+    source::Sources,
+    traversal::{item_metadata, item_path, CrateCatalog},
+};
+
+/// Methods of `App` and `PluginGroupBuilder` registering things, with what they register and whether it is
+/// given as a type argument like `init_resource::<T>()`.
+const REGISTRATIONS: &[(&str, Registered, bool)] = &[
+    ("add_plugins", Registered::Plugin, false),
+    ("add", Registered::Plugin, false),
+    ("add_before", Registered::Plugin, false),
+    ("add_after", Registered::Plugin, false),
+    ("add_group", Registered::Plugin, false),
+    ("add_systems", Registered::System, false),
+    ("init_resource", Registered::Resource, true),
+    ("insert_resource", Registered::Resource, false),
+    ("init_non_send_resource", Registered::Resource, true),
+    ("insert_non_send_resource", Registered::Resource, false),
+    ("add_event", Registered::Event, true),
+    ("add_message", Registered::Event, true),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Registered {
+    Plugin,
+    System,
+    Resource,
+    Event,
+}
+
+/// What a plugin adds to an app.
+#[derive(Default)]
+struct Setup {
+    plugins: Vec<String>,
+    systems: Vec<String>,
+    resources: Vec<String>,
+    events: Vec<String>,
+}
+
+impl Setup {
+    fn add(&mut self, registered: Registered, name: String) {
+        let names = match registered {
+            Registered::Plugin => &mut self.plugins,
+            Registered::System => &mut self.systems,
+            Registered::Resource => &mut self.resources,
+            Registered::Event => &mut self.events,
+        };
+        if !name.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+            && self.systems.is_empty()
+            && self.resources.is_empty()
+            && self.events.is_empty()
+    }
+}
+
+/// Document of what a Bevy plugin or plugin group sets up in an app, from the
+/// calls in its documentation and, when sources are read, in its `build` method.
+/// Plugins where nothing is found aren't documented.
+pub fn plugin_document(
+    item: &Item,
+    stru: &rustdoc_types::Struct,
+    current_crate: usize,
+    crates: &CrateCatalog,
+    sources: Option<&mut Sources>,
+) -> Option<Document> {
+    let krate = &crates[current_crate].as_ref().unwrap().1;
+    let (group, build) = stru.impls.iter().find_map(|id| {
+        let ItemEnum::Impl(imp) = &krate.index.get(id)?.inner else {
+            return None;
+        };
+        let trait_ = imp.trait_.as_ref()?;
+        let group = match trait_.name.rsplit("::").next() {
+            Some("Plugin") => false,
+            Some("PluginGroup") => true,
+            _ => return None,
+        };
+        if !is_bevy_trait(trait_, krate) {
+            return None;
+        }
+        let build = imp
+            .items
+            .iter()
+            .filter_map(|id| krate.index.get(id))
+            .find(|method| method.name.as_deref() == Some("build"));
+        Some((group, build))
+    })?;
+
+    let name = item.name.clone().unwrap_or_default();
+    let mut setup = Setup::default();
+    let mut found_in = vec![];
+    let docs = [item.docs.as_deref(), build.and_then(|b| b.docs.as_deref())];
+    for docs in docs.into_iter().flatten() {
+        let before = setup_size(&setup);
+        registrations(docs, group, &mut setup);
+        listed_plugins(docs, &mut setup);
+        // Examples in the documentation add the plugin itself
+        setup.plugins.retain(|plugin| *plugin != name);
+        if setup_size(&setup) > before && !found_in.contains(&"its documentation") {
+            found_in.push("its documentation");
+        }
+    }
+    if let Some(code) = build
+        .zip(sources)
+        .and_then(|(build, sources)| sources.item_code(build))
+    {
+        let before = setup_size(&setup);
+        registrations(&code, group, &mut setup);
+        if setup_size(&setup) > before {
+            found_in.push("the source of its build method");
+        }
+    }
+    if setup.is_empty() {
+        return None;
+    }
+
+    let mut text = String::new();
+    write!(text, "What does {} set up?\n\n", name).unwrap();
+    writeln!(
+        text,
+        "{} is a Bevy {}. Added to an app, it adds:",
+        name,
+        if group { "plugin group" } else { "plugin" }
+    )
+    .unwrap();
+    let mut metadata = item_metadata(item, "plugin", current_crate, crates);
+    metadata.insert("role".to_string(), "plugin".into());
+    for (label, key, names) in [
+        ("the plugins", "plugins", &setup.plugins),
+        ("the systems", "systems", &setup.systems),
+        ("the resources", "resources", &setup.resources),
+        ("the events", "events", &setup.events),
+    ] {
+        if !names.is_empty() {
+            writeln!(text, "- {} {}", label, names.join(", ")).unwrap();
+            metadata.insert(key.to_string(), names.join(", ").into());
+        }
+    }
+    write!(text, "\nAs found in {}.\n\n", found_in.join(" and ")).unwrap();
+    Some(Document {
+        path: item_path(item, current_crate, crates),
+        kind: "plugin".to_string(),
+        text,
+        metadata,
+    })
+}
+
+fn setup_size(setup: &Setup) -> usize {
+    setup.plugins.len() + setup.systems.len() + setup.resources.len() + setup.events.len()
+}
+
+/// Record what the registration calls of the code add, like
+/// `app.add_systems(Update, (move_player, jump))`.
+fn registrations(code: &str, group: bool, setup: &mut Setup) {
+    for (method, registered, typed) in REGISTRATIONS {
+        // Methods of `PluginGroupBuilder`, too common to look for in other code
+        if !group && matches!(*method, "add" | "add_before" | "add_after" | "add_group") {
+            continue;
+        }
+        let pattern = format!(".{}", method);
+        let mut rest = code;
+        while let Some(start) = rest.find(&pattern) {
+            rest = &rest[start + pattern.len()..];
+            let type_arg = match rest.strip_prefix("::<") {
+                Some(after) => {
+                    let Some(end) = closing(after, '<', '>') else {
+                        continue;
+                    };
+                    rest = &after[end + 1..];
+                    Some(&after[..end])
+                }
+                None => None,
+            };
+            let Some(after) = rest.strip_prefix('(') else {
+                continue;
+            };
+            let Some(end) = closing(after, '(', ')') else {
+                continue;
+            };
+            let args = &after[..end];
+            rest = &after[end + 1..];
+            match (typed, type_arg) {
+                (true, Some(ty)) => setup.add(*registered, name(ty)),
+                (true, None) => {}
+                (false, _) => {
+                    let mut args = split_top_level(args);
+                    if *registered == Registered::System && args.len() > 1 {
+                        // The first argument is the schedule
+                        args.remove(0);
+                    }
+                    for arg in args {
+                        for leaf in leaves(arg) {
+                            setup.add(*registered, name(leaf));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Plugins listed in documentation like `* [`LogPlugin`](crate::log::LogPlugin)`.
+fn listed_plugins(docs: &str, setup: &mut Setup) {
+    for line in docs.lines() {
+        let line = line.trim_start();
+        let Some(item) = line.strip_prefix("* ").or_else(|| line.strip_prefix("- ")) else {
+            continue;
+        };
+        let Some(listed) = item
+            .trim_start_matches('[')
+            .strip_prefix('`')
+            .and_then(|item| item.split('`').next())
+        else {
+            continue;
+        };
+        let listed = name(listed);
+        if listed.ends_with("Plugin") || listed.ends_with("Plugins") {
+            setup.add(Registered::Plugin, listed);
+        }
+    }
+}
+
+/// Position of the bracket closing the one opened just before the text.
+fn closing(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            if depth == 0 {
+                return Some(i);
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// Arguments separated by commas outside of brackets.
+fn split_top_level(args: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' | ']' | '}' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&args[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&args[start..]);
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Expressions of the elements of tuples, like `a` and `b` in `(a, b).chain()`.
+fn leaves(expr: &str) -> Vec<&str> {
+    let expr = expr.trim();
+    match expr.strip_prefix('(') {
+        Some(inner) => match closing(inner, '(', ')') {
+            Some(end) => split_top_level(&inner[..end])
+                .into_iter()
+                .flat_map(leaves)
+                .collect(),
+            None => vec![],
+        },
+        None => vec![expr],
+    }
+}
+
+/// Name of the type or function an expression starts with, like `LogPlugin` for
+/// `LogPlugin::default()` or `update` for `systems::update.after(setup)`.
+fn name(expr: &str) -> String {
+    let path = expr
+        .trim()
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+        .next()
+        .unwrap_or_default();
+    let segments = path
+        .split("::")
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let last = segments.last().copied().unwrap_or_default();
+    if last.starts_with(char::is_lowercase) {
+        // Constructors like `new` or `default` of a type
+        if let Some(ty) = segments
+            .iter()
+            .rev()
+            .find(|segment| segment.starts_with(char::is_uppercase))
+        {
+            return ty.to_string();
+        }
+    }
+    last.to_string()
+}
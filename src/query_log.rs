@@ -0,0 +1,83 @@
+use std::{error::Error, fs::OpenOptions, io::Write, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{filter::Filter, Results};
+
+/// Most results recorded for each query.
+const LOGGED_RESULTS: usize = 5;
+
+/// A query recorded with --query-log, one JSON object per line.
+#[derive(Serialize, Deserialize)]
+pub struct LoggedQuery {
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    pub query: String,
+    /// Restrictions of the query, like `kind` or `crate`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub filter: Map<String, Value>,
+    /// Ids of the top results
+    pub results: Vec<String>,
+    /// Number of results found
+    pub count: usize,
+    /// Who asked, in server mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+}
+
+/// Append a query with its filter and top results to the log.
+pub fn record(
+    path: &Path,
+    query: &str,
+    filter: &Filter,
+    results: &Results,
+    client: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let mut restrictions = Map::new();
+    for (name, value) in [
+        ("kind", filter.kind.clone()),
+        ("crate", filter.krate.clone()),
+        ("path_prefix", filter.path_prefix.clone()),
+        ("role", filter.role.clone()),
+        (
+            "features",
+            filter.features.as_ref().map(|features| features.join(",")),
+        ),
+    ] {
+        if let Some(value) = value {
+            restrictions.insert(name.to_string(), value.into());
+        }
+    }
+    let entry = LoggedQuery {
+        time: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        query: query.trim().to_string(),
+        filter: restrictions,
+        results: results
+            .ranking
+            .iter()
+            .take(LOGGED_RESULTS)
+            .map(|(id, _)| id.clone())
+            .collect(),
+        count: results.ranking.len(),
+        client: client.map(str::to_string),
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    // One write per line, so that concurrent queries don't interleave
+    file.write_all(format!("{}\n", serde_json::to_string(&entry)?).as_bytes())?;
+    Ok(())
+}
+
+/// The queries of the log, skipping lines that aren't queries.
+pub fn read(path: &Path) -> Result<Vec<LoggedQuery>, Box<dyn Error>> {
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
@@ -0,0 +1,101 @@
+use std::{collections::BTreeMap, error::Error, path::Path, time::Instant};
+
+use serde::Serialize;
+
+/// Summary of an indexing run, written to `<out_dir>/report.json`.
+#[derive(Serialize, Default)]
+pub struct Report {
+    pub visited: BTreeMap<String, usize>,
+    pub documents: usize,
+    /// Documents dropped as duplicates of another one
+    pub duplicates: usize,
+    /// Items without documentation, by crate
+    pub undocumented: BTreeMap<String, usize>,
+    pub skipped: BTreeMap<String, Vec<String>>,
+    /// Dependencies outside of the standard library without rustdoc JSON, with the
+    /// number of their items the project re-exports
+    pub missing_crates: BTreeMap<String, usize>,
+    /// Duration of each phase, in seconds
+    pub phases: BTreeMap<String, f64>,
+    /// Token counts of the documents, by embedding model
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tokens: BTreeMap<String, TokenStats>,
+}
+
+#[derive(Serialize)]
+pub struct TokenStats {
+    pub context_length: usize,
+    /// Number of documents by power of two bucket, keyed by its upper bound
+    pub histogram: BTreeMap<usize, usize>,
+    pub over_context: Vec<String>,
+}
+
+impl TokenStats {
+    pub fn new(context_length: usize, counts: &[(String, usize)]) -> Self {
+        let mut histogram = BTreeMap::new();
+        for (_, count) in counts {
+            *histogram.entry(count.next_power_of_two()).or_default() += 1;
+        }
+        TokenStats {
+            context_length,
+            histogram,
+            over_context: counts
+                .iter()
+                .filter(|(_, count)| *count >= context_length)
+                .map(|(id, _)| id.clone())
+                .collect(),
+        }
+    }
+
+    pub fn print(&self, model: &str) {
+        println!(
+            "tokens per document for {} (context of {}):",
+            model, self.context_length
+        );
+        for (bound, documents) in &self.histogram {
+            println!("  <= {:>6}: {}", bound, documents);
+        }
+        if !self.over_context.is_empty() {
+            println!("{} documents exceed the context:", self.over_context.len());
+            for id in &self.over_context {
+                println!("  {}", id);
+            }
+        }
+    }
+}
+
+impl Report {
+    pub fn visit(&mut self, kind: &str) {
+        *self.visited.entry(kind.to_string()).or_default() += 1;
+    }
+
+    pub fn skip(&mut self, reason: &str, item: String) {
+        tracing::debug!(reason, item, "skipping item");
+        self.skipped
+            .entry(reason.to_string())
+            .or_default()
+            .push(item);
+    }
+
+    /// Whether an item was already skipped for this reason.
+    pub fn skipped_item(&self, reason: &str, item: &str) -> bool {
+        self.skipped
+            .get(reason)
+            .is_some_and(|items| items.iter().any(|skipped| skipped == item))
+    }
+
+    /// Add the time elapsed since `start` to the given phase.
+    pub fn time(&mut self, phase: &str, start: Instant) {
+        tracing::info!(phase, seconds = start.elapsed().as_secs_f64(), "phase done");
+        *self.phases.entry(phase.to_string()).or_default() += start.elapsed().as_secs_f64();
+    }
+
+    pub fn write(&self, out_dir: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::create_dir_all(out_dir)?;
+        std::fs::write(
+            out_dir.join("report.json"),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+}
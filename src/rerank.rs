@@ -0,0 +1,72 @@
+use std::error::Error;
+
+use futures::StreamExt;
+use serde_json::json;
+
+use crate::llm::Llm;
+
+/// Reorders retrieved documents by asking a chat model how relevant each one is.
+pub struct Reranker {
+    llm: Llm,
+    concurrency: usize,
+}
+
+impl Reranker {
+    pub fn new(llm: Llm, concurrency: usize) -> Self {
+        Reranker {
+            llm,
+            concurrency: concurrency.max(1),
+        }
+    }
+
+    /// Score every `(id, text)` candidate from 0 to 10, best first. Candidates the
+    /// model didn't give a usable score keep their order after the scored ones.
+    pub async fn rerank(
+        &self,
+        query: &str,
+        candidates: Vec<(String, String)>,
+    ) -> Result<Vec<(String, f32)>, Box<dyn Error>> {
+        let mut scored = futures::stream::iter(candidates.into_iter().enumerate())
+            .map(|(rank, (id, text))| async move {
+                let reply = self.llm.chat(messages(query, &text), |_| Ok(())).await?;
+                Ok::<_, Box<dyn Error>>((rank, id, parse_score(&reply)))
+            })
+            .buffered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        scored.sort_by(|a, b| {
+            b.2.unwrap_or(-1.0)
+                .total_cmp(&a.2.unwrap_or(-1.0))
+                .then_with(|| a.0.cmp(&b.0))
+        });
+        Ok(scored
+            .into_iter()
+            .map(|(_, id, score)| (id, score.unwrap_or_default()))
+            .collect())
+    }
+}
+
+fn messages(query: &str, text: &str) -> serde_json::Value {
+    json!([
+        {
+            "role": "system",
+            "content": "You judge how useful a piece of Rust documentation is to answer a \
+                question. Reply with a single number from 0 (unrelated) to 10 (answers it \
+                directly), and nothing else.",
+        },
+        {
+            "role": "user",
+            "content": format!("Question: {}\n\nDocumentation:\n\n{}", query, text.trim()),
+        },
+    ])
+}
+
+/// First number in the reply, clamped to the expected range.
+fn parse_score(reply: &str) -> Option<f32> {
+    reply
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .find_map(|word| word.parse::<f32>().ok())
+        .map(|score| score.clamp(0.0, 10.0))
+}
@@ -0,0 +1,111 @@
+use std::{error::Error, future::Future, time::Duration};
+
+/// How calls to the embedding service and to Chroma are retried, so that a
+/// transient error doesn't end a long indexing run.
+#[derive(Debug, Clone, Copy)]
+pub struct Retry {
+    /// Attempts after the first one
+    pub retries: u32,
+    /// Wait before the first retry, doubled after every failure
+    pub backoff: Duration,
+    /// Longest an attempt can take
+    pub timeout: Duration,
+}
+
+impl Retry {
+    /// Make the call until it succeeds, failing with the last error once the
+    /// retries are exhausted or when the service rejected the request.
+    pub async fn run<T, F>(
+        &self,
+        what: &str,
+        mut call: impl FnMut() -> F,
+    ) -> Result<T, Box<dyn Error>>
+    where
+        F: Future<Output = Result<T, Box<dyn Error>>>,
+    {
+        let mut delay = self.backoff;
+        let mut attempt = 0;
+        loop {
+            let (result, timed_out) = match tokio::time::timeout(self.timeout, call()).await {
+                Ok(result) => (result, false),
+                Err(_) => (
+                    Err(format!(
+                        "{} timed out after {} seconds",
+                        what,
+                        self.timeout.as_secs_f64()
+                    )
+                    .into()),
+                    true,
+                ),
+            };
+            match result {
+                Err(err) if attempt < self.retries && (timed_out || transient(err.as_ref())) => {
+                    attempt += 1;
+                    tracing::warn!(what, attempt, error = %err, "retrying");
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// An error response of a service whose client only gives it as a message.
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub(crate) struct StatusError {
+    pub status: reqwest::StatusCode,
+    pub message: String,
+}
+
+/// Whether an error can go away on its own: the request timed out, couldn't
+/// connect or be sent, or the service is failing or rate limiting. Requests the
+/// service rejected and errors of anything else aren't retried.
+fn transient(err: &(dyn Error + 'static)) -> bool {
+    let retried = |status: reqwest::StatusCode| {
+        status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+    };
+    if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+        return err.is_timeout()
+            || err.is_connect()
+            || err.is_request()
+            || err.status().is_some_and(retried);
+    }
+    err.downcast_ref::<StatusError>()
+        .is_some_and(|err| retried(err.status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(code: u16) -> StatusError {
+        StatusError {
+            status: reqwest::StatusCode::from_u16(code).unwrap(),
+            message: code.to_string(),
+        }
+    }
+
+    #[test]
+    fn retries_failing_and_rate_limited_services() {
+        assert!(transient(&status(500)));
+        assert!(transient(&status(503)));
+        assert!(transient(&status(429)));
+    }
+
+    #[test]
+    fn rejected_requests_are_not_retried() {
+        assert!(!transient(&status(400)));
+        assert!(!transient(&status(401)));
+        assert!(!transient(&status(404)));
+    }
+
+    #[test]
+    fn other_errors_are_not_retried() {
+        let err: Box<dyn Error> = "invalid response".into();
+        assert!(!transient(err.as_ref()));
+        let err = serde_json::from_str::<u32>("x").unwrap_err();
+        assert!(!transient(&err));
+    }
+}
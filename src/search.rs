@@ -0,0 +1,769 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    error::Error,
+    time::Instant,
+};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    answer::{self, AnswerMode},
+    catalog::ItemCatalog,
+    chunk,
+    communities::Communities,
+    dedup, docs_rs,
+    embedding::InputType,
+    error,
+    expansion::{self, Glossary},
+    filter::Filter,
+    fusion,
+    graph::Graph,
+    lexical::{self, LexicalHit, LexicalIndex, Retrieval},
+    llm::Llm,
+    mmr, query_log,
+    rerank::Reranker,
+    store, Index, Options,
+};
+
+/// Names suggested at most for a query that looks like a misspelled identifier
+const SUGGESTIONS: usize = 3;
+
+/// Top results whose neighbours in the item graph are added with --expand-graph
+const GRAPH_SEEDS: usize = 3;
+
+/// How many more candidates than results are retrieved for --mmr to pick from
+const MMR_CANDIDATES_FACTOR: usize = 3;
+
+/// Similarity in percent of the best result below which a question is ambiguous
+const AMBIGUOUS_SIMILARITY: f32 = 50.0;
+
+/// Top results looked at to tell if a question is ambiguous
+const AMBIGUOUS_TOP: usize = 5;
+
+/// Retrieves documents for prompts from the indexes of a project.
+pub struct Retriever {
+    options: Options,
+    indexes: Vec<Index>,
+    pub lexical: Option<LexicalIndex>,
+    graph: Option<Graph>,
+    communities: Option<Communities>,
+    catalog: Option<ItemCatalog>,
+    reranker: Option<Reranker>,
+    expander: Option<Llm>,
+    glossary: Option<Glossary>,
+    filter: Filter,
+    candidates: usize,
+    /// Retrievers of other projects queried along with this one
+    others: Vec<Retriever>,
+}
+
+/// Number of documents retrieved before reranking or diversifying them.
+fn candidates(options: &Options) -> usize {
+    if options.rerank {
+        options.rerank_candidates.max(options.nb_results)
+    } else if options.mmr {
+        options.nb_results * MMR_CANDIDATES_FACTOR
+    } else {
+        options.nb_results
+    }
+}
+
+/// Results of a query, that follow-up commands refer to by number.
+#[derive(Default)]
+pub struct Results {
+    pub ranking: Vec<(String, f32)>,
+    /// Exact matches of an identifier query, listed first
+    pub pinned: Vec<String>,
+    /// Items connected to the top results in the item graph
+    pub related: Vec<String>,
+    /// Summaries of the groups of items the top results belong to, with --graph-rag
+    pub communities: Vec<String>,
+    /// Names close to an identifier query that matched nothing
+    pub suggestions: Vec<String>,
+    /// Distance function of the scores when they are distances, lower being better
+    pub space: Option<&'static str>,
+    pub texts: HashMap<String, String>,
+    pub embeddings: HashMap<String, Vec<f32>>,
+    pub metadatas: HashMap<String, Map<String, Value>>,
+    /// Project of every result, when several projects were queried
+    pub projects: HashMap<String, String>,
+    /// Seconds spent in each stage of the query, like `embedding` or `rerank`
+    pub timings: BTreeMap<&'static str, f64>,
+    /// Passages of each result most similar to the query with their score, best
+    /// first, with --explain
+    pub passages: HashMap<String, Vec<(String, f32)>>,
+}
+
+impl Results {
+    /// Add the time elapsed since `start` to a stage of the query.
+    pub fn time(&mut self, stage: &'static str, start: Instant) {
+        *self.timings.entry(stage).or_default() += start.elapsed().as_secs_f64();
+    }
+    /// `(id, text)` of the community summaries, the ranked results then the related
+    /// ones, to answer from.
+    pub fn documents(&self) -> Vec<(String, String)> {
+        self.communities
+            .iter()
+            .chain(self.ranking.iter().map(|(id, _)| id))
+            .chain(&self.related)
+            .filter_map(|id| Some((id.clone(), self.texts.get(id)?.clone())))
+            .collect()
+    }
+
+    /// Whether the results may not be what a question means: the best is far from
+    /// the query, or the top ones are in unrelated modules.
+    pub fn is_ambiguous(&self) -> bool {
+        let Some((_, best)) = self.ranking.first() else {
+            return false;
+        };
+        if self
+            .space
+            .is_some_and(|space| store::similarity(*best, space) < AMBIGUOUS_SIMILARITY)
+        {
+            return true;
+        }
+        let areas = self
+            .ranking
+            .iter()
+            .take(AMBIGUOUS_TOP)
+            .filter_map(|(id, _)| self.metadatas.get(id)?.get("module")?.as_str())
+            .map(|module| module.split("::").take(2).collect::<Vec<_>>().join("::"))
+            .collect::<Vec<_>>();
+        let mut distinct = areas.clone();
+        distinct.sort();
+        distinct.dedup();
+        // No part of the project has most of the top results
+        distinct.len() >= 3
+            && distinct
+                .iter()
+                .all(|area| areas.iter().filter(|other| *other == area).count() * 2 < areas.len())
+    }
+}
+
+impl Retriever {
+    /// Set up retrieval with the given options, failing if an index they need is missing.
+    pub fn new(options: Options, indexes: Vec<Index>) -> Result<Self, Box<dyn Error>> {
+        let reranker = options
+            .rerank
+            .then(|| options.chat_model())
+            .transpose()?
+            .map(|llm| Reranker::new(llm, options.concurrency));
+        let expander = (options.hyde || options.multi_query.is_some())
+            .then(|| options.chat_model())
+            .transpose()?;
+        let glossary = options
+            .glossary
+            .as_deref()
+            .map(Glossary::load)
+            .transpose()?;
+        let candidates = candidates(&options);
+        let filter = Filter {
+            kind: options.kind.clone(),
+            krate: options.krate.clone(),
+            path_prefix: options.path_prefix.clone(),
+            role: options.role.clone(),
+            features: options.features.clone(),
+        };
+        let lexical =
+            LexicalIndex::open(&LexicalIndex::path(&options.out_dir, &options.index_name()))?;
+        let missing = |what| error::Error::Missing {
+            what,
+            index: options.index_name(),
+        };
+        if lexical.is_none() && options.retrieval != Retrieval::Dense {
+            return Err(missing("keyword index").into());
+        }
+        let graph = if options.expand_graph {
+            let graph = Graph::load(&Graph::path(&options.out_dir, &options.index_name()));
+            if graph.is_none() || lexical.is_none() {
+                return Err(missing("item graph").into());
+            }
+            graph
+        } else {
+            None
+        };
+        let communities = if options.graph_rag {
+            let communities =
+                Communities::load(&Communities::path(&options.out_dir, &options.index_name()));
+            if communities.is_none() {
+                return Err(missing("community summaries").into());
+            }
+            communities
+        } else {
+            None
+        };
+        let catalog =
+            ItemCatalog::open(&ItemCatalog::path(&options.out_dir, &options.index_name()));
+        Ok(Retriever {
+            options,
+            indexes,
+            lexical,
+            graph,
+            communities,
+            catalog,
+            reranker,
+            expander,
+            glossary,
+            filter,
+            candidates,
+            others: vec![],
+        })
+    }
+
+    /// Also query the projects of these retrievers, merging their results with
+    /// the ones of this project.
+    pub fn with_projects(mut self, others: Vec<Retriever>) -> Self {
+        self.others = others;
+        self
+    }
+
+    /// Search the indexes of the project opened again after indexing it, with the
+    /// keyword index, item graph and catalog written along them.
+    pub fn reload(&mut self, indexes: Vec<Index>) -> Result<(), Box<dyn Error>> {
+        let mut reloaded = Retriever::new(self.options.clone(), indexes)?;
+        reloaded.others = std::mem::take(&mut self.others);
+        *self = reloaded;
+        Ok(())
+    }
+
+    /// Return this many results from now on, in every project queried.
+    pub fn set_nb_results(&mut self, nb_results: usize) {
+        self.options.nb_results = nb_results;
+        self.candidates = candidates(&self.options);
+        for other in &mut self.others {
+            other.set_nb_results(nb_results);
+        }
+    }
+
+    pub fn project(&self) -> &str {
+        &self.options.project
+    }
+
+    /// Restrictions on the results given with the options
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    /// Best documents for a prompt, with every enabled retrieval method fused and
+    /// reordered.
+    pub async fn search(&self, line: &str) -> error::Result<Results> {
+        self.search_with(line, &self.filter).await
+    }
+
+    /// Like [`Retriever::search`], with other restrictions than the ones of the options.
+    pub async fn search_with(&self, line: &str, filter: &Filter) -> error::Result<Results> {
+        self.search_from(None, line, filter).await
+    }
+
+    /// Like [`Retriever::search_with`], recording the client asking with --query-log.
+    pub async fn search_from(
+        &self,
+        client: Option<&str>,
+        line: &str,
+        filter: &Filter,
+    ) -> error::Result<Results> {
+        let mut results = if self.others.is_empty() {
+            self.search_project(line, filter).await?
+        } else {
+            let mut projects = vec![(self.project(), self.search_project(line, filter).await?)];
+            for other in &self.others {
+                projects.push((other.project(), other.search_project(line, filter).await?));
+            }
+            self.merge(projects)
+        };
+        if self.options.explain {
+            let start = Instant::now();
+            self.explain(line, &mut results).await?;
+            results.time("explain", start);
+        }
+        if let Some(path) = &self.options.query_log {
+            // The query was answered even when it can't be logged
+            if let Err(err) = query_log::record(path, line, filter, &results, client) {
+                tracing::warn!(%err, "couldn't log the query");
+            }
+        }
+        Ok(results)
+    }
+
+    /// Documents to answer the query from with the chat model, fitting in the context
+    /// budget. Snippets are written from examples found apart from the results,
+    /// which would crowd them out, with half of the budget, and from the items of the
+    /// API in the results with their docs.rs URL to link to.
+    pub async fn answer_context(
+        &self,
+        line: &str,
+        results: &Results,
+    ) -> error::Result<Vec<(String, String)>> {
+        let budget = self.options.context_tokens;
+        if self.options.answer_mode == AnswerMode::Docs {
+            return Ok(answer::pack_context(results.documents(), budget));
+        }
+        let filter = Filter {
+            kind: Some("example".to_string()),
+            ..self.filter.clone()
+        };
+        let examples = self.search_project(line, &filter).await?;
+        let mut documents = answer::pack_context(examples.documents(), budget / 2);
+        let used = documents
+            .iter()
+            .map(|(_, text)| answer::estimate_tokens(text))
+            .sum::<usize>();
+        let items = results
+            .documents()
+            .into_iter()
+            .filter(|(id, _)| !id.starts_with("example:") && !id.starts_with("source:"))
+            .map(|(id, text)| {
+                let url = results
+                    .metadatas
+                    .get(&id)
+                    .and_then(|metadata| docs_rs::item_url(&id, metadata));
+                match url {
+                    Some(url) => {
+                        let text = format!("{}\n\nDocumentation: {}", text.trim_end(), url);
+                        (id, text)
+                    }
+                    None => (id, text),
+                }
+            })
+            .collect();
+        documents.extend(answer::pack_context(items, budget.saturating_sub(used)));
+        Ok(documents)
+    }
+
+    /// Score the passages of the results against the query, by the similarity of
+    /// their embeddings with the first model, or by the words of the query they
+    /// contain without a dense index.
+    async fn explain(&self, line: &str, results: &mut Results) -> Result<(), Box<dyn Error>> {
+        let query = match self.indexes.first() {
+            Some(index) => Some(index.embedder.embeddings(line, InputType::Query).await?),
+            None => None,
+        };
+        let words = lexical::split_identifiers(line)
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<HashSet<_>>();
+        for (id, _) in &results.ranking {
+            let Some(text) = results.texts.get(id) else {
+                continue;
+            };
+            let mut passages = vec![];
+            for passage in passages_of(text) {
+                let score = match (&query, self.indexes.first()) {
+                    (Some(query), Some(index)) => {
+                        let embedding = index
+                            .embedder
+                            .embeddings(&passage, InputType::Document)
+                            .await?;
+                        mmr::cosine_similarity(query, &embedding)
+                    }
+                    _ => {
+                        let terms = lexical::split_identifiers(&passage).to_lowercase();
+                        let terms = terms.split_whitespace().collect::<HashSet<_>>();
+                        let found = words.iter().filter(|word| terms.contains(word.as_str()));
+                        found.count() as f32 / words.len().max(1) as f32
+                    }
+                };
+                passages.push((passage, score));
+            }
+            passages.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+            results.passages.insert(id.clone(), passages);
+        }
+        Ok(())
+    }
+
+    /// Fuse the results of several projects, labelling each with its project.
+    /// Exact matches of any project stay first.
+    fn merge(&self, projects: Vec<(&str, Results)>) -> Results {
+        let rankings = projects
+            .iter()
+            .map(|(_, results)| results.ranking.clone())
+            .collect::<Vec<_>>();
+        let mut merged = Results {
+            ranking: fusion::reciprocal_rank_fusion(&rankings, self.options.nb_results),
+            ..Default::default()
+        };
+        for (project, results) in projects {
+            for id in results
+                .ranking
+                .iter()
+                .map(|(id, _)| id)
+                .chain(&results.related)
+            {
+                merged
+                    .projects
+                    .entry(id.clone())
+                    .or_insert_with(|| project.to_string());
+            }
+            for id in results.pinned {
+                if !merged.pinned.contains(&id) {
+                    merged.pinned.push(id);
+                }
+            }
+            for id in results.related {
+                if !merged.related.contains(&id) {
+                    merged.related.push(id);
+                }
+            }
+            for id in results.communities {
+                if !merged.communities.contains(&id) {
+                    merged.communities.push(id);
+                }
+            }
+            merged.suggestions.extend(results.suggestions);
+            for (id, text) in results.texts {
+                merged.texts.entry(id).or_insert(text);
+            }
+            for (id, metadata) in results.metadatas {
+                merged.metadatas.entry(id).or_insert(metadata);
+            }
+            for (id, embedding) in results.embeddings {
+                merged.embeddings.entry(id).or_insert(embedding);
+            }
+            for (stage, seconds) in results.timings {
+                *merged.timings.entry(stage).or_default() += seconds;
+            }
+        }
+        merged.ranking.retain(|(id, _)| !merged.pinned.contains(id));
+        merged
+            .ranking
+            .splice(0..0, merged.pinned.iter().map(|id| (id.clone(), 0.0)));
+        merged.ranking.truncate(self.options.nb_results);
+        merged
+            .related
+            .retain(|id| !merged.ranking.iter().any(|(ranked, _)| ranked == id));
+        merged.suggestions.sort();
+        merged.suggestions.dedup();
+        merged
+    }
+
+    async fn search_project(&self, line: &str, filter: &Filter) -> Result<Results, Box<dyn Error>> {
+        let options = &self.options;
+        let candidates = self.candidates;
+        let mut found = Results::default();
+        let mut rankings = vec![];
+        let start = Instant::now();
+        if let Some(lexical) = self
+            .lexical
+            .as_ref()
+            .filter(|_| lexical::is_identifier(line))
+        {
+            for hit in lexical.lookup(line.trim(), options.nb_results, filter)? {
+                found.texts.insert(hit.id.clone(), hit.text);
+                found.metadatas.insert(hit.id.clone(), hit.metadata);
+                found.pinned.push(hit.id);
+            }
+            if found.pinned.is_empty() {
+                found.suggestions = lexical.similar_names(line.trim(), SUGGESTIONS)?;
+            }
+        }
+        found.time("lexical", start);
+        let expanded = self.glossary.as_ref().map(|glossary| glossary.expand(line));
+        let query = expanded.as_deref().unwrap_or(line);
+        let mut rewrites = vec![];
+        if let (Some(expander), Some(count)) = (&self.expander, options.multi_query) {
+            let start = Instant::now();
+            rewrites = expansion::paraphrases(expander, &options.project, line, count).await?;
+            found.time("expansion", start);
+        }
+        let lexical_queries = self
+            .lexical
+            .as_ref()
+            .filter(|_| options.retrieval != Retrieval::Dense)
+            .into_iter()
+            .flat_map(|lexical| {
+                std::iter::once(query)
+                    .chain(rewrites.iter().map(String::as_str))
+                    .map(move |query| (lexical, query))
+            });
+        for (lexical, query) in lexical_queries {
+            let start = Instant::now();
+            let mut ranking = vec![];
+            for hit in lexical.search(query, candidates, filter)? {
+                if options
+                    .min_score
+                    .is_some_and(|min_score| hit.score < min_score)
+                {
+                    continue;
+                }
+                found.texts.insert(hit.id.clone(), hit.text);
+                found.metadatas.insert(hit.id.clone(), hit.metadata);
+                ranking.push((hit.id, hit.score));
+            }
+            rankings.push(ranking);
+            found.time("lexical", start);
+        }
+        let mut queries = vec![(query.to_string(), InputType::Query)];
+        if let Some(expander) = self
+            .expander
+            .as_ref()
+            .filter(|_| options.hyde && options.retrieval != Retrieval::Lexical)
+        {
+            let start = Instant::now();
+            let draft = expansion::hypothetical_document(expander, &options.project, line).await?;
+            found.time("expansion", start);
+            queries = vec![(draft, InputType::Document)];
+        }
+        queries.extend(
+            rewrites
+                .iter()
+                .map(|rewrite| (rewrite.clone(), InputType::Query)),
+        );
+        let dense_queries = self
+            .indexes
+            .iter()
+            .filter(|_| options.retrieval != Retrieval::Lexical)
+            .flat_map(|index| queries.iter().map(move |query| (index, query)));
+        for (index, (query, input_type)) in dense_queries {
+            let first_index = std::ptr::eq(index, &self.indexes[0]);
+            let start = Instant::now();
+            let embedding = index.embedder.embeddings(query, *input_type).await?;
+            index.check_dimensions(&embedding)?;
+            found.time("embedding", start);
+            let start = Instant::now();
+            let hits = index.store.query(embedding, candidates * 2, filter).await?;
+            found.time("store", start);
+            let mut seen = HashSet::new();
+            rankings.push(
+                hits.into_iter()
+                    .filter(|hit| {
+                        options
+                            .max_distance
+                            .is_none_or(|max_distance| hit.distance <= max_distance)
+                            && options.min_similarity.is_none_or(|min_similarity| {
+                                store::similarity(hit.distance, options.distance.space())
+                                    >= min_similarity
+                            })
+                    })
+                    .map(|hit| {
+                        let id = chunk::parent_id(&hit.id).to_string();
+                        if let Some(document) = hit.document {
+                            found.texts.entry(id.clone()).or_insert(document);
+                        }
+                        if let Some(metadata) = hit.metadata {
+                            found.metadatas.entry(id.clone()).or_insert(metadata);
+                        }
+                        // Only keep vectors of one model so they can be compared
+                        if let Some(embedding) = hit.embedding.filter(|_| first_index) {
+                            found.embeddings.entry(id.clone()).or_insert(embedding);
+                        }
+                        (id, hit.distance)
+                    })
+                    // A document is as close as its closest chunk or facet
+                    .filter(|(id, _)| seen.insert(id.clone()))
+                    .take(candidates)
+                    .collect::<Vec<_>>(),
+            );
+        }
+        // A lone dense ranking is still made of distances, lower being better
+        let mut distances = rankings.len() == 1 && options.retrieval == Retrieval::Dense;
+        let mut results = if rankings.len() == 1 {
+            rankings.remove(0)
+        } else {
+            fusion::reciprocal_rank_fusion(&rankings, candidates)
+        };
+        results = dedup::collapse_results(results, &found.metadatas);
+        if let Some(reranker) = &self.reranker {
+            let candidates = results
+                .iter()
+                .filter_map(|(doc, _)| Some((doc.clone(), found.texts.get(doc)?.clone())))
+                .collect::<Vec<_>>();
+            let start = Instant::now();
+            results = reranker.rerank(line, candidates).await?;
+            found.time("rerank", start);
+            distances = false;
+        }
+        if let Some(boosts) = &options.boost {
+            fusion::apply_boosts(&mut results, boosts, distances);
+        }
+        if options.popularity > 0.0 {
+            let references = |id: &str| {
+                found
+                    .metadatas
+                    .get(id)
+                    .and_then(|metadata| metadata.get("references")?.as_u64())
+                    .unwrap_or_default() as usize
+            };
+            fusion::apply_popularity(&mut results, references, options.popularity, distances);
+        }
+        if let Some(min_tokens) = options.min_tokens {
+            let tokens = |id: &str| {
+                found
+                    .texts
+                    .get(id)
+                    .map_or(min_tokens, |text| answer::estimate_tokens(text))
+            };
+            fusion::apply_length_penalty(
+                &mut results,
+                tokens,
+                min_tokens,
+                &found.pinned,
+                distances,
+            );
+        }
+        if options.mmr {
+            results = mmr::maximal_marginal_relevance(
+                results,
+                &found.embeddings,
+                options.mmr_lambda,
+                options.nb_results,
+            );
+        }
+        results.retain(|(id, _)| !found.pinned.contains(id));
+        results.splice(0..0, found.pinned.iter().map(|id| (id.clone(), 0.0)));
+        results.truncate(options.nb_results);
+        if let (Some(graph), Some(lexical)) = (&self.graph, &self.lexical) {
+            for (id, _) in results.iter().take(GRAPH_SEEDS) {
+                let path = id.split_once(':').map_or(id.as_str(), |(_, path)| path);
+                for neighbour in graph.neighbours(path) {
+                    for hit in lexical.lookup(neighbour, 1, filter)? {
+                        if !found.related.contains(&hit.id)
+                            && !results.iter().any(|(id, _)| *id == hit.id)
+                        {
+                            found.texts.insert(hit.id.clone(), hit.text);
+                            found.metadatas.insert(hit.id.clone(), hit.metadata);
+                            found.related.push(hit.id);
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(communities) = &self.communities {
+            for (id, _) in results.iter().take(GRAPH_SEEDS) {
+                let path = id.split_once(':').map_or(id.as_str(), |(_, path)| path);
+                if let Some(community) = communities.of(path) {
+                    let id = community.id();
+                    if !found.communities.contains(&id) {
+                        found.texts.insert(id.clone(), community.text());
+                        found.communities.push(id);
+                    }
+                }
+            }
+        }
+        found.ranking = results;
+        found.space = distances.then(|| options.distance.space());
+        self.complete(&mut found)?;
+        Ok(found)
+    }
+
+    /// Fill the metadata and text of the results the store didn't return from the
+    /// item catalog, the text being the summary of the item.
+    fn complete(&self, results: &mut Results) -> Result<(), Box<dyn Error>> {
+        let Some(catalog) = &self.catalog else {
+            return Ok(());
+        };
+        let incomplete = results
+            .ranking
+            .iter()
+            .map(|(id, _)| id)
+            .chain(&results.related)
+            .filter(|id| {
+                results.metadatas.get(*id).is_none_or(Map::is_empty)
+                    || !results.texts.contains_key(*id)
+            })
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if incomplete.is_empty() {
+            return Ok(());
+        }
+        for (id, entry) in catalog.get(&incomplete)? {
+            if let Some(summary) = entry.summary {
+                results.texts.entry(id.clone()).or_insert(summary);
+            }
+            let metadata = results.metadatas.entry(id).or_default();
+            if metadata.is_empty() {
+                *metadata = entry.metadata;
+            }
+        }
+        Ok(())
+    }
+
+    /// Document of the item with this path, or this name if there is only one,
+    /// in the first project that has it.
+    pub fn item(&self, path: &str) -> Result<Option<LexicalHit>, Box<dyn Error>> {
+        if let Some(lexical) = &self.lexical {
+            let mut hits = lexical.lookup(path.trim(), 2, &Filter::default())?;
+            if hits.len() == 1 {
+                return Ok(Some(hits.remove(0)));
+            }
+        }
+        for other in &self.others {
+            if let Some(hit) = other.item(path)? {
+                return Ok(Some(hit));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Names and paths of the items of every project, to complete identifiers.
+    pub fn identifiers(&self) -> Result<BTreeSet<String>, Box<dyn Error>> {
+        let mut identifiers = match &self.lexical {
+            Some(lexical) => lexical.identifiers()?,
+            None => BTreeSet::new(),
+        };
+        for other in &self.others {
+            identifiers.extend(other.identifiers()?);
+        }
+        Ok(identifiers)
+    }
+
+    /// Nearest neighbours of a previous result, by the vectors of the first model.
+    pub async fn similar(&self, id: &str, previous: &Results) -> error::Result<Results> {
+        let options = &self.options;
+        let index = &self.indexes[0];
+        let embedding = match previous.embeddings.get(id) {
+            Some(embedding) => embedding.clone(),
+            None => {
+                let text = previous.texts.get(id).cloned().unwrap_or_default();
+                index
+                    .embedder
+                    .embeddings(&text, InputType::Document)
+                    .await?
+            }
+        };
+        let hits = index
+            .store
+            .query(embedding, (options.nb_results + 1) * 2, &self.filter)
+            .await?;
+        let mut similar = Results {
+            space: Some(options.distance.space()),
+            ..Default::default()
+        };
+        for hit in hits {
+            let parent = chunk::parent_id(&hit.id).to_string();
+            if parent == id || similar.ranking.iter().any(|(id, _)| *id == parent) {
+                continue;
+            }
+            if let Some(document) = hit.document {
+                similar.texts.insert(parent.clone(), document);
+            }
+            if let Some(embedding) = hit.embedding {
+                similar.embeddings.insert(parent.clone(), embedding);
+            }
+            if let Some(metadata) = hit.metadata {
+                similar.metadatas.insert(parent.clone(), metadata);
+            }
+            similar.ranking.push((parent, hit.distance));
+        }
+        similar.ranking.truncate(options.nb_results);
+        self.complete(&mut similar)?;
+        Ok(similar)
+    }
+}
+
+/// Passages of a document scored on their own with --explain: its paragraphs, with
+/// the items of lists apart, leaving out the ones too short to say anything.
+fn passages_of(text: &str) -> Vec<String> {
+    let mut passages = vec![];
+    for paragraph in text.split("\n\n").map(str::trim) {
+        if paragraph.lines().any(|line| line.starts_with("- ")) {
+            passages.extend(paragraph.lines().map(|line| line.trim_start_matches("- ")));
+        } else {
+            passages.push(paragraph);
+        }
+    }
+    passages
+        .into_iter()
+        .filter(|passage| passage.split_whitespace().count() >= 3)
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,123 @@
+use std::{convert::Infallible, error::Error, future::Future, net::SocketAddr};
+
+use bytes::Bytes;
+use http_body_util::{combinators::UnsyncBoxBody, BodyExt, Full, StreamBody};
+use hyper::{
+    body::{Frame, Incoming},
+    server::conn::http1,
+    service::service_fn,
+    Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+pub type Body = UnsyncBoxBody<Bytes, Infallible>;
+
+/// Address to listen on, where `:8080` means every interface.
+fn parse_addr(addr: &str) -> Result<SocketAddr, Box<dyn Error>> {
+    match addr.strip_prefix(':') {
+        Some(port) => Ok(SocketAddr::from(([0, 0, 0, 0], port.parse::<u16>()?))),
+        None => Ok(addr.parse()?),
+    }
+}
+
+/// Serve HTTP/1 requests on the address with the handler until interrupted. The
+/// handler runs on the current thread, so it can borrow the retriever.
+pub async fn listen<F, Fut>(addr: &str, handler: F) -> Result<(), Box<dyn Error>>
+where
+    F: Fn(Request<Incoming>) -> Fut + Clone + 'static,
+    Fut: Future<Output = Response<Body>> + 'static,
+{
+    let addr = parse_addr(addr)?;
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    eprintln!("Listening on http://{}", addr);
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async move {
+            loop {
+                let (stream, _) = listener.accept().await?;
+                let handler = handler.clone();
+                tokio::task::spawn_local(async move {
+                    let service = service_fn(move |request| {
+                        let response = handler(request);
+                        async move { Ok::<_, Infallible>(response.await) }
+                    });
+                    if let Err(err) = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                    {
+                        tracing::debug!(%err, "connection closed");
+                    }
+                });
+            }
+        })
+        .await
+}
+
+pub fn full(body: impl Into<Bytes>) -> Body {
+    Full::new(body.into()).boxed_unsync()
+}
+
+pub fn json(status: StatusCode, value: &Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(full(value.to_string()))
+        .unwrap()
+}
+
+/// Server-Sent Events response sending the events of the channel until it's closed.
+pub fn event_stream(receiver: mpsc::UnboundedReceiver<String>) -> Response<Body> {
+    let events = futures::stream::unfold(receiver, |mut receiver| async {
+        let event = receiver.recv().await?;
+        Some((Ok(Frame::data(Bytes::from(event))), receiver))
+    });
+    Response::builder()
+        .header("content-type", "text/event-stream")
+        .header("cache-control", "no-cache")
+        .body(StreamBody::new(events).boxed_unsync())
+        .unwrap()
+}
+
+pub fn event(name: &str, data: &str) -> String {
+    format!("event: {}\ndata: {}\n\n", name, data)
+}
+
+pub async fn read_bytes(request: Request<Incoming>) -> Option<Bytes> {
+    Some(request.into_body().collect().await.ok()?.to_bytes())
+}
+
+pub async fn read_json(request: Request<Incoming>) -> Option<Value> {
+    serde_json::from_slice(&read_bytes(request).await?).ok()
+}
+
+/// Value of a parameter of the query string.
+pub fn query_param(request: &Request<Incoming>, name: &str) -> Option<String> {
+    request.uri().query()?.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (key == name).then(|| percent_decode(value))
+    })
+}
+
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (escaped, bytes[i]) {
+            (Some(byte), _) => {
+                decoded.push(byte);
+                i += 2;
+            }
+            (None, b'+') => decoded.push(b' '),
+            (None, byte) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -0,0 +1,350 @@
+use rustdoc_types::{
+    GenericArg, GenericArgs, GenericBound, Generics, Item, ItemEnum, StructKind, Type,
+};
+
+use crate::traversal::CrateCatalog;
+
+/// Declaration of a struct with the types of its fields, like
+/// `pub struct Circle { pub radius: f32 }`.
+pub fn struct_signature(
+    item: &Item,
+    stru: &rustdoc_types::Struct,
+    current_crate: usize,
+    crates: &CrateCatalog,
+) -> String {
+    let krate = &crates[current_crate].as_ref().unwrap().1;
+    let field = |id| {
+        let field = krate.index.get(id)?;
+        match &field.inner {
+            ItemEnum::StructField(ty) => Some((field, type_name(ty))),
+            _ => None,
+        }
+    };
+    let mut signature = format!(
+        "{}struct {}{}",
+        visibility(item),
+        item.name.as_deref().unwrap_or_default(),
+        generics(&stru.generics)
+    );
+    match &stru.kind {
+        StructKind::Unit => signature.push(';'),
+        StructKind::Tuple(fields) => {
+            let fields = fields
+                .iter()
+                .map(|id| match id.as_ref().and_then(field) {
+                    Some((field, ty)) => format!("{}{}", visibility(field), ty),
+                    // Private fields aren't documented
+                    None => "_".to_string(),
+                })
+                .collect::<Vec<_>>();
+            signature.push_str(&format!("({});", fields.join(", ")));
+        }
+        StructKind::Plain {
+            fields,
+            has_stripped_fields,
+        } => {
+            let mut fields = fields
+                .iter()
+                .filter_map(field)
+                .map(|(field, ty)| {
+                    format!(
+                        "{}{}: {}",
+                        visibility(field),
+                        field.name.as_deref().unwrap_or_default(),
+                        ty
+                    )
+                })
+                .collect::<Vec<_>>();
+            if *has_stripped_fields {
+                fields.push("..".to_string());
+            }
+            signature.push_str(&format!(" {{ {} }}", fields.join(", ")));
+        }
+    }
+    signature
+}
+
+fn visibility(item: &Item) -> &'static str {
+    match item.visibility {
+        rustdoc_types::Visibility::Public => "pub ",
+        _ => "",
+    }
+}
+
+/// Names of the generic parameters, like `<'a, T>`.
+fn generics(generics: &Generics) -> String {
+    if generics.params.is_empty() {
+        return String::new();
+    }
+    let params = generics
+        .params
+        .iter()
+        .map(|param| param.name.as_str())
+        .collect::<Vec<_>>();
+    format!("<{}>", params.join(", "))
+}
+
+/// Rust syntax of a type, with paths as they are written in the source.
+pub fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::ResolvedPath(path) => path_name(path),
+        Type::DynTrait(dyn_trait) => {
+            let traits = dyn_trait
+                .traits
+                .iter()
+                .map(|poly| {
+                    format!(
+                        "{}{}",
+                        poly.trait_.name,
+                        generic_args(poly.trait_.args.as_deref())
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!("dyn {}", traits.join(" + "))
+        }
+        Type::Generic(name) | Type::Primitive(name) => name.clone(),
+        Type::FunctionPointer(pointer) => {
+            let inputs = pointer
+                .sig
+                .inputs
+                .iter()
+                .map(|(_, ty)| type_name(ty))
+                .collect::<Vec<_>>();
+            match &pointer.sig.output {
+                Some(output) => format!("fn({}) -> {}", inputs.join(", "), type_name(output)),
+                None => format!("fn({})", inputs.join(", ")),
+            }
+        }
+        Type::Tuple(types) => {
+            let types = types.iter().map(type_name).collect::<Vec<_>>();
+            format!("({})", types.join(", "))
+        }
+        Type::Slice(ty) => format!("[{}]", type_name(ty)),
+        Type::Array { type_, len } => format!("[{}; {}]", type_name(type_), len),
+        Type::Pat { type_, .. } => type_name(type_),
+        Type::ImplTrait(bounds) => {
+            let bounds = bounds
+                .iter()
+                .filter_map(|bound| match bound {
+                    GenericBound::TraitBound { trait_, .. } => Some(format!(
+                        "{}{}",
+                        trait_.name,
+                        generic_args(trait_.args.as_deref())
+                    )),
+                    GenericBound::Outlives(lifetime) => Some(lifetime.clone()),
+                    GenericBound::Use(_) => None,
+                })
+                .collect::<Vec<_>>();
+            format!("impl {}", bounds.join(" + "))
+        }
+        Type::Infer => "_".to_string(),
+        Type::RawPointer { is_mutable, type_ } => format!(
+            "*{} {}",
+            if *is_mutable { "mut" } else { "const" },
+            type_name(type_)
+        ),
+        Type::BorrowedRef {
+            lifetime,
+            is_mutable,
+            type_,
+        } => {
+            let mut name = "&".to_string();
+            if let Some(lifetime) = lifetime {
+                name.push_str(lifetime);
+                name.push(' ');
+            }
+            if *is_mutable {
+                name.push_str("mut ");
+            }
+            name.push_str(&type_name(type_));
+            name
+        }
+        Type::QualifiedPath {
+            name,
+            self_type,
+            trait_,
+            ..
+        } => match trait_ {
+            Some(trait_) => format!("<{} as {}>::{}", type_name(self_type), trait_.name, name),
+            None => format!("{}::{}", type_name(self_type), name),
+        },
+    }
+}
+
+/// Shorter form of a signature for documents: generic arguments nested more than
+/// `max_depth` deep are elided as `<..>`, and only the first `max_bounds` bounds of
+/// a list are kept, like `impl Fn() + Send + ..`. 0 keeps everything.
+pub fn simplify(signature: &str, max_depth: usize, max_bounds: usize) -> String {
+    let chars = signature.chars().collect::<Vec<_>>();
+    let mut simplified = String::with_capacity(signature.len());
+    // Number of bounds seen in the list at each bracket depth
+    let mut bounds = vec![0];
+    let mut angles = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let arrow = c == '>' && i > 0 && matches!(chars[i - 1], '-' | '=');
+        match c {
+            '<' if max_depth > 0 && angles >= max_depth => {
+                simplified.push_str("<..>");
+                i = skip_nested(&chars, i + 1) + 1;
+                continue;
+            }
+            '<' | '(' | '[' | '{' => {
+                angles += usize::from(c == '<');
+                bounds.push(0);
+            }
+            '>' if arrow => {}
+            '>' | ')' | ']' | '}' => {
+                angles -= usize::from(c == '>' && angles > 0);
+                if bounds.len() > 1 {
+                    bounds.pop();
+                }
+            }
+            ',' | ';' => *bounds.last_mut().unwrap() = 0,
+            '+' => {
+                let count = bounds.last_mut().unwrap();
+                *count += 1;
+                if max_bounds > 0 && *count >= max_bounds {
+                    simplified.push_str("+ ..");
+                    i = skip_list(&chars, i + 1);
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        simplified.push(c);
+        i += 1;
+    }
+    simplified
+}
+
+/// Position of the `>` closing the generic arguments starting at `start`.
+fn skip_nested(chars: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    for (i, c) in chars.iter().enumerate().skip(start) {
+        match c {
+            '<' => depth += 1,
+            '>' if i > 0 && matches!(chars[i - 1], '-' | '=') => {}
+            '>' if depth == 0 => return i,
+            '>' => depth -= 1,
+            _ => {}
+        }
+    }
+    chars.len()
+}
+
+/// Position of the end of the list of bounds the text at `start` is in.
+fn skip_list(chars: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    for (i, c) in chars.iter().enumerate().skip(start) {
+        match c {
+            '<' | '(' | '[' | '{' => depth += 1,
+            '>' if i > 0 && matches!(chars[i - 1], '-' | '=') => {}
+            '>' | ')' | ']' | '}' if depth == 0 => return i,
+            '>' | ')' | ']' | '}' => depth -= 1,
+            ',' | ';' | '=' if depth == 0 => return i,
+            _ => {}
+        }
+    }
+    chars.len()
+}
+
+/// Name of a trait or type with its generic arguments, like `From<f32>`.
+pub fn path_name(path: &rustdoc_types::Path) -> String {
+    format!("{}{}", path.name, generic_args(path.args.as_deref()))
+}
+
+fn generic_args(args: Option<&GenericArgs>) -> String {
+    match args {
+        Some(GenericArgs::AngleBracketed { args, .. }) if !args.is_empty() => {
+            let args = args
+                .iter()
+                .map(|arg| match arg {
+                    GenericArg::Lifetime(lifetime) => lifetime.clone(),
+                    GenericArg::Type(ty) => type_name(ty),
+                    GenericArg::Const(constant) => constant.expr.clone(),
+                    GenericArg::Infer => "_".to_string(),
+                })
+                .collect::<Vec<_>>();
+            format!("<{}>", args.join(", "))
+        }
+        Some(GenericArgs::Parenthesized { inputs, output }) => {
+            let inputs = inputs.iter().map(type_name).collect::<Vec<_>>();
+            match output {
+                Some(output) => format!("({}) -> {}", inputs.join(", "), type_name(output)),
+                None => format!("({})", inputs.join(", ")),
+            }
+        }
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_generics_past_the_depth_are_elided() {
+        assert_eq!(
+            simplify("fn f(x: Vec<Option<HashMap<String, Vec<u8>>>>)", 2, 0),
+            "fn f(x: Vec<Option<HashMap<..>>>)"
+        );
+        assert_eq!(
+            simplify("fn f(x: Vec<Box<dyn Fn(u8) -> u8>>)", 1, 0),
+            "fn f(x: Vec<Box<..>>)"
+        );
+    }
+
+    #[test]
+    fn where_clauses_keep_the_first_bounds_of_each_predicate() {
+        assert_eq!(
+            simplify(
+                "fn f<T, U>(x: T, y: U) where T: Clone + Debug + Send + Sync, U: Default",
+                3,
+                2
+            ),
+            "fn f<T, U>(x: T, y: U) where T: Clone + Debug + .., U: Default"
+        );
+    }
+
+    #[test]
+    fn impl_trait_with_a_lifetime() {
+        let signature = "fn iter<'a>(&'a self) -> impl Iterator<Item = &'a str> + 'a";
+        assert_eq!(simplify(signature, 3, 3), signature);
+        assert_eq!(
+            simplify(signature, 3, 1),
+            "fn iter<'a>(&'a self) -> impl Iterator<Item = &'a str> + .."
+        );
+    }
+
+    #[test]
+    fn arrows_of_closures_arent_closing_brackets() {
+        assert_eq!(
+            simplify(
+                "fn map<F>(f: F) where F: Fn(Vec<Vec<u8>>) -> Option<u8>",
+                1,
+                0
+            ),
+            "fn map<F>(f: F) where F: Fn(Vec<Vec<..>>) -> Option<u8>"
+        );
+        assert_eq!(
+            simplify(
+                "fn on(f: impl FnMut(&mut World) -> bool + Send + Sync + 'static)",
+                3,
+                3
+            ),
+            "fn on(f: impl FnMut(&mut World) -> bool + Send + Sync + ..)"
+        );
+    }
+
+    #[test]
+    fn short_signatures_are_kept() {
+        assert_eq!(
+            simplify("fn len(&self) -> usize", 3, 3),
+            "fn len(&self) -> usize"
+        );
+        let long = "fn f(x: Vec<Vec<Vec<Vec<u8>>>>) where T: A + B + C + D";
+        assert_eq!(simplify(long, 0, 0), long);
+    }
+}
@@ -0,0 +1,110 @@
+use std::{collections::BTreeMap, error::Error, path::Path};
+
+use rustdoc_rag::{
+    chunk, docs_rs,
+    document::Document,
+    embedding::Provider,
+    engine::{self, SiteDocument, SiteIndex},
+    Index, Options,
+};
+use serde_json::Value;
+
+const INDEX_HTML: &str = include_str!("site/index.html");
+const SEARCH_JS: &str = include_str!("site/search.js");
+
+/// Write a static site searching the documents in the browser: the documents,
+/// the vectors of the index quantized to one byte per dimension, and the page.
+/// Queries are embedded with Ollama when the page can reach it, and matched by
+/// keywords otherwise. The files can also be queried with [`engine::Engine`].
+pub async fn export(
+    options: &Options,
+    documents: &BTreeMap<String, Document>,
+    index: &Index,
+    dir: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let positions = documents
+        .keys()
+        .enumerate()
+        .map(|(i, id)| (id.as_str(), i))
+        .collect::<BTreeMap<_, _>>();
+    let documents = documents
+        .iter()
+        .map(|(id, document)| {
+            let metadata = &document.metadata;
+            let field = |name| {
+                metadata
+                    .get(name)
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            };
+            SiteDocument {
+                id: id.clone(),
+                name: field("name"),
+                kind: document.kind.clone(),
+                krate: field("crate"),
+                url: docs_rs::item_url(id, metadata),
+                text: document.text.clone(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let entries = index.store.entries().await?;
+    let dimensions = entries.first().map_or(0, |entry| entry.embedding.len());
+    let mut vectors = Vec::with_capacity(entries.len() * (dimensions + 4));
+    let mut owners = vec![];
+    for entry in &entries {
+        // Vectors of items that aren't documented anymore
+        let Some(position) = positions.get(chunk::parent_id(&entry.id)) else {
+            continue;
+        };
+        if entry.embedding.len() != dimensions {
+            continue;
+        }
+        let (scale, values) = engine::quantize(&entry.embedding);
+        engine::encode(&mut vectors, scale, &values);
+        owners.push(*position);
+    }
+
+    let metadata = index.store.metadata().unwrap_or_default();
+    let field = |name| {
+        metadata
+            .get(name)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    let manifest = SiteIndex {
+        project: options.project.clone(),
+        model: field("model"),
+        space: field("hnsw:space"),
+        query_prefix: field("query_prefix"),
+        normalize: metadata
+            .get("normalize")
+            .and_then(Value::as_bool)
+            .unwrap_or_default(),
+        dimensions,
+        embedding_url: (options.provider == Provider::Ollama)
+            .then(|| options.ollama().map(|ollama| ollama.url))
+            .transpose()?,
+        vectors: owners,
+    };
+
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(
+        dir.join("documents.json"),
+        serde_json::to_string(&documents)?,
+    )?;
+    std::fs::write(dir.join("vectors.bin"), vectors)?;
+    std::fs::write(dir.join("index.json"), serde_json::to_string(&manifest)?)?;
+    std::fs::write(
+        dir.join("index.html"),
+        INDEX_HTML.replace("{project}", &options.project),
+    )?;
+    std::fs::write(dir.join("search.js"), SEARCH_JS)?;
+    println!(
+        "Exported {} documents and {} vectors to {}",
+        documents.len(),
+        manifest.vectors.len(),
+        dir.display()
+    );
+    Ok(())
+}
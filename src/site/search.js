@@ -0,0 +1,116 @@
+// Search over the files written by `rustdoc-rag export-site`, entirely in the browser.
+// Queries are embedded with the Ollama endpoint of index.json when it's reachable,
+// and matched by keywords otherwise.
+
+const RESULTS = 20;
+
+async function load() {
+  const [index, documents, vectors] = await Promise.all([
+    fetch("index.json").then((response) => response.json()),
+    fetch("documents.json").then((response) => response.json()),
+    fetch("vectors.bin").then((response) => response.arrayBuffer()),
+  ]);
+  return { index, documents, vectors: new DataView(vectors) };
+}
+
+async function embed(index, query) {
+  if (!index.embedding_url) return null;
+  try {
+    const response = await fetch(index.embedding_url + "api/embed", {
+      method: "POST",
+      body: JSON.stringify({ model: index.model, input: (index.query_prefix || "") + query }),
+    });
+    let embedding = (await response.json()).embeddings[0];
+    const truncated = embedding.length > index.dimensions;
+    embedding = embedding.slice(0, index.dimensions);
+    if (truncated || index.normalize) {
+      const norm = Math.hypot(...embedding);
+      if (norm > 0) embedding = embedding.map((v) => v / norm);
+    }
+    return embedding;
+  } catch {
+    return null;
+  }
+}
+
+// Distance to every document, keeping the closest of its chunks
+function dense(site, embedding) {
+  const { index, vectors } = site;
+  const stride = index.dimensions + 4;
+  const queryNorm = Math.hypot(...embedding);
+  const best = new Map();
+  index.vectors.forEach((document, i) => {
+    const offset = i * stride;
+    const scale = vectors.getFloat32(offset, true);
+    let dot = 0, norm = 0, sq = 0;
+    for (let d = 0; d < index.dimensions; d++) {
+      const v = vectors.getInt8(offset + 4 + d) * scale;
+      dot += embedding[d] * v;
+      norm += v * v;
+      sq += (embedding[d] - v) * (embedding[d] - v);
+    }
+    let distance = sq;
+    if (index.space === "ip") distance = 1 - dot;
+    if (index.space === "cosine") distance = 1 - dot / (Math.sqrt(norm) * queryNorm || 1);
+    if (!best.has(document) || distance < best.get(document)) best.set(document, distance);
+  });
+  return [...best].sort((a, b) => a[1] - b[1]).slice(0, RESULTS).map(([document]) => document);
+}
+
+function keywords(site, query) {
+  const terms = query.toLowerCase().split(/\W+/).filter((term) => term.length > 1);
+  return site.documents
+    .map((document, i) => {
+      const text = document.text.toLowerCase();
+      const name = (document.name || "").toLowerCase();
+      let score = 0;
+      for (const term of terms) {
+        score += text.split(term).length - 1;
+        if (name === term) score += 10;
+        else if (name.includes(term)) score += 3;
+      }
+      return [i, score];
+    })
+    .filter(([, score]) => score > 0)
+    .sort((a, b) => b[1] - a[1])
+    .slice(0, RESULTS)
+    .map(([document]) => document);
+}
+
+function render(site, ranking) {
+  const results = document.getElementById("results");
+  results.replaceChildren(
+    ...ranking.map((i) => {
+      const doc = site.documents[i];
+      const result = document.createElement("div");
+      result.className = "result";
+      const title = document.createElement(doc.url ? "a" : "strong");
+      title.textContent = doc.id.slice(doc.id.indexOf(":") + 1);
+      if (doc.url) title.href = doc.url;
+      const kind = document.createElement("span");
+      kind.className = "kind";
+      kind.textContent = " " + (doc.kind || "");
+      const text = document.createElement("p");
+      text.textContent = doc.text.length > 300 ? doc.text.slice(0, 300) + "..." : doc.text;
+      result.append(title, kind, text);
+      return result;
+    }),
+  );
+}
+
+load().then((site) => {
+  const status = document.getElementById("status");
+  const input = document.getElementById("query");
+  status.textContent = site.documents.length + " documents";
+  let pending = 0;
+  input.addEventListener("input", async () => {
+    const query = input.value.trim();
+    const current = ++pending;
+    if (!query) return render(site, []);
+    const embedding = await embed(site.index, query);
+    if (current !== pending) return;
+    const ranking = embedding ? dense(site, embedding) : keywords(site, query);
+    status.textContent = embedding ? "Semantic search" : "Keyword search, the embedding model isn't reachable";
+    render(site, ranking);
+  });
+});
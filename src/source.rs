@@ -0,0 +1,138 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use rustdoc_types::{Item, ItemEnum, Span};
+
+use crate::{
+    document::Document,
+    traversal::{item_metadata, item_path, CrateCatalog},
+    Options,
+};
+
+/// Sources of the documented crates, read from the spans of their items. Spans of
+/// the workspace are relative to its root, which is looked for in the current
+/// directory and above the jsons directory, like `target/doc`.
+pub struct Sources {
+    roots: Vec<PathBuf>,
+    files: HashMap<PathBuf, Option<String>>,
+}
+
+impl Sources {
+    pub fn new(options: &Options) -> Self {
+        let mut roots = vec![PathBuf::new()];
+        let jsons_dir = options.jsons_dir();
+        if let Some(root) = jsons_dir.parent().and_then(Path::parent) {
+            roots.push(root.to_path_buf());
+        }
+        Sources {
+            roots,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Lines of the span, without their common indentation.
+    fn code(&mut self, span: &Span) -> Option<String> {
+        let roots = &self.roots;
+        let content = self
+            .files
+            .entry(span.filename.clone())
+            .or_insert_with(|| {
+                roots
+                    .iter()
+                    .find_map(|root| std::fs::read_to_string(root.join(&span.filename)).ok())
+            })
+            .as_ref()?;
+        let lines = content
+            .lines()
+            .skip(span.begin.0.saturating_sub(1))
+            .take(span.end.0 + 1 - span.begin.0.max(1))
+            .collect::<Vec<_>>();
+        let indent = lines
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start().len())
+            .min()
+            .unwrap_or(0);
+        let code = lines
+            .iter()
+            .map(|line| line.get(indent..).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join("\n");
+        (!code.trim().is_empty()).then_some(code)
+    }
+
+    /// Source of an item, if its span was kept and its file found.
+    pub fn item_code(&mut self, item: &Item) -> Option<String> {
+        self.code(item.span.as_ref()?)
+    }
+
+    /// Documents of the source of a function, or of the methods of the inherent
+    /// impls of a struct, kept apart from the documentation of the item.
+    pub fn documents(
+        &mut self,
+        item: &Item,
+        current_crate: usize,
+        crates: &CrateCatalog,
+    ) -> Vec<Document> {
+        let krate = &crates[current_crate].as_ref().unwrap().1;
+        let path = item_path(item, current_crate, crates);
+        match &item.inner {
+            ItemEnum::Function(_) => self
+                .document(
+                    item,
+                    "function",
+                    path,
+                    item_metadata(item, "source", current_crate, crates),
+                )
+                .into_iter()
+                .collect(),
+            ItemEnum::Struct(stru) => stru
+                .impls
+                .iter()
+                .filter_map(|id| match &krate.index.get(id)?.inner {
+                    ItemEnum::Impl(imp) if imp.trait_.is_none() && !imp.is_synthetic => {
+                        Some(&imp.items)
+                    }
+                    _ => None,
+                })
+                .flatten()
+                .filter_map(|id| krate.index.get(id))
+                .filter(|method| matches!(method.inner, ItemEnum::Function(_)))
+                .filter_map(|method| {
+                    let name = method.name.clone()?;
+                    // Methods aren't in the paths of the crate, take the module of the struct
+                    let mut metadata = item_metadata(item, "source", current_crate, crates);
+                    metadata.insert("name".to_string(), name.clone().into());
+                    self.document(method, "method", format!("{}::{}", path, name), metadata)
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn document(
+        &mut self,
+        item: &Item,
+        kind: &str,
+        path: String,
+        mut metadata: serde_json::Map<String, serde_json::Value>,
+    ) -> Option<Document> {
+        let span = item.span.as_ref()?;
+        let code = self.item_code(item)?;
+        let file = span.filename.to_string_lossy().replace('\\', "/");
+        metadata.insert("item_kind".to_string(), kind.into());
+        metadata.insert("file".to_string(), file.clone().into());
+        metadata.insert("line".to_string(), span.begin.0.into());
+        Some(Document {
+            text: format!(
+                "Source of the {} {}, from {} line {}:\n\n```rust\n{}\n```\n",
+                kind, path, file, span.begin.0, code
+            ),
+            path,
+            kind: "source".to_string(),
+            metadata,
+        })
+    }
+}
@@ -0,0 +1,81 @@
+use std::{collections::BTreeMap, error::Error};
+
+use serde_json::Value;
+
+use rustdoc_rag::store::VectorStore;
+
+/// Print what a collection holds, from the metadata stored with it and its entries.
+pub async fn print(
+    name: &str,
+    store: &VectorStore,
+    size: Option<u64>,
+) -> Result<(), Box<dyn Error>> {
+    let metadata = store.metadata().unwrap_or_default();
+    let entries = store.entry_metadatas().await?;
+    let mut kinds = BTreeMap::<&str, usize>::new();
+    let mut crates = BTreeMap::<&str, usize>::new();
+    let mut items = 0;
+    for entry in &entries {
+        // Only the first chunk of a document counts as an item
+        if entry.get("chunk").and_then(Value::as_u64).unwrap_or(0) > 0
+            || entry.contains_key("facet")
+        {
+            continue;
+        }
+        items += 1;
+        let field = |key| {
+            entry
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or("(unknown)")
+        };
+        *kinds.entry(field("kind")).or_default() += 1;
+        *crates.entry(field("crate")).or_default() += 1;
+    }
+    let field = |key| {
+        metadata
+            .get(key)
+            .map(|value| match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            })
+            .unwrap_or_else(|| "(unknown)".to_string())
+    };
+
+    println!("{}", console::style(name).bold());
+    println!("  model:    {} ({})", field("model"), field("provider"));
+    println!("  distance: {}", field("hnsw:space"));
+    println!("  items:    {} in {} entries", items, entries.len());
+    if let Some(size) = size {
+        println!("  size:     {} KiB", size.div_ceil(1024));
+    }
+    println!("  created:  {}", timestamp(metadata.get("created_at")));
+    println!("  updated:  {}", timestamp(metadata.get("updated_at")));
+    println!("  by kind:");
+    for (kind, count) in kinds {
+        println!("    {:>6} {}", count, kind);
+    }
+    println!("  by crate:");
+    for (krate, count) in crates {
+        println!("    {:>6} {}", count, krate);
+    }
+    Ok(())
+}
+
+/// How long ago a timestamp in seconds since the epoch was.
+fn timestamp(value: Option<&Value>) -> String {
+    let Some(seconds) = value.and_then(Value::as_u64) else {
+        return "(unknown)".to_string();
+    };
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(seconds, |now| now.as_secs());
+    let ago = now.saturating_sub(seconds);
+    let ago = match ago {
+        0..60 => format!("{} seconds ago", ago),
+        60..3600 => format!("{} minutes ago", ago / 60),
+        3600..86400 => format!("{} hours ago", ago / 3600),
+        _ => format!("{} days ago", ago / 86400),
+    };
+    format!("{} ({})", seconds, ago)
+}
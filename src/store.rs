@@ -0,0 +1,351 @@
+use std::{error::Error, path::PathBuf};
+
+use chromadb::v2::{
+    client::ChromaClient,
+    collection::{ChromaCollection, CollectionEntries, GetOptions, QueryOptions},
+};
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+
+use crate::{
+    error,
+    filter::Filter,
+    local_store::{LocalIndex, Quantization},
+    retry::{Retry, StatusError},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StoreKind {
+    Chroma,
+    Local,
+}
+
+/// How many more results than requested are fetched from Chroma when some filters
+/// have to be applied after the query.
+const FILTER_OVERSAMPLING: usize = 10;
+
+pub struct Entry {
+    pub id: String,
+    pub embedding: Vec<f32>,
+    pub document: String,
+    pub metadata: Option<Map<String, Value>>,
+}
+
+pub struct Hit {
+    pub id: String,
+    pub distance: f32,
+    pub document: Option<String>,
+    pub embedding: Option<Vec<f32>>,
+    pub metadata: Option<Map<String, Value>>,
+}
+
+/// Similarity from 0 to 100 of a distance in the given space, assuming normalized
+/// embeddings for squared L2 and inner product, as most embedding models produce.
+pub fn similarity(distance: f32, space: &str) -> f32 {
+    let cosine = match space {
+        "l2" => 1.0 - distance / 2.0,
+        _ => 1.0 - distance,
+    };
+    cosine.clamp(0.0, 1.0) * 100.0
+}
+
+pub enum VectorStore {
+    Chroma(ChromaCollection, Retry),
+    Local(LocalIndex),
+}
+
+impl VectorStore {
+    /// Open the Chroma collection with the given name, creating it if needed.
+    /// Also returns whether it already existed.
+    pub async fn open_chroma(
+        chroma: &ChromaClient,
+        name: &str,
+        metadata: Map<String, Value>,
+        retry: Retry,
+    ) -> Result<(Self, bool), Box<dyn Error>> {
+        let exist = chroma.get_collection(name).await.is_ok();
+        let collection = chroma
+            .get_or_create_collection(name, Some(metadata))
+            .await
+            .map_err(|err| error::Error::store(format!("creating {}: {}", name, err).into()))?;
+        Ok((VectorStore::Chroma(collection, retry), exist))
+    }
+
+    /// Open the local index stored at the given path, creating it if needed.
+    /// Also returns whether it already existed.
+    pub fn open_local(
+        path: PathBuf,
+        metadata: Map<String, Value>,
+        quantization: Quantization,
+        binary_prefilter: bool,
+    ) -> Result<(Self, bool), Box<dyn Error>> {
+        let exist = path.exists();
+        let index = LocalIndex::open(path, metadata, quantization, binary_prefilter)?;
+        Ok((VectorStore::Local(index), exist))
+    }
+
+    pub fn metadata(&self) -> Option<Map<String, Value>> {
+        match self {
+            VectorStore::Chroma(collection, _) => collection.metadata().cloned(),
+            VectorStore::Local(index) => Some(index.metadata()),
+        }
+    }
+
+    pub async fn set_metadata(&self, metadata: &Map<String, Value>) -> Result<(), Box<dyn Error>> {
+        match self {
+            VectorStore::Chroma(collection, retry) => {
+                chroma(retry, || async {
+                    collection
+                        .modify(None, Some(metadata))
+                        .await
+                        .map_err(chroma_error)
+                })
+                .await?
+            }
+            VectorStore::Local(index) => index.set_metadata(metadata.clone()),
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, fields(entries = entries.len()))]
+    pub async fn upsert(&self, entries: Vec<Entry>) -> Result<(), Box<dyn Error>> {
+        tracing::debug!("upserting");
+        match self {
+            VectorStore::Chroma(collection, retry) => {
+                chroma(retry, || async {
+                    let collection_entries = CollectionEntries {
+                        ids: entries.iter().map(|entry| entry.id.as_str()).collect(),
+                        embeddings: Some(
+                            entries
+                                .iter()
+                                .map(|entry| entry.embedding.clone())
+                                .collect(),
+                        ),
+                        documents: Some(
+                            entries
+                                .iter()
+                                .map(|entry| entry.document.as_str())
+                                .collect(),
+                        ),
+                        metadatas: entries.iter().map(|entry| entry.metadata.clone()).collect(),
+                    };
+                    collection
+                        .upsert(collection_entries, None)
+                        .await
+                        .map_err(chroma_error)
+                })
+                .await?;
+            }
+            VectorStore::Local(index) => index.upsert(entries),
+        }
+        Ok(())
+    }
+
+    /// Ids of all the entries in the store.
+    pub async fn ids(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        match self {
+            VectorStore::Chroma(collection, retry) => {
+                let result = chroma(retry, || async {
+                    let options = GetOptions {
+                        include: Some(vec![]),
+                        ..Default::default()
+                    };
+                    collection.get(options).await.map_err(chroma_error)
+                })
+                .await?;
+                Ok(result.ids)
+            }
+            VectorStore::Local(index) => Ok(index.ids()),
+        }
+    }
+
+    /// Metadata of all the entries in the store.
+    pub async fn entry_metadatas(&self) -> Result<Vec<Map<String, Value>>, Box<dyn Error>> {
+        match self {
+            VectorStore::Chroma(collection, retry) => {
+                let result = chroma(retry, || async {
+                    let options = GetOptions {
+                        include: Some(vec!["metadatas".to_string()]),
+                        ..Default::default()
+                    };
+                    collection.get(options).await.map_err(chroma_error)
+                })
+                .await?;
+                Ok(result
+                    .metadatas
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .flatten()
+                    .flatten()
+                    .collect())
+            }
+            VectorStore::Local(index) => Ok(index.entry_metadatas()),
+        }
+    }
+
+    /// Every entry of the store, with its vector, document and metadata.
+    pub async fn entries(&self) -> Result<Vec<Entry>, Box<dyn Error>> {
+        match self {
+            VectorStore::Chroma(collection, retry) => {
+                let result = chroma(retry, || async {
+                    let options = GetOptions {
+                        include: Some(vec![
+                            "documents".to_string(),
+                            "embeddings".to_string(),
+                            "metadatas".to_string(),
+                        ]),
+                        ..Default::default()
+                    };
+                    collection.get(options).await.map_err(chroma_error)
+                })
+                .await?;
+                let mut documents = result.documents.into_iter().flatten();
+                let mut embeddings = result.embeddings.into_iter().flatten();
+                let mut metadatas = result.metadatas.into_iter().flatten();
+                Ok(result
+                    .ids
+                    .into_iter()
+                    .map(|id| Entry {
+                        id,
+                        document: documents.next().flatten().unwrap_or_default(),
+                        embedding: embeddings.next().flatten().unwrap_or_default(),
+                        metadata: metadatas
+                            .next()
+                            .flatten()
+                            .into_iter()
+                            .flatten()
+                            .flatten()
+                            .next(),
+                    })
+                    .collect())
+            }
+            VectorStore::Local(index) => Ok(index.entries()),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(ids = ids.len()))]
+    pub async fn delete(&self, ids: Vec<String>) -> Result<(), Box<dyn Error>> {
+        tracing::debug!("deleting");
+        match self {
+            VectorStore::Chroma(collection, retry) => {
+                chroma(retry, || async {
+                    let ids = ids.iter().map(String::as_str).collect();
+                    collection
+                        .delete(Some(ids), None, None)
+                        .await
+                        .map_err(chroma_error)
+                })
+                .await?
+            }
+            VectorStore::Local(index) => index.delete(&ids),
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self, embedding, filter))]
+    pub async fn query(
+        &self,
+        embedding: Vec<f32>,
+        n_results: usize,
+        filter: &Filter,
+    ) -> Result<Vec<Hit>, Box<dyn Error>> {
+        tracing::debug!("querying");
+        match self {
+            VectorStore::Chroma(collection, retry) => {
+                let post_filter = filter.path_prefix.is_some() || filter.features.is_some();
+                let mut result = chroma(retry, || async {
+                    let query = QueryOptions {
+                        query_embeddings: Some(vec![embedding.clone()]),
+                        n_results: Some(if post_filter {
+                            n_results * FILTER_OVERSAMPLING
+                        } else {
+                            n_results
+                        }),
+                        where_metadata: filter.where_clause(),
+                        include: Some(vec!["distances", "documents", "metadatas", "embeddings"]),
+                        ..Default::default()
+                    };
+                    collection.query(query, None).await.map_err(chroma_error)
+                })
+                .await?;
+                let documents = result
+                    .documents
+                    .map(|mut documents| documents.remove(0))
+                    .unwrap_or_default();
+                let metadatas = result
+                    .metadatas
+                    .map(|mut metadatas| metadatas.remove(0))
+                    .unwrap_or_default();
+                let embeddings = result
+                    .embeddings
+                    .map(|mut embeddings| embeddings.remove(0))
+                    .unwrap_or_default();
+                Ok(result
+                    .ids
+                    .remove(0)
+                    .into_iter()
+                    .zip(result.distances.unwrap().remove(0))
+                    .enumerate()
+                    .map(|(i, (id, distance))| Hit {
+                        id,
+                        distance,
+                        document: documents.get(i).cloned(),
+                        embedding: embeddings.get(i).cloned(),
+                        metadata: metadatas.get(i).cloned().flatten(),
+                    })
+                    .filter(|hit| {
+                        !post_filter
+                            || hit
+                                .metadata
+                                .as_ref()
+                                .is_some_and(|metadata| filter.matches(metadata))
+                    })
+                    .take(n_results)
+                    .collect())
+            }
+            VectorStore::Local(index) => Ok(index.query(&embedding, n_results, filter)),
+        }
+    }
+
+    /// Persist pending changes. Remote stores write on every call so this is
+    /// only needed for local indexes.
+    pub fn flush(&self) -> Result<(), Box<dyn Error>> {
+        match self {
+            VectorStore::Chroma(..) => Ok(()),
+            VectorStore::Local(index) => {
+                index.save().map_err(|err| error::Error::store(err).into())
+            }
+        }
+    }
+}
+
+/// Call Chroma with retries, its failures being store errors.
+/// Error of the Chroma client, which wraps the ones of its requests with `anyhow`,
+/// unwrapped for [`Retry`] to tell the transient ones.
+fn chroma_error(err: anyhow::Error) -> Box<dyn Error> {
+    let err = match err.downcast::<reqwest::Error>() {
+        Ok(err) => return Box::new(err),
+        Err(err) => err,
+    };
+    // Error responses are only given as a message starting with their status
+    let message = err.to_string();
+    let status = message
+        .get(..3)
+        .and_then(|code| code.parse().ok())
+        .and_then(|code| reqwest::StatusCode::from_u16(code).ok());
+    match status {
+        Some(status) => Box::new(StatusError { status, message }),
+        None => err.into(),
+    }
+}
+
+async fn chroma<T, F>(retry: &Retry, call: impl FnMut() -> F) -> Result<T, Box<dyn Error>>
+where
+    F: std::future::Future<Output = Result<T, Box<dyn Error>>>,
+{
+    retry
+        .run("chroma", call)
+        .await
+        .map_err(|err| error::Error::store(err).into())
+}
@@ -0,0 +1,154 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+};
+
+use futures::StreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde_json::json;
+
+use crate::{answer, cache::content_hash, document::Document, Options};
+
+/// Prepend to each document a short plain-English summary written by the chat
+/// model, so that questions worded differently than the documentation still find
+/// it.
+pub async fn summarize_documents(
+    options: &Options,
+    documents: &mut BTreeMap<String, Document>,
+) -> Result<(), Box<dyn Error>> {
+    let system = format!(
+        "You explain the API of the Rust project {} to newcomers. Given the \
+        documentation of an item, reply with 2 to 3 sentences in plain English \
+        saying what it is for and when to use it, avoiding jargon. Only use the \
+        documentation provided.",
+        options.project
+    );
+    let texts = documents.values().map(|document| document.text.clone());
+    let summaries = replies(options, "summaries", &system, texts.collect()).await?;
+    for document in documents.values_mut() {
+        match summaries.get(&document.text) {
+            Some(summary) if !summary.is_empty() => {
+                document.text = format!("Summary: {}\n\n{}", summary, document.text);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Add a short description written by the chat model to the documents of items
+/// without documentation, from what their document says of their name, fields and
+/// module. It is marked as generated in the text and metadata.
+pub async fn describe_undocumented(
+    options: &Options,
+    documents: &mut BTreeMap<String, Document>,
+    undocumented: &BTreeSet<String>,
+) -> Result<(), Box<dyn Error>> {
+    let system = format!(
+        "You describe items of the Rust project {} that have no documentation. Given \
+        what is known of an item, its name, kind, module and fields, reply with 1 to 2 \
+        sentences guessing what it is for. Don't invent details that can't be deduced \
+        from the names.",
+        options.project
+    );
+    let prompt = |document: &Document| {
+        let module = document
+            .metadata
+            .get("module")
+            .and_then(|module| module.as_str());
+        format!(
+            "{}\n\nIt is in the module {}.",
+            document.text.trim(),
+            module.unwrap_or("?")
+        )
+    };
+    let texts = undocumented
+        .iter()
+        .filter_map(|id| documents.get(id))
+        .map(prompt)
+        .collect();
+    let descriptions = replies(options, "descriptions", &system, texts).await?;
+    for id in undocumented {
+        let Some(document) = documents.get_mut(id) else {
+            continue;
+        };
+        let Some(description) = descriptions
+            .get(&prompt(document))
+            .filter(|d| !d.is_empty())
+        else {
+            continue;
+        };
+        document.text.push_str(&format!(
+            "Generated description, the item has no documentation: {}\n\n",
+            description
+        ));
+        document
+            .metadata
+            .insert("generated_description".to_string(), true.into());
+    }
+    Ok(())
+}
+
+/// Reply of the chat model to each text with the system prompt, cached in the
+/// output directory by model and text.
+async fn replies(
+    options: &Options,
+    name: &str,
+    system: &str,
+    texts: BTreeSet<String>,
+) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+    let llm = options.chat_model()?;
+    let dir = options
+        .out_dir
+        .join(name)
+        .join(options.chat_model.replace(['/', ':', '\\'], "_"));
+    let cached = |text: &str| dir.join(format!("{}.txt", content_hash(text)));
+
+    let missing = texts
+        .iter()
+        .filter(|text| !cached(text).exists())
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        std::fs::create_dir_all(&dir)?;
+        let progress = ProgressBar::new(missing.len() as u64);
+        progress.set_style(
+            ProgressStyle::with_template("{msg:>10} [{bar:40}] {pos}/{len} ({per_sec}, ETA {eta})")
+                .unwrap()
+                .progress_chars("=> "),
+        );
+        progress.set_message(name.to_string());
+        let mut replies = futures::stream::iter(missing)
+            .map(|text| {
+                let llm = &llm;
+                async move {
+                    let packed = answer::pack_context(
+                        vec![(String::new(), text.clone())],
+                        options.context_tokens,
+                    )
+                    .pop()
+                    .map(|(_, text)| text)
+                    .unwrap_or_default();
+                    let messages = json!([
+                        { "role": "system", "content": system },
+                        { "role": "user", "content": packed },
+                    ]);
+                    Ok::<_, Box<dyn Error>>((text, llm.chat(messages, |_| Ok(())).await?))
+                }
+            })
+            .buffer_unordered(options.concurrency.max(1));
+        while let Some(reply) = replies.next().await {
+            let (text, reply) = reply?;
+            std::fs::write(cached(text), reply.trim())?;
+            progress.inc(1);
+        }
+        progress.finish();
+    }
+
+    texts
+        .into_iter()
+        .map(|text| {
+            let reply = std::fs::read_to_string(cached(&text))?;
+            Ok((text, reply))
+        })
+        .collect()
+}
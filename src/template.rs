@@ -0,0 +1,168 @@
+use std::{path::Path, sync::Arc};
+
+use rustdoc_types::{Item, ItemEnum, ItemKind};
+use serde_json::{json, Value};
+use tera::Tera;
+
+use crate::{
+    cleanup,
+    document::Document,
+    error,
+    generator::{Context, DocumentGenerator, Generated, Generators},
+    signature::type_name,
+    traversal::{item_kind, item_metadata, item_path},
+};
+
+/// Renders the documents of a kind of item with a template, given the document of
+/// the generator it replaces when there is one.
+struct Templated {
+    tera: Arc<Tera>,
+    template: String,
+    generator: Option<Arc<dyn DocumentGenerator>>,
+}
+
+impl DocumentGenerator for Templated {
+    fn generate(&self, item: &Item, context: &Context) -> Generated {
+        let mut document = match &self.generator {
+            Some(generator) => match generator.generate(item, context)? {
+                Some(document) => document,
+                None => return Ok(None),
+            },
+            None => {
+                let kind = item_kind(&item.inner);
+                Document {
+                    path: item_path(item, context.current_crate, context.crates),
+                    kind: kind.to_string(),
+                    text: String::new(),
+                    metadata: item_metadata(item, kind, context.current_crate, context.crates),
+                }
+            }
+        };
+        let data = data(item, &document, context);
+        document.text = tera::Context::from_value(data)
+            .and_then(|data| self.tera.render(&self.template, &data))
+            .map_err(|source| RenderError {
+                template: self.template.clone(),
+                path: document.path.clone(),
+                source,
+            })?;
+        Ok(Some(document))
+    }
+}
+
+/// A template that couldn't be rendered for an item.
+#[derive(Debug, thiserror::Error)]
+#[error("couldn't render the template {template} for {path}: {source}")]
+struct RenderError {
+    template: String,
+    path: String,
+    #[source]
+    source: tera::Error,
+}
+
+/// Replace the generators of the kinds of items with a template in `dir`, named
+/// after the kind like `struct.tera` or `type_alias.tera`.
+pub fn use_templates(dir: &Path, generators: &mut Generators) -> error::Result<()> {
+    let tera = Tera::new(&dir.join("*.tera").to_string_lossy()).map_err(|source| {
+        error::Error::Invalid {
+            what: format!("templates in {}", dir.display()),
+            source: Box::new(source),
+        }
+    })?;
+    let tera = Arc::new(tera);
+    let templates = tera
+        .get_template_names()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if templates.is_empty() {
+        return Err(error::Error::Options(format!(
+            "no templates in {}, add templates named after a kind of item, like struct.tera",
+            dir.display()
+        )));
+    }
+    for template in templates {
+        let name = template.trim_end_matches(".tera");
+        let Ok(kind) = serde_json::from_value::<ItemKind>(name.into()) else {
+            return Err(error::Error::Options(format!(
+                "unknown kind of item {} for the template {}, name templates after a kind like struct.tera, enum.tera or type_alias.tera",
+                name, template
+            )));
+        };
+        let generator = generators.remove(kind);
+        generators.insert(
+            kind,
+            Templated {
+                tera: tera.clone(),
+                template,
+                generator,
+            },
+        );
+    }
+    Ok(())
+}
+
+/// What templates are given about an item: its document, and its fields, variants
+/// and methods with their documentation.
+fn data(item: &Item, document: &Document, context: &Context) -> Value {
+    let krate = &context.crates[context.current_crate].as_ref().unwrap().1;
+    let docs = item.docs.as_deref().map(cleanup::clean);
+    let described = |id: &rustdoc_types::Id| {
+        let item = krate.index.get(id)?;
+        let mut value = json!({
+            "name": item.name.as_deref()?,
+            "docs": item.docs.as_deref().map(cleanup::clean),
+            "summary": item.docs.as_deref().and_then(cleanup::summary),
+        });
+        if let ItemEnum::StructField(ty) = &item.inner {
+            value["type"] = context.simplify(&type_name(ty)).into();
+        }
+        Some(value)
+    };
+    let inherent = |impls: &[rustdoc_types::Id]| {
+        impls
+            .iter()
+            .filter_map(|id| match &krate.index.get(id)?.inner {
+                ItemEnum::Impl(imp) if imp.trait_.is_none() => Some(&imp.items),
+                _ => None,
+            })
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>()
+    };
+    let (fields, variants, methods) = match &item.inner {
+        ItemEnum::Struct(stru) => {
+            let fields = match &stru.kind {
+                rustdoc_types::StructKind::Plain { fields, .. } => fields.clone(),
+                _ => vec![],
+            };
+            (fields, vec![], inherent(&stru.impls))
+        }
+        ItemEnum::Union(union) => (union.fields.clone(), vec![], inherent(&union.impls)),
+        ItemEnum::Enum(enume) => (vec![], enume.variants.clone(), inherent(&enume.impls)),
+        ItemEnum::Primitive(primitive) => (vec![], vec![], inherent(&primitive.impls)),
+        ItemEnum::Trait(trait_) => (vec![], vec![], trait_.items.clone()),
+        _ => (vec![], vec![], vec![]),
+    };
+    let methods = methods
+        .into_iter()
+        .filter(|id| {
+            matches!(
+                krate.index.get(id).map(|item| &item.inner),
+                Some(ItemEnum::Function(_))
+            )
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "name": item.name,
+        "path": document.path,
+        "kind": document.kind,
+        "docs": docs,
+        "summary": item.docs.as_deref().and_then(cleanup::summary),
+        "text": document.text,
+        "metadata": document.metadata,
+        "fields": fields.iter().filter_map(described).collect::<Vec<_>>(),
+        "variants": variants.iter().filter_map(described).collect::<Vec<_>>(),
+        "methods": methods.iter().filter_map(described).collect::<Vec<_>>(),
+        "item": item,
+    })
+}
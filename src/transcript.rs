@@ -0,0 +1,121 @@
+use std::{error::Error, fs::OpenOptions, io::Write, path::PathBuf};
+
+use rustdoc_rag::{docs_rs, Results};
+
+use crate::snippet;
+
+/// Markdown log of a session, appended to after every query.
+pub struct Transcript {
+    path: PathBuf,
+}
+
+impl Transcript {
+    pub fn new(path: PathBuf) -> Self {
+        Transcript { path }
+    }
+
+    pub fn record(
+        &self,
+        query: &str,
+        results: &Results,
+        answer: Option<&str>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut entry = format!("## {}\n\n", query.trim());
+        if let Some(answer) = answer {
+            entry.push_str(answer.trim());
+            entry.push_str("\n\n");
+        }
+        if results.ranking.is_empty() {
+            entry.push_str("No sufficiently relevant results.\n\n");
+        }
+        for (i, (id, score)) in results.ranking.iter().enumerate() {
+            entry.push_str(&format!("{}. `{}` ({:.3})\n", i + 1, id, score));
+        }
+        if !results.related.is_empty() {
+            let related = results
+                .related
+                .iter()
+                .map(|id| format!("`{}`", id))
+                .collect::<Vec<_>>();
+            entry.push_str(&format!("\nRelated: {}\n", related.join(", ")));
+        }
+        entry.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(entry.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Shareable markdown report of the queries of a run, with the snippets and docs.rs
+/// links of their results and the sources of their answers.
+pub struct Report {
+    path: PathBuf,
+}
+
+impl Report {
+    /// Start the report, replacing the file.
+    pub fn create(path: PathBuf) -> Result<Self, Box<dyn Error>> {
+        std::fs::write(&path, "")?;
+        Ok(Report { path })
+    }
+
+    pub fn record(
+        &self,
+        query: &str,
+        results: &Results,
+        answer: Option<&str>,
+        sources: &[(usize, String)],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut entry = format!("# {}\n\n", query.trim());
+        if let Some(answer) = answer {
+            entry.push_str("## Answer\n\n");
+            entry.push_str(answer.trim());
+            entry.push_str("\n\n");
+            if !sources.is_empty() {
+                entry.push_str("Sources:\n\n");
+                for (i, id) in sources {
+                    entry.push_str(&format!("- [{}] {}\n", i, link(id, results)));
+                }
+                entry.push('\n');
+            }
+        }
+        entry.push_str("## Results\n\n");
+        if results.ranking.is_empty() {
+            entry.push_str("No sufficiently relevant results.\n\n");
+        }
+        for (i, (id, _)) in results.ranking.iter().enumerate() {
+            entry.push_str(&format!("{}. {}\n", i + 1, link(id, results)));
+            if let Some(text) = results.texts.get(id) {
+                entry.push_str(&format!("   > {}\n", snippet(text)));
+            }
+        }
+        if !results.related.is_empty() {
+            let related = results
+                .related
+                .iter()
+                .map(|id| link(id, results))
+                .collect::<Vec<_>>();
+            entry.push_str(&format!("\nRelated: {}\n", related.join(", ")));
+        }
+        entry.push('\n');
+        let mut file = OpenOptions::new().append(true).open(&self.path)?;
+        file.write_all(entry.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Path of the item in code, linked to its docs.rs page when known.
+fn link(id: &str, results: &Results) -> String {
+    let (_, path) = id.split_once(':').unwrap_or(("", id));
+    match results
+        .metadatas
+        .get(id)
+        .and_then(|metadata| docs_rs::item_url(id, metadata))
+    {
+        Some(url) => format!("[`{}`]({})", path, url),
+        None => format!("`{}`", path),
+    }
+}
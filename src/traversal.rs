@@ -0,0 +1,1311 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    error::Error,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Instant,
+};
+
+use indicatif::ProgressBar;
+use rayon::prelude::*;
+use rustdoc_types::ItemKind;
+use serde_json::{Map, Value};
+
+use crate::{
+    cargo, cleanup, dedup, docs_rs, document::Document, document_extension, error, example,
+    generator, generator::Generators, graph, graph::Graph, guide, plugin, report::Report,
+    signature, source::Sources, template, Options,
+};
+
+/// Load the rustdoc JSON of the project and of its dependencies, and generate the
+/// documents of every item reachable from the root of the project, or from the
+/// root of every crate of a project directory.
+///
+/// With `progress`, a spinner shows the traversal and the dependencies without a
+/// rustdoc JSON are listed, otherwise the caller reports them from the report.
+pub fn generate_documents(
+    options: &Options,
+    project_json: &Path,
+    progress: bool,
+) -> error::Result<Traversal> {
+    traverse(options, project_json, progress).map_err(error::Error::traversal)
+}
+
+fn traverse(
+    options: &Options,
+    project_json: &Path,
+    progress: bool,
+) -> Result<Traversal, Box<dyn Error>> {
+    if !project_json.exists() {
+        return Err(error::Error::Rustdoc(format!(
+            "couldn't find the rustdoc JSON {}",
+            project_json.display()
+        ))
+        .into());
+    }
+    let mut roots = if project_json.is_dir() {
+        workspace_crates(options, project_json)?
+    } else {
+        vec![(options.project.clone(), project_json.to_path_buf())]
+    };
+    let std_dir = options
+        .with_std
+        .then(|| cargo::require_std_json_dir(&options.toolchain))
+        .transpose()?;
+    if let Some(std_dir) = &std_dir {
+        if !roots.iter().any(|(name, _)| name == "std") {
+            roots.push(("std".to_string(), std_dir.join("std.json")));
+        }
+    }
+    if roots.is_empty() {
+        let patterns = if options.include_crate.is_empty() && options.exclude_crate.is_empty() {
+            ""
+        } else {
+            " matching the --include-crate and --exclude-crate patterns"
+        };
+        return Err(error::Error::Rustdoc(format!(
+            "couldn't find any rustdoc JSON to index in {}{}",
+            project_json.display(),
+            patterns
+        ))
+        .into());
+    }
+
+    let mut traversal = Traversal::new(progress);
+    traversal.sources = options.include_source.then(|| Sources::new(options));
+    traversal.skip_undocumented = options.skip_undocumented;
+    traversal.max_depth = options.max_depth;
+    traversal.blanket_impls = options.blanket_impls;
+    traversal.signature_depth = options.signature_depth;
+    traversal.signature_bounds = options.signature_bounds;
+    traversal.generators = options.generators.clone();
+    if let Some(dir) = &options.templates {
+        template::use_templates(dir, &mut traversal.generators)?;
+    }
+    traversal.include_path = options.include_path.clone();
+    traversal.exclude_path = options.exclude_path.clone();
+    let mut root_crates = vec![];
+    for (name, path) in roots {
+        let start = Instant::now();
+        let mut loaded_crates = load_crates(
+            options,
+            name,
+            &path,
+            std_dir.as_deref(),
+            &mut traversal.report,
+        )?;
+        traversal.report.time("loading", start);
+        if let Some((name, _)) = &loaded_crates[0] {
+            root_crates.push(name.clone());
+        }
+        if traversal.version.is_none() {
+            traversal.version = loaded_crates[0]
+                .as_ref()
+                .and_then(|(_, krate)| krate.crate_version.clone());
+        }
+        let start = Instant::now();
+        // Ids are only unique within the catalog of one root crate
+        traversal.visited.clear();
+        start_krate(&loaded_crates, &mut traversal);
+        loaded_crates.failure()?;
+        traversal.report.time("traversal", start);
+        let start = Instant::now();
+        document_pending(&loaded_crates, &mut traversal)?;
+        loaded_crates.failure()?;
+        traversal.report.time("documents", start);
+    }
+    let mut duplicates = dedup::merge_duplicates(&mut traversal.documents);
+    traversal.report.duplicates = duplicates.values().map(Vec::len).sum();
+    traversal.report.documents = traversal.documents.len();
+    let aliases = traversal
+        .documents
+        .iter()
+        .map(|(id, document)| {
+            let mut aliases = traversal.aliases_of(&document.path);
+            for path in duplicates.get(id).into_iter().flatten() {
+                aliases.extend(traversal.aliases_of(path));
+                aliases.insert(path.clone());
+            }
+            aliases
+        })
+        .collect::<Vec<_>>();
+    let inbound = traversal.graph.inbound();
+    for ((id, document), aliases) in traversal.documents.iter_mut().zip(aliases) {
+        if let Some(import) = import_path(document, &aliases, &root_crates) {
+            document
+                .metadata
+                .insert("import".to_string(), format!("use {};", import).into());
+        }
+        document.add_aliases(aliases);
+        if let Some(features) = traversal.required_features.get(&document.path) {
+            document
+                .metadata
+                .insert("features".to_string(), features.join(", ").into());
+        }
+        if let Some(references) = inbound.get(document.path.as_str()) {
+            document
+                .metadata
+                .insert("references".to_string(), (*references).into());
+        }
+        if let Some(Value::String(full)) = document.metadata.get_mut("signature") {
+            *full = signature::simplify(full, options.signature_depth, options.signature_bounds);
+        }
+        if let Some(paths) = duplicates.remove(id) {
+            document
+                .metadata
+                .insert("duplicates".to_string(), paths.join(", ").into());
+        }
+    }
+    for document in guide::guide_documents(&options.extra_docs)?
+        .into_iter()
+        .chain(example::example_documents(&options.examples)?)
+    {
+        traversal.documents.insert(document.id(), document);
+    }
+    // Undocumented items are counted by crate in the report
+    traversal.progress.finish_with_message(format!(
+        "{} items visited, {} documents generated, {} items without documentation",
+        traversal.report.visited.values().sum::<usize>(),
+        traversal.documents.len(),
+        traversal.report.undocumented.values().sum::<usize>()
+    ));
+    let missing = &traversal.report.missing_crates;
+    if progress && !missing.is_empty() {
+        eprintln!(
+            "Missing the rustdoc JSON of {} dependencies, their items aren't indexed:",
+            missing.len()
+        );
+        for (name, items) in missing {
+            eprintln!("  {} ({} items re-exported)", name, items);
+        }
+        if !options.fetch_missing {
+            eprintln!("Add --fetch-missing to download them from docs.rs");
+        }
+    }
+    if options.emit_docs {
+        // Documents of a previous run that weren't generated again would stay
+        let kinds = traversal
+            .documents
+            .values()
+            .map(|document| &document.kind)
+            .collect::<BTreeSet<_>>();
+        for kind in kinds {
+            let dir = options.out_dir.join(format!("{}s", kind));
+            if std::fs::exists(&dir)? {
+                std::fs::remove_dir_all(dir)?;
+            }
+        }
+        for document in traversal.documents.values() {
+            document.emit(&options.out_dir, options.doc_format)?;
+        }
+    }
+
+    Ok(traversal)
+}
+
+/// Load a root crate and find the rustdoc JSON of its dependencies, in the jsons
+/// directory or for the standard library in `std_dir`, to read when first used.
+/// The root crate comes first.
+fn load_crates(
+    options: &Options,
+    name: String,
+    path: &Path,
+    std_dir: Option<&Path>,
+    report: &mut Report,
+) -> Result<CrateCatalog, Box<dyn Error>> {
+    let Some(krate) = parse_crate(path, options.low_memory, options.include_source)
+        .map_err(|err| err.at(path))?
+    else {
+        return Err(format!("Couldn't read {}", path.display()).into());
+    };
+    let mut catalog = CrateCatalog {
+        crates: (0..=krate.external_crates.len())
+            .map(|_| CatalogEntry::new(String::new(), None, false, false))
+            .collect(),
+        failure: OnceLock::new(),
+    };
+
+    let jsons_dir = options.jsons_dir();
+    let excluded = |name: &str| {
+        options
+            .exclude_crate
+            .iter()
+            .any(|pattern| matches_pattern(pattern, name))
+    };
+    for ext_krate in krate.external_crates.values() {
+        if excluded(&ext_krate.name) && !report.skipped_item("excluded crate", &ext_krate.name) {
+            report.skip("excluded crate", ext_krate.name.clone());
+        }
+    }
+    let dependency_json = |ext_krate: &rustdoc_types::ExternalCrate| {
+        let name = ext_krate.name.as_str();
+        let path = jsons_dir.join(format!("{}.json", name));
+        match std_dir {
+            Some(std_dir) if !path.exists() && cargo::STD_CRATES.contains(&name) => {
+                std_dir.join(format!("{}.json", name))
+            }
+            _ if !path.exists() => docs_rs::release(ext_krate)
+                .and_then(|(name, version)| {
+                    docs_rs::cached(&options.out_dir, &name, version.as_deref())
+                })
+                .unwrap_or(path),
+            _ => path,
+        }
+    };
+    for (id, ext_krate) in &krate.external_crates {
+        if excluded(&ext_krate.name) {
+            continue;
+        }
+        let path = dependency_json(ext_krate);
+        if path.exists() {
+            catalog.crates[*id as usize] = CatalogEntry::new(
+                ext_krate.name.clone(),
+                Some(path),
+                options.low_memory,
+                options.include_source,
+            );
+            continue;
+        }
+        // The standard library comes with --with-std rather than from docs.rs
+        if docs_rs::is_std(ext_krate) {
+            continue;
+        }
+        tracing::info!(
+            name = ext_krate.name,
+            "missing rustdoc JSON of a dependency"
+        );
+        // Only the paths of re-exported items of other crates are kept
+        let items = krate
+            .paths
+            .values()
+            .filter(|summary| summary.crate_id == *id)
+            .count();
+        let counted = report
+            .missing_crates
+            .entry(ext_krate.name.clone())
+            .or_default();
+        *counted = (*counted).max(items);
+    }
+    catalog.crates[0] = CatalogEntry {
+        loaded: OnceLock::from(Some((name.clone(), krate))),
+        ..CatalogEntry::new(name, None, false, false)
+    };
+    Ok(catalog)
+}
+/// Whether a crate name or an item path matches a pattern where `*` matches anything.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts = parts.collect::<Vec<_>>();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Name and path of the rustdoc JSON files of a directory selected by the
+/// include and exclude patterns.
+fn workspace_crates(
+    options: &Options,
+    dir: &Path,
+) -> Result<Vec<(String, PathBuf)>, Box<dyn Error>> {
+    let mut crates = std::fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .filter_map(|path| Some((path.file_stem()?.to_string_lossy().to_string(), path)))
+        .filter(|(name, _)| {
+            (options.include_crate.is_empty()
+                || options
+                    .include_crate
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, name)))
+                && !options
+                    .exclude_crate
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, name))
+        })
+        .collect::<Vec<_>>();
+    crates.sort();
+    Ok(crates)
+}
+
+/// The only older rustdoc JSON format version that can be read, upgraded to
+/// [`rustdoc_types::FORMAT_VERSION`] by renaming `is_object_safe`. Versions before
+/// it changed the type of ids and the names of many fields. Newer versions are read
+/// when they are still compatible.
+pub const MIN_FORMAT_VERSION: u32 = 35;
+
+/// Just enough of a rustdoc JSON file to know its format version
+#[derive(serde::Deserialize)]
+struct FormatVersion {
+    format_version: u32,
+}
+
+/// Parse a rustdoc JSON, returning `None` if the file can't be read. In low memory
+/// mode the JSON is deserialized while being read instead of being loaded as a
+/// string first, keeping the spans only if asked.
+#[tracing::instrument(skip(low_memory, keep_spans))]
+fn parse_crate(
+    path: &Path,
+    low_memory: bool,
+    keep_spans: bool,
+) -> Result<Option<rustdoc_types::Crate>, Unreadable> {
+    tracing::debug!("parsing");
+    let parsed = if low_memory {
+        let Ok(file) = std::fs::File::open(path) else {
+            return Ok(None);
+        };
+        serde_json::from_reader(std::io::BufReader::new(file))
+    } else {
+        let Ok(json_string) = std::fs::read_to_string(path) else {
+            return Ok(None);
+        };
+        serde_json::from_str(&json_string)
+    };
+    // The format version is only looked at when the file can't be read as is
+    let mut krate: rustdoc_types::Crate = match parsed {
+        Ok(krate) => krate,
+        Err(err) => {
+            let file = std::fs::File::open(path)
+                .map_err(serde_json::Error::io)
+                .map_err(Unreadable::Invalid)?;
+            let version =
+                serde_json::from_reader::<_, FormatVersion>(std::io::BufReader::new(file))
+                    .map_or(0, |version| version.format_version);
+            if version == rustdoc_types::FORMAT_VERSION {
+                return Err(Unreadable::Invalid(err));
+            }
+            if !(MIN_FORMAT_VERSION..rustdoc_types::FORMAT_VERSION).contains(&version) {
+                return Err(Unreadable::Format(version, err));
+            }
+            upgrade_crate(path, version).map_err(Unreadable::Invalid)?
+        }
+    };
+    if krate.format_version > rustdoc_types::FORMAT_VERSION {
+        tracing::warn!(
+            version = krate.format_version,
+            "rustdoc JSON format version is newer than the supported one"
+        );
+    }
+    if low_memory {
+        slim_crate(&mut krate, keep_spans);
+    }
+    Ok(Some(krate))
+}
+
+/// Why a rustdoc JSON couldn't be read, which unlike [`error::Error`] can be kept
+/// by a catalog shared with the workers generating documents.
+#[derive(Debug)]
+enum Unreadable {
+    Invalid(serde_json::Error),
+    /// Its format version, neither the supported one nor one that can be upgraded
+    Format(u32, serde_json::Error),
+}
+
+impl Unreadable {
+    fn at(self, path: &Path) -> error::Error {
+        let path = path.to_path_buf();
+        match self {
+            Unreadable::Invalid(source) => error::Error::Parse { path, source },
+            Unreadable::Format(version, source) => error::Error::Format {
+                path,
+                version,
+                source,
+            },
+        }
+    }
+}
+
+/// Read a rustdoc JSON of an older format version that can be upgraded to the
+/// supported one.
+fn upgrade_crate(path: &Path, version: u32) -> Result<rustdoc_types::Crate, serde_json::Error> {
+    tracing::debug!(version, "upgrading rustdoc JSON");
+    let file = std::fs::File::open(path).map_err(serde_json::Error::io)?;
+    let mut krate: serde_json::Value = serde_json::from_reader(std::io::BufReader::new(file))?;
+    // 36 renamed `is_object_safe` to `is_dyn_compatible`
+    if version < 36 {
+        let traits = krate["index"]
+            .as_object_mut()
+            .into_iter()
+            .flat_map(|index| index.values_mut())
+            .filter_map(|item| item["inner"].get_mut("trait")?.as_object_mut());
+        for trait_ in traits {
+            if let Some(is_object_safe) = trait_.remove("is_object_safe") {
+                trait_.insert("is_dyn_compatible".to_string(), is_object_safe);
+            }
+        }
+    }
+    serde_json::from_value(krate)
+}
+
+/// Drop the parts of a crate that the traversal never reads: spans unless sources
+/// are indexed, and paths of items from other crates that aren't re-exported.
+fn slim_crate(krate: &mut rustdoc_types::Crate, keep_spans: bool) {
+    let reexported = krate
+        .index
+        .values()
+        .filter_map(|item| match &item.inner {
+            rustdoc_types::ItemEnum::Use(used) => used.id,
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    krate
+        .paths
+        .retain(|id, summary| summary.crate_id == 0 || reexported.contains(id));
+    krate.paths.shrink_to_fit();
+    if keep_spans {
+        return;
+    }
+    for item in krate.index.values_mut() {
+        item.span = None;
+    }
+}
+
+/// Name of a crate with its rustdoc JSON
+pub type LoadedCrate = (String, rustdoc_types::Crate);
+
+/// Crates of the rustdoc JSON of a root crate by crate id, the root first. The
+/// rustdoc JSON of a dependency is only read once an item of it is reached, and
+/// dropped with the catalog when the root crate is done.
+pub struct CrateCatalog {
+    crates: Vec<CatalogEntry>,
+    /// The first rustdoc JSON of a dependency that couldn't be read, for the
+    /// traversal to fail with once the items being explored are done
+    failure: OnceLock<(PathBuf, Unreadable)>,
+}
+
+struct CatalogEntry {
+    name: String,
+    /// Rustdoc JSON to read the crate from, none for crates that aren't indexed
+    json: Option<PathBuf>,
+    low_memory: bool,
+    /// Whether spans are kept in low memory mode, for --include-source
+    include_source: bool,
+    loaded: OnceLock<Option<LoadedCrate>>,
+    /// Ids of the items of the crate by canonical path, for [`find_path`]
+    paths: OnceLock<HashMap<Vec<String>, rustdoc_types::Id>>,
+}
+
+impl CatalogEntry {
+    fn new(name: String, json: Option<PathBuf>, low_memory: bool, include_source: bool) -> Self {
+        CatalogEntry {
+            name,
+            json,
+            low_memory,
+            include_source,
+            loaded: OnceLock::new(),
+            paths: OnceLock::new(),
+        }
+    }
+}
+
+impl CrateCatalog {
+    /// Id of the crate with this name, when its items can be indexed.
+    fn position(&self, name: &str) -> Option<usize> {
+        self.crates
+            .iter()
+            .enumerate()
+            .find(|(id, entry)| entry.name == name && (*id == 0 || entry.json.is_some()))
+            .map(|(id, _)| id)
+    }
+
+    /// Error of the first rustdoc JSON of a dependency that couldn't be read.
+    fn failure(&mut self) -> error::Result<()> {
+        match self.failure.take() {
+            Some((path, unreadable)) => Err(unreadable.at(&path)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::ops::Index<usize> for CrateCatalog {
+    type Output = Option<LoadedCrate>;
+
+    fn index(&self, id: usize) -> &Self::Output {
+        let entry = &self.crates[id];
+        entry.loaded.get_or_init(|| {
+            let path = entry.json.as_ref()?;
+            let _span = tracing::info_span!("loading", name = entry.name).entered();
+            match parse_crate(path, entry.low_memory, entry.include_source) {
+                Ok(parsed) => parsed.map(|mut parsed| {
+                    if entry.low_memory {
+                        parsed.external_crates.clear();
+                    }
+                    (entry.name.clone(), parsed)
+                }),
+                Err(unreadable) => {
+                    let _ = self.failure.set((path.clone(), unreadable));
+                    None
+                }
+            }
+        })
+    }
+}
+
+/// Metadata stored alongside the embedding of an item's document.
+pub fn item_metadata(
+    item: &rustdoc_types::Item,
+    kind: &str,
+    current_crate: usize,
+    crates: &CrateCatalog,
+) -> Map<String, Value> {
+    let krate = crates[current_crate].as_ref().unwrap();
+    let module_path = krate
+        .1
+        .paths
+        .get(&item.id)
+        .map(|summary| summary.path[..summary.path.len() - 1].join("::"))
+        .unwrap_or_else(|| krate.0.clone());
+    let visibility = match &item.visibility {
+        rustdoc_types::Visibility::Public => "public",
+        rustdoc_types::Visibility::Default => "default",
+        rustdoc_types::Visibility::Crate => "crate",
+        rustdoc_types::Visibility::Restricted { .. } => "restricted",
+    };
+
+    let mut metadata = Map::new();
+    metadata.insert("name".to_string(), item.name.clone().into());
+    metadata.insert("kind".to_string(), kind.into());
+    metadata.insert("crate".to_string(), krate.0.clone().into());
+    metadata.insert("module".to_string(), module_path.into());
+    metadata.insert("visibility".to_string(), visibility.into());
+    if let Some(version) = &krate.1.crate_version {
+        metadata.insert("version".to_string(), version.clone().into());
+    }
+    metadata
+}
+
+/// Cargo features an item needs from its `cfg` attributes, like `bevy_ui` for
+/// `#[cfg(feature = "bevy_ui")]`. Features in `any` or `not` aren't needed.
+fn cfg_features(attrs: &[String]) -> Vec<String> {
+    let mut features = vec![];
+    for attr in attrs {
+        let Some(predicate) = attr
+            .strip_prefix("#[cfg(")
+            .or_else(|| attr.strip_prefix("#[doc(cfg("))
+        else {
+            continue;
+        };
+        // Operators the current position is in
+        let mut operators = vec![];
+        let mut word = String::new();
+        let mut rest = predicate;
+        while let Some(c) = rest.chars().next() {
+            rest = &rest[c.len_utf8()..];
+            match c {
+                '(' => operators.push(std::mem::take(&mut word)),
+                ')' => {
+                    operators.pop();
+                }
+                '"' => {
+                    let (value, after) = rest.split_once('"').unwrap_or((rest, ""));
+                    rest = after;
+                    let optional = operators.iter().any(|op| op == "any" || op == "not");
+                    if word == "feature" && !optional {
+                        features.push(value.to_string());
+                    }
+                    word.clear();
+                }
+                c if c.is_alphanumeric() || c == '_' => word.push(c),
+                ' ' | '=' => {}
+                _ => word.clear(),
+            }
+        }
+    }
+    features.sort();
+    features.dedup();
+    features
+}
+
+/// Documents generated from a project, with what was learned of its items along the way.
+pub struct Traversal {
+    visited: HashSet<(usize, rustdoc_types::Id)>,
+    pub documents: BTreeMap<String, Document>,
+    /// Public re-export paths of items and modules, by canonical path
+    aliases: BTreeMap<String, BTreeSet<String>>,
+    pub graph: Graph,
+    /// Version of the first crate documented, from its rustdoc JSON
+    pub version: Option<String>,
+    /// Ids of the documents of items without documentation
+    pub undocumented: BTreeSet<String>,
+    /// Whether documents of items without documentation are dropped
+    skip_undocumented: bool,
+    /// Deepest module under a crate root whose items are indexed, with --max-depth
+    max_depth: Option<usize>,
+    /// Whether documents of structs list the traits of blanket impls
+    blanket_impls: bool,
+    /// How much of the signatures in documents to keep, see [`signature::simplify`]
+    signature_depth: usize,
+    signature_bounds: usize,
+    generators: Generators,
+    include_path: Vec<String>,
+    exclude_path: Vec<String>,
+    /// Sources to index functions and methods from, with --include-source
+    sources: Option<Sources>,
+    /// Paths of the modules currently being traversed
+    modules: Vec<String>,
+    /// Items to generate the documents of once the traversal is done, with the
+    /// module they were found in
+    pending: Vec<(usize, rustdoc_types::Id, Option<String>)>,
+    /// Cargo features the modules and imports currently being traversed need
+    features: Vec<String>,
+    /// Cargo features each item needs, by path
+    required_features: BTreeMap<String, Vec<String>>,
+    pub report: Report,
+    progress: ProgressBar,
+}
+
+impl Traversal {
+    fn new(progress: bool) -> Self {
+        let progress = if progress {
+            let spinner = ProgressBar::new_spinner();
+            spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+            spinner
+        } else {
+            ProgressBar::hidden()
+        };
+        Traversal {
+            visited: HashSet::new(),
+            documents: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            graph: Graph::default(),
+            version: None,
+            undocumented: BTreeSet::new(),
+            skip_undocumented: false,
+            max_depth: None,
+            blanket_impls: false,
+            signature_depth: 0,
+            signature_bounds: 0,
+            generators: Generators::default(),
+            include_path: vec![],
+            exclude_path: vec![],
+            sources: None,
+            modules: vec![],
+            pending: vec![],
+            features: vec![],
+            required_features: BTreeMap::new(),
+            report: Report::default(),
+            progress,
+        }
+    }
+
+    /// Public paths other than the canonical one an item can be reached by, through
+    /// re-exports of the item itself or of one of its parent modules.
+    fn aliases_of(&self, path: &str) -> BTreeSet<String> {
+        let segments = path.split("::").collect::<Vec<_>>();
+        let mut aliases = BTreeSet::new();
+        for i in 1..=segments.len() {
+            let rest = &segments[i..];
+            for alias in self
+                .aliases
+                .get(&segments[..i].join("::"))
+                .into_iter()
+                .flatten()
+            {
+                aliases.insert(
+                    std::iter::once(alias.as_str())
+                        .chain(rest.iter().copied())
+                        .collect::<Vec<_>>()
+                        .join("::"),
+                );
+            }
+        }
+        aliases
+    }
+
+    /// Why the --max-depth, --include-path and --exclude-path options leave out an
+    /// item, if they do. The depth is the one of the module of its canonical path.
+    /// Modules are explored even when not included, for the included items they
+    /// contain.
+    fn filtered(&self, path: &str, module: bool) -> Option<&'static str> {
+        if self
+            .exclude_path
+            .iter()
+            .any(|pattern| matches_pattern(pattern, path))
+        {
+            return Some("excluded path");
+        }
+        let segments = path.matches("::").count();
+        let depth = if module {
+            segments
+        } else {
+            segments.saturating_sub(1)
+        };
+        if self.max_depth.is_some_and(|max_depth| depth > max_depth) {
+            return Some("deeper than --max-depth");
+        }
+        if !module
+            && !self.include_path.is_empty()
+            && !self
+                .include_path
+                .iter()
+                .any(|pattern| matches_pattern(pattern, path))
+        {
+            return Some("not an included path");
+        }
+        None
+    }
+
+    fn update_progress(&self) {
+        self.progress.set_message(format!(
+            "traversing: {} items visited, {} documents generated",
+            self.visited.len(),
+            self.documents.len()
+        ));
+    }
+}
+
+fn start_krate(crates: &CrateCatalog, traversal: &mut Traversal) {
+    let krate = &crates[0].as_ref().unwrap().1;
+    item_explorer(krate.root, 0, crates, traversal, 0);
+}
+
+fn item_explorer(
+    id: rustdoc_types::Id,
+    current_crate: usize,
+    crates: &CrateCatalog,
+    traversal: &mut Traversal,
+    depth: u32,
+) {
+    if !traversal.visited.insert((current_crate, id)) {
+        return;
+    }
+    traversal.update_progress();
+    let krate = crates[current_crate].as_ref().unwrap();
+    tracing::trace!(krate = krate.0, id = id.0, depth, "visiting item");
+    let Some(item) = krate.1.index.get(&id) else {
+        tracing::warn!(
+            krate = krate.0,
+            id = id.0,
+            "skipping an item missing from the rustdoc JSON"
+        );
+        traversal.report.skip(
+            "missing from the rustdoc JSON",
+            format!("{}:{}", krate.0, id.0),
+        );
+        return;
+    };
+    traversal.report.visit(item_kind(&item.inner));
+    if item.attrs.iter().any(|attr| attr.contains("doc(hidden)")) {
+        traversal
+            .report
+            .skip("hidden", item_path(item, current_crate, crates));
+        return;
+    }
+    // Imports are filtered by the items they lead to, variants with their enum
+    if !matches!(
+        item.inner,
+        rustdoc_types::ItemEnum::Use(_)
+            | rustdoc_types::ItemEnum::ExternCrate { .. }
+            | rustdoc_types::ItemEnum::Variant(_)
+    ) {
+        let path = item_path(item, current_crate, crates);
+        let module = matches!(item.inner, rustdoc_types::ItemEnum::Module(_));
+        if let Some(reason) = traversal.filtered(&path, module) {
+            traversal.report.skip(reason, path);
+            return;
+        }
+    }
+    // Features enabling the item also enable what it contains or re-exports
+    let outer_features = traversal.features.len();
+    traversal.features.extend(cfg_features(&item.attrs));
+    if !traversal.features.is_empty() {
+        let mut features = traversal.features.clone();
+        features.sort();
+        features.dedup();
+        traversal
+            .required_features
+            .entry(item_path(item, current_crate, crates))
+            .or_insert(features);
+    }
+    match &item.inner {
+        rustdoc_types::ItemEnum::Module(module) => {
+            let path = item_path(item, current_crate, crates);
+            if let Some(parent) = traversal.modules.last() {
+                traversal.graph.add(
+                    parent,
+                    BTreeMap::from([(path.clone(), graph::Relation::Contains)]),
+                );
+            }
+            traversal.modules.push(path);
+            module_explorer(module, current_crate, crates, traversal, depth);
+            traversal.modules.pop();
+        }
+        rustdoc_types::ItemEnum::ExternCrate { .. } => {}
+        rustdoc_types::ItemEnum::Use(used) => {
+            let summary = used.id.and_then(|id| krate.1.paths.get(&id));
+            let canonical = summary.map(|summary| summary.path.join("::"));
+            if let (Some(canonical), Some(module), false) =
+                (canonical, traversal.modules.last(), used.is_glob)
+            {
+                if item.visibility == rustdoc_types::Visibility::Public {
+                    let alias = format!("{}::{}", module, used.name);
+                    if alias != canonical {
+                        traversal
+                            .aliases
+                            .entry(canonical)
+                            .or_default()
+                            .insert(alias);
+                    }
+                }
+            }
+            if used.is_glob {
+                glob_explorer(item, used, current_crate, crates, traversal, depth);
+            } else {
+                match resolve_use(used, current_crate, crates, traversal) {
+                    Some((target_crate, id)) => {
+                        item_explorer(id, target_crate, crates, traversal, depth + 1)
+                    }
+                    // Items of excluded crates, already reported with their crate
+                    None if summary
+                        .and_then(|summary| krate.1.external_crates.get(&summary.crate_id))
+                        .is_some_and(|ext_krate| {
+                            traversal
+                                .report
+                                .skipped_item("excluded crate", &ext_krate.name)
+                        }) => {}
+                    None => traversal
+                        .report
+                        .skip("unresolved import", used.source.clone()),
+                }
+            }
+        }
+        rustdoc_types::ItemEnum::Struct(_)
+        | rustdoc_types::ItemEnum::Primitive(_)
+        | rustdoc_types::ItemEnum::Function(_)
+        | rustdoc_types::ItemEnum::Trait(_) => {
+            let module = traversal.modules.last().cloned();
+            traversal.pending.push((current_crate, item.id, module));
+        }
+        rustdoc_types::ItemEnum::StructField(_strufi) => {}
+        rustdoc_types::ItemEnum::Enum(enume) => {
+            if traversal.generators.get(ItemKind::Enum).is_some() {
+                let module = traversal.modules.last().cloned();
+                traversal.pending.push((current_crate, item.id, module));
+            }
+            enum_explorer(enume, current_crate, crates, traversal, depth);
+        }
+        rustdoc_types::ItemEnum::Variant(_) => {}
+        rustdoc_types::ItemEnum::Impl(_) => {}
+        rustdoc_types::ItemEnum::Union(_)
+        | rustdoc_types::ItemEnum::TraitAlias(_)
+        | rustdoc_types::ItemEnum::TypeAlias(_)
+        | rustdoc_types::ItemEnum::Constant { .. }
+        | rustdoc_types::ItemEnum::Static(_)
+        | rustdoc_types::ItemEnum::ExternType
+        | rustdoc_types::ItemEnum::Macro(_)
+        | rustdoc_types::ItemEnum::ProcMacro(_)
+        | rustdoc_types::ItemEnum::AssocConst { .. }
+        | rustdoc_types::ItemEnum::AssocType { .. } => {
+            if traversal
+                .generators
+                .get(generator::kind(&item.inner))
+                .is_some()
+            {
+                let module = traversal.modules.last().cloned();
+                traversal.pending.push((current_crate, item.id, module));
+            } else {
+                let name = item_path(item, current_crate, crates);
+                traversal.report.skip("unsupported kind", name);
+            }
+        }
+    }
+    traversal.features.truncate(outer_features);
+}
+
+/// Generate the documents of the items found by the traversal in parallel, then
+/// record them in the order they were found.
+fn document_pending(crates: &CrateCatalog, traversal: &mut Traversal) -> error::Result<()> {
+    let pending = std::mem::take(&mut traversal.pending);
+    let generators = &traversal.generators;
+    let blanket_impls = traversal.blanket_impls;
+    let (signature_depth, signature_bounds) =
+        (traversal.signature_depth, traversal.signature_bounds);
+    let generated = pending
+        .par_iter()
+        .map(|(current_crate, id, _)| {
+            let item = &crates[*current_crate].as_ref().unwrap().1.index[id];
+            let context = generator::Context {
+                current_crate: *current_crate,
+                crates,
+                blanket_impls,
+                signature_depth,
+                signature_bounds,
+            };
+            match generators.get(generator::kind(&item.inner)) {
+                Some(generator) => generator.generate(item, &context),
+                None => Ok(None),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| error::Error::Traversal(err))?;
+    for ((current_crate, id, module), generated) in pending.into_iter().zip(generated) {
+        let item = &crates[current_crate].as_ref().unwrap().1.index[&id];
+        record(item, generated, module, current_crate, crates, traversal);
+    }
+    Ok(())
+}
+
+/// Add the document of an item with its edges in the item graph.
+fn record(
+    item: &rustdoc_types::Item,
+    document: Option<Document>,
+    module: Option<String>,
+    current_crate: usize,
+    crates: &CrateCatalog,
+    traversal: &mut Traversal,
+) {
+    match &item.inner {
+        rustdoc_types::ItemEnum::Struct(stru) => {
+            traversal.graph.add(
+                &item_path(item, current_crate, crates),
+                graph::struct_references(stru, current_crate, crates),
+            );
+            if let Some(document) = plugin::plugin_document(
+                item,
+                stru,
+                current_crate,
+                crates,
+                traversal.sources.as_mut(),
+            ) {
+                traversal.documents.insert(document.id(), document);
+            }
+            add_sources(item, current_crate, crates, traversal);
+        }
+        rustdoc_types::ItemEnum::Function(_) => add_sources(item, current_crate, crates, traversal),
+        rustdoc_types::ItemEnum::Trait(trait_) if document.is_some() => {
+            // The extended types get the methods of the trait
+            let path = item_path(item, current_crate, crates);
+            for extended in document_extension::extended_paths(trait_, current_crate, crates) {
+                traversal.graph.add(
+                    &extended,
+                    BTreeMap::from([(path.clone(), graph::Relation::Implements)]),
+                );
+            }
+        }
+        _ => {}
+    }
+    let Some(mut document) = document else {
+        let name = item_path(item, current_crate, crates);
+        traversal.report.skip("unsupported kind", name);
+        return;
+    };
+    let keep = check_documented(item, &mut document, current_crate, crates, traversal);
+    // Primitives aren't in any module
+    let primitive = matches!(item.inner, rustdoc_types::ItemEnum::Primitive(_));
+    if let Some(module) = module.filter(|_| !primitive) {
+        traversal.graph.add(
+            &module,
+            BTreeMap::from([(document.path.clone(), graph::Relation::Contains)]),
+        );
+    }
+    if keep {
+        traversal.documents.insert(document.id(), document);
+    }
+}
+
+/// Count an item without documentation, returning whether its document is kept:
+/// it is skipped with --skip-undocumented, and says it has no documentation
+/// otherwise.
+fn check_documented(
+    item: &rustdoc_types::Item,
+    document: &mut Document,
+    current_crate: usize,
+    crates: &CrateCatalog,
+    traversal: &mut Traversal,
+) -> bool {
+    if item.docs.is_some() {
+        return true;
+    }
+    let krate = &crates[current_crate].as_ref().unwrap().0;
+    *traversal
+        .report
+        .undocumented
+        .entry(krate.clone())
+        .or_default() += 1;
+    if traversal.skip_undocumented {
+        traversal.report.skip("undocumented", document.path.clone());
+        return false;
+    }
+    document.text.push_str("It has no documentation.\n\n");
+    traversal.undocumented.insert(document.id());
+    true
+}
+
+fn add_sources(
+    item: &rustdoc_types::Item,
+    current_crate: usize,
+    crates: &CrateCatalog,
+    traversal: &mut Traversal,
+) {
+    let Some(sources) = &mut traversal.sources else {
+        return;
+    };
+    for document in sources.documents(item, current_crate, crates) {
+        traversal.documents.insert(document.id(), document);
+    }
+}
+
+/// Document of a primitive type of the standard library, like `str` or `u32`, with
+/// the methods of its inherent impls. Its path is its bare name, as primitives
+/// aren't in any module.
+pub(crate) fn document_primitive(
+    item: &rustdoc_types::Item,
+    primitive: &rustdoc_types::Primitive,
+    current_crate: usize,
+    crates: &CrateCatalog,
+) -> Document {
+    let krate = &crates[current_crate].as_ref().unwrap().1;
+    let methods = primitive
+        .impls
+        .iter()
+        .filter_map(|id| match &krate.index.get(id)?.inner {
+            rustdoc_types::ItemEnum::Impl(imp) if imp.trait_.is_none() => Some(&imp.items),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|id| krate.index.get(id)?.name.as_deref())
+        .collect::<BTreeSet<_>>();
+    let mut text = format!("{} is a primitive type.\n\n", primitive.name);
+    if let Some(docs) = &item.docs {
+        text.push_str(&format!("{}\n\n", cleanup::clean(docs)));
+    }
+    if !methods.is_empty() {
+        text.push_str(&format!(
+            "It has the following methods: {}.\n\n",
+            methods.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    let mut metadata = item_metadata(item, "primitive", current_crate, crates);
+    metadata.insert("name".to_string(), primitive.name.clone().into());
+    Document {
+        path: primitive.name.clone(),
+        kind: "primitive".to_string(),
+        text,
+        metadata,
+    }
+}
+
+pub(crate) fn item_kind(inner: &rustdoc_types::ItemEnum) -> &'static str {
+    match inner {
+        rustdoc_types::ItemEnum::Module(_) => "module",
+        rustdoc_types::ItemEnum::ExternCrate { .. } => "extern crate",
+        rustdoc_types::ItemEnum::Use(_) => "use",
+        rustdoc_types::ItemEnum::Union(_) => "union",
+        rustdoc_types::ItemEnum::Struct(_) => "struct",
+        rustdoc_types::ItemEnum::StructField(_) => "struct field",
+        rustdoc_types::ItemEnum::Enum(_) => "enum",
+        rustdoc_types::ItemEnum::Variant(_) => "variant",
+        rustdoc_types::ItemEnum::Function(_) => "function",
+        rustdoc_types::ItemEnum::Trait(_) => "trait",
+        rustdoc_types::ItemEnum::TraitAlias(_) => "trait alias",
+        rustdoc_types::ItemEnum::Impl(_) => "impl",
+        rustdoc_types::ItemEnum::TypeAlias(_) => "type alias",
+        rustdoc_types::ItemEnum::Constant { .. } => "constant",
+        rustdoc_types::ItemEnum::Static(_) => "static",
+        rustdoc_types::ItemEnum::ExternType => "extern type",
+        rustdoc_types::ItemEnum::Macro(_) => "macro",
+        rustdoc_types::ItemEnum::ProcMacro(_) => "proc macro",
+        rustdoc_types::ItemEnum::Primitive(_) => "primitive",
+        rustdoc_types::ItemEnum::AssocConst { .. } => "associated constant",
+        rustdoc_types::ItemEnum::AssocType { .. } => "associated type",
+    }
+}
+
+/// Fully-qualified path of an item when known, its bare name otherwise.
+pub fn item_path(
+    item: &rustdoc_types::Item,
+    current_crate: usize,
+    crates: &CrateCatalog,
+) -> String {
+    let krate = crates[current_crate].as_ref().unwrap();
+    match krate.1.paths.get(&item.id) {
+        Some(summary) => summary.path.join("::"),
+        None => item.name.clone().unwrap_or_default(),
+    }
+}
+
+/// Path to import an item by among its canonical path and aliases: preferably from
+/// a crate of the project like `bevy` over the crate defining it, then from a
+/// prelude, then the shortest.
+fn import_path<'a>(
+    document: &'a Document,
+    aliases: &'a BTreeSet<String>,
+    root_crates: &[String],
+) -> Option<&'a str> {
+    if matches!(
+        document.kind.as_str(),
+        "source" | "guide" | "example" | "primitive"
+    ) {
+        return None;
+    }
+    std::iter::once(&document.path)
+        .chain(aliases)
+        .map(String::as_str)
+        .min_by_key(|path| {
+            let segments = path.split("::").collect::<Vec<_>>();
+            (
+                !root_crates.iter().any(|root| root == segments[0]),
+                !segments.contains(&"prelude"),
+                segments.len(),
+                *path,
+            )
+        })
+}
+
+/// Explore the module a `use path::*` imports and add aliases for its public items
+/// in the importing module.
+fn glob_explorer(
+    item: &rustdoc_types::Item,
+    used: &rustdoc_types::Use,
+    current_crate: usize,
+    crates: &CrateCatalog,
+    traversal: &mut Traversal,
+    depth: u32,
+) {
+    let Some((target_crate, id)) = resolve_use(used, current_crate, crates, traversal) else {
+        traversal
+            .report
+            .skip("unresolved import", used.source.clone());
+        return;
+    };
+    let target_krate = &crates[target_crate].as_ref().unwrap().1;
+    if let (Some(module), rustdoc_types::Visibility::Public) =
+        (traversal.modules.last().cloned(), &item.visibility)
+    {
+        let children = match target_krate.index.get(&id).map(|target| &target.inner) {
+            Some(rustdoc_types::ItemEnum::Module(target)) => target.items.clone(),
+            Some(rustdoc_types::ItemEnum::Enum(target)) => target.variants.clone(),
+            _ => vec![],
+        };
+        for child in children {
+            let Some(summary) = target_krate.paths.get(&child) else {
+                continue;
+            };
+            let public = target_krate
+                .index
+                .get(&child)
+                .is_some_and(|child| child.visibility == rustdoc_types::Visibility::Public);
+            let (Some(name), true) = (summary.path.last(), public) else {
+                continue;
+            };
+            let canonical = summary.path.join("::");
+            let alias = format!("{}::{}", module, name);
+            if alias != canonical {
+                traversal
+                    .aliases
+                    .entry(canonical)
+                    .or_default()
+                    .insert(alias);
+            }
+        }
+    }
+    item_explorer(id, target_crate, crates, traversal, depth + 1);
+}
+
+/// Crate and id of the item a `use` imports. Items of other crates are found by
+/// the path the current crate knows them by, and imports rustdoc didn't resolve by
+/// their path.
+fn resolve_use(
+    used: &rustdoc_types::Use,
+    current_crate: usize,
+    crates: &CrateCatalog,
+    traversal: &Traversal,
+) -> Option<(usize, rustdoc_types::Id)> {
+    let krate = &crates[current_crate].as_ref().unwrap().1;
+    if let Some(id) = used.id {
+        if krate.index.contains_key(&id) {
+            return Some((current_crate, id));
+        }
+        if let Some(summary) = krate.paths.get(&id) {
+            let path = summary.path.iter().map(String::as_str).collect::<Vec<_>>();
+            return find_path(&path, crates);
+        }
+    }
+    resolve_path(&used.source, crates, traversal)
+}
+
+/// Crate and id of the item at a path of a `use`, like `crate::shapes` or
+/// `bevy_ecs::prelude`, relative to the module being explored.
+fn resolve_path(
+    source: &str,
+    crates: &CrateCatalog,
+    traversal: &Traversal,
+) -> Option<(usize, rustdoc_types::Id)> {
+    let module = traversal.modules.last()?;
+    let mut segments = source.split("::").collect::<Vec<_>>();
+    let mut base = module.split("::").collect::<Vec<_>>();
+    match segments.first() {
+        Some(&"crate") => {
+            segments.remove(0);
+            base.truncate(1);
+        }
+        Some(&"self") => {
+            segments.remove(0);
+        }
+        Some(&"super") => {
+            while segments.first() == Some(&"super") {
+                segments.remove(0);
+                base.pop();
+            }
+        }
+        Some(first) if crates.position(first).is_some() => base.clear(),
+        _ => {}
+    }
+    base.extend(segments);
+    find_path(&base, crates)
+}
+
+/// Crate and id of the item with this canonical path, in the crate it's from.
+pub fn find_path(path: &[&str], crates: &CrateCatalog) -> Option<(usize, rustdoc_types::Id)> {
+    let crate_name = path.first()?;
+    let index = crates.position(crate_name)?;
+    let krate = &crates[index].as_ref()?.1;
+    let paths = crates.crates[index].paths.get_or_init(|| {
+        krate
+            .paths
+            .iter()
+            .filter(|(id, summary)| summary.crate_id == 0 && krate.index.contains_key(id))
+            .map(|(id, summary)| (summary.path.clone(), *id))
+            .collect()
+    });
+    let path = path.iter().map(|part| part.to_string()).collect::<Vec<_>>();
+    paths.get(&path).map(|id| (index, *id))
+}
+
+fn module_explorer(
+    module: &rustdoc_types::Module,
+    current_crate: usize,
+    crates: &CrateCatalog,
+    traversal: &mut Traversal,
+    depth: u32,
+) {
+    for item in &module.items {
+        item_explorer(*item, current_crate, crates, traversal, depth + 1);
+    }
+}
+
+fn enum_explorer(
+    enumeration: &rustdoc_types::Enum,
+    current_crate: usize,
+    crates: &CrateCatalog,
+    traversal: &mut Traversal,
+    depth: u32,
+) {
+    enumeration.variants.iter().for_each(|variant| {
+        item_explorer(*variant, current_crate, crates, traversal, depth + 1);
+    });
+}
@@ -0,0 +1,145 @@
+use std::error::Error;
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+
+use rustdoc_rag::{Results, Retriever};
+
+/// Interactive search with a result list and a preview of the selected document.
+pub async fn run(retriever: &Retriever) -> Result<(), Box<dyn Error>> {
+    let mut terminal = ratatui::init();
+    let result = Tui::default().run(&mut terminal, retriever).await;
+    ratatui::restore();
+    result
+}
+
+#[derive(Default)]
+struct Tui {
+    input: String,
+    results: Results,
+    selected: ListState,
+    scroll: u16,
+    status: String,
+}
+
+impl Tui {
+    async fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        retriever: &Retriever,
+    ) -> Result<(), Box<dyn Error>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(())
+                }
+                KeyCode::Char(c) => self.input.push(c),
+                KeyCode::Backspace => {
+                    self.input.pop();
+                }
+                KeyCode::Enter if !self.input.trim().is_empty() => {
+                    self.status = "searching...".to_string();
+                    terminal.draw(|frame| self.draw(frame))?;
+                    self.results = retriever.search(&self.input).await?;
+                    self.status = if self.results.suggestions.is_empty() {
+                        format!("{} results", self.results.ranking.len())
+                    } else {
+                        format!("Did you mean: {}?", self.results.suggestions.join(", "))
+                    };
+                    self.select(0);
+                }
+                KeyCode::Up => self.select(self.selected.selected().unwrap_or(0).saturating_sub(1)),
+                KeyCode::Down => self.select(self.selected.selected().map_or(0, |i| i + 1)),
+                KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+                KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+                _ => {}
+            }
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        let count = self.results.ranking.len();
+        self.selected
+            .select((count > 0).then(|| index.min(count - 1)));
+        self.scroll = 0;
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [input, body] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(frame.area());
+        let [list, preview] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(body);
+
+        frame.render_widget(
+            Paragraph::new(self.input.as_str())
+                .block(Block::bordered().title(format!(" Prompt  {} ", self.status))),
+            input,
+        );
+
+        let items = self
+            .results
+            .ranking
+            .iter()
+            .map(|(id, _)| ListItem::new(id.as_str()))
+            .collect::<Vec<_>>();
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(" Results "))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+            list,
+            &mut self.selected,
+        );
+
+        let text = self
+            .selected
+            .selected()
+            .and_then(|i| self.results.ranking.get(i))
+            .and_then(|(id, _)| self.results.texts.get(id))
+            .map(|text| markdown_lines(text))
+            .unwrap_or_default();
+        frame.render_widget(
+            Paragraph::new(text)
+                .block(Block::bordered().title(" Preview "))
+                .wrap(Wrap { trim: false })
+                .scroll((self.scroll, 0)),
+            preview,
+        );
+    }
+}
+
+/// Lines of a markdown document, with headings in bold and code blocks dimmed.
+fn markdown_lines(text: &str) -> Vec<Line<'_>> {
+    let mut in_code = false;
+    text.lines()
+        .map(|line| {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+                Line::from(Span::styled(line, Style::new().add_modifier(Modifier::DIM)))
+            } else if in_code {
+                Line::from(Span::styled(line, Style::new().add_modifier(Modifier::DIM)))
+            } else if line.starts_with('#') {
+                Line::from(Span::styled(
+                    line.trim_start_matches('#').trim(),
+                    Style::new().add_modifier(Modifier::BOLD),
+                ))
+            } else {
+                Line::from(line)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,83 @@
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{cargo, Indexer};
+
+/// How often the watched files are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Latest modification time of the files under the paths, skipping build
+/// outputs and hidden directories.
+fn last_modified(paths: &[PathBuf]) -> Option<SystemTime> {
+    fn visit(path: &Path, latest: &mut Option<SystemTime>) {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return;
+        };
+        if let Ok(modified) = metadata.modified() {
+            *latest = (*latest).max(Some(modified));
+        }
+        if !metadata.is_dir() {
+            return;
+        }
+        for entry in std::fs::read_dir(path).into_iter().flatten().flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name != "target" && !name.starts_with('.') {
+                visit(&entry.path(), latest);
+            }
+        }
+    }
+    let mut latest = None;
+    for path in paths {
+        visit(path, &mut latest);
+    }
+    latest
+}
+
+/// Index, then update the index whenever the rustdoc JSON changes, until
+/// interrupted. With `--manifest-path` the sources of the package are watched
+/// instead, and its rustdoc JSON regenerated when they change. A `serve` or
+/// REPL running on the same index answers from the updated documents.
+pub async fn watch(indexer: &Indexer) -> Result<(), Box<dyn Error>> {
+    let options = indexer.options();
+    let package = match &options.manifest_path {
+        Some(manifest_path) => Some(cargo::package(Some(manifest_path), None)?),
+        None => None,
+    };
+    let paths = match &package {
+        Some(package) => vec![package
+            .manifest_path
+            .parent()
+            .unwrap_or(Path::new("."))
+            .to_path_buf()],
+        None => vec![indexer.project_json().to_path_buf()],
+    };
+    let paths = paths
+        .into_iter()
+        .chain(options.extra_docs.iter().cloned())
+        .chain(options.examples.iter().cloned())
+        .collect::<Vec<_>>();
+
+    indexer.open().await?;
+    eprintln!("Watching {} for changes", paths[0].display());
+    let mut seen = last_modified(&paths);
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let modified = last_modified(&paths);
+        if modified == seen {
+            continue;
+        }
+        seen = modified;
+        if let Some(package) = &package {
+            if !package.run_rustdoc(&options.toolchain)? {
+                eprintln!("Couldn't generate the rustdoc JSON, waiting for the next change");
+                continue;
+            }
+        }
+        indexer.open().await?;
+        eprintln!("Index updated, watching for changes");
+    }
+}